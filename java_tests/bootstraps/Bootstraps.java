@@ -0,0 +1,17 @@
+import java.util.function.Function;
+import java.util.function.Supplier;
+
+public class Bootstraps {
+    record Point(int x, int y) {}
+
+    static String describe(Object o, int n) {
+        Supplier<String> empty = String::new;
+        Function<String, Integer> length = String::length;
+        String kind = switch (o) {
+            case String s -> "string";
+            case Point p -> "point";
+            default -> "other";
+        };
+        return "n=" + n + " is " + kind + empty.get() + length.apply("");
+    }
+}
@@ -0,0 +1,3 @@
+module shop.api {
+    exports shop;
+}
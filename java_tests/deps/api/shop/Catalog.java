@@ -0,0 +1,8 @@
+package shop;
+
+import java.io.IOException;
+import java.util.List;
+
+public interface Catalog {
+    List<Item> items() throws IOException;
+}
@@ -0,0 +1,28 @@
+package shop.impl;
+
+import java.io.IOException;
+import java.util.ArrayList;
+import java.util.HashMap;
+import java.util.List;
+import java.util.Map;
+
+import shop.Catalog;
+import shop.Item;
+
+@Deprecated
+public class FileCatalog implements Catalog {
+    private final Map<String, Item> cache = new HashMap<>();
+
+    @Override
+    public List<Item> items() throws IOException {
+        try {
+            return new ArrayList<>(cache.values());
+        } catch (IllegalStateException e) {
+            throw new IOException(e);
+        }
+    }
+
+    private static StringBuilder describe(Item item) {
+        return new StringBuilder(item.name());
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt::{Debug, Display};
+
+/// JString is the contents of a `java.lang.String`: a sequence of UTF-16 code units, which
+/// unlike a Rust `String` may hold unpaired surrogates.
+/// Strings made only of code units up to `0xff` are stored compactly as Latin-1, one byte per
+/// code unit, like the JVM does. Every string is kept in the most compact form it fits, so two
+/// strings are equal exactly when their code units are.
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct JString(Repr);
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Repr {
+    Latin1(Vec<u8>),
+    Utf16(Vec<u16>),
+}
+
+impl Default for Repr {
+    fn default() -> Self {
+        Repr::Latin1(Vec::new())
+    }
+}
+
+impl JString {
+    pub fn from_utf16(units: Vec<u16>) -> Self {
+        match units.iter().all(|unit| *unit <= 0xff) {
+            true => JString(Repr::Latin1(units.into_iter().map(|unit| unit as u8).collect())),
+            false => JString(Repr::Utf16(units)),
+        }
+    }
+    /// Length in UTF-16 code units, as `String.length` counts it.
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Repr::Latin1(bytes) => bytes.len(),
+            Repr::Utf16(units) => units.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Whether the string is stored one byte per code unit.
+    pub fn is_latin1(&self) -> bool {
+        matches!(self.0, Repr::Latin1(_))
+    }
+    /// The code unit at `index`, as `String.charAt` returns it.
+    pub fn char_at(&self, index: usize) -> Option<u16> {
+        match &self.0 {
+            Repr::Latin1(bytes) => bytes.get(index).map(|byte| *byte as u16),
+            Repr::Utf16(units) => units.get(index).copied(),
+        }
+    }
+    pub fn units(&self) -> impl Iterator<Item = u16> + '_ {
+        let (bytes, units) = match &self.0 {
+            Repr::Latin1(bytes) => (&bytes[..], &[][..]),
+            Repr::Utf16(units) => (&[][..], &units[..]),
+        };
+        bytes.iter().map(|byte| *byte as u16).chain(units.iter().copied())
+    }
+    pub fn concat(&self, other: &JString) -> JString {
+        match (&self.0, &other.0) {
+            (Repr::Latin1(a), Repr::Latin1(b)) => JString(Repr::Latin1([&a[..], &b[..]].concat())),
+            _ => JString(Repr::Utf16(self.units().chain(other.units()).collect())),
+        }
+    }
+    /// `String.hashCode`: the code units as the digits of a number in base 31, wrapping.
+    pub fn hash_code(&self) -> i32 {
+        self.units().fold(0i32, |hash, unit| hash.wrapping_mul(31).wrapping_add(unit as i32))
+    }
+    /// Bytes the code units take.
+    pub fn size(&self) -> usize {
+        match &self.0 {
+            Repr::Latin1(bytes) => bytes.len(),
+            Repr::Utf16(units) => units.len() * 2,
+        }
+    }
+    /// The string in UTF-8, with unpaired surrogates replaced by U+FFFD.
+    pub fn to_string_lossy(&self) -> String {
+        match &self.0 {
+            Repr::Latin1(bytes) => bytes.iter().map(|byte| *byte as char).collect(),
+            Repr::Utf16(units) => String::from_utf16_lossy(units),
+        }
+    }
+}
+
+impl From<&str> for JString {
+    fn from(value: &str) -> Self {
+        JString::from_utf16(value.encode_utf16().collect())
+    }
+}
+
+impl From<String> for JString {
+    fn from(value: String) -> Self {
+        JString::from(value.as_str())
+    }
+}
+
+impl Display for JString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_string_lossy())
+    }
+}
+
+impl Debug for JString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.to_string_lossy())
+    }
+}
@@ -1,7 +1,13 @@
+use crate::runtime::heap::ObjectRef;
 
+use super::j_string::JString;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum JValue {
     Null, // Null value, used for void return types as well as null references
 
+    Boolean(bool), // fully stack allocated
+
     // Int types
     Byte(i8), // fully stack allocated
     UByte(u8), // fully stack allocated
@@ -21,7 +27,7 @@ pub enum JValue {
     UBigInt(u128), // fully stack allocated
 
     // Reference types
-    String(String), // stack allocated pointer to heap allocated string of dynamic size
-    Object
+    String(JString), // UTF-16 contents of a string that isn't on the runtime heap
+    Object(ObjectRef), // handle to an object on the runtime heap
 
 }
\ No newline at end of file
@@ -4,4 +4,5 @@ pub mod j_array;
 pub mod j_num;
 pub mod j_bool;
 pub mod j_value;
-pub mod j_object;
\ No newline at end of file
+pub mod j_object;
+pub mod j_string;
\ No newline at end of file
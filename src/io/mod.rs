@@ -1,8 +1,10 @@
 mod prebuffer;
+mod inflate;
+pub mod zip;
 
 pub use prebuffer::Prebuffer;
 
-use std::io::{Read, Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom, Write};
 
 use crate::util::code_err::ClassParseError;
 
@@ -99,3 +101,102 @@ pub trait BufferReadable : Read + Seek {
 auto trait BlanketBufferReadableImpl {}
 
 impl<T: Read + Seek + BlanketBufferReadableImpl> BufferReadable for T {}
+
+/// The writing counterpart of [BufferReadable], every value is written big-endian
+/// the same way the classfile format expects it.
+pub trait BufferWritable : Write {
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), ClassParseError> {
+        self.write_all(&[byte]).map_err(ClassParseError::IOError)
+    }
+    fn write_u2(&mut self, value: u16) -> Result<(), ClassParseError> {
+        self.write_all(&value.to_be_bytes()).map_err(ClassParseError::IOError)
+    }
+    fn write_u4(&mut self, value: u32) -> Result<(), ClassParseError> {
+        self.write_all(&value.to_be_bytes()).map_err(ClassParseError::IOError)
+    }
+    fn write_u8(&mut self, value: u64) -> Result<(), ClassParseError> {
+        self.write_all(&value.to_be_bytes()).map_err(ClassParseError::IOError)
+    }
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), ClassParseError> {
+        self.write_all(bytes).map_err(ClassParseError::IOError)
+    }
+    fn write_string(&mut self, value: &str) -> Result<(), ClassParseError> {
+        self.write_utf16(&value.encode_utf16().collect::<Vec<_>>())
+    }
+    /// Writes UTF-16 code units as a Utf8 constant, unpaired surrogates included.
+    fn write_utf16(&mut self, units: &[u16]) -> Result<(), ClassParseError> {
+        let bytes = encode_modified_utf8(units);
+        if bytes.len() > u16::MAX as usize {
+            return Err(ClassParseError::BadValue {
+                expected: format!("string of at most {} bytes", u16::MAX),
+                got: format!("string of {} bytes", bytes.len()),
+                for_what: "Utf8 Constant".to_string(),
+            });
+        }
+        self.write_u2(bytes.len() as u16)?;
+        self.write_bytes(&bytes)
+    }
+}
+
+impl<T: Write> BufferWritable for T {}
+
+/// Encodes UTF-16 code units the way the classfile format stores Utf8 constants (JVMS 4.4.7):
+/// NUL takes two bytes and supplementary characters are written as a surrogate pair,
+/// each surrogate taking three bytes.
+pub fn encode_modified_utf8(units: &[u16]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(units.len());
+    for &unit in units {
+        match unit {
+            0x0001..=0x007F => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07FF => {
+                bytes.push(0xC0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            },
+            _ => {
+                bytes.push(0xE0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                bytes.push(0x80 | (unit & 0x3F) as u8);
+            },
+        }
+    }
+    bytes
+}
+
+/// Decodes a Utf8 constant written by [encode_modified_utf8] or any other compiler to its
+/// UTF-16 code units, which may hold surrogates that don't form a pair.
+pub fn decode_modified_utf8(bytes: &[u8]) -> Result<Vec<u16>, ClassParseError> {
+    let malformed = |at: usize| ClassParseError::BadValue {
+        expected: "modified UTF-8".to_string(),
+        got: format!("malformed byte sequence at offset {}", at),
+        for_what: "Utf8 Constant".to_string(),
+    };
+    let continuation = |at: usize| match bytes.get(at) {
+        Some(&byte) if byte & 0xC0 == 0x80 => Ok((byte & 0x3F) as u16),
+        _ => Err(malformed(at)),
+    };
+    let mut units = Vec::with_capacity(bytes.len());
+    let mut at = 0;
+    while at < bytes.len() {
+        let byte = bytes[at];
+        let unit = match byte {
+            0x01..=0x7F => {
+                at += 1;
+                byte as u16
+            },
+            0xC0..=0xDF => {
+                let unit = ((byte & 0x1F) as u16) << 6 | continuation(at + 1)?;
+                at += 2;
+                unit
+            },
+            0xE0..=0xEF => {
+                let unit = ((byte & 0x0F) as u16) << 12 | continuation(at + 1)? << 6 | continuation(at + 2)?;
+                at += 3;
+                unit
+            },
+            _ => return Err(malformed(at)),
+        };
+        units.push(unit);
+    }
+    Ok(units)
+}
@@ -0,0 +1,119 @@
+use crate::util::code_err::ClassParseError;
+
+use super::inflate::inflate;
+
+// https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const CENTRAL_DIRECTORY_HEADER: u32 = 0x02014b50;
+const LOCAL_FILE_HEADER: u32 = 0x04034b50;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    pub name: String,
+    pub method: u16,
+    pub compressed_size: u32,
+    pub size: u32,
+    /// Offset of the entry's local header in the archive
+    header_offset: u32,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// A zip archive such as a jar, read from memory. Entries are listed from the central
+/// directory and decompressed on demand, only stored and deflated entries are supported.
+#[derive(Debug)]
+pub struct ZipArchive {
+    data: Vec<u8>,
+    pub entries: Vec<ZipEntry>,
+}
+
+fn u2(data: &[u8], at: usize) -> Result<u16, ClassParseError> {
+    data.get(at..at + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| ClassParseError::EarlyEOF(format!("EOF in zip archive at {}", at)))
+}
+fn u4(data: &[u8], at: usize) -> Result<u32, ClassParseError> {
+    data.get(at..at + 4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| ClassParseError::EarlyEOF(format!("EOF in zip archive at {}", at)))
+}
+fn bad_signature(what: &str, at: usize) -> ClassParseError {
+    ClassParseError::BadValue {
+        expected: what.to_string(),
+        got: format!("another signature at {}", at),
+        for_what: "zip archive".to_string(),
+    }
+}
+
+impl ZipArchive {
+    pub fn new(data: Vec<u8>) -> Result<Self, ClassParseError> {
+        // the end record is 22 bytes followed by a comment of at most 65535
+        let lowest = data.len().saturating_sub(22 + 0xFFFF);
+        let end = (lowest..=data.len().saturating_sub(22)).rev()
+            .find(|&at| u4(&data, at).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+            .ok_or_else(|| bad_signature("end of central directory record", data.len()))?;
+        let count = u2(&data, end + 10)?;
+        let mut at = u4(&data, end + 16)? as usize;
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            if u4(&data, at)? != CENTRAL_DIRECTORY_HEADER {
+                return Err(bad_signature("central directory file header", at));
+            }
+            let name_length = u2(&data, at + 28)? as usize;
+            let extra_length = u2(&data, at + 30)? as usize;
+            let comment_length = u2(&data, at + 32)? as usize;
+            let name = data.get(at + 46..at + 46 + name_length)
+                .ok_or_else(|| ClassParseError::EarlyEOF(format!("EOF in zip archive at {}", at)))?;
+            entries.push(ZipEntry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method: u2(&data, at + 10)?,
+                compressed_size: u4(&data, at + 20)?,
+                size: u4(&data, at + 24)?,
+                header_offset: u4(&data, at + 42)?,
+            });
+            at += 46 + name_length + extra_length + comment_length;
+        }
+        Ok(Self { data, entries })
+    }
+    pub fn open(path: &std::path::Path) -> Result<Self, ClassParseError> {
+        Self::new(std::fs::read(path).map_err(ClassParseError::IOError)?)
+    }
+    pub fn find(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+    /// The uncompressed contents of `entry`.
+    pub fn read(&self, entry: &ZipEntry) -> Result<Vec<u8>, ClassParseError> {
+        let at = entry.header_offset as usize;
+        if u4(&self.data, at)? != LOCAL_FILE_HEADER {
+            return Err(bad_signature("local file header", at));
+        }
+        // the local header repeats the name but may carry a different extra field
+        let start = at + 30 + u2(&self.data, at + 26)? as usize + u2(&self.data, at + 28)? as usize;
+        let compressed = self.data.get(start..start + entry.compressed_size as usize)
+            .ok_or_else(|| ClassParseError::EarlyEOF(format!("EOF in zip entry {}", entry.name)))?;
+        let contents = match entry.method {
+            STORED => compressed.to_vec(),
+            DEFLATED => inflate(compressed)?,
+            method => return Err(ClassParseError::BadValue {
+                expected: "stored or deflated entry".to_string(),
+                got: format!("compression method {}", method),
+                for_what: entry.name.clone(),
+            }),
+        };
+        if contents.len() != entry.size as usize {
+            return Err(ClassParseError::BadValue {
+                expected: format!("{} bytes", entry.size),
+                got: format!("{} bytes", contents.len()),
+                for_what: entry.name.clone(),
+            });
+        }
+        Ok(contents)
+    }
+}
@@ -0,0 +1,365 @@
+use std::{collections::{BTreeMap, BTreeSet, VecDeque}, fmt::{Display, Write}};
+
+use crate::{
+    jvm::reader::{
+        access_flags::AccessFlags,
+        bootstrap::{Bootstrap, BootstrapMethods, CallSite},
+        code::instruction::Instruction,
+        constant_pool::{ConstantPool, MemberRef},
+        method_handle_kind::MethodHandleKind,
+    },
+    util::{code_err::ClassParseError, json::Json},
+};
+
+use super::{classpath::ClassPath, hierarchy::ClassHierarchy};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MethodId {
+    /// Internal name of the declaring class
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+impl MethodId {
+    pub fn new(class: &str, name: &str, descriptor: &str) -> Self {
+        Self { class: class.to_string(), name: name.to_string(), descriptor: descriptor.to_string() }
+    }
+}
+
+impl Display for MethodId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}{}", self.class, self.name, self.descriptor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CallKind {
+    Static,
+    Special,
+    Virtual,
+    Interface,
+    /// From an `invokedynamic` to the bootstrap method linking it
+    Bootstrap,
+    /// From the method creating a lambda or method reference to the method it stands for
+    Lambda,
+}
+
+impl CallKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallKind::Static => "static",
+            CallKind::Special => "special",
+            CallKind::Virtual => "virtual",
+            CallKind::Interface => "interface",
+            CallKind::Bootstrap => "bootstrap",
+            CallKind::Lambda => "lambda",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CallEdge {
+    pub caller: MethodId,
+    pub callee: MethodId,
+    pub kind: CallKind,
+    /// Offset of the invoking instruction in the caller's code
+    pub pc: u32,
+}
+
+/// How the targets of `invokevirtual` and `invokeinterface` are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Class hierarchy analysis: any concrete subtype of the receiver's static type
+    Cha,
+    /// Rapid type analysis: only subtypes instantiated by reachable code
+    Rta,
+}
+
+/// The methods reachable from a set of entry points and the calls between them. Methods
+/// off the class path are kept as leaves so calls into libraries stay visible.
+#[derive(Debug, Default)]
+pub struct CallGraph {
+    pub entry_points: Vec<MethodId>,
+    pub methods: BTreeSet<MethodId>,
+    /// Reached methods whose code is not on the class path
+    pub external: BTreeSet<MethodId>,
+    pub edges: BTreeSet<CallEdge>,
+}
+
+/// A virtual call, kept around so it can be dispatched to types instantiated later on.
+#[derive(Debug, Clone)]
+struct VirtualSite {
+    caller: MethodId,
+    pc: u32,
+    kind: CallKind,
+    target: MemberRef,
+}
+
+struct Builder<'a> {
+    hierarchy: ClassHierarchy<'a>,
+    resolution: Resolution,
+    graph: CallGraph,
+    worklist: Vec<MethodId>,
+    instantiated: BTreeSet<&'a str>,
+    sites: Vec<VirtualSite>,
+}
+
+impl CallGraph {
+    /// Builds the graph of everything reachable from `entry_points`, or from every method on
+    /// the class path when none are given.
+    pub fn build(classpath: &ClassPath, entry_points: &[MethodId], resolution: Resolution) -> Result<Self, ClassParseError> {
+        let mut roots = entry_points.to_vec();
+        if roots.is_empty() {
+            for (name, class) in classpath.classes() {
+                for method in &class.class.methods.0 {
+                    roots.push(MethodId::new(name, method.name(&class.class.cp)?, method.descriptor(&class.class.cp)?));
+                }
+            }
+        }
+        let mut builder = Builder {
+            hierarchy: ClassHierarchy::new(classpath)?,
+            resolution,
+            graph: CallGraph { entry_points: roots.clone(), ..Default::default() },
+            worklist: Vec::new(),
+            instantiated: BTreeSet::new(),
+            sites: Vec::new(),
+        };
+        match resolution {
+            Resolution::Cha => {
+                builder.instantiated = classpath.classes()
+                    .filter(|(_, class)| !class.class.has_flag(AccessFlags::Interface) && !class.class.has_flag(AccessFlags::Abstract))
+                    .map(|(name, _)| name)
+                    .collect();
+            },
+            Resolution::Rta => {
+                // whoever calls an instance entry point must have created the receiver
+                for root in &roots {
+                    let is_static = classpath.get(&root.class)
+                        .and_then(|class| class.class.find_method(&root.name, &root.descriptor))
+                        .is_none_or(|method| method.has_flag(AccessFlags::Static));
+                    if !is_static {
+                        builder.instantiate(&root.class);
+                    }
+                }
+            },
+        }
+        for root in roots {
+            builder.reach(root);
+        }
+        while let Some(method) = builder.worklist.pop() {
+            builder.scan(&method)?;
+        }
+        Ok(builder.graph)
+    }
+
+    pub fn callees<'a>(&'a self, method: &'a MethodId) -> impl Iterator<Item = &'a CallEdge> {
+        self.edges.iter().filter(move |edge| edge.caller == *method)
+    }
+    pub fn callers<'a>(&'a self, method: &'a MethodId) -> impl Iterator<Item = &'a CallEdge> {
+        self.edges.iter().filter(move |edge| edge.callee == *method)
+    }
+
+    fn successors(&self) -> BTreeMap<&MethodId, Vec<&MethodId>> {
+        let mut successors: BTreeMap<&MethodId, Vec<&MethodId>> = BTreeMap::new();
+        for edge in &self.edges {
+            successors.entry(&edge.caller).or_default().push(&edge.callee);
+        }
+        successors
+    }
+
+    /// The shortest call chain from `from` to a method matching `is_target`, both included.
+    pub fn path_to<'a>(&'a self, from: &'a MethodId, is_target: impl Fn(&MethodId) -> bool) -> Option<Vec<&'a MethodId>> {
+        let successors = self.successors();
+        let mut parents: BTreeMap<&MethodId, Option<&MethodId>> = BTreeMap::new();
+        let mut queue = VecDeque::from([from]);
+        parents.insert(from, None);
+        while let Some(method) = queue.pop_front() {
+            if is_target(method) {
+                let mut path = vec![method];
+                while let Some(Some(parent)) = parents.get(path.last().unwrap()) {
+                    path.push(parent);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in successors.get(method).into_iter().flatten() {
+                if !parents.contains_key(next) {
+                    parents.insert(next, Some(method));
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Every entry point from which a method matching `is_target` can be called, along with
+    /// the shortest chain of calls that gets there.
+    pub fn entry_points_reaching(&self, is_target: impl Fn(&MethodId) -> bool) -> Vec<Vec<&MethodId>> {
+        self.entry_points.iter().filter_map(|entry| self.path_to(entry, &is_target)).collect()
+    }
+
+    /// Graphviz rendering, methods off the class path are drawn dashed.
+    pub fn to_dot(&self) -> String {
+        let quote = |method: &MethodId| format!("\"{}\"", method.to_string().replace('\\', "\\\\").replace('"', "\\\""));
+        let mut out = String::from("digraph calls {\n    node [shape=box];\n");
+        for method in &self.methods {
+            let style = if self.external.contains(method) { " [style=dashed]" } else { "" };
+            writeln!(out, "    {}{};", quote(method), style).unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(out, "    {} -> {} [label=\"{}\"];", quote(&edge.caller), quote(&edge.callee), edge.kind.name()).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object([
+            ("entry_points", Json::Array(self.entry_points.iter().map(|m| Json::string(m.to_string())).collect())),
+            ("methods", Json::Array(self.methods.iter().map(|method| Json::object([
+                ("id", Json::string(method.to_string())),
+                ("class", Json::string(&method.class)),
+                ("name", Json::string(&method.name)),
+                ("descriptor", Json::string(&method.descriptor)),
+                ("external", Json::Bool(self.external.contains(method))),
+            ])).collect())),
+            ("edges", Json::Array(self.edges.iter().map(|edge| Json::object([
+                ("caller", Json::string(edge.caller.to_string())),
+                ("callee", Json::string(edge.callee.to_string())),
+                ("kind", Json::string(edge.kind.name())),
+                ("pc", Json::Number(edge.pc as i64)),
+            ])).collect())),
+        ])
+    }
+}
+
+impl<'a> Builder<'a> {
+    fn reach(&mut self, method: MethodId) {
+        if self.graph.methods.contains(&method) {
+            return;
+        }
+        let has_code = self.hierarchy.classpath().get(&method.class)
+            .and_then(|class| class.class.find_method(&method.name, &method.descriptor))
+            .is_some_and(|info| info.code.is_some());
+        if has_code {
+            self.worklist.push(method.clone());
+        } else {
+            self.graph.external.insert(method.clone());
+        }
+        self.graph.methods.insert(method);
+    }
+
+    fn edge(&mut self, caller: &MethodId, callee: MethodId, kind: CallKind, pc: u32) {
+        self.graph.edges.insert(CallEdge { caller: caller.clone(), callee: callee.clone(), kind, pc });
+        self.reach(callee);
+    }
+
+    /// Resolves a call that has a single target, keeping the symbolic reference when it is
+    /// declared off the class path.
+    fn direct(&mut self, caller: &MethodId, target: &MemberRef, kind: CallKind, pc: u32) {
+        let class = self.hierarchy.find_declaration(&target.class, &target.name, &target.descriptor).unwrap_or(&target.class);
+        let callee = MethodId::new(class, &target.name, &target.descriptor);
+        self.edge(caller, callee, kind, pc);
+    }
+
+    fn instantiate(&mut self, class: &str) {
+        let class = match self.hierarchy.classpath().get_key_value(class) {
+            Some((name, info)) if !info.class.has_flag(AccessFlags::Interface) && !info.class.has_flag(AccessFlags::Abstract) => name,
+            _ => return,
+        };
+        if !self.instantiated.insert(class) {
+            return;
+        }
+        let sites: Vec<VirtualSite> = self.sites.iter()
+            .filter(|site| self.hierarchy.is_subtype(class, &site.target.class))
+            .cloned()
+            .collect();
+        for site in sites {
+            self.dispatch(&site, class);
+        }
+    }
+
+    fn dispatch(&mut self, site: &VirtualSite, class: &str) {
+        let target = &site.target;
+        // a reference to a class off the class path can still be dispatched from the receiver
+        let selected = match self.hierarchy.resolve_any_method(&target.class, &target.name, &target.descriptor) {
+            Ok(resolved) => self.hierarchy.select(resolved, class).ok().map(|method| method.class),
+            Err(_) => self.hierarchy.select_method(class, &target.name, &target.descriptor),
+        };
+        if let Some(declaring) = selected {
+            let callee = MethodId::new(declaring, &site.target.name, &site.target.descriptor);
+            self.edge(&site.caller, callee, site.kind, site.pc);
+        }
+    }
+
+    fn virtual_call(&mut self, caller: &MethodId, target: MemberRef, kind: CallKind, pc: u32) {
+        let site = VirtualSite { caller: caller.clone(), pc, kind, target };
+        if self.hierarchy.find_declaration(&site.target.class, &site.target.name, &site.target.descriptor).is_none() {
+            // declared by a library class, which may well be the one that runs
+            let callee = MethodId::new(&site.target.class, &site.target.name, &site.target.descriptor);
+            self.edge(caller, callee, kind, pc);
+        }
+        let receivers: Vec<&'a str> = self.hierarchy.subtypes(&site.target.class).into_iter()
+            .filter(|class| self.instantiated.contains(class))
+            .collect();
+        for class in receivers {
+            self.dispatch(&site, class);
+        }
+        self.sites.push(site);
+    }
+
+    /// Adds the edge for a method handle passed to a lambda bootstrap method.
+    fn handle(&mut self, caller: &MethodId, kind: &MethodHandleKind, target: MemberRef, pc: u32) {
+        match kind {
+            MethodHandleKind::InvokeStatic | MethodHandleKind::InvokeSpecial => self.direct(caller, &target, CallKind::Lambda, pc),
+            MethodHandleKind::NewInvokeSpecial => {
+                if self.resolution == Resolution::Rta {
+                    self.instantiate(&target.class);
+                }
+                self.direct(caller, &target, CallKind::Lambda, pc);
+            },
+            MethodHandleKind::InvokeVirtual | MethodHandleKind::InvokeInterface => self.virtual_call(caller, target, CallKind::Lambda, pc),
+            MethodHandleKind::GetField | MethodHandleKind::GetStatic | MethodHandleKind::PutField | MethodHandleKind::PutStatic => {},
+        }
+    }
+
+    fn scan(&mut self, method: &MethodId) -> Result<(), ClassParseError> {
+        let classpath = self.hierarchy.classpath();
+        let raw = &classpath.get(&method.class).unwrap().class;
+        let code = match raw.find_method(&method.name, &method.descriptor).and_then(|m| m.code.as_ref()) {
+            Some(code) => code,
+            None => return Ok(()),
+        };
+        let cp = &raw.cp;
+        let mut bootstrap_methods = None;
+        let mut pc = 0;
+        for instruction in &code.code {
+            match instruction {
+                Instruction::Invokestatic(index) => self.direct(method, &cp.get_member_ref(*index)?, CallKind::Static, pc),
+                Instruction::Invokespecial(index) => self.direct(method, &cp.get_member_ref(*index)?, CallKind::Special, pc),
+                Instruction::Invokevirtual(index) => self.virtual_call(method, cp.get_member_ref(*index)?, CallKind::Virtual, pc),
+                Instruction::Invokeinterface(index, _, _) => self.virtual_call(method, cp.get_member_ref(*index)?, CallKind::Interface, pc),
+                Instruction::New(index) if self.resolution == Resolution::Rta => self.instantiate(cp.get_class_name(*index)?),
+                Instruction::Invokedynamic(index, _) => {
+                    if bootstrap_methods.is_none() {
+                        bootstrap_methods = Some(BootstrapMethods::of(raw)?);
+                    }
+                    self.invokedynamic(method, cp, bootstrap_methods.as_ref().unwrap(), *index, pc)?;
+                },
+                _ => {},
+            }
+            pc += instruction.len_at(pc);
+        }
+        Ok(())
+    }
+
+    fn invokedynamic(&mut self, caller: &MethodId, cp: &ConstantPool, bootstrap_methods: &BootstrapMethods, index: u16, pc: u32) -> Result<(), ClassParseError> {
+        let site = CallSite::resolve(cp, bootstrap_methods, index)?;
+        if let Bootstrap::Lambda { implementation, .. } = site.classify() {
+            self.handle(caller, &implementation.kind, implementation.member, pc);
+        }
+        self.direct(caller, &site.bootstrap.member, CallKind::Bootstrap, pc);
+        Ok(())
+    }
+}
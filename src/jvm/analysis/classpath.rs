@@ -0,0 +1,149 @@
+use std::{collections::{btree_map::Entry, BTreeMap}, fs, path::Path};
+
+use crate::{
+    io::zip::ZipArchive,
+    jvm::reader::{classfile::ClassFile, constant_pool::ConstantPoolInfo},
+    util::code_err::ClassParseError,
+};
+
+/// A set of parsed classes, keyed by internal name. Like the JVM, the first definition of a
+/// class that is added wins.
+///
+/// Each class remembers the directory or jar it came from in [ClassFile::classpath], and
+/// origins that declare a module, through `module-info.class` or an `Automatic-Module-Name`,
+/// are recorded as such.
+#[derive(Debug, Default)]
+pub struct ClassPath {
+    classes: BTreeMap<String, ClassFile>,
+    /// Module name of each origin that has one
+    modules: BTreeMap<String, String>,
+}
+
+impl ClassPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Adds `class` unless a class of the same name is already present, returns whether it was added.
+    pub fn add(&mut self, class: ClassFile) -> Result<bool, ClassParseError> {
+        let name = class.class.name()?.to_string();
+        if self.classes.contains_key(&name) {
+            return Ok(false);
+        }
+        self.classes.insert(name, class);
+        Ok(true)
+    }
+    pub fn add_file(&mut self, path: &Path) -> Result<bool, ClassParseError> {
+        let class = ClassFile::from_bytes(fs::read(path).map_err(ClassParseError::IOError)?)?;
+        self.add_from(class, path.display().to_string(), String::new())
+    }
+    /// Adds a class read from `origin`, or records the module of `origin` for a module descriptor.
+    fn add_from(&mut self, mut class: ClassFile, path: String, origin: String) -> Result<bool, ClassParseError> {
+        class.path = path;
+        class.classpath = origin;
+        if class.class.name()? != "module-info" {
+            return self.add(class);
+        }
+        let raw = &class.class;
+        if let Some(attribute) = raw.attributes.find_by_name("Module", &raw.cp)? {
+            let index = attribute.info.get(..2).map(|b| (b[0] as u16) << 8 | b[1] as u16)
+                .ok_or_else(|| ClassParseError::EarlyEOF("EOF in Module attribute".to_string()))?;
+            let name = match raw.cp.get_info(index)? {
+                ConstantPoolInfo::Module(name) => raw.cp.get_utf8(*name)?,
+                other => return Err(ClassParseError::BadValue {
+                    expected: "Module".to_string(),
+                    got: format!("{:?}", other),
+                    for_what: "Module attribute".to_string(),
+                }),
+            };
+            self.modules.insert(class.classpath.clone(), name.to_string());
+        }
+        Ok(false)
+    }
+    /// Adds every `.class` file below `root`, in a stable order.
+    pub fn add_directory(&mut self, root: &Path) -> Result<usize, ClassParseError> {
+        let mut pending = vec![root.to_path_buf()];
+        let mut added = 0;
+        while let Some(dir) = pending.pop() {
+            let mut entries = fs::read_dir(&dir)
+                .and_then(|entries| entries.map(|entry| entry.map(|e| e.path())).collect::<Result<Vec<_>, _>>())
+                .map_err(ClassParseError::IOError)?;
+            entries.sort();
+            for path in entries.into_iter().rev() {
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "class") {
+                    let class = ClassFile::from_bytes(fs::read(&path).map_err(ClassParseError::IOError)?)?;
+                    if self.add_from(class, path.display().to_string(), root.display().to_string())? {
+                        added += 1;
+                    }
+                }
+            }
+        }
+        Ok(added)
+    }
+    /// Adds every class of a jar, leaving out the versioned copies of multi-release jars.
+    pub fn add_jar(&mut self, path: &Path) -> Result<usize, ClassParseError> {
+        let jar = ZipArchive::open(path)?;
+        let origin = path.display().to_string();
+        let mut added = 0;
+        for entry in &jar.entries {
+            if entry.is_dir() || !entry.name.ends_with(".class") || entry.name.starts_with("META-INF/") {
+                continue;
+            }
+            let class = ClassFile::from_bytes(jar.read(entry)?)?;
+            if self.add_from(class, format!("{}!/{}", origin, entry.name), origin.clone())? {
+                added += 1;
+            }
+        }
+        if let (Entry::Vacant(module), Some(manifest)) = (self.modules.entry(origin), jar.find("META-INF/MANIFEST.MF")) {
+            let manifest = String::from_utf8_lossy(&jar.read(manifest)?).into_owned();
+            if let Some(name) = manifest.lines().find_map(|line| line.strip_prefix("Automatic-Module-Name:")) {
+                module.insert(name.trim().to_string());
+            }
+        }
+        Ok(added)
+    }
+    pub fn get(&self, name: &str) -> Option<&ClassFile> {
+        self.classes.get(name)
+    }
+    /// Like [Self::get], along with the name as the class path stores it.
+    pub fn get_key_value(&self, name: &str) -> Option<(&str, &ClassFile)> {
+        self.classes.get_key_value(name).map(|(name, class)| (name.as_str(), class))
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.classes.contains_key(name)
+    }
+    /// The directory or jar `name` was loaded from, empty for classes added one by one.
+    pub fn origin_of(&self, name: &str) -> Option<&str> {
+        self.classes.get(name).map(|class| class.classpath.as_str())
+    }
+    /// The module `name` belongs to, when its origin declares one.
+    pub fn module_of(&self, name: &str) -> Option<&str> {
+        self.modules.get(self.origin_of(name)?).map(String::as_str)
+    }
+    /// Classes in order of their names.
+    pub fn classes(&self) -> impl Iterator<Item = (&str, &ClassFile)> {
+        self.classes.iter().map(|(name, class)| (name.as_str(), class))
+    }
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+    /// Whether the class, or the given method of it, carries a `Deprecated` attribute,
+    /// which javac emits for `@Deprecated` declarations.
+    pub fn is_deprecated(&self, class: &str, method: Option<(&str, &str)>) -> Result<bool, ClassParseError> {
+        let class = match self.get(class) {
+            Some(class) => &class.class,
+            None => return Ok(false),
+        };
+        if class.attributes.find_by_name("Deprecated", &class.cp)?.is_some() {
+            return Ok(true);
+        }
+        match method.and_then(|(name, descriptor)| class.find_method(name, descriptor)) {
+            Some(method) => Ok(method.attributes.find_by_name("Deprecated", &class.cp)?.is_some()),
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    io::{BufferReadable, Prebuffer},
+    jvm::reader::{
+        access_flags::AccessFlags,
+        attribute::Attributes,
+        code::instruction::Instruction,
+        constant_pool::{ConstantPool, ConstantPoolInfo},
+        descriptor::{FieldType, MethodDescriptor},
+        raw_class::RawClass,
+        signature::signature_classes,
+    },
+    util::{code_err::ClassParseError, json::Json},
+};
+
+use super::classpath::ClassPath;
+
+/// Stands in for the module or archive of a class that is not on the class path.
+pub const NOT_FOUND: &str = "not found";
+
+const ANNOTATIONS: [&str; 2] = ["RuntimeVisibleAnnotations", "RuntimeInvisibleAnnotations"];
+const PARAMETER_ANNOTATIONS: [&str; 2] = ["RuntimeVisibleParameterAnnotations", "RuntimeInvisibleParameterAnnotations"];
+const TYPE_ANNOTATIONS: [&str; 2] = ["RuntimeVisibleTypeAnnotations", "RuntimeInvisibleTypeAnnotations"];
+
+/// The classes a single class refers to, split by whether they appear in its API: the super
+/// types, and the descriptors, generic signatures, thrown exceptions and annotations of the
+/// class and its public and protected members, for public classes. Everything else, such as
+/// the classes used by code or private members, is an implementation dependency.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassDependencies {
+    pub name: String,
+    pub api: BTreeSet<String>,
+    /// Dependencies that are not also API dependencies
+    pub implementation: BTreeSet<String>,
+}
+
+struct Collector<'a> {
+    cp: &'a ConstantPool,
+    deps: ClassDependencies,
+}
+
+impl<'a> Collector<'a> {
+    fn add(&mut self, class: &str, api: bool) {
+        if class == self.deps.name {
+            return;
+        }
+        if api {
+            self.deps.api.insert(class.to_string());
+        } else {
+            self.deps.implementation.insert(class.to_string());
+        }
+    }
+    /// A `ClassRef` name, which may be an array descriptor.
+    fn class_ref(&mut self, name: &str, api: bool) -> Result<(), ClassParseError> {
+        if let Some(class) = FieldType::from_class_name(name)?.element_class() {
+            self.add(class, api);
+        }
+        Ok(())
+    }
+    fn field_descriptor(&mut self, descriptor: &str, api: bool) -> Result<(), ClassParseError> {
+        if let Some(class) = FieldType::parse(descriptor)?.element_class() {
+            self.add(class, api);
+        }
+        Ok(())
+    }
+    fn method_descriptor(&mut self, descriptor: &str, api: bool) -> Result<(), ClassParseError> {
+        for class in MethodDescriptor::parse(descriptor)?.classes() {
+            self.add(class, api);
+        }
+        Ok(())
+    }
+    fn member_ref(&mut self, index: u16) -> Result<(), ClassParseError> {
+        let member = self.cp.get_member_ref(index)?;
+        self.class_ref(&member.class, false)?;
+        if member.descriptor.starts_with('(') {
+            self.method_descriptor(&member.descriptor, false)
+        } else {
+            self.field_descriptor(&member.descriptor, false)
+        }
+    }
+    /// The generic signature and annotations shared by classes, fields and methods.
+    fn attributes(&mut self, attributes: &Attributes, api: bool) -> Result<(), ClassParseError> {
+        for attribute in &attributes.0 {
+            let name = attribute.name(self.cp)?;
+            let mut info = Prebuffer::copy_from_vec(&attribute.info);
+            match name.as_str() {
+                "Signature" => {
+                    for class in signature_classes(self.cp.get_utf8(info.read_u2()?)?)? {
+                        self.add(&class, api);
+                    }
+                },
+                name if ANNOTATIONS.contains(&name) => {
+                    for _ in 0..info.read_u2()? {
+                        self.annotation(&mut info, api)?;
+                    }
+                },
+                name if PARAMETER_ANNOTATIONS.contains(&name) => {
+                    for _ in 0..info.read_byte()? {
+                        for _ in 0..info.read_u2()? {
+                            self.annotation(&mut info, api)?;
+                        }
+                    }
+                },
+                name if TYPE_ANNOTATIONS.contains(&name) => {
+                    for _ in 0..info.read_u2()? {
+                        skip_type_annotation_target(&mut info)?;
+                        self.annotation(&mut info, api)?;
+                    }
+                },
+                "AnnotationDefault" => self.element_value(&mut info, api)?,
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+    // https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.16
+    fn annotation(&mut self, info: &mut Prebuffer, api: bool) -> Result<(), ClassParseError> {
+        let descriptor = self.cp.get_utf8(info.read_u2()?)?;
+        self.field_descriptor(descriptor, api)?;
+        for _ in 0..info.read_u2()? {
+            info.read_u2()?;
+            self.element_value(info, api)?;
+        }
+        Ok(())
+    }
+    fn element_value(&mut self, info: &mut Prebuffer, api: bool) -> Result<(), ClassParseError> {
+        match info.read_byte()? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' | b's' => {
+                info.read_u2()?;
+            },
+            b'e' => {
+                let descriptor = self.cp.get_utf8(info.read_u2()?)?;
+                self.field_descriptor(descriptor, api)?;
+                info.read_u2()?;
+            },
+            b'c' => {
+                let descriptor = self.cp.get_utf8(info.read_u2()?)?;
+                if descriptor != "V" {
+                    self.field_descriptor(descriptor, api)?;
+                }
+            },
+            b'@' => self.annotation(info, api)?,
+            b'[' => {
+                for _ in 0..info.read_u2()? {
+                    self.element_value(info, api)?;
+                }
+            },
+            tag => return Err(ClassParseError::BadValue {
+                expected: "element value tag".to_string(),
+                got: format!("{:?}", tag as char),
+                for_what: "annotation".to_string(),
+            }),
+        }
+        Ok(())
+    }
+}
+
+/// Skips the `target_info` and `type_path` that precede the annotation of a type annotation.
+// https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.20
+fn skip_type_annotation_target(info: &mut Prebuffer) -> Result<(), ClassParseError> {
+    let target_type = info.read_byte()?;
+    let skip = match target_type {
+        0x00 | 0x01 | 0x16 => 1,
+        0x10..=0x12 | 0x17 | 0x42..=0x46 => 2,
+        0x13..=0x15 => 0,
+        0x47..=0x4B => 3,
+        0x40 | 0x41 => 6 * info.read_u2()? as usize,
+        other => return Err(ClassParseError::BadValue {
+            expected: "type annotation target".to_string(),
+            got: format!("0x{:02x}", other),
+            for_what: "type annotation".to_string(),
+        }),
+    };
+    info.read_n_bytes(skip)?;
+    let path_length = info.read_byte()? as usize;
+    info.read_n_bytes(2 * path_length)?;
+    Ok(())
+}
+
+impl ClassDependencies {
+    pub fn of(class: &RawClass) -> Result<Self, ClassParseError> {
+        let cp = &class.cp;
+        let mut collector = Collector { cp, deps: ClassDependencies { name: class.name()?.to_string(), ..Default::default() } };
+        let exported = class.has_flag(AccessFlags::Public);
+        let visible = |flags: u16| exported && flags & (AccessFlags::Public as u16 | AccessFlags::Protected as u16) != 0;
+
+        for super_type in class.super_name()?.into_iter().chain(class.interface_names()?) {
+            collector.add(super_type, exported);
+        }
+        collector.attributes(&class.attributes, exported)?;
+        for field in &class.fields.0 {
+            let api = visible(field.access_flags);
+            collector.field_descriptor(field.descriptor(cp)?, api)?;
+            collector.attributes(&field.attributes, api)?;
+        }
+        for method in &class.methods.0 {
+            let api = visible(method.access_flags);
+            collector.method_descriptor(method.descriptor(cp)?, api)?;
+            collector.attributes(&method.attributes, api)?;
+            if let Some(exceptions) = method.attributes.find_by_name("Exceptions", cp)? {
+                let mut info = Prebuffer::copy_from_vec(&exceptions.info);
+                for _ in 0..info.read_u2()? {
+                    collector.class_ref(cp.get_class_name(info.read_u2()?)?, api)?;
+                }
+            }
+            let code = match &method.code {
+                Some(code) => code,
+                None => continue,
+            };
+            for handler in &code.exception_table.0 {
+                if handler.catch_type != 0 {
+                    collector.class_ref(cp.get_class_name(handler.catch_type)?, false)?;
+                }
+            }
+            collector.attributes(&code.attributes, false)?;
+            for instruction in &code.code {
+                match instruction {
+                    Instruction::Getstatic(index) | Instruction::Putstatic(index)
+                    | Instruction::Getfield(index) | Instruction::Putfield(index)
+                    | Instruction::Invokevirtual(index) | Instruction::Invokespecial(index)
+                    | Instruction::Invokestatic(index) | Instruction::Invokeinterface(index, _, _) => collector.member_ref(*index)?,
+                    Instruction::Invokedynamic(index, _) => {
+                        if let ConstantPoolInfo::InvokeDynamic { name_and_type_index, .. } = cp.get_info(*index)? {
+                            collector.method_descriptor(cp.get_name_and_type(*name_and_type_index)?.1, false)?;
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+        // whatever else names a class: instruction operands, inner classes, method types in
+        // bootstrap arguments and so on
+        for entry in &cp.0 {
+            match &entry.info {
+                ConstantPoolInfo::ClassRef(name) => collector.class_ref(cp.get_utf8(*name)?, false)?,
+                ConstantPoolInfo::MethodType(descriptor) => collector.method_descriptor(cp.get_utf8(*descriptor)?, false)?,
+                _ => {},
+            }
+        }
+
+        let mut deps = collector.deps;
+        deps.implementation.retain(|class| !deps.api.contains(class));
+        Ok(deps)
+    }
+}
+
+/// How far dependencies are aggregated, from one class to another up to one jar or
+/// directory to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Class,
+    /// Packages in internal form, e.g. `java/util`
+    Package,
+    Module,
+    Archive,
+}
+
+impl Granularity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Granularity::Class => "class",
+            Granularity::Package => "package",
+            Granularity::Module => "module",
+            Granularity::Archive => "archive",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Dependency {
+    pub from: String,
+    pub to: String,
+    /// Whether any of the class dependencies it stands for is an API dependency
+    pub api: bool,
+}
+
+pub fn package_of(class: &str) -> &str {
+    class.rfind('/').map_or("", |end| &class[..end])
+}
+
+/// The dependencies of every class on a class path.
+#[derive(Debug)]
+pub struct DependencyReport<'a> {
+    classpath: &'a ClassPath,
+    pub classes: Vec<ClassDependencies>,
+}
+
+impl<'a> DependencyReport<'a> {
+    pub fn new(classpath: &'a ClassPath) -> Result<Self, ClassParseError> {
+        let classes = classpath.classes()
+            .map(|(_, class)| ClassDependencies::of(&class.class))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { classpath, classes })
+    }
+    /// The unit `class` is counted under. Classes in the unnamed module have an empty module
+    /// name, classes off the class path belong to the [NOT_FOUND] module and archive.
+    pub fn unit_of<'b>(&self, class: &'b str, granularity: Granularity) -> &'b str
+    where 'a: 'b {
+        match granularity {
+            Granularity::Class => class,
+            Granularity::Package => package_of(class),
+            Granularity::Module if self.classpath.contains(class) => self.classpath.module_of(class).unwrap_or(""),
+            Granularity::Archive => self.classpath.origin_of(class).unwrap_or(NOT_FOUND),
+            Granularity::Module => NOT_FOUND,
+        }
+    }
+    /// Dependencies between distinct units, a unit never depends on itself.
+    pub fn dependencies(&self, granularity: Granularity) -> Vec<Dependency> {
+        let mut found: BTreeMap<(&str, &str), bool> = BTreeMap::new();
+        for class in &self.classes {
+            let from = self.unit_of(&class.name, granularity);
+            let targets = class.api.iter().map(|c| (c, true)).chain(class.implementation.iter().map(|c| (c, false)));
+            for (target, api) in targets {
+                let to = self.unit_of(target, granularity);
+                if from != to {
+                    *found.entry((from, to)).or_default() |= api;
+                }
+            }
+        }
+        found.into_iter()
+            .map(|((from, to), api)| Dependency { from: from.to_string(), to: to.to_string(), api })
+            .collect()
+    }
+    /// Every class with its dependencies, followed by the aggregated dependencies of each
+    /// granularity.
+    pub fn to_json(&self) -> Json {
+        let names = |set: &BTreeSet<String>| Json::Array(set.iter().map(Json::string).collect());
+        let mut entries = vec![("classes".to_string(), Json::Array(self.classes.iter().map(|class| Json::object([
+            ("name", Json::string(&class.name)),
+            ("archive", Json::string(self.unit_of(&class.name, Granularity::Archive))),
+            ("module", Json::string(self.unit_of(&class.name, Granularity::Module))),
+            ("api", names(&class.api)),
+            ("implementation", names(&class.implementation)),
+        ])).collect()))];
+        for granularity in [Granularity::Package, Granularity::Module, Granularity::Archive] {
+            let dependencies = self.dependencies(granularity).into_iter().map(|dependency| Json::object([
+                ("from", Json::String(dependency.from)),
+                ("to", Json::String(dependency.to)),
+                ("api", Json::Bool(dependency.api)),
+            ]));
+            entries.push((format!("{}_dependencies", granularity.name()), Json::Array(dependencies.collect())));
+        }
+        Json::Object(entries)
+    }
+}
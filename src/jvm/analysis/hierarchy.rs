@@ -0,0 +1,378 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    jvm::{
+        asm::frames::TypeMerger,
+        reader::{access_flags::AccessFlags, field::FieldInfo, method::MethodInfo, raw_class::RawClass},
+    },
+    util::code_err::{ClassParseError, ResolutionError},
+};
+
+use super::classpath::ClassPath;
+
+const OBJECT: &str = "java/lang/Object";
+/// `ACC_VARARGS` shares its bit with `ACC_TRANSIENT`
+const VARARGS: u16 = 0x0080;
+
+/// A field found by field resolution, along with the class declaring it.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedField<'a> {
+    pub class: &'a str,
+    pub field: &'a FieldInfo,
+}
+
+/// A method found by method resolution or selection, along with the class declaring it.
+#[derive(Debug, Clone, Copy)]
+pub struct ResolvedMethod<'a> {
+    pub class: &'a str,
+    pub method: &'a MethodInfo,
+}
+
+impl<'a> ResolvedMethod<'a> {
+    fn is_same(&self, other: &ResolvedMethod) -> bool {
+        self.class == other.class && std::ptr::eq(self.method, other.method)
+    }
+}
+
+/// Super type relations between the classes of a [ClassPath]. Types that are referenced but
+/// not on the class path are known by name only, so walks up the hierarchy stop at them.
+///
+/// Resolution and selection follow JVMS §5.4.3 and §5.4.6 with a single class loader, so a
+/// runtime package is just a package name. Any other class missing from the class path fails
+/// resolution, except `java/lang/Object` which is then taken to declare nothing.
+#[derive(Debug)]
+pub struct ClassHierarchy<'a> {
+    classpath: &'a ClassPath,
+    /// Super class and direct super interfaces of every class on the class path
+    supers: HashMap<&'a str, (Option<&'a str>, Vec<&'a str>)>,
+    /// Direct subclasses and implementors, also of types off the class path
+    subtypes: HashMap<&'a str, Vec<&'a str>>,
+}
+
+fn package_of(class: &str) -> &str {
+    class.rfind('/').map_or("", |end| &class[..end])
+}
+
+impl<'a> ClassHierarchy<'a> {
+    pub fn new(classpath: &'a ClassPath) -> Result<Self, ClassParseError> {
+        let mut supers = HashMap::new();
+        let mut subtypes: HashMap<&'a str, Vec<&'a str>> = HashMap::new();
+        for (name, class) in classpath.classes() {
+            let super_name = class.class.super_name()?;
+            let interfaces = class.class.interface_names()?;
+            for parent in super_name.iter().chain(interfaces.iter()) {
+                subtypes.entry(*parent).or_default().push(name);
+            }
+            supers.insert(name, (super_name, interfaces));
+        }
+        Ok(Self { classpath, supers, subtypes })
+    }
+    pub fn classpath(&self) -> &'a ClassPath {
+        self.classpath
+    }
+    /// The class named `name` along with its name borrowed from the class path.
+    fn class(&self, name: &str) -> Result<(&'a str, &'a RawClass), ResolutionError> {
+        match (self.supers.get_key_value(name), self.classpath.get(name)) {
+            (Some((name, _)), Some(class)) => Ok((name, &class.class)),
+            _ => Err(ResolutionError::NoClassDefFound(name.to_string())),
+        }
+    }
+    /// Like [Self::class], but a missing `java/lang/Object` is `None` instead of an error.
+    fn super_class(&self, name: &str) -> Result<Option<(&'a str, &'a RawClass)>, ResolutionError> {
+        match self.class(name) {
+            Ok(found) => Ok(Some(found)),
+            Err(_) if name == OBJECT => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+    pub fn super_name(&self, name: &str) -> Option<&'a str> {
+        self.supers.get(name).and_then(|(super_name, _)| *super_name)
+    }
+    pub fn interfaces(&self, name: &str) -> &[&'a str] {
+        self.supers.get(name).map_or(&[], |(_, interfaces)| interfaces.as_slice())
+    }
+    pub fn direct_subtypes(&self, name: &str) -> &[&'a str] {
+        self.subtypes.get(name).map_or(&[], |subtypes| subtypes.as_slice())
+    }
+    /// Every class on the class path that extends or implements `name`, directly or not, and
+    /// `name` itself if it is on the class path.
+    pub fn subtypes(&self, name: &str) -> BTreeSet<&'a str> {
+        let mut found = BTreeSet::new();
+        let mut pending = self.direct_subtypes(name).to_vec();
+        pending.extend(self.supers.get_key_value(name).map(|(key, _)| *key));
+        while let Some(name) = pending.pop() {
+            if found.insert(name) {
+                pending.extend_from_slice(self.direct_subtypes(name));
+            }
+        }
+        found
+    }
+    pub fn is_interface(&self, name: &str) -> bool {
+        self.classpath.get(name).is_some_and(|class| class.class.has_flag(AccessFlags::Interface))
+    }
+    pub fn is_abstract(&self, name: &str) -> bool {
+        self.classpath.get(name).is_some_and(|class| class.class.has_flag(AccessFlags::Abstract))
+    }
+    /// Whether `sub` is `sup` or one of its subtypes, as far as the class path tells.
+    pub fn is_subtype(&self, sub: &str, sup: &str) -> bool {
+        if sub == sup || sup == OBJECT {
+            return true;
+        }
+        let mut pending = vec![sub];
+        let mut seen = BTreeSet::new();
+        while let Some(name) = pending.pop() {
+            if name == sup {
+                return true;
+            }
+            if seen.insert(name) {
+                pending.extend(self.super_name(name));
+                pending.extend_from_slice(self.interfaces(name));
+            }
+        }
+        false
+    }
+    /// `name` followed by its super classes, up to `java/lang/Object` or the first class that
+    /// is not on the class path.
+    pub fn superclasses<'b>(&self, name: &'b str) -> Vec<&'b str>
+    where 'a: 'b {
+        let mut chain = vec![name];
+        while let Some(super_name) = self.super_name(chain.last().unwrap()) {
+            if chain.contains(&super_name) {
+                break;
+            }
+            chain.push(super_name);
+        }
+        chain
+    }
+    /// The most specific class both `a` and `b` extend, as the verifier computes it when two
+    /// references merge: interfaces count as `java/lang/Object`, and so do classes whose
+    /// hierarchy leaves the class path before the two meet.
+    pub fn least_common_superclass(&self, a: &str, b: &str) -> String {
+        if self.is_interface(a) || self.is_interface(b) {
+            return OBJECT.to_string();
+        }
+        let ancestors = self.superclasses(a);
+        self.superclasses(b).into_iter()
+            .find(|class| ancestors.contains(class))
+            .unwrap_or(OBJECT)
+            .to_string()
+    }
+    /// Every interface `name` implements or extends, directly or through its super classes
+    /// and super interfaces, in depth first order.
+    fn superinterfaces(&self, name: &str) -> Result<Vec<&'a str>, ResolutionError> {
+        let mut found = Vec::new();
+        let mut pending: Vec<&'a str> = Vec::new();
+        for class in self.superclasses(name) {
+            self.super_class(class)?;
+            pending.extend(self.interfaces(class).iter().rev());
+            while let Some(interface) = pending.pop() {
+                self.class(interface)?;
+                if !found.contains(&interface) {
+                    found.push(interface);
+                    pending.extend(self.interfaces(interface).iter().rev());
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    /// Field resolution (JVMS §5.4.3.2): the class itself, then its super interfaces, then
+    /// its super class.
+    pub fn resolve_field(&self, class: &str, name: &str, descriptor: &str) -> Result<ResolvedField<'a>, ResolutionError> {
+        self.lookup_field(class, name, descriptor)?.ok_or_else(|| ResolutionError::NoSuchField {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        })
+    }
+    fn lookup_field(&self, class: &str, name: &str, descriptor: &str) -> Result<Option<ResolvedField<'a>>, ResolutionError> {
+        let Some((class, raw)) = self.super_class(class)? else { return Ok(None) };
+        if let Some(field) = raw.find_field(name, descriptor) {
+            return Ok(Some(ResolvedField { class, field }));
+        }
+        for interface in self.interfaces(class) {
+            if let Some(found) = self.lookup_field(interface, name, descriptor)? {
+                return Ok(Some(found));
+            }
+        }
+        match self.super_name(class) {
+            Some(super_name) => self.lookup_field(super_name, name, descriptor),
+            None => Ok(None),
+        }
+    }
+
+    /// A signature polymorphic method of `MethodHandle` or `VarHandle` named `name` (JVMS §2.9.3),
+    /// which matches any descriptor.
+    fn signature_polymorphic(&self, class: &'a str, raw: &'a RawClass, name: &str) -> Option<ResolvedMethod<'a>> {
+        if class != "java/lang/invoke/MethodHandle" && class != "java/lang/invoke/VarHandle" {
+            return None;
+        }
+        let mut named = raw.methods.0.iter().filter(|method| method.name(&raw.cp).is_ok_and(|n| n == name));
+        let method = named.next()?;
+        let polymorphic = named.next().is_none()
+            && method.descriptor(&raw.cp).is_ok_and(|d| d == "([Ljava/lang/Object;)Ljava/lang/Object;")
+            && method.access_flags & (VARARGS | AccessFlags::Native as u16) == VARARGS | AccessFlags::Native as u16;
+        polymorphic.then_some(ResolvedMethod { class, method })
+    }
+    /// Method resolution for a `Methodref` (JVMS §5.4.3.3).
+    pub fn resolve_method(&self, class: &str, name: &str, descriptor: &str) -> Result<ResolvedMethod<'a>, ResolutionError> {
+        let (class, raw) = self.class(class)?;
+        if raw.has_flag(AccessFlags::Interface) {
+            return Err(ResolutionError::IncompatibleClassChange(format!("found interface {}, but class was expected", class)));
+        }
+        for current in self.superclasses(class) {
+            let Some((current, raw)) = self.super_class(current)? else { continue };
+            if let Some(found) = self.signature_polymorphic(current, raw, name) {
+                return Ok(found);
+            }
+            if let Some(method) = raw.find_method(name, descriptor) {
+                return Ok(ResolvedMethod { class: current, method });
+            }
+        }
+        self.superinterface_method(class, name, descriptor)?.ok_or_else(|| ResolutionError::NoSuchMethod {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        })
+    }
+    /// Interface method resolution for an `InterfaceMethodref` (JVMS §5.4.3.4).
+    pub fn resolve_interface_method(&self, class: &str, name: &str, descriptor: &str) -> Result<ResolvedMethod<'a>, ResolutionError> {
+        let (class, raw) = self.class(class)?;
+        if !raw.has_flag(AccessFlags::Interface) {
+            return Err(ResolutionError::IncompatibleClassChange(format!("found class {}, but interface was expected", class)));
+        }
+        if let Some(method) = raw.find_method(name, descriptor) {
+            return Ok(ResolvedMethod { class, method });
+        }
+        if let Some((object, raw)) = self.super_class(OBJECT)? {
+            if let Some(method) = raw.find_method(name, descriptor) {
+                if method.has_flag(AccessFlags::Public) && !method.has_flag(AccessFlags::Static) {
+                    return Ok(ResolvedMethod { class: object, method });
+                }
+            }
+        }
+        self.superinterface_method(class, name, descriptor)?.ok_or_else(|| ResolutionError::NoSuchMethod {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        })
+    }
+    /// Resolves a method reference as `Methodref` or `InterfaceMethodref` depending on what
+    /// `class` turns out to be.
+    pub fn resolve_any_method(&self, class: &str, name: &str, descriptor: &str) -> Result<ResolvedMethod<'a>, ResolutionError> {
+        if self.is_interface(class) {
+            self.resolve_interface_method(class, name, descriptor)
+        } else {
+            self.resolve_method(class, name, descriptor)
+        }
+    }
+
+    /// Instance methods named `name` with `descriptor` declared by the super interfaces of `class`.
+    fn superinterface_candidates(&self, class: &str, name: &str, descriptor: &str) -> Result<Vec<ResolvedMethod<'a>>, ResolutionError> {
+        let mut candidates = Vec::new();
+        for interface in self.superinterfaces(class)? {
+            let (_, raw) = self.class(interface)?;
+            if let Some(method) = raw.find_method(name, descriptor) {
+                if !method.has_flag(AccessFlags::Private) && !method.has_flag(AccessFlags::Static) {
+                    candidates.push(ResolvedMethod { class: interface, method });
+                }
+            }
+        }
+        Ok(candidates)
+    }
+    /// The maximally-specific superinterface methods of `class` (JVMS §5.4.3.3): those not
+    /// redeclared by a more specific super interface of `class`.
+    pub fn maximally_specific_methods(&self, class: &str, name: &str, descriptor: &str) -> Result<Vec<ResolvedMethod<'a>>, ResolutionError> {
+        let candidates = self.superinterface_candidates(class, name, descriptor)?;
+        Ok(candidates.iter()
+            .filter(|method| !candidates.iter().any(|other| other.class != method.class && self.is_subtype(other.class, method.class)))
+            .copied()
+            .collect())
+    }
+    /// The last step of method resolution: the only non-abstract maximally-specific method,
+    /// or else any superinterface method.
+    fn superinterface_method(&self, class: &str, name: &str, descriptor: &str) -> Result<Option<ResolvedMethod<'a>>, ResolutionError> {
+        let maximal = self.maximally_specific_methods(class, name, descriptor)?;
+        let mut concrete = maximal.iter().filter(|method| !method.method.has_flag(AccessFlags::Abstract));
+        if let (Some(method), None) = (concrete.next(), concrete.next()) {
+            return Ok(Some(*method));
+        }
+        Ok(self.superinterface_candidates(class, name, descriptor)?.into_iter().next())
+    }
+
+    /// Whether `sub`, declared in `sub_class`, overrides `sup` declared in `sup_class` without
+    /// help from a method in between (JVMS §5.4.5).
+    fn overrides_directly(&self, sub_class: &str, sub: &MethodInfo, sup_class: &str, sup: &MethodInfo) -> bool {
+        if sub.has_flag(AccessFlags::Private) || sub.has_flag(AccessFlags::Static) {
+            return false;
+        }
+        sup.has_flag(AccessFlags::Public) || sup.has_flag(AccessFlags::Protected)
+            || (!sup.has_flag(AccessFlags::Private) && package_of(sub_class) == package_of(sup_class))
+    }
+    /// Method selection (JVMS §5.4.6): the method that runs when `resolved` is invoked on an
+    /// instance of `class`.
+    pub fn select(&self, resolved: ResolvedMethod<'a>, class: &str) -> Result<ResolvedMethod<'a>, ResolutionError> {
+        if resolved.method.has_flag(AccessFlags::Private) {
+            return Ok(resolved);
+        }
+        let raw = &self.class(resolved.class)?.1;
+        let (name, descriptor) = (resolved.method.name(&raw.cp).unwrap_or(""), resolved.method.descriptor(&raw.cp).unwrap_or(""));
+        let not_found = || ResolutionError::AbstractMethod {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        };
+        let mut declarations = Vec::new();
+        for current in self.superclasses(class) {
+            let Some((current, raw)) = self.super_class(current)? else { continue };
+            if let Some(method) = raw.find_method(name, descriptor).filter(|m| !m.has_flag(AccessFlags::Static)) {
+                declarations.push(ResolvedMethod { class: current, method });
+            }
+            if current == resolved.class {
+                break;
+            }
+        }
+        // a method overrides the resolved one directly or through an overriding method in
+        // between, so overriders are found starting from the top
+        let mut overriders = vec![resolved];
+        for declaration in declarations.iter().rev() {
+            if declaration.is_same(&resolved)
+                || overriders.iter().any(|o| self.overrides_directly(declaration.class, declaration.method, o.class, o.method)) {
+                overriders.push(*declaration);
+            }
+        }
+        if let Some(selected) = declarations.iter().find(|d| overriders.iter().any(|o| o.is_same(d))) {
+            return match selected.method.has_flag(AccessFlags::Abstract) {
+                true => Err(not_found()),
+                false => Ok(*selected),
+            };
+        }
+        let maximal = self.maximally_specific_methods(class, name, descriptor)?;
+        let concrete: Vec<_> = maximal.iter().filter(|method| !method.method.has_flag(AccessFlags::Abstract)).collect();
+        match concrete.as_slice() {
+            [method] => Ok(**method),
+            [] => Err(not_found()),
+            _ => Err(ResolutionError::IncompatibleClassChange(format!(
+                "conflicting default methods for {}{} in {}: {}",
+                name, descriptor, class, concrete.iter().map(|m| m.class).collect::<Vec<_>>().join(", "),
+            ))),
+        }
+    }
+    /// The class declaring the implementation a virtual call to `name` with `descriptor` on an
+    /// instance of `class` runs. `None` when the implementation is abstract or can't be found on
+    /// the class path.
+    pub fn select_method(&self, class: &str, name: &str, descriptor: &str) -> Option<&'a str> {
+        let resolved = self.resolve_method(class, name, descriptor).ok()?;
+        self.select(resolved, class).ok().map(|method| method.class)
+    }
+    /// The class declaring the method a symbolic reference to `class` names.
+    pub fn find_declaration(&self, class: &str, name: &str, descriptor: &str) -> Option<&'a str> {
+        self.resolve_any_method(class, name, descriptor).ok().map(|method| method.class)
+    }
+}
+
+impl<'a> TypeMerger for ClassHierarchy<'a> {
+    fn common_superclass(&self, a: &str, b: &str) -> String {
+        self.least_common_superclass(a, b)
+    }
+}
@@ -0,0 +1,4 @@
+pub mod classpath;
+pub mod hierarchy;
+pub mod callgraph;
+pub mod dependencies;
@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+
+use crate::{
+    io::BufferWritable,
+    jvm::reader::{
+        access_flags::AccessFlags,
+        attribute::{AttributeInfo, Attributes},
+        bootstrap::{BootstrapMethod, BootstrapMethods},
+        classfile::{ClassFile, ClassFileMetadata},
+        code::{block::CodeBlock, exception_table::{ExceptionTable, ExceptionTableEntry}, instruction::{switch_padding, Instruction, LookupSwitch, TableSwitch}, opcode::OpcodeInfo},
+        descriptor::{FieldType, MethodDescriptor},
+        field::{FieldInfo, Fields},
+        interface::Interfaces,
+        method::{MethodInfo, Methods},
+        method_handle_kind::MethodHandleKind,
+        raw_class::RawClass,
+        stack_map::{StackMapTable, VerificationType},
+    },
+    util::code_err::AssemblyError,
+};
+
+use super::{
+    ast::{AsmClass, AsmConstant, AsmField, AsmHandle, AsmInstruction, AsmMemberRef, AsmMethod, AsmOperand, AsmStatement},
+    frames::{self, FrameAnalysis, Handler, MethodContext, ObjectMerger, TypeMerger, VType},
+    pool::ConstantPoolBuilder,
+};
+
+/// Turns a parsed [AsmClass] into a [ClassFile], building the constant pool on the way.
+pub struct Assembler<'a> {
+    pool: ConstantPoolBuilder,
+    bootstrap_methods: BootstrapMethods,
+    merger: &'a dyn TypeMerger,
+}
+
+/// An instruction whose size is known, but whose branch offsets are not resolved yet.
+enum Pending {
+    Fixed(Vec<u8>),
+    Branch { opcode: u8, label: String },
+    TableSwitch { low: i32, targets: Vec<String>, default: String },
+    LookupSwitch { pairs: Vec<(i32, String)>, default: String },
+}
+
+impl Pending {
+    fn size(&self, pc: u32) -> u32 {
+        match self {
+            Pending::Fixed(bytes) => bytes.len() as u32,
+            Pending::Branch { opcode, .. } => OpcodeInfo::get(*opcode).and_then(OpcodeInfo::length).unwrap_or(3),
+            Pending::TableSwitch { targets, .. } => 1 + switch_padding(pc) + 12 + 4 * targets.len() as u32,
+            Pending::LookupSwitch { pairs, .. } => 1 + switch_padding(pc) + 8 + 8 * pairs.len() as u32,
+        }
+    }
+}
+
+impl<'a> Default for Assembler<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Assembler<'a> {
+    pub fn new() -> Self {
+        Self::with_merger(&ObjectMerger)
+    }
+    /// Uses `merger` to find the common superclass of references when computing stack map frames.
+    pub fn with_merger(merger: &'a dyn TypeMerger) -> Self {
+        Self {
+            pool: ConstantPoolBuilder::new(),
+            bootstrap_methods: BootstrapMethods::default(),
+            merger,
+        }
+    }
+
+    pub fn assemble(mut self, class: &AsmClass) -> Result<ClassFile, AssemblyError> {
+        let this_class = self.pool.class(&class.name)?;
+        let super_class = match (&class.super_name, class.name.as_str()) {
+            (Some(name), _) => self.pool.class(name)?,
+            (None, "java/lang/Object") => 0,
+            (None, _) => self.pool.class("java/lang/Object")?,
+        };
+        let mut interfaces = Vec::new();
+        for interface in &class.interfaces {
+            interfaces.push(self.pool.class(interface)?);
+        }
+        let mut fields = Vec::new();
+        for field in &class.fields {
+            fields.push(self.assemble_field(field)?);
+        }
+        let mut methods = Vec::new();
+        for method in &class.methods {
+            methods.push(self.assemble_method(class, method)?);
+        }
+        let mut attributes = Vec::new();
+        if let Some(source) = &class.source {
+            let name = self.pool.utf8("SourceFile")?;
+            let source = self.pool.utf8(source)?;
+            attributes.push(AttributeInfo::new(name, source.to_be_bytes().to_vec()));
+        }
+        if !self.bootstrap_methods.0.is_empty() {
+            let name = self.pool.utf8("BootstrapMethods")?;
+            let mut info = Vec::new();
+            self.bootstrap_methods.write(&mut info)?;
+            attributes.push(AttributeInfo::new(name, info));
+        }
+        Ok(ClassFile {
+            path: String::new(),
+            classpath: String::new(),
+            metadata: ClassFileMetadata::with_version(class.version.0, class.version.1),
+            class: RawClass {
+                access_flags: class.access_flags,
+                this_class,
+                super_class,
+                cp: self.pool.finish(),
+                interfaces: Interfaces(interfaces),
+                fields: Fields(fields),
+                methods: Methods(methods),
+                attributes: Attributes(attributes),
+            },
+        })
+    }
+
+    fn assemble_field(&mut self, field: &AsmField) -> Result<FieldInfo, AssemblyError> {
+        let ty = FieldType::parse(&field.descriptor).map_err(|_| syntax(field.line, format!("bad field descriptor `{}`", field.descriptor)))?;
+        let mut attributes = Vec::new();
+        if let Some(value) = &field.value {
+            let mismatch = || syntax(field.line, format!("{:?} is not a valid value for a field of type `{}`", value, field.descriptor));
+            let index = match (&ty, value) {
+                (FieldType::Int | FieldType::Short | FieldType::Char | FieldType::Byte | FieldType::Boolean, AsmConstant::Int(v)) => self.pool.integer(*v)?,
+                (FieldType::Long, AsmConstant::Int(v)) => self.pool.long(*v as i64)?,
+                (FieldType::Long, AsmConstant::Long(v)) => self.pool.long(*v)?,
+                (FieldType::Float, AsmConstant::Int(v)) => self.pool.float(*v as f32)?,
+                (FieldType::Float, AsmConstant::Float(v)) => self.pool.float(*v)?,
+                (FieldType::Float, AsmConstant::Double(v)) => self.pool.float(*v as f32)?,
+                (FieldType::Double, AsmConstant::Int(v)) => self.pool.double(*v as f64)?,
+                (FieldType::Double, AsmConstant::Float(v)) => self.pool.double(*v as f64)?,
+                (FieldType::Double, AsmConstant::Double(v)) => self.pool.double(*v)?,
+                (FieldType::Object(class), AsmConstant::String(v)) if class == "java/lang/String" => self.pool.string(v)?,
+                _ => return Err(mismatch()),
+            };
+            let name = self.pool.utf8("ConstantValue")?;
+            attributes.push(AttributeInfo::new(name, index.to_be_bytes().to_vec()));
+        }
+        Ok(FieldInfo {
+            access_flags: field.access_flags,
+            name_index: self.pool.utf8(&field.name)?,
+            descriptor_index: self.pool.utf8(&field.descriptor)?,
+            attributes: Attributes(attributes),
+        })
+    }
+
+    fn assemble_method(&mut self, class: &AsmClass, method: &AsmMethod) -> Result<MethodInfo, AssemblyError> {
+        let descriptor = MethodDescriptor::parse(&method.descriptor)
+            .map_err(|_| syntax(method.line, format!("bad method descriptor `{}`", method.descriptor)))?;
+        let name_index = self.pool.utf8(&method.name)?;
+        let descriptor_index = self.pool.utf8(&method.descriptor)?;
+        let mut attributes = Vec::new();
+        let has_code = method.access_flags & (AccessFlags::Abstract as u16 | AccessFlags::Native as u16) == 0;
+        let mut code = None;
+        if has_code {
+            let block = self.assemble_code(class, method, &descriptor)?;
+            let mut info = Vec::new();
+            block.write(&mut info)?;
+            attributes.push(AttributeInfo::new(self.pool.utf8("Code")?, info));
+            code = Some(block);
+        } else if method.body.iter().any(|s| matches!(s, AsmStatement::Instruction(_))) {
+            return Err(syntax(method.line, format!("abstract or native method `{}` can not have code", method.name)));
+        }
+        if !method.throws.is_empty() {
+            let mut info = Vec::new();
+            info.write_u2(method.throws.len() as u16)?;
+            for exception in &method.throws {
+                info.write_u2(self.pool.class(exception)?)?;
+            }
+            attributes.push(AttributeInfo::new(self.pool.utf8("Exceptions")?, info));
+        }
+        Ok(MethodInfo {
+            access_flags: method.access_flags,
+            name_index,
+            descriptor_index,
+            attributes: Attributes(attributes),
+            code,
+        })
+    }
+
+    fn assemble_code(&mut self, class: &AsmClass, method: &AsmMethod, descriptor: &MethodDescriptor) -> Result<CodeBlock, AssemblyError> {
+        // lay the code out, the size of every instruction is known once its constants are in the pool
+        let mut labels: HashMap<&str, u32> = HashMap::new();
+        let mut pending = Vec::new();
+        let mut line_numbers = Vec::new();
+        let mut pc = 0;
+        for statement in &method.body {
+            match statement {
+                AsmStatement::Label { name, line } => {
+                    if labels.insert(name, pc).is_some() {
+                        return Err(AssemblyError::DuplicateLabel { line: *line, label: name.clone() });
+                    }
+                },
+                AsmStatement::Instruction(instruction) => {
+                    let item = self.prepare(instruction)?;
+                    let size = item.size(pc);
+                    pending.push((pc, instruction.line, item));
+                    pc += size;
+                },
+                AsmStatement::Line { number, .. } => line_numbers.push((pc, *number)),
+                AsmStatement::Catch { .. } => {},
+            }
+        }
+        if pc == 0 {
+            return Err(syntax(method.line, format!("method `{}` has no code", method.name)));
+        }
+        if pc > u16::MAX as u32 {
+            return Err(syntax(method.line, format!("method `{}` is {} bytes long, the limit is {}", method.name, pc, u16::MAX)));
+        }
+        let resolve = |label: &str, line: usize| -> Result<u32, AssemblyError> {
+            labels.get(label).copied().ok_or_else(|| AssemblyError::UndefinedLabel { line, label: label.to_string() })
+        };
+
+        let mut bytes = Vec::new();
+        let mut lines = Vec::new();
+        for (pc, line, item) in &pending {
+            let offset = |label: &str| -> Result<i32, AssemblyError> { Ok(resolve(label, *line)? as i32 - *pc as i32) };
+            let instruction = match item {
+                Pending::Fixed(encoded) => {
+                    bytes.extend_from_slice(encoded);
+                    lines.push(*line);
+                    continue;
+                },
+                Pending::Branch { opcode, label } => {
+                    let offset = offset(label)?;
+                    if *opcode == 200 || *opcode == 201 {
+                        if *opcode == 200 { Instruction::GotoW(offset) } else { Instruction::JsrW(offset) }
+                    } else {
+                        let offset = i16::try_from(offset)
+                            .map_err(|_| syntax(*line, format!("branch to `{}` is too far, use goto_w", label)))?;
+                        let mut encoded = vec![*opcode];
+                        encoded.write_u2(offset as u16)?;
+                        CodeBlock::decode(&encoded)?.remove(0)
+                    }
+                },
+                Pending::TableSwitch { low, targets, default } => {
+                    let mut offsets = Vec::new();
+                    for target in targets {
+                        offsets.push(offset(target)?);
+                    }
+                    let high = *low + offsets.len() as i32 - 1;
+                    Instruction::Tableswitch(TableSwitch::new(offset(default)?, *low, high, offsets)?)
+                },
+                Pending::LookupSwitch { pairs, default } => {
+                    let mut matches = Vec::new();
+                    for (key, target) in pairs {
+                        matches.push((*key, offset(target)?));
+                    }
+                    matches.sort_by_key(|(key, _)| *key);
+                    if matches.windows(2).any(|w| w[0].0 == w[1].0) {
+                        return Err(syntax(*line, "`lookupswitch` has duplicate keys".to_string()));
+                    }
+                    Instruction::Lookupswitch(LookupSwitch::new(offset(default)?, matches)?)
+                },
+            };
+            instruction.write(&mut bytes, *pc)?;
+            lines.push(*line);
+        }
+        let code = CodeBlock::decode(&bytes)?;
+
+        let mut exception_table = Vec::new();
+        let mut handlers = Vec::new();
+        for statement in &method.body {
+            if let AsmStatement::Catch { catch_type, from, to, using, line } = statement {
+                let (start_pc, end_pc, handler_pc) = (resolve(from, *line)?, resolve(to, *line)?, resolve(using, *line)?);
+                if start_pc >= end_pc {
+                    return Err(syntax(*line, format!("`.catch` range from `{}` to `{}` is empty", from, to)));
+                }
+                let catch_index = match catch_type {
+                    Some(name) => self.pool.class(name)?,
+                    None => 0,
+                };
+                exception_table.push(ExceptionTableEntry { start_pc: start_pc as u16, end_pc: end_pc as u16, handler_pc: handler_pc as u16, catch_type: catch_index });
+                handlers.push(Handler { start: start_pc, end: end_pc, handler: handler_pc, catch_type: catch_type.clone() });
+            }
+        }
+
+        let with_pcs: Vec<(u32, Instruction)> = pending.iter().map(|(pc, _, _)| *pc).zip(code.iter().cloned()).collect();
+        let ctx = MethodContext {
+            class_name: &class.name,
+            method_name: &method.name,
+            descriptor,
+            is_static: method.access_flags & AccessFlags::Static as u16 != 0,
+        };
+        let need_frames = class.version.0 >= 50;
+        let analysis = frames::analyze(&ctx, &with_pcs, &handlers, self.pool.pool(), self.merger, need_frames)
+            .map_err(|err| AssemblyError::Frames { line: lines[err.index], message: err.message })?;
+
+        let mut attributes = Vec::new();
+        if need_frames && !analysis.frames.is_empty() {
+            let table = self.stack_map_table(&analysis)?;
+            let mut info = Vec::new();
+            table.write(&mut info)?;
+            attributes.push(AttributeInfo::new(self.pool.utf8("StackMapTable")?, info));
+        }
+        if !line_numbers.is_empty() {
+            let mut info = Vec::new();
+            info.write_u2(line_numbers.len() as u16)?;
+            for (start_pc, number) in &line_numbers {
+                info.write_u2(*start_pc as u16)?;
+                info.write_u2(*number)?;
+            }
+            attributes.push(AttributeInfo::new(self.pool.utf8("LineNumberTable")?, info));
+        }
+
+        Ok(CodeBlock {
+            max_stack: method.max_stack.unwrap_or(analysis.max_stack),
+            max_locals: method.max_locals.unwrap_or(analysis.max_locals),
+            code,
+            exception_table: ExceptionTable(exception_table),
+            attributes: Attributes(attributes),
+        })
+    }
+
+    fn stack_map_table(&mut self, analysis: &FrameAnalysis) -> Result<StackMapTable, AssemblyError> {
+        let initial = self.verification_types(&analysis.initial_locals)?;
+        let mut frames = Vec::new();
+        for frame in &analysis.frames {
+            frames.push((frame.pc as u16, self.verification_types(&frame.locals)?, self.verification_types(&frame.stack)?));
+        }
+        Ok(StackMapTable::compress(&initial, &frames))
+    }
+
+    fn verification_types(&mut self, types: &[VType]) -> Result<Vec<VerificationType>, AssemblyError> {
+        let mut out = Vec::new();
+        for ty in types {
+            out.push(match ty {
+                VType::Top => VerificationType::Top,
+                VType::Integer => VerificationType::Integer,
+                VType::Float => VerificationType::Float,
+                VType::Long => VerificationType::Long,
+                VType::Double => VerificationType::Double,
+                VType::Null => VerificationType::Null,
+                VType::UninitializedThis => VerificationType::UninitializedThis,
+                VType::Object(name) => VerificationType::Object(self.pool.class(name)?),
+                VType::Uninitialized(pc) => VerificationType::Uninitialized(*pc as u16),
+            });
+        }
+        Ok(out)
+    }
+
+    /// Resolves the operands of an instruction into constant pool indices and encodes it
+    /// as far as possible.
+    fn prepare(&mut self, instruction: &AsmInstruction) -> Result<Pending, AssemblyError> {
+        let opcode = instruction.opcode;
+        let line = instruction.line;
+        let mut bytes = vec![opcode];
+        match &instruction.operand {
+            AsmOperand::None => {},
+            AsmOperand::Int(value) if opcode == 16 => bytes.push(*value as u8),
+            AsmOperand::Int(value) => bytes.write_u2(*value as u16)?,
+            AsmOperand::Local(index) => {
+                if instruction.wide || *index > u8::MAX as u16 {
+                    bytes = vec![196, opcode];
+                    bytes.write_u2(*index)?;
+                } else {
+                    bytes.push(*index as u8);
+                }
+            },
+            AsmOperand::Iinc(index, increment) => {
+                if instruction.wide || *index > u8::MAX as u16 || i8::try_from(*increment).is_err() {
+                    bytes = vec![196, opcode];
+                    bytes.write_u2(*index)?;
+                    bytes.write_u2(*increment as u16)?;
+                } else {
+                    bytes.push(*index as u8);
+                    bytes.push(*increment as u8);
+                }
+            },
+            AsmOperand::Label(label) => return Ok(Pending::Branch { opcode, label: label.clone() }),
+            AsmOperand::TableSwitch { low, targets, default } => {
+                if *low as i64 + targets.len() as i64 - 1 > i32::MAX as i64 {
+                    return Err(syntax(line, "`tableswitch` keys overflow an int".to_string()));
+                }
+                return Ok(Pending::TableSwitch { low: *low, targets: targets.clone(), default: default.clone() });
+            },
+            AsmOperand::LookupSwitch { pairs, default } => return Ok(Pending::LookupSwitch { pairs: pairs.clone(), default: default.clone() }),
+            AsmOperand::Constant(constant) => {
+                let index = self.constant(constant)?;
+                if opcode == 18 && index > u8::MAX as u16 {
+                    // the constant pool outgrew `ldc`
+                    bytes = vec![19];
+                    bytes.write_u2(index)?;
+                } else if opcode == 18 {
+                    bytes.push(index as u8);
+                } else {
+                    bytes.write_u2(index)?;
+                }
+            },
+            AsmOperand::Class(name) => bytes.write_u2(self.pool.class(name)?)?,
+            AsmOperand::Field(field) => bytes.write_u2(self.member(field, false)?)?,
+            AsmOperand::Method(method) => bytes.write_u2(self.member(method, true)?)?,
+            AsmOperand::InvokeInterface(method, count) => {
+                let descriptor = MethodDescriptor::parse(&method.descriptor)
+                    .map_err(|_| syntax(line, format!("bad method descriptor `{}`", method.descriptor)))?;
+                let count = count.unwrap_or(descriptor.param_slots() as u8 + 1);
+                bytes.write_u2(self.member(method, true)?)?;
+                bytes.push(count);
+                bytes.push(0);
+            },
+            AsmOperand::InvokeDynamic { name, descriptor, bootstrap, args } => {
+                MethodDescriptor::parse(descriptor).map_err(|_| syntax(line, format!("bad method descriptor `{}`", descriptor)))?;
+                let bootstrap_index = self.bootstrap_method(bootstrap, args)?;
+                bytes.write_u2(self.pool.invoke_dynamic(bootstrap_index, name, descriptor)?)?;
+                bytes.write_u2(0)?;
+            },
+            AsmOperand::ArrayType(atype) => bytes.push(atype.to_code()),
+            AsmOperand::MultiANewArray(class, dimensions) => {
+                if *dimensions == 0 || class.chars().take_while(|c| *c == '[').count() < *dimensions as usize {
+                    return Err(syntax(line, format!("`{}` has fewer than {} dimensions", class, dimensions)));
+                }
+                bytes.write_u2(self.pool.class(class)?)?;
+                bytes.push(*dimensions);
+            },
+        }
+        Ok(Pending::Fixed(bytes))
+    }
+
+    fn member(&mut self, member: &AsmMemberRef, method: bool) -> Result<u16, AssemblyError> {
+        Ok(if !method {
+            self.pool.field_ref(&member.owner, &member.name, &member.descriptor)?
+        } else if member.interface {
+            self.pool.interface_method_ref(&member.owner, &member.name, &member.descriptor)?
+        } else {
+            self.pool.method_ref(&member.owner, &member.name, &member.descriptor)?
+        })
+    }
+
+    fn handle(&mut self, handle: &AsmHandle) -> Result<u16, AssemblyError> {
+        let is_field = matches!(handle.kind, MethodHandleKind::GetField | MethodHandleKind::GetStatic | MethodHandleKind::PutField | MethodHandleKind::PutStatic);
+        let reference = self.member(&handle.member, !is_field)?;
+        Ok(self.pool.method_handle(handle.kind, reference)?)
+    }
+
+    fn bootstrap_method(&mut self, bootstrap: &AsmHandle, args: &[AsmConstant]) -> Result<u16, AssemblyError> {
+        let handle = self.handle(bootstrap)?;
+        let mut arg_indices = Vec::new();
+        for arg in args {
+            arg_indices.push(self.constant(arg)?);
+        }
+        let entry = BootstrapMethod { method_ref: handle, arguments: arg_indices };
+        let index = match self.bootstrap_methods.0.iter().position(|e| *e == entry) {
+            Some(index) => index,
+            None => {
+                self.bootstrap_methods.0.push(entry);
+                self.bootstrap_methods.0.len() - 1
+            },
+        };
+        Ok(index as u16)
+    }
+
+    fn constant(&mut self, constant: &AsmConstant) -> Result<u16, AssemblyError> {
+        Ok(match constant {
+            AsmConstant::Int(value) => self.pool.integer(*value)?,
+            AsmConstant::Long(value) => self.pool.long(*value)?,
+            AsmConstant::Float(value) => self.pool.float(*value)?,
+            AsmConstant::Double(value) => self.pool.double(*value)?,
+            AsmConstant::String(value) => self.pool.string(value)?,
+            AsmConstant::Class(name) => self.pool.class(name)?,
+            AsmConstant::MethodType(descriptor) => self.pool.method_type(descriptor)?,
+            AsmConstant::MethodHandle(handle) => self.handle(handle)?,
+        })
+    }
+}
+
+fn syntax(line: usize, message: String) -> AssemblyError {
+    AssemblyError::Syntax { line, column: 1, message }
+}
@@ -0,0 +1,126 @@
+use crate::jvm::reader::{code::instruction::ArrayType, method_handle_kind::MethodHandleKind};
+
+/// A whole `.class` or `.interface` unit, as written in the source.
+#[derive(Debug, Clone)]
+pub struct AsmClass {
+    pub version: (u16, u16), // (major, minor)
+    pub source: Option<String>,
+    pub access_flags: u16,
+    pub name: String,
+    pub super_name: Option<String>,
+    pub interfaces: Vec<String>,
+    pub fields: Vec<AsmField>,
+    pub methods: Vec<AsmMethod>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmField {
+    pub access_flags: u16,
+    pub name: String,
+    pub descriptor: String,
+    pub value: Option<AsmConstant>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmMethod {
+    pub access_flags: u16,
+    pub name: String,
+    pub descriptor: String,
+    pub max_stack: Option<u16>,
+    pub max_locals: Option<u16>,
+    pub throws: Vec<String>,
+    pub body: Vec<AsmStatement>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum AsmStatement {
+    Label {
+        name: String,
+        line: usize,
+    },
+    Instruction(AsmInstruction),
+    /// `.catch <class|all> from <label> to <label> using <label>`
+    Catch {
+        catch_type: Option<String>,
+        from: String,
+        to: String,
+        using: String,
+        line: usize,
+    },
+    /// `.line <n>`, maps the following instructions to line `number` of the original source
+    Line {
+        number: u16,
+        line: usize,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmInstruction {
+    pub opcode: u8,
+    pub operand: AsmOperand,
+    /// Set by an explicit `wide` prefix, local and `iinc` operands are widened automatically otherwise
+    pub wide: bool,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum AsmOperand {
+    None,
+    Int(i32),
+    Local(u16),
+    Iinc(u16, i16),
+    Label(String),
+    Constant(AsmConstant),
+    Class(String),
+    Field(AsmMemberRef),
+    Method(AsmMemberRef),
+    InvokeInterface(AsmMemberRef, Option<u8>),
+    InvokeDynamic {
+        name: String,
+        descriptor: String,
+        bootstrap: AsmHandle,
+        args: Vec<AsmConstant>,
+    },
+    ArrayType(ArrayType),
+    MultiANewArray(String, u8),
+    TableSwitch {
+        low: i32,
+        targets: Vec<String>,
+        default: String,
+    },
+    LookupSwitch {
+        pairs: Vec<(i32, String)>,
+        default: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmMemberRef {
+    pub owner: String,
+    pub name: String,
+    pub descriptor: String,
+    /// Whether the owner is an interface, written as an `interface` keyword before the reference
+    pub interface: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsmHandle {
+    pub kind: MethodHandleKind,
+    pub member: AsmMemberRef,
+}
+
+#[derive(Debug, Clone)]
+pub enum AsmConstant {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Class(String),
+    MethodType(String),
+    MethodHandle(AsmHandle),
+}
@@ -0,0 +1,741 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::jvm::reader::{
+    code::instruction::{Instruction, WideOp},
+    constant_pool::{ConstantPool, ConstantPoolInfo},
+    descriptor::{FieldType, MethodDescriptor},
+};
+
+/// The type of a single local variable or operand stack slot during inference.
+/// Long and Double occupy two slots, the second one being `Top`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    /// Internal name of a class, or the descriptor of an array class
+    Object(String),
+    /// Created by the `new` at the given pc, but not yet passed to `<init>`
+    Uninitialized(u32),
+}
+
+impl VType {
+    pub fn from_field_type(ty: &FieldType) -> Self {
+        match ty {
+            FieldType::Byte | FieldType::Char | FieldType::Short | FieldType::Boolean | FieldType::Int => VType::Integer,
+            FieldType::Float => VType::Float,
+            FieldType::Long => VType::Long,
+            FieldType::Double => VType::Double,
+            FieldType::Object(_) | FieldType::Array(_) => VType::Object(ty.class_name().unwrap()),
+        }
+    }
+    pub fn is_wide(&self) -> bool {
+        matches!(self, VType::Long | VType::Double)
+    }
+    fn is_reference(&self) -> bool {
+        matches!(self, VType::Null | VType::Object(_))
+    }
+}
+
+/// Finds the closest common superclass of two classes when control flow merges two references.
+pub trait TypeMerger {
+    fn common_superclass(&self, a: &str, b: &str) -> String;
+}
+
+/// Merges every pair of distinct classes into `java/lang/Object`. This needs no class
+/// hierarchy, but the result may be too weak if the merged value is used as a specific type.
+pub struct ObjectMerger;
+
+impl TypeMerger for ObjectMerger {
+    fn common_superclass(&self, _: &str, _: &str) -> String {
+        "java/lang/Object".to_string()
+    }
+}
+
+/// The method being analyzed.
+pub struct MethodContext<'a> {
+    pub class_name: &'a str,
+    pub method_name: &'a str,
+    pub descriptor: &'a MethodDescriptor,
+    pub is_static: bool,
+}
+
+pub struct Handler {
+    pub start: u32,
+    pub end: u32,
+    pub handler: u32,
+    /// `None` catches everything
+    pub catch_type: Option<String>,
+}
+
+/// A frame as it appears in the StackMapTable: one entry per value, trailing `Top` locals removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub pc: u32,
+    pub locals: Vec<VType>,
+    pub stack: Vec<VType>,
+}
+
+#[derive(Debug)]
+pub struct FrameAnalysis {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    /// Locals of the implicit first frame, derived from the descriptor
+    pub initial_locals: Vec<VType>,
+    /// Frames at every branch target and exception handler, ordered by pc
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Debug)]
+pub struct FrameError {
+    /// Index of the offending instruction
+    pub index: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct State {
+    locals: Vec<VType>,
+    stack: Vec<VType>,
+}
+
+impl State {
+    fn push(&mut self, ty: VType) {
+        let wide = ty.is_wide();
+        self.stack.push(ty);
+        if wide {
+            self.stack.push(VType::Top);
+        }
+    }
+    fn pop(&mut self) -> Result<VType, String> {
+        self.stack.pop().ok_or_else(|| "operand stack underflow".to_string())
+    }
+    fn pop_n(&mut self, slots: usize) -> Result<(), String> {
+        if self.stack.len() < slots {
+            return Err("operand stack underflow".to_string());
+        }
+        self.stack.truncate(self.stack.len() - slots);
+        Ok(())
+    }
+    fn pop_value(&mut self, ty: &FieldType) -> Result<(), String> {
+        self.pop_n(ty.slots() as usize)
+    }
+    fn load(&self, index: usize, expected: &str) -> Result<VType, String> {
+        match self.locals.get(index) {
+            None | Some(VType::Top) => Err(format!("local {} has no {} value here", index, expected)),
+            Some(ty) => Ok(ty.clone()),
+        }
+    }
+    fn store(&mut self, index: usize, ty: VType) {
+        let wide = ty.is_wide();
+        let needed = index + if wide { 2 } else { 1 };
+        if self.locals.len() < needed {
+            self.locals.resize(needed, VType::Top);
+        }
+        if index > 0 && self.locals[index - 1].is_wide() {
+            self.locals[index - 1] = VType::Top;
+        }
+        self.locals[index] = ty;
+        if wide {
+            self.locals[index + 1] = VType::Top;
+        }
+    }
+    fn replace(&mut self, from: &VType, to: &VType) {
+        for slot in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if slot == from {
+                *slot = to.clone();
+            }
+        }
+    }
+}
+
+struct Analyzer<'a> {
+    ctx: &'a MethodContext<'a>,
+    code: &'a [(u32, Instruction)],
+    pool: &'a ConstantPool,
+    merger: &'a dyn TypeMerger,
+    by_pc: HashMap<u32, usize>,
+}
+
+/// Infers the types of every local and stack slot, yielding the limits of the method and
+/// the frames a StackMapTable needs. When `need_frames` is false unreachable code and
+/// subroutines are tolerated, since only the limits are of interest.
+pub fn analyze(
+    ctx: &MethodContext,
+    code: &[(u32, Instruction)],
+    handlers: &[Handler],
+    pool: &ConstantPool,
+    merger: &dyn TypeMerger,
+    need_frames: bool,
+) -> Result<FrameAnalysis, FrameError> {
+    let analyzer = Analyzer {
+        ctx,
+        code,
+        pool,
+        merger,
+        by_pc: code.iter().enumerate().map(|(i, (pc, _))| (*pc, i)).collect(),
+    };
+    analyzer.run(handlers, need_frames)
+}
+
+impl<'a> Analyzer<'a> {
+    fn index_of(&self, pc: u32, from: usize) -> Result<usize, FrameError> {
+        match self.by_pc.get(&pc) {
+            Some(index) => Ok(*index),
+            None => Err(FrameError { index: from, message: format!("jump to pc {}, which is not the start of an instruction", pc) }),
+        }
+    }
+
+    fn initial_state(&self) -> State {
+        let mut state = State { locals: Vec::new(), stack: Vec::new() };
+        if !self.ctx.is_static {
+            let this = if self.ctx.method_name == "<init>" && self.ctx.class_name != "java/lang/Object" {
+                VType::UninitializedThis
+            } else {
+                VType::Object(self.ctx.class_name.to_string())
+            };
+            state.store(0, this);
+        }
+        for param in &self.ctx.descriptor.params {
+            let index = state.locals.len();
+            state.store(index, VType::from_field_type(param));
+        }
+        state
+    }
+
+    fn run(&self, handlers: &[Handler], need_frames: bool) -> Result<FrameAnalysis, FrameError> {
+        let mut frame_points = BTreeSet::new();
+        let mut handler_ranges = Vec::new();
+        for handler in handlers {
+            let target = self.index_of(handler.handler, 0)?;
+            frame_points.insert(target);
+            let catch_type = handler.catch_type.clone().unwrap_or_else(|| "java/lang/Throwable".to_string());
+            handler_ranges.push((handler.start, handler.end, target, catch_type));
+        }
+
+        let initial = self.initial_state();
+        let mut max_stack = 0;
+        let mut max_locals = initial.locals.len();
+        let mut states: Vec<Option<State>> = vec![None; self.code.len()];
+        let mut worklist = BTreeSet::new();
+        if !self.code.is_empty() {
+            states[0] = Some(initial.clone());
+            worklist.insert(0);
+        }
+
+        while let Some(index) = worklist.pop_first() {
+            let state = states[index].clone().unwrap();
+            let (pc, instruction) = &self.code[index];
+            for (start, end, target, catch_type) in &handler_ranges {
+                if *start <= *pc && *pc < *end {
+                    let incoming = State { locals: state.locals.clone(), stack: vec![VType::Object(catch_type.clone())] };
+                    if self.merge_into(&mut states[*target], incoming, *target)? {
+                        worklist.insert(*target);
+                    }
+                }
+            }
+            let mut out = state;
+            self.step(*pc, instruction, &mut out, need_frames).map_err(|message| FrameError { index, message })?;
+            max_stack = max_stack.max(out.stack.len());
+            max_locals = max_locals.max(out.locals.len());
+            let mut successors = Vec::new();
+            for target in instruction.branch_targets(*pc) {
+                let target = self.index_of(target, index)?;
+                frame_points.insert(target);
+                successors.push(target);
+            }
+            if instruction.flow().falls_through() {
+                if index + 1 >= self.code.len() {
+                    return Err(FrameError { index, message: "execution falls off the end of the code".to_string() });
+                }
+                successors.push(index + 1);
+            }
+            for successor in successors {
+                if self.merge_into(&mut states[successor], out.clone(), successor)? {
+                    worklist.insert(successor);
+                }
+            }
+        }
+
+        let mut frames = Vec::new();
+        if need_frames {
+            if let Some(index) = states.iter().position(|s| s.is_none()) {
+                return Err(FrameError { index, message: "unreachable code can not be described by stack map frames".to_string() });
+            }
+            for index in frame_points {
+                let state = states[index].as_ref().unwrap();
+                frames.push(Frame { pc: self.code[index].0, locals: to_entries(&state.locals, true), stack: to_entries(&state.stack, false) });
+            }
+        }
+        Ok(FrameAnalysis {
+            max_stack: max_stack as u16,
+            max_locals: max_locals as u16,
+            initial_locals: to_entries(&initial.locals, true),
+            frames,
+        })
+    }
+
+    /// Merges `incoming` into the state recorded for `index`, returning whether it changed.
+    fn merge_into(&self, slot: &mut Option<State>, incoming: State, index: usize) -> Result<bool, FrameError> {
+        let current = match slot {
+            None => {
+                *slot = Some(incoming);
+                return Ok(true);
+            },
+            Some(current) => current,
+        };
+        if current.stack.len() != incoming.stack.len() {
+            return Err(FrameError {
+                index,
+                message: format!("stack height {} does not match height {} from another path", incoming.stack.len(), current.stack.len()),
+            });
+        }
+        let mut merged = State { locals: Vec::new(), stack: Vec::new() };
+        for (a, b) in current.stack.iter().zip(incoming.stack.iter()) {
+            let ty = self.merge(a, b);
+            if ty == VType::Top && (*a != VType::Top || *b != VType::Top) {
+                return Err(FrameError { index, message: format!("stack slot is {:?} on one path and {:?} on another", a, b) });
+            }
+            merged.stack.push(ty);
+        }
+        let length = current.locals.len().min(incoming.locals.len());
+        for i in 0..length {
+            merged.locals.push(self.merge(&current.locals[i], &incoming.locals[i]));
+        }
+        // a Long or Double can't survive if only one of its halves did
+        for i in 0..merged.locals.len() {
+            if merged.locals[i].is_wide() && merged.locals.get(i + 1) != Some(&VType::Top) {
+                merged.locals[i] = VType::Top;
+            }
+        }
+        while merged.locals.last() == Some(&VType::Top) {
+            merged.locals.pop();
+        }
+        let mut trimmed = current.clone();
+        while trimmed.locals.last() == Some(&VType::Top) {
+            trimmed.locals.pop();
+        }
+        if merged == trimmed {
+            return Ok(false);
+        }
+        *current = merged;
+        Ok(true)
+    }
+
+    fn merge(&self, a: &VType, b: &VType) -> VType {
+        if a == b {
+            return a.clone();
+        }
+        match (a, b) {
+            (VType::Null, other) | (other, VType::Null) if other.is_reference() => other.clone(),
+            (VType::Object(a), VType::Object(b)) => VType::Object(self.merge_names(a, b)),
+            _ => VType::Top,
+        }
+    }
+
+    fn merge_names(&self, a: &str, b: &str) -> String {
+        if a == b {
+            return a.to_string();
+        }
+        match (a.strip_prefix('['), b.strip_prefix('[')) {
+            (Some(a), Some(b)) => {
+                let reference = |c: &str| c.starts_with('L') || c.starts_with('[');
+                if reference(a) && reference(b) {
+                    let component = self.merge_names(&component_name(a), &component_name(b));
+                    match FieldType::from_class_name(&component) {
+                        Ok(ty) => format!("[{}", ty),
+                        Err(_) => "java/lang/Object".to_string(),
+                    }
+                } else {
+                    "java/lang/Object".to_string()
+                }
+            },
+            (None, None) => self.merger.common_superclass(a, b),
+            _ => "java/lang/Object".to_string(),
+        }
+    }
+
+    fn class_operand(&self, index: u16) -> Result<String, String> {
+        self.pool.get_class_name(index).map(|s| s.to_string()).map_err(|e| e.to_string())
+    }
+
+    fn ldc_type(&self, index: u16) -> Result<VType, String> {
+        Ok(match self.pool.get_info(index).map_err(|e| e.to_string())? {
+            ConstantPoolInfo::Integer(_) => VType::Integer,
+            ConstantPoolInfo::Float(_) => VType::Float,
+            ConstantPoolInfo::Long(_) => VType::Long,
+            ConstantPoolInfo::Double(_) => VType::Double,
+            ConstantPoolInfo::StringRef(_) => VType::Object("java/lang/String".to_string()),
+            ConstantPoolInfo::ClassRef(_) => VType::Object("java/lang/Class".to_string()),
+            ConstantPoolInfo::MethodType(_) => VType::Object("java/lang/invoke/MethodType".to_string()),
+            ConstantPoolInfo::MethodHandle { .. } => VType::Object("java/lang/invoke/MethodHandle".to_string()),
+            ConstantPoolInfo::Dynamic { name_and_type_index, .. } => {
+                let (_, descriptor) = self.pool.get_name_and_type(*name_and_type_index).map_err(|e| e.to_string())?;
+                VType::from_field_type(&FieldType::parse(descriptor).map_err(|e| e.to_string())?)
+            },
+            other => return Err(format!("can not load constant {:?}", other)),
+        })
+    }
+
+    /// Applies one instruction to `state`.
+    fn step(&self, pc: u32, instruction: &Instruction, state: &mut State, need_frames: bool) -> Result<(), String> {
+        use Instruction as I;
+        match instruction {
+            I::Nop => {},
+            I::AconstNull => state.push(VType::Null),
+            I::IconstM1 | I::Iconst0 | I::Iconst1 | I::Iconst2 | I::Iconst3 | I::Iconst4 | I::Iconst5
+            | I::Bipush(_) | I::Sipush(_) => state.push(VType::Integer),
+            I::Lconst0 | I::Lconst1 => state.push(VType::Long),
+            I::Fconst0 | I::Fconst1 | I::Fconst2 => state.push(VType::Float),
+            I::Dconst0 | I::Dconst1 => state.push(VType::Double),
+            I::Ldc(index) => state.push(self.ldc_type(*index as u16)?),
+            I::LdcW(index) | I::Ldc2W(index) => state.push(self.ldc_type(*index)?),
+            I::Iload(n) => self.load(state, *n as usize, VType::Integer)?,
+            I::Lload(n) => self.load(state, *n as usize, VType::Long)?,
+            I::Fload(n) => self.load(state, *n as usize, VType::Float)?,
+            I::Dload(n) => self.load(state, *n as usize, VType::Double)?,
+            I::Aload(n) => self.load_reference(state, *n as usize)?,
+            I::Iload0 => self.load(state, 0, VType::Integer)?,
+            I::Iload1 => self.load(state, 1, VType::Integer)?,
+            I::Iload2 => self.load(state, 2, VType::Integer)?,
+            I::Iload3 => self.load(state, 3, VType::Integer)?,
+            I::Lload0 => self.load(state, 0, VType::Long)?,
+            I::Lload1 => self.load(state, 1, VType::Long)?,
+            I::Lload2 => self.load(state, 2, VType::Long)?,
+            I::Lload3 => self.load(state, 3, VType::Long)?,
+            I::Fload0 => self.load(state, 0, VType::Float)?,
+            I::Fload1 => self.load(state, 1, VType::Float)?,
+            I::Fload2 => self.load(state, 2, VType::Float)?,
+            I::Fload3 => self.load(state, 3, VType::Float)?,
+            I::Dload0 => self.load(state, 0, VType::Double)?,
+            I::Dload1 => self.load(state, 1, VType::Double)?,
+            I::Dload2 => self.load(state, 2, VType::Double)?,
+            I::Dload3 => self.load(state, 3, VType::Double)?,
+            I::Aload0 => self.load_reference(state, 0)?,
+            I::Aload1 => self.load_reference(state, 1)?,
+            I::Aload2 => self.load_reference(state, 2)?,
+            I::Aload3 => self.load_reference(state, 3)?,
+            I::Iaload | I::Baload | I::Caload | I::Saload => {
+                state.pop_n(2)?;
+                state.push(VType::Integer);
+            },
+            I::Laload => {
+                state.pop_n(2)?;
+                state.push(VType::Long);
+            },
+            I::Faload => {
+                state.pop_n(2)?;
+                state.push(VType::Float);
+            },
+            I::Daload => {
+                state.pop_n(2)?;
+                state.push(VType::Double);
+            },
+            I::Aaload => {
+                state.pop()?;
+                let component = match state.pop()? {
+                    VType::Null => VType::Null,
+                    VType::Object(array) if array.starts_with("[L") || array.starts_with("[[") => VType::Object(component_name(&array[1..])),
+                    other => return Err(format!("aaload expects an array of references, found {:?}", other)),
+                };
+                state.push(component);
+            },
+            I::Istore(n) => self.store(state, *n as usize, 1, VType::Integer)?,
+            I::Lstore(n) => self.store(state, *n as usize, 2, VType::Long)?,
+            I::Fstore(n) => self.store(state, *n as usize, 1, VType::Float)?,
+            I::Dstore(n) => self.store(state, *n as usize, 2, VType::Double)?,
+            I::Astore(n) => self.store_reference(state, *n as usize)?,
+            I::Istore0 => self.store(state, 0, 1, VType::Integer)?,
+            I::Istore1 => self.store(state, 1, 1, VType::Integer)?,
+            I::Istore2 => self.store(state, 2, 1, VType::Integer)?,
+            I::Istore3 => self.store(state, 3, 1, VType::Integer)?,
+            I::Lstore0 => self.store(state, 0, 2, VType::Long)?,
+            I::Lstore1 => self.store(state, 1, 2, VType::Long)?,
+            I::Lstore2 => self.store(state, 2, 2, VType::Long)?,
+            I::Lstore3 => self.store(state, 3, 2, VType::Long)?,
+            I::Fstore0 => self.store(state, 0, 1, VType::Float)?,
+            I::Fstore1 => self.store(state, 1, 1, VType::Float)?,
+            I::Fstore2 => self.store(state, 2, 1, VType::Float)?,
+            I::Fstore3 => self.store(state, 3, 1, VType::Float)?,
+            I::Dstore0 => self.store(state, 0, 2, VType::Double)?,
+            I::Dstore1 => self.store(state, 1, 2, VType::Double)?,
+            I::Dstore2 => self.store(state, 2, 2, VType::Double)?,
+            I::Dstore3 => self.store(state, 3, 2, VType::Double)?,
+            I::Astore0 => self.store_reference(state, 0)?,
+            I::Astore1 => self.store_reference(state, 1)?,
+            I::Astore2 => self.store_reference(state, 2)?,
+            I::Astore3 => self.store_reference(state, 3)?,
+            I::Iastore | I::Fastore | I::Aastore | I::Bastore | I::Castore | I::Sastore => state.pop_n(3)?,
+            I::Lastore | I::Dastore => state.pop_n(4)?,
+            I::Pop => state.pop_n(1)?,
+            I::Pop2 => state.pop_n(2)?,
+            I::Dup => {
+                let v1 = state.pop()?;
+                state.stack.extend([v1.clone(), v1]);
+            },
+            I::DupX1 => {
+                let v1 = state.pop()?;
+                let v2 = state.pop()?;
+                state.stack.extend([v1.clone(), v2, v1]);
+            },
+            I::DupX2 => {
+                let v1 = state.pop()?;
+                let v2 = state.pop()?;
+                let v3 = state.pop()?;
+                state.stack.extend([v1.clone(), v3, v2, v1]);
+            },
+            I::Dup2 => {
+                let v1 = state.pop()?;
+                let v2 = state.pop()?;
+                state.stack.extend([v2.clone(), v1.clone(), v2, v1]);
+            },
+            I::Dup2X1 => {
+                let v1 = state.pop()?;
+                let v2 = state.pop()?;
+                let v3 = state.pop()?;
+                state.stack.extend([v2.clone(), v1.clone(), v3, v2, v1]);
+            },
+            I::Dup2X2 => {
+                let v1 = state.pop()?;
+                let v2 = state.pop()?;
+                let v3 = state.pop()?;
+                let v4 = state.pop()?;
+                state.stack.extend([v2.clone(), v1.clone(), v4, v3, v2, v1]);
+            },
+            I::Swap => {
+                let v1 = state.pop()?;
+                let v2 = state.pop()?;
+                state.stack.extend([v1, v2]);
+            },
+            I::Iadd | I::Isub | I::Imul | I::Idiv | I::Irem | I::Ishl | I::Ishr | I::Iushr
+            | I::Iand | I::Ior | I::Ixor | I::Fcmpl | I::Fcmpg => {
+                state.pop_n(2)?;
+                state.push(VType::Integer);
+            },
+            I::Fadd | I::Fsub | I::Fmul | I::Fdiv | I::Frem => {
+                state.pop_n(2)?;
+                state.push(VType::Float);
+            },
+            I::Ladd | I::Lsub | I::Lmul | I::Ldiv | I::Lrem | I::Land | I::Lor | I::Lxor => {
+                state.pop_n(4)?;
+                state.push(VType::Long);
+            },
+            I::Dadd | I::Dsub | I::Dmul | I::Ddiv | I::Drem => {
+                state.pop_n(4)?;
+                state.push(VType::Double);
+            },
+            I::Lshl | I::Lshr | I::Lushr => {
+                state.pop_n(3)?;
+                state.push(VType::Long);
+            },
+            I::Ineg | I::F2i | I::I2b | I::I2c | I::I2s => {
+                state.pop_n(1)?;
+                state.push(VType::Integer);
+            },
+            I::Fneg | I::I2f => {
+                state.pop_n(1)?;
+                state.push(VType::Float);
+            },
+            I::Lneg | I::D2l => {
+                state.pop_n(2)?;
+                state.push(VType::Long);
+            },
+            I::Dneg | I::L2d => {
+                state.pop_n(2)?;
+                state.push(VType::Double);
+            },
+            I::I2l | I::F2l => {
+                state.pop_n(1)?;
+                state.push(VType::Long);
+            },
+            I::I2d | I::F2d => {
+                state.pop_n(1)?;
+                state.push(VType::Double);
+            },
+            I::L2i | I::D2i => {
+                state.pop_n(2)?;
+                state.push(VType::Integer);
+            },
+            I::L2f | I::D2f => {
+                state.pop_n(2)?;
+                state.push(VType::Float);
+            },
+            I::Lcmp | I::Dcmpl | I::Dcmpg => {
+                state.pop_n(4)?;
+                state.push(VType::Integer);
+            },
+            I::Iinc(n, _) => {
+                state.load(*n as usize, "int")?;
+            },
+            I::Ifeq(_) | I::Ifne(_) | I::Iflt(_) | I::Ifge(_) | I::Ifgt(_) | I::Ifle(_) | I::Ifnull(_) | I::Ifnonnull(_) => state.pop_n(1)?,
+            I::IfIcmpeq(_) | I::IfIcmpne(_) | I::IfIcmplt(_) | I::IfIcmpge(_)
+            | I::IfIcmpgt(_) | I::IfIcmple(_) | I::IfAcmpeq(_) | I::IfAcmpne(_) => state.pop_n(2)?,
+            I::Goto(_) | I::GotoW(_) => {},
+            I::Jsr(_) | I::JsrW(_) | I::Ret(_) if need_frames => {
+                return Err("jsr and ret can not be described by stack map frames, use class version 49 or lower".to_string());
+            },
+            I::Jsr(_) | I::JsrW(_) => state.stack.push(VType::Top),
+            I::Ret(_) => {},
+            I::Tableswitch(_) | I::Lookupswitch(_) => state.pop_n(1)?,
+            I::Ireturn | I::Freturn | I::Areturn | I::Athrow => state.pop_n(1)?,
+            I::Lreturn | I::Dreturn => state.pop_n(2)?,
+            I::Return => {},
+            I::Getstatic(index) | I::Getfield(index) => {
+                let field = self.pool.get_member_ref(*index).map_err(|e| e.to_string())?;
+                let ty = FieldType::parse(&field.descriptor).map_err(|e| e.to_string())?;
+                if matches!(instruction, I::Getfield(_)) {
+                    state.pop_n(1)?;
+                }
+                state.push(VType::from_field_type(&ty));
+            },
+            I::Putstatic(index) | I::Putfield(index) => {
+                let field = self.pool.get_member_ref(*index).map_err(|e| e.to_string())?;
+                let ty = FieldType::parse(&field.descriptor).map_err(|e| e.to_string())?;
+                state.pop_value(&ty)?;
+                if matches!(instruction, I::Putfield(_)) {
+                    state.pop_n(1)?;
+                }
+            },
+            I::Invokevirtual(index) | I::Invokespecial(index) | I::Invokestatic(index) | I::Invokeinterface(index, _, _) => {
+                let method = self.pool.get_member_ref(*index).map_err(|e| e.to_string())?;
+                let descriptor = MethodDescriptor::parse(&method.descriptor).map_err(|e| e.to_string())?;
+                state.pop_n(descriptor.param_slots() as usize)?;
+                if !matches!(instruction, I::Invokestatic(_)) {
+                    let receiver = state.pop()?;
+                    if method.name == "<init>" {
+                        let initialized = match &receiver {
+                            VType::UninitializedThis => VType::Object(self.ctx.class_name.to_string()),
+                            VType::Uninitialized(new_pc) => VType::Object(self.new_class(*new_pc)?),
+                            other => return Err(format!("<init> called on {:?}, which is already initialized", other)),
+                        };
+                        state.replace(&receiver, &initialized);
+                    }
+                }
+                if let Some(ret) = &descriptor.ret {
+                    state.push(VType::from_field_type(ret));
+                }
+            },
+            I::Invokedynamic(index, _) => {
+                let (_, descriptor) = match self.pool.get_info(*index).map_err(|e| e.to_string())? {
+                    ConstantPoolInfo::InvokeDynamic { name_and_type_index, .. } => {
+                        self.pool.get_name_and_type(*name_and_type_index).map_err(|e| e.to_string())?
+                    },
+                    other => return Err(format!("invokedynamic expects an InvokeDynamic constant, found {:?}", other)),
+                };
+                let descriptor = MethodDescriptor::parse(descriptor).map_err(|e| e.to_string())?;
+                state.pop_n(descriptor.param_slots() as usize)?;
+                if let Some(ret) = &descriptor.ret {
+                    state.push(VType::from_field_type(ret));
+                }
+            },
+            I::New(_) => state.push(VType::Uninitialized(pc)),
+            I::Newarray(atype) => {
+                state.pop_n(1)?;
+                state.push(VType::Object(format!("[{}", atype.element())));
+            },
+            I::ANewarray(index) => {
+                state.pop_n(1)?;
+                let component = FieldType::from_class_name(&self.class_operand(*index)?).map_err(|e| e.to_string())?;
+                state.push(VType::Object(format!("[{}", component)));
+            },
+            I::Arraylength | I::Instanceof(_) => {
+                state.pop_n(1)?;
+                state.push(VType::Integer);
+            },
+            I::Checkcast(index) => {
+                state.pop_n(1)?;
+                state.push(VType::Object(self.class_operand(*index)?));
+            },
+            I::Monitorenter | I::Monitorexit => state.pop_n(1)?,
+            I::Wide(op) => {
+                let n = op.index() as usize;
+                match op {
+                    WideOp::Iload(_) => self.load(state, n, VType::Integer)?,
+                    WideOp::Lload(_) => self.load(state, n, VType::Long)?,
+                    WideOp::Fload(_) => self.load(state, n, VType::Float)?,
+                    WideOp::Dload(_) => self.load(state, n, VType::Double)?,
+                    WideOp::Aload(_) => self.load_reference(state, n)?,
+                    WideOp::Istore(_) => self.store(state, n, 1, VType::Integer)?,
+                    WideOp::Lstore(_) => self.store(state, n, 2, VType::Long)?,
+                    WideOp::Fstore(_) => self.store(state, n, 1, VType::Float)?,
+                    WideOp::Dstore(_) => self.store(state, n, 2, VType::Double)?,
+                    WideOp::Astore(_) => self.store_reference(state, n)?,
+                    WideOp::Iinc(..) => {
+                        state.load(n, "int")?;
+                    },
+                    WideOp::Ret(_) if need_frames => return Err("ret can not be described by stack map frames".to_string()),
+                    WideOp::Ret(_) => {},
+                }
+            },
+            I::Multianewarray(index, dimensions) => {
+                state.pop_n(*dimensions as usize)?;
+                state.push(VType::Object(self.class_operand(*index)?));
+            },
+        }
+        Ok(())
+    }
+
+    fn load(&self, state: &mut State, index: usize, ty: VType) -> Result<(), String> {
+        let what = format!("{:?}", ty).to_lowercase();
+        if state.load(index, &what)? != ty {
+            return Err(format!("local {} is not {}", index, what));
+        }
+        state.push(ty);
+        Ok(())
+    }
+    fn load_reference(&self, state: &mut State, index: usize) -> Result<(), String> {
+        let ty = state.load(index, "reference")?;
+        if !matches!(ty, VType::Null | VType::Object(_) | VType::UninitializedThis | VType::Uninitialized(_)) {
+            return Err(format!("local {} holds {:?}, not a reference", index, ty));
+        }
+        state.push(ty);
+        Ok(())
+    }
+    fn store(&self, state: &mut State, index: usize, slots: usize, ty: VType) -> Result<(), String> {
+        state.pop_n(slots)?;
+        state.store(index, ty);
+        Ok(())
+    }
+    fn store_reference(&self, state: &mut State, index: usize) -> Result<(), String> {
+        let ty = state.pop()?;
+        if ty.is_wide() || ty == VType::Top {
+            return Err(format!("astore expects a reference, found {:?}", ty));
+        }
+        state.store(index, ty);
+        Ok(())
+    }
+    fn new_class(&self, pc: u32) -> Result<String, String> {
+        match self.by_pc.get(&pc).map(|i| &self.code[*i].1) {
+            Some(Instruction::New(index)) => self.class_operand(*index),
+            _ => Err(format!("no `new` instruction at pc {}", pc)),
+        }
+    }
+}
+
+/// `Ljava/lang/String;` -> `java/lang/String`, `[I` -> `[I`
+fn component_name(descriptor: &str) -> String {
+    match descriptor.strip_prefix('L').and_then(|d| d.strip_suffix(';')) {
+        Some(name) => name.to_string(),
+        None => descriptor.to_string(),
+    }
+}
+
+/// Converts slots into frame entries, dropping the `Top` that follows a Long or Double.
+fn to_entries(slots: &[VType], trim: bool) -> Vec<VType> {
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while i < slots.len() {
+        entries.push(slots[i].clone());
+        i += if slots[i].is_wide() { 2 } else { 1 };
+    }
+    if trim {
+        while entries.last() == Some(&VType::Top) {
+            entries.pop();
+        }
+    }
+    entries
+}
@@ -0,0 +1,213 @@
+use crate::util::code_err::AssemblyError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatSuffix {
+    None,
+    F,
+    D,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// `.class`, `.method`, ...; stored without the leading dot
+    Directive(String),
+    /// Names, descriptors, member references and mnemonics
+    Word(String),
+    /// `name:`
+    LabelDef(String),
+    Int(i64),
+    /// An integer with an `L` suffix
+    Long(i64),
+    Float(f64, FloatSuffix),
+    Str(String),
+    Colon,
+    Equals,
+    Comma,
+    LBrace,
+    RBrace,
+    Newline,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+pub(crate) fn is_special(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '{' | '}' | ',' | '=')
+}
+
+/// Splits the source into tokens. `;` starts a comment only at the start of a token,
+/// so descriptors such as `Ljava/lang/String;` stay intact.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, AssemblyError> {
+    let mut tokens = Vec::new();
+    for (line_index, text) in source.lines().enumerate() {
+        let line = line_index + 1;
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let column = i + 1;
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if c == ';' {
+                break;
+            }
+            let single = match c {
+                '{' => Some(TokenKind::LBrace),
+                '}' => Some(TokenKind::RBrace),
+                ',' => Some(TokenKind::Comma),
+                '=' => Some(TokenKind::Equals),
+                _ => None,
+            };
+            if let Some(kind) = single {
+                tokens.push(Token { kind, line, column });
+                i += 1;
+                continue;
+            }
+            if c == '"' {
+                let (value, end) = read_string(&chars, i + 1, line)?;
+                tokens.push(Token { kind: TokenKind::Str(value), line, column });
+                i = end;
+                continue;
+            }
+            let start = i;
+            while i < chars.len() && !is_special(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            push_word(&mut tokens, word, line, column)?;
+        }
+        tokens.push(Token { kind: TokenKind::Newline, line, column: chars.len() + 1 });
+    }
+    Ok(tokens)
+}
+
+fn push_word(tokens: &mut Vec<Token>, word: String, line: usize, column: usize) -> Result<(), AssemblyError> {
+    if word == ":" {
+        tokens.push(Token { kind: TokenKind::Colon, line, column });
+        return Ok(());
+    }
+    if let Some(name) = word.strip_suffix(':') {
+        if let Some(number) = parse_number(name) {
+            tokens.push(Token { kind: number, line, column });
+            tokens.push(Token { kind: TokenKind::Colon, line, column: column + name.len() });
+        } else {
+            tokens.push(Token { kind: TokenKind::LabelDef(name.to_string()), line, column });
+        }
+        return Ok(());
+    }
+    if let Some(directive) = word.strip_prefix('.') {
+        if !directive.is_empty() && directive.chars().all(|c| c.is_ascii_alphabetic()) {
+            tokens.push(Token { kind: TokenKind::Directive(directive.to_string()), line, column });
+            return Ok(());
+        }
+    }
+    let kind = match parse_number(&word) {
+        Some(number) => number,
+        None => {
+            let first = word.chars().next().unwrap_or(' ');
+            if first.is_ascii_digit() || ((first == '-' || first == '+') && word.len() > 1 && word[1..].starts_with(|c: char| c.is_ascii_digit())) {
+                return Err(AssemblyError::Syntax { line, column, message: format!("malformed number `{}`", word) });
+            }
+            TokenKind::Word(word)
+        },
+    };
+    tokens.push(Token { kind, line, column });
+    Ok(())
+}
+
+fn parse_number(word: &str) -> Option<TokenKind> {
+    let (negative, body) = match word.as_bytes().first()? {
+        b'-' => (true, &word[1..]),
+        b'+' => (false, &word[1..]),
+        _ => (false, word),
+    };
+    let sign = if negative { -1.0 } else { 1.0 };
+    let (special, suffix) = match body {
+        "NaN" | "Infinity" => (body, FloatSuffix::None),
+        "NaNf" | "NaNF" | "Infinityf" | "InfinityF" => (&body[..body.len() - 1], FloatSuffix::F),
+        "NaNd" | "NaND" | "Infinityd" | "InfinityD" => (&body[..body.len() - 1], FloatSuffix::D),
+        _ => ("", FloatSuffix::None),
+    };
+    match special {
+        "NaN" => return Some(TokenKind::Float(f64::NAN, suffix)),
+        "Infinity" => return Some(TokenKind::Float(sign * f64::INFINITY, suffix)),
+        _ => {},
+    }
+    if !body.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let (digits, long) = match body.strip_suffix(['l', 'L']) {
+        Some(digits) => (digits, true),
+        None => (body, false),
+    };
+    let integer = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok().map(|v| v as i64)
+    } else {
+        // the magnitude of the most negative value is one past the largest positive one
+        digits.parse::<u64>().ok().filter(|v| *v <= i64::MAX as u64 + negative as u64).map(|v| v as i64)
+    };
+    if let Some(value) = integer {
+        let value = if negative { value.wrapping_neg() } else { value };
+        return Some(if long { TokenKind::Long(value) } else { TokenKind::Int(value) });
+    }
+    if long {
+        return None;
+    }
+    let (digits, suffix) = match body.chars().last()? {
+        'f' | 'F' => (&body[..body.len() - 1], FloatSuffix::F),
+        'd' | 'D' => (&body[..body.len() - 1], FloatSuffix::D),
+        _ => (body, FloatSuffix::None),
+    };
+    if digits.chars().any(|c| !(c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '-' | '+'))) {
+        return None;
+    }
+    digits.parse::<f64>().ok().map(|v| TokenKind::Float(sign * v, suffix))
+}
+
+fn read_string(chars: &[char], mut i: usize, line: usize) -> Result<(String, usize), AssemblyError> {
+    let start = i;
+    let mut value = String::new();
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Ok((value, i + 1)),
+            '\\' => {
+                let escape = chars.get(i + 1).copied();
+                i += 2;
+                match escape {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('0') => value.push('\0'),
+                    Some('\\') => value.push('\\'),
+                    Some('"') => value.push('"'),
+                    Some('\'') => value.push('\''),
+                    Some('u') => {
+                        let hex: String = chars.iter().skip(i).take(4).collect();
+                        let code = u32::from_str_radix(&hex, 16).ok().filter(|_| hex.len() == 4);
+                        match code.and_then(char::from_u32) {
+                            Some(c) => value.push(c),
+                            None => return Err(AssemblyError::Syntax { line, column: i - 1, message: format!("bad unicode escape `\\u{}`", hex) }),
+                        }
+                        i += 4;
+                    },
+                    other => return Err(AssemblyError::Syntax {
+                        line,
+                        column: i - 1,
+                        message: format!("unknown escape `\\{}`", other.map(String::from).unwrap_or_default()),
+                    }),
+                }
+            },
+            c => {
+                value.push(c);
+                i += 1;
+            },
+        }
+    }
+    Err(AssemblyError::Syntax { line, column: start, message: "unterminated string".to_string() })
+}
@@ -0,0 +1,38 @@
+//! A Jasmin-like textual assembler for classfiles.
+//!
+//! ```text
+//! .class public HelloWorld
+//! .super java/lang/Object
+//!
+//! .method public static main([Ljava/lang/String;)V
+//!     getstatic java/lang/System/out Ljava/io/PrintStream;
+//!     ldc "Hello World!"
+//!     invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+//!     return
+//! .end method
+//! ```
+//!
+//! Mnemonics are the ones of the JVM specification. Constants are written inline and
+//! interned into the constant pool, `.limit stack` and `.limit locals` are computed when
+//! omitted, and a StackMapTable is generated for class versions 50 and up.
+pub mod ast;
+pub mod lexer;
+pub mod parser;
+pub mod pool;
+pub mod frames;
+pub mod assembler;
+
+use crate::{jvm::reader::classfile::ClassFile, util::code_err::AssemblyError};
+
+use self::assembler::Assembler;
+
+/// Assembles the source of a single class.
+pub fn assemble(source: &str) -> Result<ClassFile, AssemblyError> {
+    let class = parser::parse(source)?;
+    Assembler::new().assemble(&class)
+}
+
+/// Assembles the source of a single class into the bytes of a classfile.
+pub fn assemble_to_bytes(source: &str) -> Result<Vec<u8>, AssemblyError> {
+    Ok(assemble(source)?.to_bytes()?)
+}
@@ -0,0 +1,624 @@
+use crate::{
+    jvm::reader::{code::{instruction::ArrayType, opcode::{tag, OpcodeInfo, OperandKind}}, method_handle_kind::MethodHandleKind},
+    util::code_err::AssemblyError,
+};
+
+use super::{
+    ast::{AsmClass, AsmConstant, AsmField, AsmHandle, AsmInstruction, AsmMemberRef, AsmMethod, AsmOperand, AsmStatement},
+    lexer::{tokenize, FloatSuffix, Token, TokenKind},
+};
+
+/// How the operands of a mnemonic are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OperandSyntax {
+    None,
+    Byte,
+    Short,
+    Ldc,
+    Ldc2,
+    Local,
+    Iinc,
+    Branch,
+    TableSwitch,
+    LookupSwitch,
+    Field,
+    Method,
+    InterfaceMethod,
+    InvokeDynamic,
+    Class,
+    ArrayType,
+    MultiANewArray,
+    Wide,
+}
+
+use OperandSyntax as S;
+
+impl OperandSyntax {
+    /// Derives the written form from the operands the opcode table lists.
+    fn of(info: &OpcodeInfo) -> Self {
+        use OperandKind as O;
+        match info.operands {
+            [] => S::None,
+            [O::Immediate { bytes: 1 }] => S::Byte,
+            [O::Immediate { .. }] => S::Short,
+            [O::Constant { tags, .. }] if tags.contains(&tag::LONG) => S::Ldc2,
+            [O::Constant { tags, .. }] if tags.contains(&tag::INTEGER) => S::Ldc,
+            [O::Constant { tags, .. }] if tags.contains(&tag::FIELD_REF) => S::Field,
+            [O::Constant { tags, .. }] if tags.contains(&tag::METHOD_REF) => S::Method,
+            [O::Constant { .. }, O::Count] => S::MultiANewArray,
+            [O::Constant { .. }, O::Count, O::Zero { .. }] => S::InterfaceMethod,
+            [O::Constant { .. }, O::Zero { .. }] => S::InvokeDynamic,
+            [O::Constant { .. }] => S::Class,
+            [O::Local] => S::Local,
+            [O::Local, O::Immediate { .. }] => S::Iinc,
+            [O::Branch { .. }] => S::Branch,
+            [O::Switch] if info.mnemonic == "tableswitch" => S::TableSwitch,
+            [O::Switch] => S::LookupSwitch,
+            [O::ArrayType] => S::ArrayType,
+            [O::Widened] => S::Wide,
+            other => unreachable!("no syntax for the operands of {}: {:?}", info.mnemonic, other),
+        }
+    }
+}
+
+pub(crate) const CLASS_FLAGS: &[(&str, u16)] = &[
+    ("public", 0x0001), ("final", 0x0010), ("super", 0x0020), ("interface", 0x0200), ("abstract", 0x0400),
+    ("synthetic", 0x1000), ("annotation", 0x2000), ("enum", 0x4000), ("module", 0x8000),
+];
+pub(crate) const FIELD_FLAGS: &[(&str, u16)] = &[
+    ("public", 0x0001), ("private", 0x0002), ("protected", 0x0004), ("static", 0x0008), ("final", 0x0010),
+    ("volatile", 0x0040), ("transient", 0x0080), ("synthetic", 0x1000), ("enum", 0x4000),
+];
+pub(crate) const METHOD_FLAGS: &[(&str, u16)] = &[
+    ("public", 0x0001), ("private", 0x0002), ("protected", 0x0004), ("static", 0x0008), ("final", 0x0010),
+    ("synchronized", 0x0020), ("bridge", 0x0040), ("varargs", 0x0080), ("native", 0x0100),
+    ("abstract", 0x0400), ("strict", 0x0800), ("synthetic", 0x1000),
+];
+
+pub fn parse(source: &str) -> Result<AsmClass, AssemblyError> {
+    Parser::new(source)?.parse_class()
+}
+
+/// A cursor over the tokens of a source, shared with the rootir text format.
+pub(crate) struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    pub(crate) fn new(source: &str) -> Result<Self, AssemblyError> {
+        Ok(Parser { tokens: tokenize(source)?, pos: 0 })
+    }
+    pub(crate) fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+    pub(crate) fn location(&self) -> (usize, usize) {
+        match self.tokens.get(self.pos).or(self.tokens.last()) {
+            Some(token) => (token.line, token.column),
+            None => (1, 1),
+        }
+    }
+    pub(crate) fn error<T>(&self, message: impl Into<String>) -> Result<T, AssemblyError> {
+        let (line, column) = self.location();
+        Err(AssemblyError::Syntax { line, column, message: message.into() })
+    }
+    pub(crate) fn next(&mut self) -> Option<TokenKind> {
+        let token = self.tokens.get(self.pos).map(|t| t.kind.clone());
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+    pub(crate) fn skip_newlines(&mut self) {
+        while self.peek() == Some(&TokenKind::Newline) {
+            self.pos += 1;
+        }
+    }
+    pub(crate) fn expect_newline(&mut self) -> Result<(), AssemblyError> {
+        match self.peek() {
+            Some(TokenKind::Newline) | None => {
+                self.pos += 1;
+                Ok(())
+            },
+            Some(other) => self.error(format!("expected end of line, found {}", describe(other))),
+        }
+    }
+    /// A word, or a quoted string for names with spaces or other special characters.
+    pub(crate) fn expect_word(&mut self, what: &str) -> Result<String, AssemblyError> {
+        match self.peek() {
+            Some(TokenKind::Word(word) | TokenKind::Str(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            },
+            Some(other) => self.error(format!("expected {}, found {}", what, describe(other))),
+            None => self.error(format!("expected {}, found end of file", what)),
+        }
+    }
+    pub(crate) fn expect_keyword(&mut self, keyword: &str) -> Result<(), AssemblyError> {
+        match self.peek() {
+            Some(TokenKind::Word(word)) if word == keyword => {
+                self.pos += 1;
+                Ok(())
+            },
+            _ => self.error(format!("expected `{}`", keyword)),
+        }
+    }
+    pub(crate) fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(TokenKind::Word(word)) if word == keyword) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+    pub(crate) fn expect_int(&mut self, what: &str) -> Result<i64, AssemblyError> {
+        match self.peek() {
+            Some(TokenKind::Int(value)) => {
+                let value = *value;
+                self.pos += 1;
+                Ok(value)
+            },
+            Some(other) => self.error(format!("expected {}, found {}", what, describe(other))),
+            None => self.error(format!("expected {}, found end of file", what)),
+        }
+    }
+    pub(crate) fn expect_int_in<T: TryFrom<i64>>(&mut self, what: &str) -> Result<T, AssemblyError> {
+        let (line, column) = self.location();
+        let value = self.expect_int(what)?;
+        T::try_from(value).map_err(|_| AssemblyError::Syntax { line, column, message: format!("{} is out of range for {}", value, what) })
+    }
+    pub(crate) fn expect_token(&mut self, kind: TokenKind) -> Result<(), AssemblyError> {
+        if self.peek() == Some(&kind) {
+            self.pos += 1;
+            return Ok(());
+        }
+        self.error(format!("expected {}", describe(&kind)))
+    }
+    pub(crate) fn parse_flags(&mut self, table: &[(&str, u16)]) -> u16 {
+        let mut flags = 0;
+        while let Some(TokenKind::Word(word)) = self.peek() {
+            match table.iter().find(|(name, _)| name == word) {
+                Some((_, flag)) => {
+                    flags |= flag;
+                    self.pos += 1;
+                },
+                None => break,
+            }
+        }
+        flags
+    }
+
+    fn parse_class(mut self) -> Result<AsmClass, AssemblyError> {
+        let mut class = AsmClass {
+            version: (52, 0),
+            source: None,
+            access_flags: 0,
+            name: String::new(),
+            super_name: None,
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            line: 0,
+        };
+        loop {
+            self.skip_newlines();
+            let (line, _) = self.location();
+            let directive = match self.next() {
+                None => break,
+                Some(TokenKind::Directive(directive)) => directive,
+                Some(other) => {
+                    self.pos -= 1;
+                    return self.error(format!("expected a directive, found {}", describe(&other)));
+                },
+            };
+            match directive.as_str() {
+                "version" => {
+                    let major = self.expect_int_in("major version")?;
+                    let minor = match self.peek() {
+                        Some(TokenKind::Int(_)) => self.expect_int_in("minor version")?,
+                        _ => 0,
+                    };
+                    class.version = (major, minor);
+                },
+                "source" => match self.next() {
+                    Some(TokenKind::Str(source)) | Some(TokenKind::Word(source)) => class.source = Some(source),
+                    _ => {
+                        self.pos -= 1;
+                        return self.error("expected a source file name");
+                    },
+                },
+                "class" | "interface" => {
+                    if class.line != 0 {
+                        return self.error(format!("`.{}` is given more than once", directive));
+                    }
+                    class.access_flags = self.parse_flags(CLASS_FLAGS);
+                    if directive == "interface" {
+                        class.access_flags |= 0x0200 | 0x0400;
+                    } else if class.access_flags & 0x0200 == 0 {
+                        class.access_flags |= 0x0020;
+                    }
+                    class.name = self.expect_word("class name")?;
+                    class.line = line;
+                },
+                "super" => class.super_name = Some(self.expect_word("super class name")?),
+                "implements" => class.interfaces.push(self.expect_word("interface name")?),
+                "field" => {
+                    let access_flags = self.parse_flags(FIELD_FLAGS);
+                    let name = self.expect_word("field name")?;
+                    let descriptor = self.expect_word("field descriptor")?;
+                    let value = if self.peek() == Some(&TokenKind::Equals) {
+                        self.pos += 1;
+                        Some(self.parse_constant()?)
+                    } else {
+                        None
+                    };
+                    class.fields.push(AsmField { access_flags, name, descriptor, value, line });
+                },
+                "method" => {
+                    let access_flags = self.parse_flags(METHOD_FLAGS);
+                    let signature = self.expect_word("method name and descriptor")?;
+                    let (name, descriptor) = match signature.find('(') {
+                        Some(i) if i > 0 => (signature[..i].to_string(), signature[i..].to_string()),
+                        _ => {
+                            self.pos -= 1;
+                            return self.error(format!("expected `name(descriptor)`, found `{}`", signature));
+                        },
+                    };
+                    self.expect_newline()?;
+                    let mut method = AsmMethod {
+                        access_flags,
+                        name,
+                        descriptor,
+                        max_stack: None,
+                        max_locals: None,
+                        throws: Vec::new(),
+                        body: Vec::new(),
+                        line,
+                    };
+                    self.parse_method_body(&mut method)?;
+                    class.methods.push(method);
+                    continue;
+                },
+                other => {
+                    self.pos -= 1;
+                    return self.error(format!("unknown or misplaced directive `.{}`", other));
+                },
+            }
+            self.expect_newline()?;
+        }
+        if class.line == 0 {
+            return Err(AssemblyError::Syntax { line: 1, column: 1, message: "missing `.class` or `.interface`".to_string() });
+        }
+        Ok(class)
+    }
+
+    fn parse_method_body(&mut self, method: &mut AsmMethod) -> Result<(), AssemblyError> {
+        loop {
+            self.skip_newlines();
+            let (line, column) = self.location();
+            match self.next() {
+                None => return Err(AssemblyError::Syntax { line, column, message: format!("missing `.end method` for `{}`", method.name) }),
+                Some(TokenKind::LabelDef(name)) => {
+                    method.body.push(AsmStatement::Label { name, line });
+                    continue;
+                },
+                Some(TokenKind::Directive(directive)) => match directive.as_str() {
+                    "end" => {
+                        self.expect_keyword("method")?;
+                        return self.expect_newline();
+                    },
+                    "limit" => {
+                        let what = self.expect_word("`stack` or `locals`")?;
+                        let value = self.expect_int_in("limit")?;
+                        match what.as_str() {
+                            "stack" => method.max_stack = Some(value),
+                            "locals" => method.max_locals = Some(value),
+                            _ => return Err(AssemblyError::Syntax { line, column, message: format!("unknown limit `{}`", what) }),
+                        }
+                    },
+                    "catch" => {
+                        let catch_type = match self.expect_word("exception class or `all`")? {
+                            all if all == "all" => None,
+                            class => Some(class),
+                        };
+                        self.expect_keyword("from")?;
+                        let from = self.expect_word("label")?;
+                        self.expect_keyword("to")?;
+                        let to = self.expect_word("label")?;
+                        self.expect_keyword("using")?;
+                        let using = self.expect_word("label")?;
+                        method.body.push(AsmStatement::Catch { catch_type, from, to, using, line });
+                    },
+                    "line" => {
+                        let number = self.expect_int_in("line number")?;
+                        method.body.push(AsmStatement::Line { number, line });
+                    },
+                    "throws" => method.throws.push(self.expect_word("exception class")?),
+                    other => return Err(AssemblyError::Syntax { line, column, message: format!("unknown or misplaced directive `.{}`", other) }),
+                },
+                Some(TokenKind::Word(mnemonic)) => {
+                    let instruction = self.parse_instruction(&mnemonic, line, column)?;
+                    method.body.push(AsmStatement::Instruction(instruction));
+                    continue;
+                },
+                Some(other) => return Err(AssemblyError::Syntax { line, column, message: format!("expected an instruction, found {}", describe(&other)) }),
+            }
+            self.expect_newline()?;
+        }
+    }
+
+    fn parse_instruction(&mut self, mnemonic: &str, line: usize, column: usize) -> Result<AsmInstruction, AssemblyError> {
+        let (opcode, syntax) = match OpcodeInfo::by_mnemonic(mnemonic) {
+            Some(info) => (info.opcode, OperandSyntax::of(info)),
+            None => return Err(AssemblyError::UnknownMnemonic { line, column, mnemonic: mnemonic.to_string() }),
+        };
+        if syntax == S::Wide {
+            let (line, column) = self.location();
+            let inner = self.expect_word("instruction to widen")?;
+            let mut instruction = self.parse_instruction(&inner, line, column)?;
+            if !matches!(instruction.operand, AsmOperand::Local(_) | AsmOperand::Iinc(..)) {
+                return Err(AssemblyError::Syntax { line, column, message: format!("`{}` can not be widened", inner) });
+            }
+            instruction.wide = true;
+            return Ok(instruction);
+        }
+        let operand = match syntax {
+            S::None => AsmOperand::None,
+            S::Byte => AsmOperand::Int(self.expect_int_in::<i8>("byte")? as i32),
+            S::Short => AsmOperand::Int(self.expect_int_in::<i16>("short")? as i32),
+            S::Ldc => AsmOperand::Constant(match self.parse_constant()? {
+                AsmConstant::Double(value) if matches!(self.tokens[self.pos - 1].kind, TokenKind::Float(_, FloatSuffix::None)) => {
+                    AsmConstant::Float(value as f32)
+                },
+                AsmConstant::Long(_) | AsmConstant::Double(_) => {
+                    return Err(AssemblyError::Syntax { line, column, message: format!("`{}` can not load a long or double, use `ldc2_w`", mnemonic) })
+                },
+                constant => constant,
+            }),
+            S::Ldc2 => AsmOperand::Constant(match self.parse_constant()? {
+                AsmConstant::Int(value) => AsmConstant::Long(value as i64),
+                constant @ (AsmConstant::Long(_) | AsmConstant::Double(_)) => constant,
+                _ => return Err(AssemblyError::Syntax { line, column, message: "`ldc2_w` expects a long or double".to_string() }),
+            }),
+            S::Local => AsmOperand::Local(self.expect_int_in("local variable index")?),
+            S::Iinc => {
+                let index = self.expect_int_in("local variable index")?;
+                let increment = self.expect_int_in("increment")?;
+                AsmOperand::Iinc(index, increment)
+            },
+            S::Branch => AsmOperand::Label(self.expect_word("label")?),
+            S::TableSwitch => return self.parse_tableswitch(opcode, line, column),
+            S::LookupSwitch => return self.parse_lookupswitch(opcode, line, column),
+            S::Field => {
+                let interface = self.eat_keyword("interface");
+                AsmOperand::Field(self.parse_field_ref(interface)?)
+            },
+            S::Method => {
+                let interface = self.eat_keyword("interface");
+                AsmOperand::Method(self.parse_method_ref(interface)?)
+            },
+            S::InterfaceMethod => {
+                let method = self.parse_method_ref(true)?;
+                let count = match self.peek() {
+                    Some(TokenKind::Int(_)) => Some(self.expect_int_in("argument count")?),
+                    _ => None,
+                };
+                AsmOperand::InvokeInterface(method, count)
+            },
+            S::InvokeDynamic => {
+                let signature = self.expect_word("name(descriptor)")?;
+                let (name, descriptor) = match signature.find('(') {
+                    Some(i) if i > 0 => (signature[..i].to_string(), signature[i..].to_string()),
+                    _ => return Err(AssemblyError::Syntax { line, column, message: format!("expected `name(descriptor)`, found `{}`", signature) }),
+                };
+                let bootstrap = self.parse_handle()?;
+                let mut args = Vec::new();
+                if self.peek() == Some(&TokenKind::LBrace) {
+                    self.pos += 1;
+                    while self.peek() != Some(&TokenKind::RBrace) {
+                        args.push(self.parse_constant()?);
+                        if self.peek() == Some(&TokenKind::Comma) {
+                            self.pos += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    self.expect_token(TokenKind::RBrace)?;
+                }
+                AsmOperand::InvokeDynamic { name, descriptor, bootstrap, args }
+            },
+            S::Class => AsmOperand::Class(self.expect_word("class name")?),
+            S::ArrayType => {
+                let name = self.expect_word("array type")?;
+                let atype = match ArrayType::from_name(&name) {
+                    Some(atype) => atype,
+                    None => return Err(AssemblyError::Syntax { line, column, message: format!("unknown primitive array type `{}`", name) }),
+                };
+                AsmOperand::ArrayType(atype)
+            },
+            S::MultiANewArray => {
+                let class = self.expect_word("array class")?;
+                let dimensions = self.expect_int_in("dimensions")?;
+                AsmOperand::MultiANewArray(class, dimensions)
+            },
+            S::Wide => unreachable!(),
+        };
+        self.expect_newline()?;
+        Ok(AsmInstruction { opcode, operand, wide: false, line, column })
+    }
+
+    /// ```text
+    /// tableswitch <low>
+    ///     <label>
+    ///     ...
+    ///     default : <label>
+    /// ```
+    fn parse_tableswitch(&mut self, opcode: u8, line: usize, column: usize) -> Result<AsmInstruction, AssemblyError> {
+        let low = self.expect_int_in("low key")?;
+        let high: Option<i32> = match self.peek() {
+            Some(TokenKind::Int(_)) => Some(self.expect_int_in("high key")?),
+            _ => None,
+        };
+        self.expect_newline()?;
+        let mut targets = Vec::new();
+        let default = loop {
+            self.skip_newlines();
+            if let Some(label) = self.parse_default()? {
+                break label;
+            }
+            targets.push(self.expect_word("label or `default`")?);
+            self.expect_newline()?;
+        };
+        if targets.is_empty() {
+            return Err(AssemblyError::Syntax { line, column, message: "`tableswitch` needs at least one target".to_string() });
+        }
+        if let Some(high) = high {
+            if high as i64 - low as i64 + 1 != targets.len() as i64 {
+                return Err(AssemblyError::Syntax { line, column, message: format!("`tableswitch` from {} to {} needs {} targets, found {}", low, high, high as i64 - low as i64 + 1, targets.len()) });
+            }
+        }
+        Ok(AsmInstruction { opcode, operand: AsmOperand::TableSwitch { low, targets, default }, wide: false, line, column })
+    }
+
+    /// ```text
+    /// lookupswitch
+    ///     <key> : <label>
+    ///     ...
+    ///     default : <label>
+    /// ```
+    fn parse_lookupswitch(&mut self, opcode: u8, line: usize, column: usize) -> Result<AsmInstruction, AssemblyError> {
+        self.expect_newline()?;
+        let mut pairs = Vec::new();
+        let default = loop {
+            self.skip_newlines();
+            if let Some(label) = self.parse_default()? {
+                break label;
+            }
+            let key = self.expect_int_in("key or `default`")?;
+            self.expect_token(TokenKind::Colon)?;
+            pairs.push((key, self.expect_word("label")?));
+            self.expect_newline()?;
+        };
+        Ok(AsmInstruction { opcode, operand: AsmOperand::LookupSwitch { pairs, default }, wide: false, line, column })
+    }
+
+    fn parse_default(&mut self) -> Result<Option<String>, AssemblyError> {
+        match self.peek() {
+            Some(TokenKind::LabelDef(name)) if name == "default" => self.pos += 1,
+            Some(TokenKind::Word(name)) if name == "default" => {
+                self.pos += 1;
+                self.expect_token(TokenKind::Colon)?;
+            },
+            _ => return Ok(None),
+        }
+        let label = self.expect_word("label")?;
+        self.expect_newline()?;
+        Ok(Some(label))
+    }
+
+    /// `owner/name descriptor`
+    pub(crate) fn parse_field_ref(&mut self, interface: bool) -> Result<AsmMemberRef, AssemblyError> {
+        let path = self.expect_word("field reference")?;
+        let descriptor = self.expect_word("field descriptor")?;
+        match path.rfind('/') {
+            Some(i) if i > 0 && i + 1 < path.len() => Ok(AsmMemberRef {
+                owner: path[..i].to_string(),
+                name: path[i + 1..].to_string(),
+                descriptor,
+                interface,
+            }),
+            _ => {
+                self.pos -= 2;
+                self.error(format!("expected `owner/name`, found `{}`", path))
+            },
+        }
+    }
+
+    /// `owner/name(descriptor)`
+    pub(crate) fn parse_method_ref(&mut self, interface: bool) -> Result<AsmMemberRef, AssemblyError> {
+        let path = self.expect_word("method reference")?;
+        let paren = path.find('(').unwrap_or(path.len());
+        match path[..paren].rfind('/') {
+            Some(i) if i > 0 && i + 1 < paren && paren < path.len() => Ok(AsmMemberRef {
+                owner: path[..i].to_string(),
+                name: path[i + 1..paren].to_string(),
+                descriptor: path[paren..].to_string(),
+                interface,
+            }),
+            _ => {
+                self.pos -= 1;
+                self.error(format!("expected `owner/name(descriptor)`, found `{}`", path))
+            },
+        }
+    }
+
+    /// `<kind> [interface] <reference>`
+    pub(crate) fn parse_handle(&mut self) -> Result<AsmHandle, AssemblyError> {
+        let kind_name = self.expect_word("method handle kind")?;
+        let kind = match kind_name.as_str() {
+            "getfield" => MethodHandleKind::GetField,
+            "getstatic" => MethodHandleKind::GetStatic,
+            "putfield" => MethodHandleKind::PutField,
+            "putstatic" => MethodHandleKind::PutStatic,
+            "invokevirtual" => MethodHandleKind::InvokeVirtual,
+            "invokestatic" => MethodHandleKind::InvokeStatic,
+            "invokespecial" => MethodHandleKind::InvokeSpecial,
+            "newinvokespecial" => MethodHandleKind::NewInvokeSpecial,
+            "invokeinterface" => MethodHandleKind::InvokeInterface,
+            _ => {
+                self.pos -= 1;
+                return self.error(format!("unknown method handle kind `{}`", kind_name));
+            },
+        };
+        let interface = self.eat_keyword("interface") || matches!(kind, MethodHandleKind::InvokeInterface);
+        let member = match kind {
+            MethodHandleKind::GetField | MethodHandleKind::GetStatic
+            | MethodHandleKind::PutField | MethodHandleKind::PutStatic => self.parse_field_ref(interface)?,
+            _ => self.parse_method_ref(interface)?,
+        };
+        Ok(AsmHandle { kind, member })
+    }
+
+    fn parse_constant(&mut self) -> Result<AsmConstant, AssemblyError> {
+        let (line, column) = self.location();
+        let out_of_range = |value: i64| AssemblyError::Syntax { line, column, message: format!("{} does not fit in an int, add an `L` suffix", value) };
+        Ok(match self.next() {
+            Some(TokenKind::Int(value)) => AsmConstant::Int(i32::try_from(value).map_err(|_| out_of_range(value))?),
+            Some(TokenKind::Long(value)) => AsmConstant::Long(value),
+            Some(TokenKind::Float(value, FloatSuffix::D)) => AsmConstant::Double(value),
+            Some(TokenKind::Float(value, FloatSuffix::F)) => AsmConstant::Float(value as f32),
+            // without a suffix the instruction decides, `ldc` takes a float and `ldc2_w` a double
+            Some(TokenKind::Float(value, FloatSuffix::None)) => AsmConstant::Double(value),
+            Some(TokenKind::Str(value)) => AsmConstant::String(value),
+            Some(TokenKind::Word(word)) => match word.as_str() {
+                "class" => AsmConstant::Class(self.expect_word("class name")?),
+                "methodtype" => AsmConstant::MethodType(self.expect_word("method descriptor")?),
+                "handle" => AsmConstant::MethodHandle(self.parse_handle()?),
+                _ => return Err(AssemblyError::Syntax { line, column, message: format!("expected a constant, found `{}`", word) }),
+            },
+            other => return Err(AssemblyError::Syntax {
+                line,
+                column,
+                message: format!("expected a constant, found {}", other.as_ref().map(describe).unwrap_or_else(|| "end of file".to_string())),
+            }),
+        })
+    }
+}
+
+pub(crate) fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Directive(directive) => format!("`.{}`", directive),
+        TokenKind::Word(word) => format!("`{}`", word),
+        TokenKind::LabelDef(label) => format!("label `{}:`", label),
+        TokenKind::Int(value) => format!("`{}`", value),
+        TokenKind::Long(value) => format!("`{}L`", value),
+        TokenKind::Float(value, _) => format!("`{}`", value),
+        TokenKind::Str(value) => format!("{:?}", value),
+        TokenKind::Colon => "`:`".to_string(),
+        TokenKind::Equals => "`=`".to_string(),
+        TokenKind::Comma => "`,`".to_string(),
+        TokenKind::LBrace => "`{`".to_string(),
+        TokenKind::RBrace => "`}`".to_string(),
+        TokenKind::Newline => "end of line".to_string(),
+    }
+}
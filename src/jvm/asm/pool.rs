@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use crate::{jvm::reader::{constant_pool::{ConstantPool, ConstantPoolInfo}, method_handle_kind::MethodHandleKind}, util::code_err::ClassParseError};
+
+/// Hashable identity of a constant pool entry, floats are compared by their bits.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PoolKey {
+    Utf8(String),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    Class(u16),
+    String(u16),
+    Field(u16, u16),
+    Method(u16, u16),
+    InterfaceMethod(u16, u16),
+    NameAndType(u16, u16),
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    Dynamic(u16, u16),
+    InvokeDynamic(u16, u16),
+}
+
+/// Builds a [ConstantPool], handing out the index of an existing entry instead of
+/// adding the same constant twice.
+#[derive(Debug, Default)]
+pub struct ConstantPoolBuilder {
+    pool: ConstantPool,
+    entries: HashMap<PoolKey, u16>,
+}
+
+impl ConstantPoolBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn pool(&self) -> &ConstantPool {
+        &self.pool
+    }
+    pub fn finish(self) -> ConstantPool {
+        self.pool
+    }
+
+    fn intern(&mut self, key: PoolKey, info: ConstantPoolInfo) -> Result<u16, ClassParseError> {
+        if let Some(index) = self.entries.get(&key) {
+            return Ok(*index);
+        }
+        let slots = if matches!(info, ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_)) { 2 } else { 1 };
+        if self.pool.count() + slots > u16::MAX as usize {
+            return Err(ClassParseError::BadValue {
+                expected: format!("at most {} constant pool entries", u16::MAX - 1),
+                got: "more".to_string(),
+                for_what: "Constant Pool".to_string(),
+            });
+        }
+        let index = self.pool.push(info);
+        self.entries.insert(key, index);
+        Ok(index)
+    }
+
+    pub fn utf8(&mut self, value: &str) -> Result<u16, ClassParseError> {
+        self.intern(PoolKey::Utf8(value.to_string()), ConstantPoolInfo::Utf8(value.to_string()))
+    }
+    pub fn integer(&mut self, value: i32) -> Result<u16, ClassParseError> {
+        self.intern(PoolKey::Integer(value), ConstantPoolInfo::Integer(value))
+    }
+    pub fn float(&mut self, value: f32) -> Result<u16, ClassParseError> {
+        self.intern(PoolKey::Float(value.to_bits()), ConstantPoolInfo::Float(value))
+    }
+    pub fn long(&mut self, value: i64) -> Result<u16, ClassParseError> {
+        self.intern(PoolKey::Long(value), ConstantPoolInfo::Long(value))
+    }
+    pub fn double(&mut self, value: f64) -> Result<u16, ClassParseError> {
+        self.intern(PoolKey::Double(value.to_bits()), ConstantPoolInfo::Double(value))
+    }
+    /// `name` is an internal name (`java/lang/Object`) or an array descriptor (`[I`).
+    pub fn class(&mut self, name: &str) -> Result<u16, ClassParseError> {
+        let name = self.utf8(name)?;
+        self.intern(PoolKey::Class(name), ConstantPoolInfo::ClassRef(name))
+    }
+    pub fn string(&mut self, value: &str) -> Result<u16, ClassParseError> {
+        let value = self.utf8(value)?;
+        self.intern(PoolKey::String(value), ConstantPoolInfo::StringRef(value))
+    }
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> Result<u16, ClassParseError> {
+        let name = self.utf8(name)?;
+        let descriptor = self.utf8(descriptor)?;
+        self.intern(PoolKey::NameAndType(name, descriptor), ConstantPoolInfo::NameAndType(name, descriptor))
+    }
+    pub fn field_ref(&mut self, owner: &str, name: &str, descriptor: &str) -> Result<u16, ClassParseError> {
+        let class = self.class(owner)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.intern(PoolKey::Field(class, name_and_type), ConstantPoolInfo::FieldRef { class, name_and_type })
+    }
+    pub fn method_ref(&mut self, owner: &str, name: &str, descriptor: &str) -> Result<u16, ClassParseError> {
+        let class = self.class(owner)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.intern(PoolKey::Method(class, name_and_type), ConstantPoolInfo::MethodRef { class, name_and_type })
+    }
+    pub fn interface_method_ref(&mut self, owner: &str, name: &str, descriptor: &str) -> Result<u16, ClassParseError> {
+        let class = self.class(owner)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.intern(PoolKey::InterfaceMethod(class, name_and_type), ConstantPoolInfo::InterfaceMethodRef { class, name_and_type })
+    }
+    /// `reference` is the index of the field or method ref the handle points to.
+    pub fn method_handle(&mut self, kind: MethodHandleKind, reference: u16) -> Result<u16, ClassParseError> {
+        self.intern(PoolKey::MethodHandle(kind.to_ordinal(), reference), ConstantPoolInfo::MethodHandle { kind, index: reference })
+    }
+    pub fn method_type(&mut self, descriptor: &str) -> Result<u16, ClassParseError> {
+        let descriptor = self.utf8(descriptor)?;
+        self.intern(PoolKey::MethodType(descriptor), ConstantPoolInfo::MethodType(descriptor))
+    }
+    pub fn dynamic(&mut self, bootstrap_method_attr_index: u16, name: &str, descriptor: &str) -> Result<u16, ClassParseError> {
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.intern(
+            PoolKey::Dynamic(bootstrap_method_attr_index, name_and_type_index),
+            ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index },
+        )
+    }
+    pub fn invoke_dynamic(&mut self, bootstrap_method_attr_index: u16, name: &str, descriptor: &str) -> Result<u16, ClassParseError> {
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.intern(
+            PoolKey::InvokeDynamic(bootstrap_method_attr_index, name_and_type_index),
+            ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index },
+        )
+    }
+}
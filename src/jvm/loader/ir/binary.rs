@@ -0,0 +1,1026 @@
+//! The binary form of rootir, the artifact eden produces from class files and jars.
+//!
+//! ```text
+//! module   := magic:"\0rir" major:u16 minor:u16 section*
+//! section  := id:u8 size:uleb128 payload[size]
+//! strings  := count (length utf8[length])*                                  id 1
+//! types    := count type*                                                   id 2
+//! classes  := count class*                                                  id 3
+//! code     := function bodies, found through the offsets in `classes`       id 4
+//! debug    := per class an optional source and per function block offsets  id 5
+//! ```
+//!
+//! Fixed-size integers are little-endian, every other number is LEB128, signed where a value
+//! can be negative. Names are indices into the string table, descriptors indices into the
+//! type table, whose entries refer to earlier entries for component, parameter and return
+//! types. Readers skip sections they don't know, so later minor versions can add some; a
+//! different major version is rejected.
+//!
+//! Function bodies are only decoded when asked for, a module can be opened to take a single
+//! function out of it.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{
+    jvm::reader::{
+        bootstrap::{BootstrapArgument, CallSite, MethodHandleRef},
+        constant_pool::MemberRef,
+        descriptor::{FieldType, MethodDescriptor},
+        method_handle_kind::MethodHandleKind,
+    },
+    util::code_err::ClassParseError,
+};
+
+use super::{
+    function::{Block, Function, Handler, Statement},
+    ir_instructions::{ArithmeticOp, ArithmeticOpType, CompareOp, Condition, ElementType, Instruction, InvokeKind},
+    ir_value::{BlockId, Constant, IrType, IrValue, Variable},
+    module::{ClassDebugInfo, DebugInfo, IrClass, IrField, Module},
+};
+
+pub const MAGIC: [u8; 4] = *b"\0rir";
+/// Readers reject other major versions and accept any minor version.
+pub const VERSION: (u16, u16) = (2, 0);
+
+const STRINGS: u8 = 1;
+const TYPES: u8 = 2;
+const CLASSES: u8 = 3;
+const CODE: u8 = 4;
+const DEBUG: u8 = 5;
+
+// tags of the type table, the primitives are tagged with their descriptor character
+const OBJECT: u8 = b'L';
+const ARRAY: u8 = b'[';
+const METHOD: u8 = b'(';
+/// A descriptor that doesn't parse, kept as written
+const RAW: u8 = b'?';
+
+const IR_TYPES: [IrType; 6] = [IrType::Int, IrType::Long, IrType::Float, IrType::Double, IrType::Reference, IrType::ReturnAddress];
+const ARITHMETIC_OPS: [ArithmeticOp; 11] = {
+    use ArithmeticOp::*;
+    [Add, Sub, Mul, Div, Rem, And, Or, Xor, Shl, Shr, Ushr]
+};
+const OP_TYPES: [ArithmeticOpType; 12] = {
+    use ArithmeticOpType::*;
+    [U8, U16, U32, U64, U128, I8, I16, I32, I64, I128, F32, F64]
+};
+const COMPARE_OPS: [CompareOp; 3] = [CompareOp::Cmp, CompareOp::CmpL, CompareOp::CmpG];
+const CONDITIONS: [Condition; 6] = [Condition::Eq, Condition::Ne, Condition::Lt, Condition::Ge, Condition::Gt, Condition::Le];
+const INVOKE_KINDS: [InvokeKind; 4] = [InvokeKind::Virtual, InvokeKind::Special, InvokeKind::Static, InvokeKind::Interface];
+const ELEMENT_TYPES: [ElementType; 8] = {
+    use ElementType::*;
+    [Byte, Char, Short, Int, Long, Float, Double, Reference]
+};
+
+fn ordinal<T: PartialEq>(table: &[T], value: &T) -> u8 {
+    table.iter().position(|entry| entry == value).unwrap() as u8
+}
+
+/// Encodes a module, with its debug info when given.
+pub fn write_module(module: &Module, debug: Option<&DebugInfo>) -> Vec<u8> {
+    let mut writer = Writer::default();
+    let mut classes = Vec::new();
+    uleb(&mut classes, module.classes.len() as u64);
+    for class in &module.classes {
+        writer.class(&mut classes, class);
+    }
+    let debug = debug.map(|debug| writer.debug(debug));
+
+    let mut out = MAGIC.to_vec();
+    out.extend(VERSION.0.to_le_bytes());
+    out.extend(VERSION.1.to_le_bytes());
+    let mut strings = Vec::new();
+    uleb(&mut strings, writer.strings.len() as u64);
+    for string in &writer.strings {
+        uleb(&mut strings, string.len() as u64);
+        strings.extend(string.as_bytes());
+    }
+    let mut types = Vec::new();
+    uleb(&mut types, writer.type_count as u64);
+    types.extend(&writer.types);
+    for (id, payload) in [(STRINGS, strings), (TYPES, types), (CLASSES, classes), (CODE, writer.code)].into_iter().chain(debug.map(|debug| (DEBUG, debug))) {
+        out.push(id);
+        uleb(&mut out, payload.len() as u64);
+        out.extend(payload);
+    }
+    out
+}
+
+fn uleb(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn sleb(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    strings: Vec<String>,
+    string_indices: HashMap<String, u32>,
+    /// Encoded entries of the type table
+    types: Vec<u8>,
+    type_count: u32,
+    type_indices: HashMap<String, u32>,
+    code: Vec<u8>,
+}
+
+impl Writer {
+    fn string(&mut self, out: &mut Vec<u8>, string: &str) {
+        let index = match self.string_indices.get(string) {
+            Some(index) => *index,
+            None => {
+                self.strings.push(string.to_string());
+                self.string_indices.insert(string.to_string(), self.strings.len() as u32 - 1);
+                self.strings.len() as u32 - 1
+            },
+        };
+        uleb(out, index as u64);
+    }
+    fn descriptor(&mut self, out: &mut Vec<u8>, descriptor: &str) {
+        let index = self.type_index(descriptor);
+        uleb(out, index as u64);
+    }
+    /// Interns a descriptor, after the types it is made of.
+    fn type_index(&mut self, descriptor: &str) -> u32 {
+        if let Some(index) = self.type_indices.get(descriptor) {
+            return *index;
+        }
+        let mut entry = Vec::new();
+        let field = FieldType::parse(descriptor).ok().filter(|ty| ty.to_string() == descriptor);
+        let method = MethodDescriptor::parse(descriptor).ok().filter(|ty| ty.to_string() == descriptor);
+        match (field, method) {
+            (Some(FieldType::Object(name)), _) => {
+                entry.push(OBJECT);
+                self.string(&mut entry, &name);
+            },
+            (Some(FieldType::Array(component)), _) => {
+                let component = self.type_index(&component.to_string());
+                entry.push(ARRAY);
+                uleb(&mut entry, component as u64);
+            },
+            (Some(_), _) => entry.push(descriptor.as_bytes()[0]),
+            (None, Some(method)) => {
+                let params: Vec<u32> = method.params.iter().map(|param| self.type_index(&param.to_string())).collect();
+                // the return type is 0 for void, its index plus one otherwise
+                let ret = method.ret.map(|ret| self.type_index(&ret.to_string()) + 1).unwrap_or(0);
+                entry.push(METHOD);
+                uleb(&mut entry, params.len() as u64);
+                for param in params {
+                    uleb(&mut entry, param as u64);
+                }
+                uleb(&mut entry, ret as u64);
+            },
+            (None, None) => {
+                entry.push(RAW);
+                self.string(&mut entry, descriptor);
+            },
+        }
+        self.types.extend(entry);
+        self.type_count += 1;
+        self.type_indices.insert(descriptor.to_string(), self.type_count - 1);
+        self.type_count - 1
+    }
+
+    fn class(&mut self, out: &mut Vec<u8>, class: &IrClass) {
+        self.string(out, &class.name);
+        match &class.super_name {
+            Some(super_name) => {
+                out.push(1);
+                self.string(out, super_name);
+            },
+            None => out.push(0),
+        }
+        uleb(out, class.interfaces.len() as u64);
+        for interface in &class.interfaces {
+            self.string(out, interface);
+        }
+        uleb(out, class.access_flags as u64);
+        uleb(out, class.fields.len() as u64);
+        for field in &class.fields {
+            self.string(out, &field.name);
+            self.descriptor(out, &field.descriptor);
+            uleb(out, field.access_flags as u64);
+        }
+        uleb(out, class.functions.len() as u64);
+        for function in &class.functions {
+            self.string(out, &function.name);
+            self.descriptor(out, &function.descriptor);
+            uleb(out, function.access_flags as u64);
+            // a body of length 0 is a method without code
+            let start = self.code.len();
+            if !function.blocks.is_empty() {
+                let mut body = Vec::new();
+                self.body(&mut body, function);
+                self.code.extend(body);
+            }
+            uleb(out, start as u64);
+            uleb(out, (self.code.len() - start) as u64);
+        }
+    }
+
+    fn body(&mut self, out: &mut Vec<u8>, function: &Function) {
+        uleb(out, function.values.len() as u64);
+        out.extend(function.values.iter().map(|ty| ordinal(&IR_TYPES, ty)));
+        uleb(out, function.blocks.len() as u64);
+        for block in &function.blocks {
+            // the class is 0 for a handler catching anything, the string plus one otherwise
+            uleb(out, block.handlers.len() as u64);
+            for handler in &block.handlers {
+                match &handler.class {
+                    Some(class) => {
+                        out.push(1);
+                        self.string(out, class);
+                    },
+                    None => out.push(0),
+                }
+                uleb(out, handler.target.0 as u64);
+            }
+            uleb(out, block.statements.len() as u64);
+            for statement in &block.statements {
+                // the result is 0 for none, the value plus one otherwise
+                uleb(out, statement.result.map(|value| value.0 as u64 + 1).unwrap_or(0));
+                self.instruction(out, &statement.instruction);
+            }
+        }
+    }
+
+    fn instruction(&mut self, out: &mut Vec<u8>, instruction: &Instruction<IrValue>) {
+        use Instruction as I;
+        let value = |out: &mut Vec<u8>, value: &IrValue| uleb(out, value.0 as u64);
+        let values = |out: &mut Vec<u8>, values: &[IrValue]| {
+            uleb(out, values.len() as u64);
+            for v in values {
+                uleb(out, v.0 as u64);
+            }
+        };
+        let block = |out: &mut Vec<u8>, block: &BlockId| uleb(out, block.0 as u64);
+        out.push(opcode(instruction));
+        match instruction {
+            I::Param { index } => uleb(out, *index as u64),
+            I::Const { value } => self.constant(out, value),
+            I::GetVar { var } => variable(out, var),
+            I::SetVar { var, value: v } => {
+                variable(out, var);
+                value(out, v);
+            },
+            I::Arithmetic { l, r, op, ty } => {
+                out.push(ordinal(&ARITHMETIC_OPS, op));
+                out.push(ordinal(&OP_TYPES, ty));
+                value(out, l);
+                value(out, r);
+            },
+            I::Negate { value: v, ty } => {
+                out.push(ordinal(&OP_TYPES, ty));
+                value(out, v);
+            },
+            I::ArrayLength { array: v } | I::NullCheck { value: v } | I::MonitorEnter { object: v }
+            | I::MonitorExit { object: v } | I::Focus { value: v } | I::Throw { value: v } => value(out, v),
+            I::Catch => {},
+            I::Convert { value: v, from, to } => {
+                out.push(ordinal(&OP_TYPES, from));
+                out.push(ordinal(&OP_TYPES, to));
+                value(out, v);
+            },
+            I::Compare { l, r, op, ty } => {
+                out.push(ordinal(&COMPARE_OPS, op));
+                out.push(ordinal(&OP_TYPES, ty));
+                value(out, l);
+                value(out, r);
+            },
+            I::NewSizedArray { element, size } => {
+                self.descriptor(out, &element.to_string());
+                uleb(out, *size as u64);
+            },
+            I::NewDynamicArray { element, length } => {
+                self.descriptor(out, &element.to_string());
+                value(out, length);
+            },
+            I::NewMultiArray { class, dimensions } => {
+                self.descriptor(out, &class.to_string());
+                values(out, dimensions);
+            },
+            I::New { class } => self.string(out, class),
+            I::ArrayLoad { array, index, element } => {
+                out.push(ordinal(&ELEMENT_TYPES, element));
+                value(out, array);
+                value(out, index);
+            },
+            I::ArrayStore { array, index, value: v, element } => {
+                out.push(ordinal(&ELEMENT_TYPES, element));
+                value(out, array);
+                value(out, index);
+                value(out, v);
+            },
+            I::GetField { field, object } => {
+                self.member(out, field);
+                values(out, object.as_slice());
+            },
+            I::PutField { field, object, value: v } => {
+                self.member(out, field);
+                values(out, object.as_slice());
+                value(out, v);
+            },
+            I::Invoke { kind, method, num_args, args } => {
+                out.push(ordinal(&INVOKE_KINDS, kind));
+                self.member(out, method);
+                uleb(out, *num_args as u64);
+                values(out, args);
+            },
+            I::InvokeDynamic { site, args } => {
+                self.call_site(out, site);
+                values(out, args);
+            },
+            I::CheckCast { value: v, class } | I::InstanceOf { value: v, class } => {
+                self.string(out, class);
+                value(out, v);
+            },
+            I::Phi { inputs } => {
+                uleb(out, inputs.len() as u64);
+                for (from, v) in inputs {
+                    block(out, from);
+                    value(out, v);
+                }
+            },
+            I::Goto { target } => block(out, target),
+            I::Branch { l, r, cond, if_true, if_false } => {
+                out.push(ordinal(&CONDITIONS, cond));
+                value(out, l);
+                value(out, r);
+                block(out, if_true);
+                block(out, if_false);
+            },
+            I::Switch { value: v, cases, default } => {
+                value(out, v);
+                block(out, default);
+                uleb(out, cases.len() as u64);
+                for (key, target) in cases {
+                    sleb(out, *key as i64);
+                    block(out, target);
+                }
+            },
+            I::IndirectJump { address, targets } => {
+                value(out, address);
+                uleb(out, targets.len() as u64);
+                for target in targets {
+                    block(out, target);
+                }
+            },
+            I::Return { value: v } => values(out, v.as_slice()),
+        }
+    }
+
+    fn constant(&mut self, out: &mut Vec<u8>, constant: &Constant) {
+        match constant {
+            Constant::Int(value) => {
+                out.push(0);
+                sleb(out, *value as i64);
+            },
+            Constant::Long(value) => {
+                out.push(1);
+                sleb(out, *value);
+            },
+            Constant::Float(value) => {
+                out.push(2);
+                out.extend(value.to_bits().to_le_bytes());
+            },
+            Constant::Double(value) => {
+                out.push(3);
+                out.extend(value.to_bits().to_le_bytes());
+            },
+            Constant::Null => out.push(4),
+            Constant::String(value) => {
+                out.push(5);
+                self.string(out, value);
+            },
+            Constant::Class(name) => {
+                out.push(6);
+                self.string(out, name);
+            },
+            Constant::MethodType(descriptor) => {
+                out.push(7);
+                self.descriptor(out, descriptor);
+            },
+            Constant::MethodHandle(handle) => {
+                out.push(8);
+                self.handle(out, handle);
+            },
+            Constant::Dynamic(site) => {
+                out.push(9);
+                self.call_site(out, site);
+            },
+            Constant::ReturnAddress(block) => {
+                out.push(10);
+                uleb(out, block.0 as u64);
+            },
+        }
+    }
+    fn member(&mut self, out: &mut Vec<u8>, member: &MemberRef) {
+        self.string(out, &member.class);
+        self.string(out, &member.name);
+        self.descriptor(out, &member.descriptor);
+        out.push(member.interface as u8);
+    }
+    fn handle(&mut self, out: &mut Vec<u8>, handle: &MethodHandleRef) {
+        out.push(handle.kind.to_ordinal());
+        self.member(out, &handle.member);
+    }
+    fn call_site(&mut self, out: &mut Vec<u8>, site: &CallSite) {
+        self.string(out, &site.name);
+        self.descriptor(out, &site.descriptor);
+        self.handle(out, &site.bootstrap);
+        uleb(out, site.arguments.len() as u64);
+        for argument in &site.arguments {
+            let constant = match argument {
+                BootstrapArgument::Int(value) => Constant::Int(*value),
+                BootstrapArgument::Float(value) => Constant::Float(*value),
+                BootstrapArgument::Long(value) => Constant::Long(*value),
+                BootstrapArgument::Double(value) => Constant::Double(*value),
+                BootstrapArgument::String(value) => Constant::String(value.clone()),
+                BootstrapArgument::Class(value) => Constant::Class(value.clone()),
+                BootstrapArgument::MethodType(value) => Constant::MethodType(value.clone()),
+                BootstrapArgument::MethodHandle(value) => Constant::MethodHandle(value.clone()),
+                BootstrapArgument::Dynamic(value) => Constant::Dynamic(value.clone()),
+            };
+            self.constant(out, &constant);
+        }
+    }
+
+    fn debug(&mut self, debug: &DebugInfo) -> Vec<u8> {
+        let mut out = Vec::new();
+        uleb(&mut out, debug.classes.len() as u64);
+        for class in &debug.classes {
+            match &class.source {
+                Some(source) => {
+                    out.push(1);
+                    self.string(&mut out, source);
+                },
+                None => out.push(0),
+            }
+            uleb(&mut out, class.block_offsets.len() as u64);
+            for offsets in &class.block_offsets {
+                uleb(&mut out, offsets.len() as u64);
+                for (block, offset) in offsets {
+                    uleb(&mut out, block.0 as u64);
+                    uleb(&mut out, *offset as u64);
+                }
+            }
+        }
+        out
+    }
+}
+
+fn variable(out: &mut Vec<u8>, var: &Variable) {
+    let (tag, index, ty) = match var {
+        Variable::Local(index, ty) => (0, index, ty),
+        Variable::Stack(depth, ty) => (1, depth, ty),
+    };
+    out.push(tag);
+    uleb(out, *index as u64);
+    out.push(ordinal(&IR_TYPES, ty));
+}
+
+fn opcode(instruction: &Instruction<IrValue>) -> u8 {
+    use Instruction as I;
+    match instruction {
+        I::Param { .. } => 0,
+        I::Const { .. } => 1,
+        I::GetVar { .. } => 2,
+        I::SetVar { .. } => 3,
+        I::Arithmetic { .. } => 4,
+        I::Negate { .. } => 5,
+        I::Convert { .. } => 6,
+        I::Compare { .. } => 7,
+        I::NewSizedArray { .. } => 8,
+        I::NewDynamicArray { .. } => 9,
+        I::NewMultiArray { .. } => 10,
+        I::New { .. } => 11,
+        I::ArrayLength { .. } => 12,
+        I::ArrayLoad { .. } => 13,
+        I::ArrayStore { .. } => 14,
+        I::GetField { .. } => 15,
+        I::PutField { .. } => 16,
+        I::Invoke { .. } => 17,
+        I::InvokeDynamic { .. } => 18,
+        I::CheckCast { .. } => 19,
+        I::InstanceOf { .. } => 20,
+        I::NullCheck { .. } => 21,
+        I::MonitorEnter { .. } => 22,
+        I::MonitorExit { .. } => 23,
+        I::Focus { .. } => 24,
+        I::Phi { .. } => 25,
+        I::Goto { .. } => 26,
+        I::Branch { .. } => 27,
+        I::Switch { .. } => 28,
+        I::IndirectJump { .. } => 29,
+        I::Return { .. } => 30,
+        I::Throw { .. } => 31,
+        I::Catch => 32,
+    }
+}
+
+/// A function of a [BinaryModule], whose body is decoded on demand.
+#[derive(Debug, Clone)]
+struct FunctionEntry {
+    name: String,
+    descriptor: String,
+    access_flags: u16,
+    /// Range of the body in the code section, empty for methods without code
+    body: Range<usize>,
+}
+
+/// An encoded module, read up to its class headers. Function bodies stay encoded until
+/// [function](Self::function) or one of the methods building whole classes asks for them.
+#[derive(Debug)]
+pub struct BinaryModule {
+    data: Vec<u8>,
+    pub version: (u16, u16),
+    strings: Vec<String>,
+    /// Each entry of the type table, as a descriptor
+    types: Vec<String>,
+    /// The classes, without their functions
+    classes: Vec<IrClass>,
+    functions: Vec<Vec<FunctionEntry>>,
+    code: Range<usize>,
+    debug: Option<Range<usize>>,
+}
+
+impl BinaryModule {
+    pub fn new(data: Vec<u8>) -> Result<Self, ClassParseError> {
+        let mut reader = Reader { data: &data, at: 0, end: data.len() };
+        if reader.bytes(4)? != MAGIC {
+            return Err(ClassParseError::BadValue {
+                expected: format!("magic {:?}", MAGIC),
+                got: format!("{:?}", &data[..4]),
+                for_what: "rootir module".to_string(),
+            });
+        }
+        let version = (reader.u2()?, reader.u2()?);
+        if version.0 != VERSION.0 {
+            return Err(ClassParseError::BadValue {
+                expected: format!("rootir version {}.x", VERSION.0),
+                got: format!("version {}.{}", version.0, version.1),
+                for_what: "rootir module".to_string(),
+            });
+        }
+        let mut sections: HashMap<u8, Range<usize>> = HashMap::new();
+        while reader.at < data.len() {
+            let id = reader.u1()?;
+            let size = reader.index()?;
+            let start = reader.at;
+            reader.bytes(size)?;
+            // unknown sections are skipped, a repeated one replaces the earlier
+            sections.insert(id, start..reader.at);
+        }
+        let section = |id: u8, name: &str| sections.get(&id).cloned().ok_or_else(|| ClassParseError::BadValue {
+            expected: format!("a {} section", name),
+            got: "none".to_string(),
+            for_what: "rootir module".to_string(),
+        });
+
+        let mut module = BinaryModule {
+            version,
+            strings: Vec::new(),
+            types: Vec::new(),
+            classes: Vec::new(),
+            functions: Vec::new(),
+            code: section(CODE, "code")?,
+            debug: sections.get(&DEBUG).cloned(),
+            data: Vec::new(),
+        };
+        let mut reader = Reader::section(&data, section(STRINGS, "string")?);
+        for _ in 0..reader.index()? {
+            let length = reader.index()?;
+            let bytes = reader.bytes(length)?.to_vec();
+            module.strings.push(String::from_utf8(bytes.clone()).map_err(|internal| ClassParseError::StringDecodeError { internal, buffer: bytes })?);
+        }
+        let mut reader = Reader::section(&data, section(TYPES, "type")?);
+        for _ in 0..reader.index()? {
+            let descriptor = module.read_type(&mut reader)?;
+            module.types.push(descriptor);
+        }
+        let mut reader = Reader::section(&data, section(CLASSES, "class")?);
+        for _ in 0..reader.index()? {
+            module.read_class(&mut reader)?;
+        }
+        module.data = data;
+        Ok(module)
+    }
+    pub fn open(path: &std::path::Path) -> Result<Self, ClassParseError> {
+        Self::new(std::fs::read(path).map_err(ClassParseError::IOError)?)
+    }
+
+    fn string(&self, reader: &mut Reader) -> Result<String, ClassParseError> {
+        let index = reader.index()?;
+        self.strings.get(index).cloned().ok_or_else(|| reader.bad("string index", index))
+    }
+    fn descriptor(&self, reader: &mut Reader) -> Result<String, ClassParseError> {
+        let index = reader.index()?;
+        self.types.get(index).cloned().ok_or_else(|| reader.bad("type index", index))
+    }
+    fn read_type(&self, reader: &mut Reader) -> Result<String, ClassParseError> {
+        let tag = reader.u1()?;
+        Ok(match tag {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => (tag as char).to_string(),
+            OBJECT => format!("L{};", self.string(reader)?),
+            ARRAY => format!("[{}", self.descriptor(reader)?),
+            METHOD => {
+                let mut descriptor = "(".to_string();
+                for _ in 0..reader.index()? {
+                    descriptor.push_str(&self.descriptor(reader)?);
+                }
+                descriptor.push(')');
+                match reader.index()? {
+                    0 => descriptor.push('V'),
+                    ret => descriptor.push_str(self.types.get(ret - 1).ok_or_else(|| reader.bad("type index", ret - 1))?),
+                }
+                descriptor
+            },
+            RAW => self.string(reader)?,
+            tag => return Err(reader.bad("type tag", tag as usize)),
+        })
+    }
+    fn read_class(&mut self, reader: &mut Reader) -> Result<(), ClassParseError> {
+        let name = self.string(reader)?;
+        let super_name = match reader.u1()? {
+            0 => None,
+            _ => Some(self.string(reader)?),
+        };
+        let interfaces = (0..reader.index()?).map(|_| self.string(reader)).collect::<Result<_, _>>()?;
+        let access_flags = reader.u2_leb()?;
+        let mut fields = Vec::new();
+        for _ in 0..reader.index()? {
+            fields.push(IrField { name: self.string(reader)?, descriptor: self.descriptor(reader)?, access_flags: reader.u2_leb()? });
+        }
+        let mut functions = Vec::new();
+        for _ in 0..reader.index()? {
+            let name = self.string(reader)?;
+            let descriptor = self.descriptor(reader)?;
+            let access_flags = reader.u2_leb()?;
+            let start = reader.index()?;
+            let length = reader.index()?;
+            if start.checked_add(length).filter(|end| *end <= self.code.len()).is_none() {
+                return Err(reader.bad("function body offset", start));
+            }
+            functions.push(FunctionEntry { name, descriptor, access_flags, body: start..start + length });
+        }
+        self.classes.push(IrClass { name, super_name, interfaces, access_flags, fields, functions: Vec::new() });
+        self.functions.push(functions);
+        Ok(())
+    }
+
+    pub fn class_count(&self) -> usize {
+        self.classes.len()
+    }
+    pub fn class_name(&self, class: usize) -> &str {
+        &self.classes[class].name
+    }
+    pub fn find_class(&self, name: &str) -> Option<usize> {
+        self.classes.iter().position(|class| class.name == name)
+    }
+    /// Name and descriptor of each function of a class, without decoding any of them.
+    pub fn function_signatures(&self, class: usize) -> impl Iterator<Item = (&str, &str)> {
+        self.functions[class].iter().map(|function| (function.name.as_str(), function.descriptor.as_str()))
+    }
+    pub fn find_function(&self, class: usize, name: &str, descriptor: &str) -> Option<usize> {
+        self.functions[class].iter().position(|function| function.name == name && function.descriptor == descriptor)
+    }
+    /// Decodes the `index`th function of a class.
+    pub fn function(&self, class: usize, index: usize) -> Result<Function, ClassParseError> {
+        let entry = &self.functions[class][index];
+        let mut function = Function::new(&entry.name, &entry.descriptor, entry.access_flags);
+        if entry.body.is_empty() {
+            return Ok(function);
+        }
+        let body = self.code.start + entry.body.start..self.code.start + entry.body.end;
+        let mut reader = Reader::section(&self.data, body);
+        for _ in 0..reader.index()? {
+            let ty = reader.u1()?;
+            function.values.push(*IR_TYPES.get(ty as usize).ok_or_else(|| reader.bad("value type", ty as usize))?);
+        }
+        for _ in 0..reader.index()? {
+            let mut block = Block::default();
+            for _ in 0..reader.index()? {
+                let class = match reader.u1()? {
+                    0 => None,
+                    _ => Some(self.string(&mut reader)?),
+                };
+                block.handlers.push(Handler { class, target: reader.block()? });
+            }
+            for _ in 0..reader.index()? {
+                let result = match reader.index()? {
+                    0 => None,
+                    value => Some(IrValue(value as u32 - 1)),
+                };
+                let instruction = self.instruction(&mut reader)?;
+                block.statements.push(Statement { result, instruction });
+            }
+            function.blocks.push(block);
+        }
+        if let Some(handler) = function.blocks.iter().flat_map(|block| &block.handlers).find(|handler| handler.target.0 as usize >= function.blocks.len()) {
+            return Err(reader.bad("block", handler.target.0 as usize));
+        }
+        for statement in function.blocks.iter().flat_map(|block| &block.statements) {
+            if let Some(value) = statement.result.iter().chain(statement.instruction.operands()).find(|value| value.0 as usize >= function.values.len()) {
+                return Err(reader.bad("value", value.0 as usize));
+            }
+            if let Some(block) = statement.instruction.successors().into_iter().find(|block| block.0 as usize >= function.blocks.len()) {
+                return Err(reader.bad("block", block.0 as usize));
+            }
+        }
+        Ok(function)
+    }
+    /// Decodes a class with all its functions.
+    pub fn class(&self, class: usize) -> Result<IrClass, ClassParseError> {
+        let mut ir = self.classes[class].clone();
+        ir.functions = (0..self.functions[class].len()).map(|index| self.function(class, index)).collect::<Result<_, _>>()?;
+        Ok(ir)
+    }
+    /// Decodes every class.
+    pub fn module(&self) -> Result<Module, ClassParseError> {
+        Ok(Module { classes: (0..self.classes.len()).map(|class| self.class(class)).collect::<Result<_, _>>()? })
+    }
+    /// The debug info, if the module was written with some.
+    pub fn debug_info(&self) -> Result<Option<DebugInfo>, ClassParseError> {
+        let range = match &self.debug {
+            Some(range) => range.clone(),
+            None => return Ok(None),
+        };
+        let mut reader = Reader::section(&self.data, range);
+        let mut debug = DebugInfo::default();
+        for _ in 0..reader.index()? {
+            let source = match reader.u1()? {
+                0 => None,
+                _ => Some(self.string(&mut reader)?),
+            };
+            let mut block_offsets = Vec::new();
+            for _ in 0..reader.index()? {
+                let mut offsets = Vec::new();
+                for _ in 0..reader.index()? {
+                    offsets.push((BlockId(reader.u4_leb()?), reader.u4_leb()?));
+                }
+                block_offsets.push(offsets);
+            }
+            debug.classes.push(ClassDebugInfo { source, block_offsets });
+        }
+        Ok(Some(debug))
+    }
+
+    fn instruction(&self, reader: &mut Reader) -> Result<Instruction<IrValue>, ClassParseError> {
+        use Instruction as I;
+        let opcode = reader.u1()?;
+        Ok(match opcode {
+            0 => I::Param { index: reader.index()? },
+            1 => I::Const { value: self.constant(reader)? },
+            2 => I::GetVar { var: reader.variable()? },
+            3 => I::SetVar { var: reader.variable()?, value: reader.value()? },
+            4 => I::Arithmetic {
+                op: reader.ordinal(&ARITHMETIC_OPS, "arithmetic op")?,
+                ty: reader.ordinal(&OP_TYPES, "operation type")?,
+                l: reader.value()?,
+                r: reader.value()?,
+            },
+            5 => I::Negate { ty: reader.ordinal(&OP_TYPES, "operation type")?, value: reader.value()? },
+            6 => I::Convert {
+                from: reader.ordinal(&OP_TYPES, "operation type")?,
+                to: reader.ordinal(&OP_TYPES, "operation type")?,
+                value: reader.value()?,
+            },
+            7 => I::Compare {
+                op: reader.ordinal(&COMPARE_OPS, "compare op")?,
+                ty: reader.ordinal(&OP_TYPES, "operation type")?,
+                l: reader.value()?,
+                r: reader.value()?,
+            },
+            8 => I::NewSizedArray { element: self.field_type(reader)?, size: reader.index()? },
+            9 => I::NewDynamicArray { element: self.field_type(reader)?, length: reader.value()? },
+            10 => I::NewMultiArray { class: self.field_type(reader)?, dimensions: reader.values()? },
+            11 => I::New { class: self.string(reader)? },
+            12 => I::ArrayLength { array: reader.value()? },
+            13 => I::ArrayLoad { element: reader.ordinal(&ELEMENT_TYPES, "element type")?, array: reader.value()?, index: reader.value()? },
+            14 => I::ArrayStore {
+                element: reader.ordinal(&ELEMENT_TYPES, "element type")?,
+                array: reader.value()?,
+                index: reader.value()?,
+                value: reader.value()?,
+            },
+            15 => I::GetField { field: self.member(reader)?, object: reader.optional_value()? },
+            16 => I::PutField { field: self.member(reader)?, object: reader.optional_value()?, value: reader.value()? },
+            17 => I::Invoke {
+                kind: reader.ordinal(&INVOKE_KINDS, "invoke kind")?,
+                method: self.member(reader)?,
+                num_args: reader.index()?,
+                args: reader.values()?,
+            },
+            18 => I::InvokeDynamic { site: self.call_site(reader)?, args: reader.values()? },
+            19 => I::CheckCast { class: self.string(reader)?, value: reader.value()? },
+            20 => I::InstanceOf { class: self.string(reader)?, value: reader.value()? },
+            21 => I::NullCheck { value: reader.value()? },
+            22 => I::MonitorEnter { object: reader.value()? },
+            23 => I::MonitorExit { object: reader.value()? },
+            24 => I::Focus { value: reader.value()? },
+            25 => I::Phi { inputs: (0..reader.index()?).map(|_| Ok((reader.block()?, reader.value()?))).collect::<Result<_, ClassParseError>>()? },
+            26 => I::Goto { target: reader.block()? },
+            32 => I::Catch,
+            27 => I::Branch {
+                cond: reader.ordinal(&CONDITIONS, "condition")?,
+                l: reader.value()?,
+                r: reader.value()?,
+                if_true: reader.block()?,
+                if_false: reader.block()?,
+            },
+            28 => {
+                let value = reader.value()?;
+                let default = reader.block()?;
+                let mut cases = Vec::new();
+                for _ in 0..reader.index()? {
+                    let key = reader.sleb()?;
+                    let key = i32::try_from(key).map_err(|_| reader.bad("switch key", key as usize))?;
+                    cases.push((key, reader.block()?));
+                }
+                I::Switch { value, cases, default }
+            },
+            29 => I::IndirectJump { address: reader.value()?, targets: (0..reader.index()?).map(|_| reader.block()).collect::<Result<_, _>>()? },
+            30 => I::Return { value: reader.optional_value()? },
+            31 => I::Throw { value: reader.value()? },
+            opcode => return Err(reader.bad("opcode", opcode as usize)),
+        })
+    }
+    fn field_type(&self, reader: &mut Reader) -> Result<FieldType, ClassParseError> {
+        FieldType::parse(&self.descriptor(reader)?)
+    }
+    fn constant(&self, reader: &mut Reader) -> Result<Constant, ClassParseError> {
+        let tag = reader.u1()?;
+        Ok(match tag {
+            0 => {
+                let value = reader.sleb()?;
+                Constant::Int(i32::try_from(value).map_err(|_| reader.bad("int constant", value as usize))?)
+            },
+            1 => Constant::Long(reader.sleb()?),
+            2 => Constant::Float(f32::from_bits(u32::from_le_bytes(reader.bytes(4)?.try_into().unwrap()))),
+            3 => Constant::Double(f64::from_bits(u64::from_le_bytes(reader.bytes(8)?.try_into().unwrap()))),
+            4 => Constant::Null,
+            5 => Constant::String(self.string(reader)?),
+            6 => Constant::Class(self.string(reader)?),
+            7 => Constant::MethodType(self.descriptor(reader)?),
+            8 => Constant::MethodHandle(self.handle(reader)?),
+            9 => Constant::Dynamic(Box::new(self.call_site(reader)?)),
+            10 => Constant::ReturnAddress(reader.block()?),
+            tag => return Err(reader.bad("constant tag", tag as usize)),
+        })
+    }
+    fn member(&self, reader: &mut Reader) -> Result<MemberRef, ClassParseError> {
+        Ok(MemberRef { class: self.string(reader)?, name: self.string(reader)?, descriptor: self.descriptor(reader)?, interface: reader.u1()? != 0 })
+    }
+    fn handle(&self, reader: &mut Reader) -> Result<MethodHandleRef, ClassParseError> {
+        let ordinal = reader.u1()?;
+        let kind = MethodHandleKind::from_ordinal(ordinal).ok_or_else(|| reader.bad("method handle kind", ordinal as usize))?;
+        Ok(MethodHandleRef { kind, member: self.member(reader)? })
+    }
+    fn call_site(&self, reader: &mut Reader) -> Result<CallSite, ClassParseError> {
+        let name = self.string(reader)?;
+        let descriptor = self.descriptor(reader)?;
+        let bootstrap = self.handle(reader)?;
+        let mut arguments = Vec::new();
+        for _ in 0..reader.index()? {
+            arguments.push(match self.constant(reader)? {
+                Constant::Int(value) => BootstrapArgument::Int(value),
+                Constant::Float(value) => BootstrapArgument::Float(value),
+                Constant::Long(value) => BootstrapArgument::Long(value),
+                Constant::Double(value) => BootstrapArgument::Double(value),
+                Constant::String(value) => BootstrapArgument::String(value),
+                Constant::Class(value) => BootstrapArgument::Class(value),
+                Constant::MethodType(value) => BootstrapArgument::MethodType(value),
+                Constant::MethodHandle(value) => BootstrapArgument::MethodHandle(value),
+                Constant::Dynamic(value) => BootstrapArgument::Dynamic(value),
+                _ => return Err(reader.bad("bootstrap argument", reader.at)),
+            });
+        }
+        Ok(CallSite { bootstrap, arguments, name, descriptor })
+    }
+}
+
+/// A cursor over one section, or the whole module while the sections are listed.
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn section(data: &'a [u8], range: Range<usize>) -> Self {
+        Reader { data, at: range.start, end: range.end }
+    }
+    fn bad(&self, what: &str, got: usize) -> ClassParseError {
+        ClassParseError::BadValue {
+            expected: format!("a valid {}", what),
+            got: format!("{} at {}", got, self.at),
+            for_what: "rootir module".to_string(),
+        }
+    }
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], ClassParseError> {
+        let bytes = self.at.checked_add(count).filter(|end| *end <= self.end).map(|end| &self.data[self.at..end])
+            .ok_or_else(|| ClassParseError::EarlyEOF(format!("EOF in rootir module at {}", self.at)))?;
+        self.at += count;
+        Ok(bytes)
+    }
+    fn u1(&mut self) -> Result<u8, ClassParseError> {
+        Ok(self.bytes(1)?[0])
+    }
+    fn u2(&mut self) -> Result<u16, ClassParseError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    fn uleb(&mut self) -> Result<u64, ClassParseError> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u1()?;
+            if shift >= 64 || (shift == 63 && byte > 1) {
+                return Err(self.bad("LEB128 number", byte as usize));
+            }
+            value |= ((byte & 0x7F) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+    fn sleb(&mut self) -> Result<i64, ClassParseError> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u1()?;
+            if shift >= 64 {
+                return Err(self.bad("LEB128 number", byte as usize));
+            }
+            value |= ((byte & 0x7F) as i64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+    /// A count, index or offset.
+    fn index(&mut self) -> Result<usize, ClassParseError> {
+        let value = self.uleb()?;
+        usize::try_from(value).map_err(|_| self.bad("index", usize::MAX))
+    }
+    fn u2_leb(&mut self) -> Result<u16, ClassParseError> {
+        let value = self.index()?;
+        u16::try_from(value).map_err(|_| self.bad("16-bit number", value))
+    }
+    fn u4_leb(&mut self) -> Result<u32, ClassParseError> {
+        let value = self.index()?;
+        u32::try_from(value).map_err(|_| self.bad("32-bit number", value))
+    }
+    fn ordinal<T: Copy>(&mut self, table: &[T], what: &str) -> Result<T, ClassParseError> {
+        let ordinal = self.u1()?;
+        table.get(ordinal as usize).copied().ok_or_else(|| self.bad(what, ordinal as usize))
+    }
+    fn value(&mut self) -> Result<IrValue, ClassParseError> {
+        Ok(IrValue(self.u4_leb()?))
+    }
+    fn values(&mut self) -> Result<Vec<IrValue>, ClassParseError> {
+        (0..self.index()?).map(|_| self.value()).collect()
+    }
+    fn optional_value(&mut self) -> Result<Option<IrValue>, ClassParseError> {
+        let mut values = self.values()?;
+        if values.len() > 1 {
+            return Err(self.bad("optional value", values.len()));
+        }
+        Ok(values.pop())
+    }
+    fn block(&mut self) -> Result<BlockId, ClassParseError> {
+        Ok(BlockId(self.u4_leb()?))
+    }
+    fn variable(&mut self) -> Result<Variable, ClassParseError> {
+        let tag = self.u1()?;
+        let index = self.u2_leb()?;
+        let ty = self.ordinal(&IR_TYPES, "variable type")?;
+        match tag {
+            0 => Ok(Variable::Local(index, ty)),
+            1 => Ok(Variable::Stack(index, ty)),
+            tag => Err(self.bad("variable kind", tag as usize)),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeSet;
+
+use super::{function::Function, ir_value::BlockId};
+
+/// Dominators of a function's blocks, computed with the iterative algorithm of Cooper, Harvey
+/// and Kennedy. Blocks the entry can't reach have no dominator and dominate nothing.
+///
+/// Edges to landing pads count like any other. A block dominating its landing pad has only been
+/// entered on the way there, the statements after the one that threw have not run.
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    idom: Vec<Option<BlockId>>,
+    children: Vec<Vec<BlockId>>,
+    /// Reachable blocks in reverse postorder
+    order: Vec<BlockId>,
+    /// Preorder and postorder numbers in the tree, so dominance is a range check
+    numbering: Vec<(usize, usize)>,
+}
+
+impl DominatorTree {
+    pub fn new(function: &Function) -> Self {
+        let count = function.blocks.len();
+        let mut postorder = Vec::with_capacity(count);
+        let mut visited = vec![false; count];
+        if count > 0 {
+            // (block, next successor to visit)
+            let mut pending = vec![(BlockId(0), 0)];
+            visited[0] = true;
+            while let Some((block, next)) = pending.pop() {
+                let successors = function.block(block).successors();
+                match successors.get(next) {
+                    Some(successor) => {
+                        pending.push((block, next + 1));
+                        if !std::mem::replace(&mut visited[successor.0 as usize], true) {
+                            pending.push((*successor, 0));
+                        }
+                    },
+                    None => postorder.push(block),
+                }
+            }
+        }
+        let order: Vec<BlockId> = postorder.into_iter().rev().collect();
+        let mut rank = vec![usize::MAX; count];
+        for (i, block) in order.iter().enumerate() {
+            rank[block.0 as usize] = i;
+        }
+        let predecessors = function.predecessors();
+        let mut idom: Vec<Option<BlockId>> = vec![None; count];
+        if count > 0 {
+            idom[0] = Some(BlockId(0));
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in order.iter().skip(1) {
+                let mut new_idom: Option<BlockId> = None;
+                for predecessor in &predecessors[block.0 as usize] {
+                    if idom[predecessor.0 as usize].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => *predecessor,
+                        Some(mut other) => {
+                            let mut finger = *predecessor;
+                            while finger != other {
+                                while rank[finger.0 as usize] > rank[other.0 as usize] {
+                                    finger = idom[finger.0 as usize].unwrap();
+                                }
+                                while rank[other.0 as usize] > rank[finger.0 as usize] {
+                                    other = idom[other.0 as usize].unwrap();
+                                }
+                            }
+                            finger
+                        },
+                    });
+                }
+                if idom[block.0 as usize] != new_idom {
+                    idom[block.0 as usize] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        let mut children = vec![Vec::new(); count];
+        for block in order.iter().skip(1) {
+            if let Some(parent) = idom[block.0 as usize] {
+                children[parent.0 as usize].push(*block);
+            }
+        }
+        let mut numbering = vec![(usize::MAX, usize::MAX); count];
+        if count > 0 {
+            let mut clock = 0;
+            let mut pending = vec![(BlockId(0), false)];
+            while let Some((block, done)) = pending.pop() {
+                if done {
+                    numbering[block.0 as usize].1 = clock;
+                } else {
+                    numbering[block.0 as usize].0 = clock;
+                    pending.push((block, true));
+                    pending.extend(children[block.0 as usize].iter().rev().map(|child| (*child, false)));
+                }
+                clock += 1;
+            }
+        }
+        Self { idom, children, order, numbering }
+    }
+    /// The immediate dominator, `None` for the entry and unreachable blocks.
+    pub fn idom(&self, block: BlockId) -> Option<BlockId> {
+        self.idom[block.0 as usize].filter(|_| block.0 != 0)
+    }
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        &self.children[block.0 as usize]
+    }
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idom[block.0 as usize].is_some()
+    }
+    /// Reachable blocks in reverse postorder, the entry first.
+    pub fn reverse_postorder(&self) -> &[BlockId] {
+        &self.order
+    }
+    /// Whether every path from the entry to `b` goes through `a`; a block dominates itself.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+        let (a, b) = (self.numbering[a.0 as usize], self.numbering[b.0 as usize]);
+        a.0 <= b.0 && b.1 <= a.1
+    }
+    /// The dominance frontier of every block: where its dominance ends.
+    pub fn frontiers(&self, function: &Function) -> Vec<BTreeSet<BlockId>> {
+        let mut frontiers = vec![BTreeSet::new(); self.idom.len()];
+        for (block, predecessors) in function.predecessors().into_iter().enumerate() {
+            let block = BlockId(block as u32);
+            if predecessors.len() < 2 || !self.is_reachable(block) {
+                continue;
+            }
+            let idom = self.idom[block.0 as usize];
+            for predecessor in predecessors {
+                let mut runner = Some(predecessor).filter(|p| self.is_reachable(*p));
+                while let Some(current) = runner.filter(|current| Some(*current) != idom) {
+                    frontiers[current.0 as usize].insert(block);
+                    runner = self.idom(current);
+                }
+            }
+        }
+        frontiers
+    }
+}
@@ -0,0 +1,208 @@
+use super::{ir_instructions::Instruction, ir_value::{BlockId, IrType, IrValue}};
+
+/// Where a statement sits: its block and index within the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    pub block: BlockId,
+    pub index: usize,
+}
+
+/// An instruction and the value it defines, if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub result: Option<IrValue>,
+    pub instruction: Instruction<IrValue>,
+}
+
+/// Where an exception of `class`, or of any class when it is `None`, thrown in a block goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handler {
+    pub class: Option<String>,
+    pub target: BlockId,
+}
+
+/// A basic block, whose last statement is its only terminator.
+///
+/// An exception thrown by a statement of the block is caught by the first of its `handlers`
+/// whose class it is an instance of, and continues at the handler's landing pad. The landing
+/// pad sees the values and variables as they were when the block was entered: lowering starts
+/// a new block after each write of a variable in protected code, and a phi in a landing pad
+/// takes the value its input has at the start of the protected block.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+    pub handlers: Vec<Handler>,
+}
+
+impl Block {
+    pub fn terminator(&self) -> Option<&Instruction<IrValue>> {
+        self.statements.last().map(|statement| &statement.instruction).filter(|instruction| instruction.is_terminator())
+    }
+    /// The blocks the terminator continues at, then the landing pads of the handlers.
+    pub fn successors(&self) -> Vec<BlockId> {
+        let mut successors = self.normal_successors();
+        successors.extend(self.handlers.iter().map(|handler| handler.target));
+        successors
+    }
+    /// The blocks the terminator continues at.
+    pub fn normal_successors(&self) -> Vec<BlockId> {
+        self.terminator().map(Instruction::successors).unwrap_or_default()
+    }
+    /// Whether an exception thrown in this block can land in `block`.
+    pub fn unwinds_to(&self, block: BlockId) -> bool {
+        self.handlers.iter().any(|handler| handler.target == block)
+    }
+}
+
+/// A method in IR form. Block 0 is the entry and defines the parameters; methods without
+/// code have no blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+    /// Type of each value, indexed by its number
+    pub values: Vec<IrType>,
+    pub blocks: Vec<Block>,
+}
+
+impl Function {
+    pub fn new(name: &str, descriptor: &str, access_flags: u16) -> Self {
+        Self {
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            access_flags,
+            values: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+    pub fn new_value(&mut self, ty: IrType) -> IrValue {
+        self.values.push(ty);
+        IrValue(self.values.len() as u32 - 1)
+    }
+    pub fn value_type(&self, value: IrValue) -> IrType {
+        self.values[value.0 as usize]
+    }
+    pub fn add_block(&mut self) -> BlockId {
+        self.blocks.push(Block::default());
+        BlockId(self.blocks.len() as u32 - 1)
+    }
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0 as usize]
+    }
+    pub fn block_mut(&mut self, id: BlockId) -> &mut Block {
+        &mut self.blocks[id.0 as usize]
+    }
+    /// Appends `instruction` to `block`, defining a new value of type `ty` unless it is `None`.
+    pub fn push(&mut self, block: BlockId, instruction: Instruction<IrValue>, ty: Option<IrType>) -> Option<IrValue> {
+        let result = ty.map(|ty| self.new_value(ty));
+        self.block_mut(block).statements.push(Statement { result, instruction });
+        result
+    }
+    pub fn block_ids(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.blocks.len() as u32).map(BlockId)
+    }
+    /// Predecessors of every block, indexed by block number, the blocks unwinding to it included.
+    pub fn predecessors(&self) -> Vec<Vec<BlockId>> {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for id in self.block_ids() {
+            for successor in self.block(id).successors() {
+                if !predecessors[successor.0 as usize].contains(&id) {
+                    predecessors[successor.0 as usize].push(id);
+                }
+            }
+        }
+        predecessors
+    }
+    pub fn statement(&self, at: Location) -> &Statement {
+        &self.block(at.block).statements[at.index]
+    }
+    /// Blocks reachable from the entry, indexed by block number.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = if self.blocks.is_empty() { Vec::new() } else { vec![BlockId(0)] };
+        while let Some(block) = pending.pop() {
+            if !std::mem::replace(&mut reachable[block.0 as usize], true) {
+                pending.extend(self.block(block).successors());
+            }
+        }
+        reachable
+    }
+    /// Removes the blocks the entry can't reach and renumbers the others, returns how many were removed.
+    pub fn remove_unreachable_blocks(&mut self) -> usize {
+        let reachable = self.reachable();
+        let mut renumbered = Vec::with_capacity(self.blocks.len());
+        let mut next = 0;
+        for keep in &reachable {
+            renumbered.push(BlockId(next));
+            next += *keep as u32;
+        }
+        let removed = self.blocks.len() - next as usize;
+        if removed == 0 {
+            return 0;
+        }
+        let blocks = std::mem::take(&mut self.blocks);
+        self.blocks = blocks.into_iter().zip(&reachable).filter(|(_, keep)| **keep).map(|(block, _)| block).collect();
+        for block in &mut self.blocks {
+            for handler in &mut block.handlers {
+                handler.target = renumbered[handler.target.0 as usize];
+            }
+            for statement in &mut block.statements {
+                if let Instruction::Phi { inputs } = &mut statement.instruction {
+                    inputs.retain(|(from, _)| reachable[from.0 as usize]);
+                }
+                for target in statement.instruction.successors_mut() {
+                    *target = renumbered[target.0 as usize];
+                }
+                if let Instruction::Phi { inputs } = &mut statement.instruction {
+                    for (from, _) in inputs {
+                        *from = renumbered[from.0 as usize];
+                    }
+                }
+            }
+        }
+        removed
+    }
+    /// Renumbers the values in order of appearance, dropping the numbers nothing defines or reads.
+    pub fn compact_values(&mut self) {
+        let mut renumbered: Vec<Option<IrValue>> = vec![None; self.values.len()];
+        let mut values = Vec::new();
+        for block in &mut self.blocks {
+            for statement in &mut block.statements {
+                for value in statement.result.iter_mut().chain(statement.instruction.operands_mut()) {
+                    let slot = &mut renumbered[value.0 as usize];
+                    if slot.is_none() {
+                        values.push(self.values[value.0 as usize]);
+                        *slot = Some(IrValue(values.len() as u32 - 1));
+                    }
+                    *value = slot.unwrap();
+                }
+            }
+        }
+        self.values = values;
+    }
+    /// Puts a new block on the edge from `from` to `to`, which phis in `to` now see as their
+    /// predecessor. The edge must be one the terminator of `from` takes: an exception can't
+    /// detour through a block of its own.
+    pub fn split_edge(&mut self, from: BlockId, to: BlockId) -> BlockId {
+        let middle = self.add_block();
+        self.push(middle, Instruction::Goto { target: to }, None);
+        if let Some(statement) = self.block_mut(from).statements.last_mut() {
+            for target in statement.instruction.successors_mut() {
+                if *target == to {
+                    *target = middle;
+                }
+            }
+        }
+        for statement in &mut self.block_mut(to).statements {
+            if let Instruction::Phi { inputs } = &mut statement.instruction {
+                for (predecessor, _) in inputs {
+                    if *predecessor == from {
+                        *predecessor = middle;
+                    }
+                }
+            }
+        }
+        middle
+    }
+}
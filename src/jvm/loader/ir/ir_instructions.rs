@@ -1,45 +1,349 @@
-use std::marker::PhantomData;
-use super::ir_value::IrValue;
+use crate::jvm::reader::{bootstrap::CallSite, constant_pool::MemberRef, descriptor::{FieldType, MethodDescriptor}};
 
+use super::ir_value::{BlockId, Constant, IrType, Variable};
 
+/// An IR instruction over operands of type `T`, which are [IrValue](super::ir_value::IrValue)s
+/// in a [Function](super::function::Function). Dereferences of a possibly null reference are
+/// preceded by an explicit `NullCheck`, the instructions themselves assume a non-null operand.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Instruction<T> {
+    /// The `index`th parameter, `this` included
+    Param {
+        index: usize,
+    },
+    Const {
+        value: Constant,
+    },
+    /// Reads a variable, only found before SSA construction
+    GetVar {
+        var: Variable,
+    },
+    /// Writes a variable, only found before SSA construction
+    SetVar {
+        var: Variable,
+        value: T,
+    },
+    /// `l op r` on values of type `ty`, which is `I32`, `I64`, `F32` or `F64`. Shift distances
+    /// are ints whatever `ty` is, and only their low 5 or 6 bits count.
     Arithmetic {
         l: T,
         r: T,
         op: ArithmeticOp,
+        ty: ArithmeticOpType,
+    },
+    Negate {
+        value: T,
+        ty: ArithmeticOpType,
+    },
+    Convert {
+        value: T,
+        from: ArithmeticOpType,
+        to: ArithmeticOpType,
+    },
+    /// `lcmp`, `fcmpl`, `fcmpg`, `dcmpl` and `dcmpg` on values of type `ty`, producing -1, 0 or 1
+    Compare {
+        l: T,
+        r: T,
+        op: CompareOp,
+        ty: ArithmeticOpType,
     },
 
     NewSizedArray {
+        element: FieldType,
         size: usize,
-        _ty: PhantomData<T>
     },
     NewDynamicArray {
-        _ty: PhantomData<T>
+        element: FieldType,
+        length: T,
+    },
+    NewMultiArray {
+        /// The array type, with at least as many dimensions as `dimensions`
+        class: FieldType,
+        dimensions: Vec<T>,
+    },
+    New {
+        class: String,
+    },
+    ArrayLength {
+        array: T,
+    },
+    ArrayLoad {
+        array: T,
+        index: T,
+        element: ElementType,
+    },
+    ArrayStore {
+        array: T,
+        index: T,
+        value: T,
+        element: ElementType,
+    },
+    /// A static field when `object` is `None`
+    GetField {
+        field: MemberRef,
+        object: Option<T>,
+    },
+    PutField {
+        field: MemberRef,
+        object: Option<T>,
+        value: T,
     },
-
-
-
 
     Invoke {
-        method: String,
-        num_args: usize, // Number of arguments to pop off the stack
+        kind: InvokeKind,
+        method: MemberRef,
+        num_args: usize, // Number of arguments, the receiver included
+        args: Vec<T>,
+    },
+    InvokeDynamic {
+        site: CallSite,
+        args: Vec<T>,
     },
+    CheckCast {
+        value: T,
+        class: String,
+    },
+    InstanceOf {
+        value: T,
+        class: String,
+    },
+    /// Throws `NullPointerException` when `value` is null
+    NullCheck {
+        value: T,
+    },
+    MonitorEnter {
+        object: T,
+    },
+    MonitorExit {
+        object: T,
+    },
+    /// A copy of `value`
     Focus {
-        value: IrValue,
-    }
-
+        value: T,
+    },
+    /// The value coming from each predecessor, only found at the start of a block in SSA form
+    Phi {
+        inputs: Vec<(BlockId, T)>,
+    },
+    /// The exception a landing pad was entered with, only found in landing pads, after the phis
+    Catch,
 
+    Goto {
+        target: BlockId,
+    },
+    Branch {
+        l: T,
+        r: T,
+        cond: Condition,
+        if_true: BlockId,
+        if_false: BlockId,
+    },
+    Switch {
+        value: T,
+        cases: Vec<(i32, BlockId)>,
+        default: BlockId,
+    },
+    /// `ret`, which continues at the return address held in `address`, one of `targets`
+    IndirectJump {
+        address: T,
+        targets: Vec<BlockId>,
+    },
+    Return {
+        value: Option<T>,
+    },
+    Throw {
+        value: T,
+    },
+}
 
+impl<T> Instruction<T> {
+    /// Whether the instruction ends its block.
+    pub fn is_terminator(&self) -> bool {
+        matches!(self, Instruction::Goto { .. } | Instruction::Branch { .. } | Instruction::Switch { .. }
+            | Instruction::IndirectJump { .. } | Instruction::Return { .. } | Instruction::Throw { .. })
+    }
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Instruction::Goto { target } => vec![*target],
+            Instruction::Branch { if_true, if_false, .. } => vec![*if_true, *if_false],
+            Instruction::Switch { cases, default, .. } => cases.iter().map(|(_, target)| *target).chain([*default]).collect(),
+            Instruction::IndirectJump { targets, .. } => targets.clone(),
+            _ => Vec::new(),
+        }
+    }
+    /// The values the instruction reads, in operand order.
+    pub fn operands(&self) -> Vec<&T> {
+        use Instruction as I;
+        match self {
+            I::Param { .. } | I::Const { .. } | I::GetVar { .. } | I::NewSizedArray { .. } | I::New { .. } | I::Catch
+            | I::Goto { .. } => Vec::new(),
+            I::SetVar { value, .. } | I::Negate { value, .. } | I::Convert { value, .. } | I::CheckCast { value, .. }
+            | I::InstanceOf { value, .. } | I::NullCheck { value } | I::Focus { value } | I::Switch { value, .. }
+            | I::Throw { value } => vec![value],
+            I::Arithmetic { l, r, .. } | I::Compare { l, r, .. } | I::Branch { l, r, .. } => vec![l, r],
+            I::NewDynamicArray { length, .. } => vec![length],
+            I::NewMultiArray { dimensions, .. } => dimensions.iter().collect(),
+            I::ArrayLength { array } => vec![array],
+            I::ArrayLoad { array, index, .. } => vec![array, index],
+            I::ArrayStore { array, index, value, .. } => vec![array, index, value],
+            I::GetField { object, .. } => object.iter().collect(),
+            I::PutField { object, value, .. } => object.iter().chain([value]).collect(),
+            I::Invoke { args, .. } | I::InvokeDynamic { args, .. } => args.iter().collect(),
+            I::MonitorEnter { object } | I::MonitorExit { object } => vec![object],
+            I::IndirectJump { address, .. } => vec![address],
+            I::Return { value } => value.iter().collect(),
+            I::Phi { inputs } => inputs.iter().map(|(_, value)| value).collect(),
+        }
+    }
+    pub fn operands_mut(&mut self) -> Vec<&mut T> {
+        use Instruction as I;
+        match self {
+            I::Param { .. } | I::Const { .. } | I::GetVar { .. } | I::NewSizedArray { .. } | I::New { .. } | I::Catch
+            | I::Goto { .. } => Vec::new(),
+            I::SetVar { value, .. } | I::Negate { value, .. } | I::Convert { value, .. } | I::CheckCast { value, .. }
+            | I::InstanceOf { value, .. } | I::NullCheck { value } | I::Focus { value } | I::Switch { value, .. }
+            | I::Throw { value } => vec![value],
+            I::Arithmetic { l, r, .. } | I::Compare { l, r, .. } | I::Branch { l, r, .. } => vec![l, r],
+            I::NewDynamicArray { length, .. } => vec![length],
+            I::NewMultiArray { dimensions, .. } => dimensions.iter_mut().collect(),
+            I::ArrayLength { array } => vec![array],
+            I::ArrayLoad { array, index, .. } => vec![array, index],
+            I::ArrayStore { array, index, value, .. } => vec![array, index, value],
+            I::GetField { object, .. } => object.iter_mut().collect(),
+            I::PutField { object, value, .. } => object.iter_mut().chain([value]).collect(),
+            I::Invoke { args, .. } | I::InvokeDynamic { args, .. } => args.iter_mut().collect(),
+            I::MonitorEnter { object } | I::MonitorExit { object } => vec![object],
+            I::IndirectJump { address, .. } => vec![address],
+            I::Return { value } => value.iter_mut().collect(),
+            I::Phi { inputs } => inputs.iter_mut().map(|(_, value)| value).collect(),
+        }
+    }
+    /// The blocks a terminator jumps to, for retargeting edges.
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Instruction::Goto { target } => vec![target],
+            Instruction::Branch { if_true, if_false, .. } => vec![if_true, if_false],
+            Instruction::Switch { cases, default, .. } => cases.iter_mut().map(|(_, target)| target).chain([default]).collect(),
+            Instruction::IndirectJump { targets, .. } => targets.iter_mut().collect(),
+            _ => Vec::new(),
+        }
+    }
+    /// The types the operands accept, in operand order.
+    pub fn operand_types(&self) -> Vec<OperandType> {
+        use Instruction as I;
+        use OperandType::{Any, Exactly, Same};
+        let reference = Exactly(IrType::Reference);
+        let int = Exactly(IrType::Int);
+        match self {
+            I::Param { .. } | I::Const { .. } | I::GetVar { .. } | I::NewSizedArray { .. } | I::New { .. } | I::Catch
+            | I::Goto { .. } => Vec::new(),
+            I::SetVar { var, .. } => vec![Exactly(var.ty())],
+            I::Arithmetic { op, ty, .. } => vec![Exactly(ty.ty()), if op.is_shift() { int } else { Exactly(ty.ty()) }],
+            I::Negate { ty, .. } => vec![Exactly(ty.ty())],
+            I::Compare { ty, .. } => vec![Exactly(ty.ty()); 2],
+            I::Convert { from, .. } => vec![Exactly(from.ty())],
+            I::NewDynamicArray { .. } => vec![int],
+            I::NewMultiArray { dimensions, .. } => vec![int; dimensions.len()],
+            I::ArrayLength { .. } | I::CheckCast { .. } | I::InstanceOf { .. } | I::NullCheck { .. } | I::MonitorEnter { .. }
+            | I::MonitorExit { .. } | I::Throw { .. } => vec![reference],
+            I::ArrayLoad { .. } => vec![reference, int],
+            I::ArrayStore { element, .. } => vec![reference, int, Exactly(element.ty())],
+            I::GetField { object, .. } => object.iter().map(|_| reference).collect(),
+            I::PutField { field, object, .. } => {
+                let value = FieldType::parse(&field.descriptor).map(|ty| Exactly(IrType::of(&ty))).unwrap_or(Any);
+                object.iter().map(|_| reference).chain([value]).collect()
+            },
+            I::Invoke { kind, method, args, .. } => {
+                let receiver = (*kind != InvokeKind::Static).then_some(reference);
+                let params = MethodDescriptor::parse(&method.descriptor).map(|descriptor| descriptor.params).unwrap_or_default();
+                let declared = receiver.into_iter().chain(params.iter().map(|ty| Exactly(IrType::of(ty))));
+                // arguments the descriptor doesn't account for are left to the verifier's count check
+                declared.chain(std::iter::repeat(Any)).take(args.len()).collect()
+            },
+            I::InvokeDynamic { site, args } => {
+                let params = MethodDescriptor::parse(&site.descriptor).map(|descriptor| descriptor.params).unwrap_or_default();
+                params.iter().map(|ty| Exactly(IrType::of(ty))).chain(std::iter::repeat(Any)).take(args.len()).collect()
+            },
+            I::Focus { .. } | I::Return { .. } => vec![Any; self.operands().len()],
+            I::Phi { inputs } => vec![Same; inputs.len()],
+            I::Branch { .. } => vec![Same; 2],
+            I::Switch { .. } => vec![int],
+            I::IndirectJump { .. } => vec![Exactly(IrType::ReturnAddress)],
+        }
+    }
+    /// The type of the value the instruction defines. `None` when it defines none, and for
+    /// `Param`, `Focus` and `Phi`, whose type is the parameter's or the operands'.
+    pub fn result_type(&self) -> Option<IrType> {
+        use Instruction as I;
+        let returned = |descriptor: &str| MethodDescriptor::parse(descriptor).ok()?.ret.map(|ty| IrType::of(&ty));
+        match self {
+            I::Const { value } => Some(value.ty()),
+            I::GetVar { var } => Some(var.ty()),
+            I::Arithmetic { ty, .. } | I::Negate { ty, .. } => Some(ty.ty()),
+            I::Convert { to, .. } => Some(to.ty()),
+            I::Compare { .. } | I::ArrayLength { .. } | I::InstanceOf { .. } => Some(IrType::Int),
+            I::NewSizedArray { .. } | I::NewDynamicArray { .. } | I::NewMultiArray { .. } | I::New { .. }
+            | I::CheckCast { .. } | I::Catch => Some(IrType::Reference),
+            I::ArrayLoad { element, .. } => Some(element.ty()),
+            I::GetField { field, .. } => FieldType::parse(&field.descriptor).ok().map(|ty| IrType::of(&ty)),
+            I::Invoke { method, .. } => returned(&method.descriptor),
+            I::InvokeDynamic { site, .. } => returned(&site.descriptor),
+            _ => None,
+        }
+    }
+}
 
-    
+/// What an operand accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandType {
+    Exactly(IrType),
+    /// Any type, as long as every operand marked `Same` has it
+    Same,
+    Any,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ArithmeticOp {
     Add,
     Sub,
     Mul,
     Div,
-    Rem
+    Rem,
+    And,
+    Or,
+    Xor,
+    Shl,
+    /// Arithmetic shift right
+    Shr,
+    /// Logical shift right
+    Ushr,
+}
+impl ArithmeticOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArithmeticOp::Add => "add",
+            ArithmeticOp::Sub => "sub",
+            ArithmeticOp::Mul => "mul",
+            ArithmeticOp::Div => "div",
+            ArithmeticOp::Rem => "rem",
+            ArithmeticOp::And => "and",
+            ArithmeticOp::Or => "or",
+            ArithmeticOp::Xor => "xor",
+            ArithmeticOp::Shl => "shl",
+            ArithmeticOp::Shr => "shr",
+            ArithmeticOp::Ushr => "ushr",
+        }
+    }
+    pub fn is_shift(&self) -> bool {
+        matches!(self, ArithmeticOp::Shl | ArithmeticOp::Shr | ArithmeticOp::Ushr)
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ArithmeticOp::Add, ArithmeticOp::Sub, ArithmeticOp::Mul, ArithmeticOp::Div, ArithmeticOp::Rem, ArithmeticOp::And, ArithmeticOp::Or, ArithmeticOp::Xor, ArithmeticOp::Shl, ArithmeticOp::Shr, ArithmeticOp::Ushr]
+            .into_iter()
+            .find(|value| value.name() == name)
+    }
 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ArithmeticOpType {
     U8,
     U16,
@@ -55,4 +359,172 @@ pub enum ArithmeticOpType {
 
     F32,
     F64
-}
\ No newline at end of file
+}
+impl ArithmeticOpType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArithmeticOpType::U8 => "u8",
+            ArithmeticOpType::U16 => "u16",
+            ArithmeticOpType::U32 => "u32",
+            ArithmeticOpType::U64 => "u64",
+            ArithmeticOpType::U128 => "u128",
+            ArithmeticOpType::I8 => "i8",
+            ArithmeticOpType::I16 => "i16",
+            ArithmeticOpType::I32 => "i32",
+            ArithmeticOpType::I64 => "i64",
+            ArithmeticOpType::I128 => "i128",
+            ArithmeticOpType::F32 => "f32",
+            ArithmeticOpType::F64 => "f64",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ArithmeticOpType::U8, ArithmeticOpType::U16, ArithmeticOpType::U32, ArithmeticOpType::U64, ArithmeticOpType::U128, ArithmeticOpType::I8, ArithmeticOpType::I16, ArithmeticOpType::I32, ArithmeticOpType::I64, ArithmeticOpType::I128, ArithmeticOpType::F32, ArithmeticOpType::F64]
+            .into_iter()
+            .find(|value| value.name() == name)
+    }
+    /// The type arithmetic on `ty` works on, none for references and return addresses.
+    pub fn of(ty: IrType) -> Option<Self> {
+        match ty {
+            IrType::Int => Some(ArithmeticOpType::I32),
+            IrType::Long => Some(ArithmeticOpType::I64),
+            IrType::Float => Some(ArithmeticOpType::F32),
+            IrType::Double => Some(ArithmeticOpType::F64),
+            IrType::Reference | IrType::ReturnAddress => None,
+        }
+    }
+    /// The type of a value converted to this type; narrow integers widen back to `Int`.
+    pub fn ty(&self) -> IrType {
+        match self {
+            ArithmeticOpType::U64 | ArithmeticOpType::I64 | ArithmeticOpType::U128 | ArithmeticOpType::I128 => IrType::Long,
+            ArithmeticOpType::F32 => IrType::Float,
+            ArithmeticOpType::F64 => IrType::Double,
+            _ => IrType::Int,
+        }
+    }
+}
+
+/// How `Compare` orders NaN: `CmpL` treats it as less than anything, `CmpG` as greater.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareOp {
+    Cmp,
+    CmpL,
+    CmpG,
+}
+impl CompareOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompareOp::Cmp => "cmp",
+            CompareOp::CmpL => "cmpl",
+            CompareOp::CmpG => "cmpg",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        [CompareOp::Cmp, CompareOp::CmpL, CompareOp::CmpG]
+            .into_iter()
+            .find(|value| value.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Condition {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Gt,
+    Le,
+}
+impl Condition {
+    /// The condition that holds exactly when this one doesn't.
+    pub fn negate(&self) -> Self {
+        match self {
+            Condition::Eq => Condition::Ne,
+            Condition::Ne => Condition::Eq,
+            Condition::Lt => Condition::Ge,
+            Condition::Ge => Condition::Lt,
+            Condition::Gt => Condition::Le,
+            Condition::Le => Condition::Gt,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Condition::Eq => "eq",
+            Condition::Ne => "ne",
+            Condition::Lt => "lt",
+            Condition::Ge => "ge",
+            Condition::Gt => "gt",
+            Condition::Le => "le",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Condition::Eq, Condition::Ne, Condition::Lt, Condition::Ge, Condition::Gt, Condition::Le]
+            .into_iter()
+            .find(|value| value.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InvokeKind {
+    Virtual,
+    Special,
+    Static,
+    Interface,
+}
+impl InvokeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InvokeKind::Virtual => "virtual",
+            InvokeKind::Special => "special",
+            InvokeKind::Static => "static",
+            InvokeKind::Interface => "interface",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        [InvokeKind::Virtual, InvokeKind::Special, InvokeKind::Static, InvokeKind::Interface]
+            .into_iter()
+            .find(|value| value.name() == name)
+    }
+}
+
+/// The element type an array access works on. `Byte` also covers `boolean` arrays, as `baload` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementType {
+    Byte,
+    Char,
+    Short,
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference,
+}
+
+impl ElementType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ElementType::Byte => "byte",
+            ElementType::Char => "char",
+            ElementType::Short => "short",
+            ElementType::Int => "int",
+            ElementType::Long => "long",
+            ElementType::Float => "float",
+            ElementType::Double => "double",
+            ElementType::Reference => "ref",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ElementType::Byte, ElementType::Char, ElementType::Short, ElementType::Int, ElementType::Long, ElementType::Float, ElementType::Double, ElementType::Reference]
+            .into_iter()
+            .find(|value| value.name() == name)
+    }
+    /// The type of a loaded element.
+    pub fn ty(&self) -> IrType {
+        match self {
+            ElementType::Long => IrType::Long,
+            ElementType::Float => IrType::Float,
+            ElementType::Double => IrType::Double,
+            ElementType::Reference => IrType::Reference,
+            _ => IrType::Int,
+        }
+    }
+}
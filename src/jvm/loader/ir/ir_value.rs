@@ -1,2 +1,127 @@
+use std::fmt::Display;
 
-pub struct IrValue {}
\ No newline at end of file
+use crate::jvm::reader::{bootstrap::{CallSite, MethodHandleRef}, descriptor::FieldType};
+
+/// A value defined by one instruction or function parameter, numbered within its function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IrValue(pub u32);
+
+impl Display for IrValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+/// A basic block, numbered within its function. Block 0 is the entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(pub u32);
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+/// The type of a value. These are the computational types of the JVM (JVMS §2.11.1):
+/// `boolean`, `byte`, `char` and `short` values are held as `Int`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IrType {
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference,
+    /// Pushed by `jsr` and consumed by `ret`
+    ReturnAddress,
+}
+
+impl IrType {
+    pub fn of(ty: &FieldType) -> Self {
+        match ty {
+            FieldType::Long => IrType::Long,
+            FieldType::Float => IrType::Float,
+            FieldType::Double => IrType::Double,
+            FieldType::Object(_) | FieldType::Array(_) => IrType::Reference,
+            _ => IrType::Int,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            IrType::Int => "int",
+            IrType::Long => "long",
+            IrType::Float => "float",
+            IrType::Double => "double",
+            IrType::Reference => "ref",
+            IrType::ReturnAddress => "retaddr",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        [IrType::Int, IrType::Long, IrType::Float, IrType::Double, IrType::Reference, IrType::ReturnAddress]
+            .into_iter()
+            .find(|ty| ty.name() == name)
+    }
+}
+
+impl Display for IrType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A constant operand, anything `ldc` and the `const` instructions can push.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    Null,
+    String(String),
+    /// Internal name or array descriptor
+    Class(String),
+    MethodType(String),
+    MethodHandle(MethodHandleRef),
+    Dynamic(Box<CallSite>),
+    /// The block a `jsr` returns to
+    ReturnAddress(BlockId),
+}
+
+impl Constant {
+    pub fn ty(&self) -> IrType {
+        match self {
+            Constant::Int(_) => IrType::Int,
+            Constant::Long(_) => IrType::Long,
+            Constant::Float(_) => IrType::Float,
+            Constant::Double(_) => IrType::Double,
+            Constant::Dynamic(site) => FieldType::parse(&site.descriptor).map(|ty| IrType::of(&ty)).unwrap_or(IrType::Reference),
+            Constant::ReturnAddress(_) => IrType::ReturnAddress,
+            _ => IrType::Reference,
+        }
+    }
+}
+
+/// A JVM local variable or operand stack slot before SSA construction. Slots reused for
+/// values of different types are different variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variable {
+    Local(u16, IrType),
+    /// Stack slot by depth, counted in values rather than JVM slots
+    Stack(u16, IrType),
+}
+
+impl Variable {
+    pub fn ty(&self) -> IrType {
+        match self {
+            Variable::Local(_, ty) | Variable::Stack(_, ty) => *ty,
+        }
+    }
+}
+
+impl Display for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variable::Local(index, ty) => write!(f, "local{}:{}", index, ty),
+            Variable::Stack(depth, ty) => write!(f, "stack{}:{}", depth, ty),
+        }
+    }
+}
@@ -0,0 +1,697 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    jvm::{analysis::classpath::ClassPath, reader::{
+        access_flags::AccessFlags,
+        bootstrap::{BootstrapMethods, CallSite, MethodHandleRef},
+        code::{block::CodeBlock, instruction::{Instruction as Op, WideOp}},
+        constant_pool::ConstantPoolInfo,
+        descriptor::{FieldType, MethodDescriptor},
+        method::MethodInfo,
+        raw_class::RawClass,
+    }},
+    util::code_err::{ClassParseError, CodeParseError},
+};
+
+use super::{
+    function::{Function, Handler},
+    ir_instructions::{ArithmeticOp, ArithmeticOpType, CompareOp, Condition, ElementType, Instruction, InvokeKind},
+    ir_value::{BlockId, Constant, IrType, IrValue, Variable},
+    module::{ClassDebugInfo, DebugInfo, IrClass, IrField, Module},
+};
+
+/// Types of the `xload`, `xstore` and arithmetic families, in opcode order.
+const TYPES: [IrType; 5] = [IrType::Int, IrType::Long, IrType::Float, IrType::Double, IrType::Reference];
+/// Element types of `iaload` through `saload` and `iastore` through `sastore`.
+const ELEMENTS: [ElementType; 8] = [
+    ElementType::Int, ElementType::Long, ElementType::Float, ElementType::Double,
+    ElementType::Reference, ElementType::Byte, ElementType::Char, ElementType::Short,
+];
+/// Source and target of `i2l` through `i2s`.
+const CONVERSIONS: [(ArithmeticOpType, ArithmeticOpType); 15] = {
+    use ArithmeticOpType::*;
+    [
+        (I32, I64), (I32, F32), (I32, F64),
+        (I64, I32), (I64, F32), (I64, F64),
+        (F32, I32), (F32, I64), (F32, F64),
+        (F64, I32), (F64, I64), (F64, F32),
+        (I32, I8), (I32, U16), (I32, I16),
+    ]
+};
+
+/// Converts every method of `class` into IR.
+pub fn lower_class(class: &RawClass) -> Result<IrClass, ClassParseError> {
+    lower_class_with_debug(class).map(|(class, _)| class)
+}
+
+/// Converts every class of a class path, in order of their names, recording where each came from.
+pub fn lower_class_path(path: &ClassPath) -> Result<(Module, DebugInfo), ClassParseError> {
+    let mut module = Module::new();
+    let mut debug = DebugInfo::default();
+    for (_, file) in path.classes() {
+        let (class, mut class_debug) = lower_class_with_debug(&file.class)?;
+        class_debug.source = Some(file.path.clone()).filter(|path| !path.is_empty());
+        module.classes.push(class);
+        debug.classes.push(class_debug);
+    }
+    Ok((module, debug))
+}
+
+/// Like [lower_class], also mapping each block back to the bytecode it came from.
+pub fn lower_class_with_debug(class: &RawClass) -> Result<(IrClass, ClassDebugInfo), ClassParseError> {
+    let mut debug = ClassDebugInfo::default();
+    let mut functions = Vec::with_capacity(class.methods.0.len());
+    for method in &class.methods.0 {
+        let (function, offsets) = lower_method_with_offsets(class, method)?;
+        functions.push(function);
+        debug.block_offsets.push(offsets);
+    }
+    let class = IrClass {
+        name: class.name()?.to_string(),
+        super_name: class.super_name()?.map(str::to_string),
+        interfaces: class.interface_names()?.into_iter().map(str::to_string).collect(),
+        access_flags: class.access_flags,
+        fields: class.fields.0.iter()
+            .map(|field| Ok(IrField {
+                name: field.name(&class.cp)?.to_string(),
+                descriptor: field.descriptor(&class.cp)?.to_string(),
+                access_flags: field.access_flags,
+            }))
+            .collect::<Result<_, ClassParseError>>()?,
+        functions,
+    };
+    Ok((class, debug))
+}
+
+/// Converts a method's bytecode into IR. Operand stack entries become values; JVM locals,
+/// and stack entries that live across blocks, are read and written through variables
+/// until SSA construction replaces them.
+///
+/// Every block lists the exception table entries covering its code as handlers, in table
+/// order, so nested and overlapping ranges are tried innermost first as javac orders them.
+/// Each handler gets a landing pad that takes the exception with `catch` and enters the
+/// handler's code with it in stack slot 0. `finally` needs nothing of its own: javac copies
+/// its code onto the normal paths and adds a handler catching anything, whose copy rethrows.
+pub fn lower_method(class: &RawClass, method: &MethodInfo) -> Result<Function, ClassParseError> {
+    lower_method_with_offsets(class, method).map(|(function, _)| function)
+}
+
+/// Like [lower_method], also returning the bytecode offset of each block that starts at one,
+/// in block order.
+pub fn lower_method_with_offsets(class: &RawClass, method: &MethodInfo) -> Result<(Function, Vec<(BlockId, u32)>), ClassParseError> {
+    let name = method.name(&class.cp)?;
+    let descriptor = method.descriptor(&class.cp)?;
+    let mut function = Function::new(name, descriptor, method.access_flags);
+    let code = match &method.code {
+        Some(code) => code,
+        None => return Ok((function, Vec::new())),
+    };
+    let mut pcs = Vec::with_capacity(code.code.len());
+    let mut pc = 0;
+    for op in &code.code {
+        pcs.push(pc);
+        pc += op.len_at(pc);
+    }
+    let mut leaders = BTreeSet::from([0]);
+    for (i, op) in code.code.iter().enumerate() {
+        leaders.extend(op.branch_targets(pcs[i]));
+        if op.ends_block() {
+            leaders.extend(pcs.get(i + 1));
+        }
+    }
+    for entry in &code.exception_table.0 {
+        leaders.extend([entry.start_pc as u32, entry.end_pc as u32, entry.handler_pc as u32]);
+    }
+    // a landing pad sees the variables as the protected block found them, so protected code
+    // starts a new block after each write of a local
+    let protected = |pc: u32| code.exception_table.0.iter().any(|entry| (entry.start_pc as u32..entry.end_pc as u32).contains(&pc));
+    for (i, op) in code.code.iter().enumerate() {
+        if writes_local(op) && protected(pcs[i]) {
+            leaders.extend(pcs.get(i + 1));
+        }
+    }
+    function.add_block();
+    let mut lowerer = Lowerer {
+        class,
+        code,
+        signature: format!("{}.{}{}", class.name()?, name, descriptor),
+        bootstraps: None,
+        index: pcs.iter().enumerate().map(|(i, pc)| (*pc, i)).collect(),
+        pcs,
+        leaders,
+        blocks: BTreeMap::new(),
+        landing_pads: BTreeMap::new(),
+        pending: Vec::new(),
+        returns: Vec::new(),
+        rets: Vec::new(),
+        function,
+        current: BlockId(0),
+        stack: Vec::new(),
+    };
+    lowerer.entry(&MethodDescriptor::parse(descriptor)?, method.has_flag(AccessFlags::Static))?;
+    let returns = lowerer.returns.clone();
+    for block in lowerer.rets.clone() {
+        if let Some(Instruction::IndirectJump { targets, .. }) = lowerer.function.block_mut(block).statements.last_mut().map(|s| &mut s.instruction) {
+            *targets = returns.clone();
+        }
+    }
+    // landing pads map to the handler they catch for
+    let mut offsets: Vec<(BlockId, u32)> = lowerer.blocks.iter().map(|(pc, (block, _))| (*block, *pc))
+        .chain(lowerer.landing_pads.iter().map(|(pc, pad)| (*pad, *pc)))
+        .collect();
+    offsets.sort();
+    Ok((lowerer.function, offsets))
+}
+
+struct Lowerer<'a> {
+    class: &'a RawClass,
+    code: &'a CodeBlock,
+    /// `class.name(descriptor)`, for errors
+    signature: String,
+    bootstraps: Option<BootstrapMethods>,
+    pcs: Vec<u32>,
+    /// Instruction index of each pc
+    index: BTreeMap<u32, usize>,
+    /// Pcs that start a basic block
+    leaders: BTreeSet<u32>,
+    /// Block of each lowered leader, with the types on the stack when entering it
+    blocks: BTreeMap<u32, (BlockId, Vec<IrType>)>,
+    /// Landing pad of each handler pc
+    landing_pads: BTreeMap<u32, BlockId>,
+    pending: Vec<u32>,
+    /// Blocks `jsr` returns to
+    returns: Vec<BlockId>,
+    /// Blocks ending with `ret`
+    rets: Vec<BlockId>,
+    function: Function,
+    current: BlockId,
+    stack: Vec<IrValue>,
+}
+
+impl<'a> Lowerer<'a> {
+    fn error(&self, pc: u32, what: String) -> ClassParseError {
+        ClassParseError::CodeParseError {
+            internal: CodeParseError::InvalidBytecode { at: format!("pc {}", pc), what },
+            classpath: None,
+            signature: Some(self.signature.clone()),
+        }
+    }
+    fn emit(&mut self, instruction: Instruction<IrValue>, ty: IrType) -> IrValue {
+        self.function.push(self.current, instruction, Some(ty)).unwrap()
+    }
+    fn effect(&mut self, instruction: Instruction<IrValue>) {
+        self.function.push(self.current, instruction, None);
+    }
+    fn push(&mut self, instruction: Instruction<IrValue>, ty: IrType) {
+        let value = self.emit(instruction, ty);
+        self.stack.push(value);
+    }
+    fn pop(&mut self, pc: u32) -> Result<IrValue, ClassParseError> {
+        self.stack.pop().ok_or_else(|| self.error(pc, "operand stack underflow".to_string()))
+    }
+    /// Pops `count` values, returned bottom first.
+    fn pop_many(&mut self, pc: u32, count: usize) -> Result<Vec<IrValue>, ClassParseError> {
+        if self.stack.len() < count {
+            return Err(self.error(pc, "operand stack underflow".to_string()));
+        }
+        Ok(self.stack.split_off(self.stack.len() - count))
+    }
+    /// Pops values taking `slots` JVM stack slots, returned bottom first.
+    fn pop_slots(&mut self, pc: u32, slots: usize) -> Result<Vec<IrValue>, ClassParseError> {
+        let mut values = Vec::new();
+        let mut popped = 0;
+        while popped < slots {
+            let value = self.pop(pc)?;
+            popped += if matches!(self.function.value_type(value), IrType::Long | IrType::Double) { 2 } else { 1 };
+            values.push(value);
+        }
+        if popped != slots {
+            return Err(self.error(pc, "instruction splits a long or double on the stack".to_string()));
+        }
+        values.reverse();
+        Ok(values)
+    }
+    fn constant(&mut self, value: Constant) -> IrValue {
+        let ty = value.ty();
+        self.emit(Instruction::Const { value }, ty)
+    }
+    fn null_check(&mut self, value: IrValue) {
+        self.effect(Instruction::NullCheck { value });
+    }
+    fn bootstraps(&mut self) -> Result<&BootstrapMethods, ClassParseError> {
+        if self.bootstraps.is_none() {
+            self.bootstraps = Some(BootstrapMethods::of(self.class)?);
+        }
+        Ok(self.bootstraps.as_ref().unwrap())
+    }
+
+    /// Fills the entry block, which stores the parameters into their locals.
+    fn entry(&mut self, descriptor: &MethodDescriptor, is_static: bool) -> Result<(), ClassParseError> {
+        let mut slot = 0;
+        let receiver = (!is_static).then_some(IrType::Reference);
+        let params = receiver.into_iter().chain(descriptor.params.iter().map(IrType::of));
+        for (index, ty) in params.enumerate() {
+            let value = self.emit(Instruction::Param { index }, ty);
+            self.effect(Instruction::SetVar { var: Variable::Local(slot, ty), value });
+            slot += if matches!(ty, IrType::Long | IrType::Double) { 2 } else { 1 };
+        }
+        let target = self.block_at(0, Vec::new())?;
+        self.effect(Instruction::Goto { target });
+        self.drain()
+    }
+    /// The block starting at `pc`, queued for lowering the first time it is reached.
+    fn block_at(&mut self, pc: u32, types: Vec<IrType>) -> Result<BlockId, ClassParseError> {
+        if !self.index.contains_key(&pc) {
+            return Err(self.error(pc, "jump to the middle of an instruction or past the code".to_string()));
+        }
+        if let Some((block, entered)) = self.blocks.get(&pc) {
+            if *entered != types {
+                return Err(self.error(pc, format!("stack is entered with both {:?} and {:?}", entered, types)));
+            }
+            return Ok(*block);
+        }
+        let block = self.function.add_block();
+        self.blocks.insert(pc, (block, types));
+        self.pending.push(pc);
+        Ok(block)
+    }
+    /// The handlers of the code at `pc`, in the order the exception table tries them.
+    fn handlers_at(&mut self, pc: u32) -> Result<Vec<Handler>, ClassParseError> {
+        let code = self.code;
+        let mut handlers = Vec::new();
+        for entry in code.exception_table.0.iter().filter(|entry| (entry.start_pc as u32..entry.end_pc as u32).contains(&pc)) {
+            let class = match entry.catch_type {
+                0 => None,
+                index => Some(self.class_operand(index)?),
+            };
+            let handler = Handler { class, target: self.landing_pad(entry.handler_pc as u32)? };
+            // javac repeats entries when a range is split around the code of a `finally`
+            if !handlers.contains(&handler) {
+                handlers.push(handler);
+            }
+        }
+        Ok(handlers)
+    }
+    /// The block catching exceptions for the handler at `pc`, which jumps to the handler's code
+    /// with the exception on the stack.
+    fn landing_pad(&mut self, pc: u32) -> Result<BlockId, ClassParseError> {
+        if let Some(pad) = self.landing_pads.get(&pc) {
+            return Ok(*pad);
+        }
+        let pad = self.function.add_block();
+        self.landing_pads.insert(pc, pad);
+        let exception = self.function.push(pad, Instruction::Catch, Some(IrType::Reference)).unwrap();
+        self.function.push(pad, Instruction::SetVar { var: Variable::Stack(0, IrType::Reference), value: exception }, None);
+        let target = self.block_at(pc, vec![IrType::Reference])?;
+        self.function.push(pad, Instruction::Goto { target }, None);
+        Ok(pad)
+    }
+    fn drain(&mut self) -> Result<(), ClassParseError> {
+        while let Some(pc) = self.pending.pop() {
+            self.lower_block(pc)?;
+        }
+        Ok(())
+    }
+    /// Stores the stack into its variables at the end of a block, returning its types.
+    fn flush(&mut self) -> Vec<IrType> {
+        let stack = std::mem::take(&mut self.stack);
+        let mut types = Vec::with_capacity(stack.len());
+        for (depth, value) in stack.into_iter().enumerate() {
+            let ty = self.function.value_type(value);
+            self.effect(Instruction::SetVar { var: Variable::Stack(depth as u16, ty), value });
+            types.push(ty);
+        }
+        types
+    }
+    fn lower_block(&mut self, start: u32) -> Result<(), ClassParseError> {
+        let code = self.code;
+        let (block, types) = self.blocks[&start].clone();
+        self.function.block_mut(block).handlers = self.handlers_at(start)?;
+        self.current = block;
+        self.stack.clear();
+        for (depth, ty) in types.into_iter().enumerate() {
+            self.push(Instruction::GetVar { var: Variable::Stack(depth as u16, ty) }, ty);
+        }
+        let mut i = self.index[&start];
+        loop {
+            let pc = self.pcs[i];
+            let op = &code.code[i];
+            let next = self.pcs.get(i + 1).copied();
+            if op.ends_block() {
+                return self.terminate(pc, next, op);
+            }
+            self.lower(pc, op)?;
+            let next = next.ok_or_else(|| self.error(pc, "execution falls off the end of the code".to_string()))?;
+            if self.leaders.contains(&next) {
+                let types = self.flush();
+                let target = self.block_at(next, types)?;
+                self.effect(Instruction::Goto { target });
+                return Ok(());
+            }
+            i += 1;
+        }
+    }
+
+    fn load(&mut self, index: u16, ty: IrType) {
+        self.push(Instruction::GetVar { var: Variable::Local(index, ty) }, ty);
+    }
+    fn store(&mut self, pc: u32, index: u16) -> Result<(), ClassParseError> {
+        let value = self.pop(pc)?;
+        let ty = self.function.value_type(value);
+        self.effect(Instruction::SetVar { var: Variable::Local(index, ty), value });
+        Ok(())
+    }
+    fn increment(&mut self, index: u16, by: i32) {
+        let var = Variable::Local(index, IrType::Int);
+        let l = self.emit(Instruction::GetVar { var }, IrType::Int);
+        let r = self.constant(Constant::Int(by));
+        let value = self.emit(Instruction::Arithmetic { l, r, op: ArithmeticOp::Add, ty: ArithmeticOpType::I32 }, IrType::Int);
+        self.effect(Instruction::SetVar { var, value });
+    }
+    fn binary(&mut self, pc: u32, op: ArithmeticOp, ty: IrType) -> Result<(), ClassParseError> {
+        let r = self.pop(pc)?;
+        let l = self.pop(pc)?;
+        self.push(Instruction::Arithmetic { l, r, op, ty: ArithmeticOpType::of(ty).unwrap() }, ty);
+        Ok(())
+    }
+    /// Duplicates the top `take` slots below the `skip` slots under them.
+    fn dup(&mut self, pc: u32, take: usize, skip: usize) -> Result<(), ClassParseError> {
+        let copied = self.pop_slots(pc, take)?;
+        let skipped = self.pop_slots(pc, skip)?;
+        self.stack.extend(copied.iter().chain(&skipped).chain(&copied));
+        Ok(())
+    }
+    fn ldc(&mut self, pc: u32, index: u16) -> Result<(), ClassParseError> {
+        let cp = &self.class.cp;
+        let value = match cp.get_info(index)? {
+            ConstantPoolInfo::Integer(value) => Constant::Int(*value),
+            ConstantPoolInfo::Float(value) => Constant::Float(*value),
+            ConstantPoolInfo::Long(value) => Constant::Long(*value),
+            ConstantPoolInfo::Double(value) => Constant::Double(*value),
+            ConstantPoolInfo::StringRef(value) => Constant::String(String::from_utf16_lossy(&cp.get_utf16(*value)?)),
+            ConstantPoolInfo::ClassRef(_) => Constant::Class(cp.get_class_name(index)?.to_string()),
+            ConstantPoolInfo::MethodType(descriptor) => Constant::MethodType(cp.get_utf8(*descriptor)?.to_string()),
+            ConstantPoolInfo::MethodHandle { .. } => Constant::MethodHandle(MethodHandleRef::load(cp, index)?),
+            ConstantPoolInfo::Dynamic { .. } => {
+                let site = CallSite::resolve(cp, self.bootstraps()?, index)?;
+                Constant::Dynamic(Box::new(site))
+            },
+            other => return Err(self.error(pc, format!("ldc of {:?}", other))),
+        };
+        let value = self.constant(value);
+        self.stack.push(value);
+        Ok(())
+    }
+    fn field(&mut self, pc: u32, index: u16, is_static: bool, put: bool) -> Result<(), ClassParseError> {
+        let field = self.class.cp.get_member_ref(index)?;
+        let ty = IrType::of(&FieldType::parse(&field.descriptor)?);
+        let value = if put { Some(self.pop(pc)?) } else { None };
+        let object = if is_static { None } else { Some(self.pop(pc)?) };
+        if let Some(object) = object {
+            self.null_check(object);
+        }
+        match value {
+            Some(value) => self.effect(Instruction::PutField { field, object, value }),
+            None => self.push(Instruction::GetField { field, object }, ty),
+        }
+        Ok(())
+    }
+    fn invoke(&mut self, pc: u32, kind: InvokeKind, index: u16) -> Result<(), ClassParseError> {
+        let method = self.class.cp.get_member_ref(index)?;
+        let descriptor = MethodDescriptor::parse(&method.descriptor)?;
+        let receiver = kind != InvokeKind::Static;
+        let args = self.pop_many(pc, descriptor.params.len() + receiver as usize)?;
+        if receiver {
+            self.null_check(args[0]);
+        }
+        let instruction = Instruction::Invoke { kind, method, num_args: args.len(), args };
+        match &descriptor.ret {
+            Some(ret) => self.push(instruction, IrType::of(ret)),
+            None => self.effect(instruction),
+        }
+        Ok(())
+    }
+    fn invoke_dynamic(&mut self, pc: u32, index: u16) -> Result<(), ClassParseError> {
+        let class = self.class;
+        let site = CallSite::resolve(&class.cp, self.bootstraps()?, index)?;
+        let descriptor = MethodDescriptor::parse(&site.descriptor)?;
+        let args = self.pop_many(pc, descriptor.params.len())?;
+        let instruction = Instruction::InvokeDynamic { site, args };
+        match &descriptor.ret {
+            Some(ret) => self.push(instruction, IrType::of(ret)),
+            None => self.effect(instruction),
+        }
+        Ok(())
+    }
+    fn class_operand(&self, index: u16) -> Result<String, ClassParseError> {
+        Ok(self.class.cp.get_class_name(index)?.to_string())
+    }
+
+    /// Lowers an instruction that does not end its block.
+    fn lower(&mut self, pc: u32, op: &Op) -> Result<(), ClassParseError> {
+        let opcode = op.opcode();
+        match (opcode, op) {
+            (0, _) => {},
+            (1, _) => self.push(Instruction::Const { value: Constant::Null }, IrType::Reference),
+            (2..=8, _) => self.push(Instruction::Const { value: Constant::Int(opcode as i32 - 3) }, IrType::Int),
+            (9..=10, _) => self.push(Instruction::Const { value: Constant::Long(opcode as i64 - 9) }, IrType::Long),
+            (11..=13, _) => self.push(Instruction::Const { value: Constant::Float((opcode - 11) as f32) }, IrType::Float),
+            (14..=15, _) => self.push(Instruction::Const { value: Constant::Double((opcode - 14) as f64) }, IrType::Double),
+            (_, Op::Bipush(value)) => self.push(Instruction::Const { value: Constant::Int(*value as i8 as i32) }, IrType::Int),
+            (_, Op::Sipush(value)) => self.push(Instruction::Const { value: Constant::Int(*value as i16 as i32) }, IrType::Int),
+            (_, Op::Ldc(index)) => self.ldc(pc, *index as u16)?,
+            (_, Op::LdcW(index) | Op::Ldc2W(index)) => self.ldc(pc, *index)?,
+            (_, Op::Iload(index) | Op::Lload(index) | Op::Fload(index) | Op::Dload(index) | Op::Aload(index)) => {
+                self.load(*index as u16, TYPES[opcode as usize - 21]);
+            },
+            (26..=45, _) => self.load((opcode - 26) as u16 % 4, TYPES[(opcode - 26) as usize / 4]),
+            (46..=53, _) => {
+                let element = ELEMENTS[opcode as usize - 46];
+                let index = self.pop(pc)?;
+                let array = self.pop(pc)?;
+                self.null_check(array);
+                self.push(Instruction::ArrayLoad { array, index, element }, element.ty());
+            },
+            (_, Op::Istore(index) | Op::Lstore(index) | Op::Fstore(index) | Op::Dstore(index) | Op::Astore(index)) => {
+                self.store(pc, *index as u16)?;
+            },
+            (59..=78, _) => self.store(pc, (opcode - 59) as u16 % 4)?,
+            (79..=86, _) => {
+                let element = ELEMENTS[opcode as usize - 79];
+                let value = self.pop(pc)?;
+                let index = self.pop(pc)?;
+                let array = self.pop(pc)?;
+                self.null_check(array);
+                self.effect(Instruction::ArrayStore { array, index, value, element });
+            },
+            (_, Op::Pop) => {
+                self.pop_slots(pc, 1)?;
+            },
+            (_, Op::Pop2) => {
+                self.pop_slots(pc, 2)?;
+            },
+            (_, Op::Dup) => self.dup(pc, 1, 0)?,
+            (_, Op::DupX1) => self.dup(pc, 1, 1)?,
+            (_, Op::DupX2) => self.dup(pc, 1, 2)?,
+            (_, Op::Dup2) => self.dup(pc, 2, 0)?,
+            (_, Op::Dup2X1) => self.dup(pc, 2, 1)?,
+            (_, Op::Dup2X2) => self.dup(pc, 2, 2)?,
+            (_, Op::Swap) => {
+                let top = self.pop_slots(pc, 1)?;
+                let below = self.pop_slots(pc, 1)?;
+                self.stack.extend(top.into_iter().chain(below));
+            },
+            (96..=115, _) => {
+                let op = [ArithmeticOp::Add, ArithmeticOp::Sub, ArithmeticOp::Mul, ArithmeticOp::Div, ArithmeticOp::Rem][(opcode - 96) as usize / 4];
+                self.binary(pc, op, TYPES[(opcode - 96) as usize % 4])?;
+            },
+            (116..=119, _) => {
+                let value = self.pop(pc)?;
+                let ty = TYPES[(opcode - 116) as usize];
+                self.push(Instruction::Negate { value, ty: ArithmeticOpType::of(ty).unwrap() }, ty);
+            },
+            (120..=131, _) => {
+                let op = [ArithmeticOp::Shl, ArithmeticOp::Shr, ArithmeticOp::Ushr, ArithmeticOp::And, ArithmeticOp::Or, ArithmeticOp::Xor][(opcode - 120) as usize / 2];
+                self.binary(pc, op, TYPES[(opcode - 120) as usize % 2])?;
+            },
+            (_, Op::Iinc(index, by)) => self.increment(*index as u16, *by as i32),
+            (_, Op::Wide(wide)) => match wide {
+                WideOp::Iload(index) => self.load(*index, IrType::Int),
+                WideOp::Lload(index) => self.load(*index, IrType::Long),
+                WideOp::Fload(index) => self.load(*index, IrType::Float),
+                WideOp::Dload(index) => self.load(*index, IrType::Double),
+                WideOp::Aload(index) => self.load(*index, IrType::Reference),
+                WideOp::Iinc(index, by) => self.increment(*index, *by as i32),
+                WideOp::Ret(_) => return Err(self.error(pc, "ret does not fall through".to_string())),
+                _ => self.store(pc, wide.index())?,
+            },
+            (133..=147, _) => {
+                let (from, to) = CONVERSIONS[(opcode - 133) as usize];
+                let value = self.pop(pc)?;
+                self.push(Instruction::Convert { value, from, to }, to.ty());
+            },
+            (148..=152, _) => {
+                let op = [CompareOp::Cmp, CompareOp::CmpL, CompareOp::CmpG, CompareOp::CmpL, CompareOp::CmpG][(opcode - 148) as usize];
+                let ty = [ArithmeticOpType::I64, ArithmeticOpType::F32, ArithmeticOpType::F32, ArithmeticOpType::F64, ArithmeticOpType::F64][(opcode - 148) as usize];
+                let r = self.pop(pc)?;
+                let l = self.pop(pc)?;
+                self.push(Instruction::Compare { l, r, op, ty }, IrType::Int);
+            },
+            (_, Op::Getstatic(index)) => self.field(pc, *index, true, false)?,
+            (_, Op::Putstatic(index)) => self.field(pc, *index, true, true)?,
+            (_, Op::Getfield(index)) => self.field(pc, *index, false, false)?,
+            (_, Op::Putfield(index)) => self.field(pc, *index, false, true)?,
+            (_, Op::Invokevirtual(index)) => self.invoke(pc, InvokeKind::Virtual, *index)?,
+            (_, Op::Invokespecial(index)) => self.invoke(pc, InvokeKind::Special, *index)?,
+            (_, Op::Invokestatic(index)) => self.invoke(pc, InvokeKind::Static, *index)?,
+            (_, Op::Invokeinterface(index, _, _)) => self.invoke(pc, InvokeKind::Interface, *index)?,
+            (_, Op::Invokedynamic(index, _)) => self.invoke_dynamic(pc, *index)?,
+            (_, Op::New(index)) => {
+                let class = self.class_operand(*index)?;
+                self.push(Instruction::New { class }, IrType::Reference);
+            },
+            (_, Op::Newarray(atype)) => {
+                let length = self.pop(pc)?;
+                self.push(Instruction::NewDynamicArray { element: atype.element(), length }, IrType::Reference);
+            },
+            (_, Op::ANewarray(index)) => {
+                let element = FieldType::from_class_name(&self.class_operand(*index)?)?;
+                let length = self.pop(pc)?;
+                self.push(Instruction::NewDynamicArray { element, length }, IrType::Reference);
+            },
+            (_, Op::Multianewarray(index, dimensions)) => {
+                let class = FieldType::from_class_name(&self.class_operand(*index)?)?;
+                let dimensions = self.pop_many(pc, *dimensions as usize)?;
+                self.push(Instruction::NewMultiArray { class, dimensions }, IrType::Reference);
+            },
+            (_, Op::Arraylength) => {
+                let array = self.pop(pc)?;
+                self.null_check(array);
+                self.push(Instruction::ArrayLength { array }, IrType::Int);
+            },
+            (_, Op::Checkcast(index)) => {
+                let class = self.class_operand(*index)?;
+                let value = self.pop(pc)?;
+                self.push(Instruction::CheckCast { value, class }, IrType::Reference);
+            },
+            (_, Op::Instanceof(index)) => {
+                let class = self.class_operand(*index)?;
+                let value = self.pop(pc)?;
+                self.push(Instruction::InstanceOf { value, class }, IrType::Int);
+            },
+            (_, Op::Monitorenter | Op::Monitorexit) => {
+                let object = self.pop(pc)?;
+                self.null_check(object);
+                self.effect(match op {
+                    Op::Monitorenter => Instruction::MonitorEnter { object },
+                    _ => Instruction::MonitorExit { object },
+                });
+            },
+            _ => return Err(self.error(pc, format!("{} does not fall through", op.mnemonic()))),
+        }
+        Ok(())
+    }
+
+    /// Lowers the instruction that ends the current block.
+    fn terminate(&mut self, pc: u32, next: Option<u32>, op: &Op) -> Result<(), ClassParseError> {
+        let target = op.branch_targets(pc).first().copied().unwrap_or(pc);
+        let next = || next.ok_or_else(|| self.error(pc, "execution falls off the end of the code".to_string()));
+        let next = match op.flow().falls_through() {
+            true => next()?,
+            false => 0,
+        };
+        let opcode = op.opcode();
+        match op {
+            Op::Goto(_) | Op::GotoW(_) => {
+                let types = self.flush();
+                let target = self.block_at(target, types)?;
+                self.effect(Instruction::Goto { target });
+            },
+            Op::Ifeq(_) | Op::Ifne(_) | Op::Iflt(_) | Op::Ifge(_) | Op::Ifgt(_) | Op::Ifle(_) => {
+                let l = self.pop(pc)?;
+                let r = self.constant(Constant::Int(0));
+                self.branch(target, next, l, r, CONDITIONS[(opcode - 153) as usize])?;
+            },
+            Op::IfIcmpeq(_) | Op::IfIcmpne(_) | Op::IfIcmplt(_) | Op::IfIcmpge(_) | Op::IfIcmpgt(_) | Op::IfIcmple(_)
+            | Op::IfAcmpeq(_) | Op::IfAcmpne(_) => {
+                let r = self.pop(pc)?;
+                let l = self.pop(pc)?;
+                self.branch(target, next, l, r, CONDITIONS[(opcode - 159) as usize % 6])?;
+            },
+            Op::Ifnull(_) | Op::Ifnonnull(_) => {
+                let l = self.pop(pc)?;
+                let r = self.constant(Constant::Null);
+                let cond = if opcode == 198 { Condition::Eq } else { Condition::Ne };
+                self.branch(target, next, l, r, cond)?;
+            },
+            Op::Tableswitch(_) | Op::Lookupswitch(_) => {
+                let value = self.pop(pc)?;
+                let types = self.flush();
+                let keys: Vec<i32> = match op {
+                    Op::Tableswitch(table) => (table.low..=table.high).collect(),
+                    Op::Lookupswitch(lookup) => lookup.matches.iter().map(|(key, _)| *key).collect(),
+                    _ => unreachable!(),
+                };
+                let targets = op.branch_targets(pc);
+                let default = self.block_at(targets[0], types.clone())?;
+                let mut cases = Vec::with_capacity(keys.len());
+                for (key, target) in keys.into_iter().zip(&targets[1..]) {
+                    cases.push((key, self.block_at(*target, types.clone())?));
+                }
+                self.effect(Instruction::Switch { value, cases, default });
+            },
+            Op::Ireturn | Op::Lreturn | Op::Freturn | Op::Dreturn | Op::Areturn => {
+                let value = self.pop(pc)?;
+                self.effect(Instruction::Return { value: Some(value) });
+            },
+            Op::Return => self.effect(Instruction::Return { value: None }),
+            Op::Athrow => {
+                let value = self.pop(pc)?;
+                self.null_check(value);
+                self.effect(Instruction::Throw { value });
+            },
+            Op::Jsr(_) | Op::JsrW(_) => {
+                let mut types = self.flush();
+                let returns = self.block_at(next, types.clone())?;
+                self.returns.push(returns);
+                let value = self.constant(Constant::ReturnAddress(returns));
+                let var = Variable::Stack(types.len() as u16, IrType::ReturnAddress);
+                self.effect(Instruction::SetVar { var, value });
+                types.push(IrType::ReturnAddress);
+                let target = self.block_at(target, types)?;
+                self.effect(Instruction::Goto { target });
+            },
+            Op::Ret(_) | Op::Wide(WideOp::Ret(_)) => {
+                let index = match op {
+                    Op::Ret(index) => *index as u16,
+                    Op::Wide(wide) => wide.index(),
+                    _ => unreachable!(),
+                };
+                let address = self.emit(Instruction::GetVar { var: Variable::Local(index, IrType::ReturnAddress) }, IrType::ReturnAddress);
+                self.flush();
+                self.effect(Instruction::IndirectJump { address, targets: Vec::new() });
+                self.rets.push(self.current);
+            },
+            _ => return Err(self.error(pc, format!("{} does not end a block", op.mnemonic()))),
+        }
+        Ok(())
+    }
+    fn branch(&mut self, target: u32, next: u32, l: IrValue, r: IrValue, cond: Condition) -> Result<(), ClassParseError> {
+        let types = self.flush();
+        let if_true = self.block_at(target, types.clone())?;
+        let if_false = self.block_at(next, types)?;
+        self.effect(Instruction::Branch { l, r, cond, if_true, if_false });
+        Ok(())
+    }
+}
+
+/// Whether the instruction writes a local variable.
+fn writes_local(op: &Op) -> bool {
+    match op {
+        Op::Wide(wide) => !matches!(wide, WideOp::Iload(_) | WideOp::Lload(_) | WideOp::Fload(_) | WideOp::Dload(_) | WideOp::Aload(_) | WideOp::Ret(_)),
+        op => matches!(op.opcode(), 54..=78 | 132),
+    }
+}
+
+/// Conditions of `ifeq` through `ifle`, and of the `if_icmp` and `if_acmp` families.
+const CONDITIONS: [Condition; 6] = [Condition::Eq, Condition::Ne, Condition::Lt, Condition::Ge, Condition::Gt, Condition::Le];
@@ -1,2 +1,13 @@
 pub mod ir_instructions;
-pub mod ir_value;
\ No newline at end of file
+pub mod ir_value;
+pub mod function;
+pub mod module;
+pub mod lower;
+pub mod dominance;
+pub mod ssa;
+pub mod printer;
+pub mod parser;
+pub mod binary;
+pub mod verifier;
+pub mod passes;
+pub mod regalloc;
@@ -0,0 +1,55 @@
+use super::{function::Function, ir_value::BlockId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrField {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+}
+
+/// A class in IR form, with a function for each of its methods.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrClass {
+    pub name: String,
+    pub super_name: Option<String>,
+    pub interfaces: Vec<String>,
+    pub access_flags: u16,
+    pub fields: Vec<IrField>,
+    pub functions: Vec<Function>,
+}
+
+impl IrClass {
+    pub fn find_function(&self, name: &str, descriptor: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name && function.descriptor == descriptor)
+    }
+}
+
+/// A unit of rootir: the classes converted from a class file, directory or jar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Module {
+    pub classes: Vec<IrClass>,
+}
+
+impl Module {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn find_class(&self, name: &str) -> Option<&IrClass> {
+        self.classes.iter().find(|class| class.name == name)
+    }
+}
+
+/// What a module keeps about where its code came from, with an entry for each class.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebugInfo {
+    pub classes: Vec<ClassDebugInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClassDebugInfo {
+    /// The class file or jar entry the class was read from
+    pub source: Option<String>,
+    /// For each function, the bytecode offset each block lowered from bytecode starts at, or for a
+    /// landing pad, the offset of its handler
+    pub block_offsets: Vec<Vec<(BlockId, u32)>>,
+}
@@ -0,0 +1,568 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    jvm::{
+        asm::{ast::{AsmHandle, AsmMemberRef}, lexer::TokenKind, parser::{describe, Parser, CLASS_FLAGS, FIELD_FLAGS, METHOD_FLAGS}},
+        reader::{bootstrap::{BootstrapArgument, CallSite, MethodHandleRef}, constant_pool::MemberRef, descriptor::FieldType},
+    },
+    util::code_err::AssemblyError,
+};
+
+use super::{
+    function::{Function, Handler, Statement},
+    ir_instructions::{ArithmeticOp, ArithmeticOpType, CompareOp, Condition, ElementType, Instruction, InvokeKind},
+    ir_value::{BlockId, Constant, IrType, IrValue, Variable},
+    module::{IrClass, IrField, Module},
+};
+
+/// Parses the classes of a module, as the printer writes them.
+pub fn parse_module(source: &str) -> Result<Module, AssemblyError> {
+    let mut p = Parser::new(source)?;
+    let mut module = Module::new();
+    p.skip_newlines();
+    while p.peek().is_some() {
+        module.classes.push(parse_class(&mut p)?);
+        p.skip_newlines();
+    }
+    Ok(module)
+}
+
+/// Parses a single function outside of any class, mostly for hand-written tests.
+pub fn parse_function(source: &str) -> Result<Function, AssemblyError> {
+    let mut p = Parser::new(source)?;
+    p.skip_newlines();
+    let function = function(&mut p)?;
+    p.skip_newlines();
+    match p.peek() {
+        None => Ok(function),
+        Some(other) => p.error(format!("expected end of file, found {}", describe(other))),
+    }
+}
+
+/// Flag words, and numbers for the bits that have no word.
+fn flags(p: &mut Parser, table: &[(&str, u16)]) -> Result<u16, AssemblyError> {
+    let mut flags = p.parse_flags(table);
+    while let Some(TokenKind::Int(_)) = p.peek() {
+        flags |= p.expect_int_in::<u16>("access flags")?;
+        flags |= p.parse_flags(table);
+    }
+    Ok(flags)
+}
+
+fn parse_class(p: &mut Parser) -> Result<IrClass, AssemblyError> {
+    p.expect_keyword("class")?;
+    let access_flags = flags(p, CLASS_FLAGS)?;
+    let name = p.expect_word("class name")?;
+    let super_name = match p.eat_keyword("extends") {
+        true => Some(p.expect_word("superclass name")?),
+        false => None,
+    };
+    let mut interfaces = Vec::new();
+    if p.eat_keyword("implements") {
+        interfaces.push(p.expect_word("interface name")?);
+        while p.peek() == Some(&TokenKind::Comma) {
+            p.next();
+            interfaces.push(p.expect_word("interface name")?);
+        }
+    }
+    p.expect_token(TokenKind::LBrace)?;
+    p.expect_newline()?;
+    let mut class = IrClass { name, super_name, interfaces, access_flags, fields: Vec::new(), functions: Vec::new() };
+    loop {
+        p.skip_newlines();
+        match p.peek() {
+            Some(TokenKind::RBrace) => {
+                p.next();
+                p.expect_newline()?;
+                return Ok(class);
+            },
+            Some(TokenKind::Word(word)) if word == "field" => {
+                p.next();
+                let access_flags = flags(p, FIELD_FLAGS)?;
+                let name = p.expect_word("field name")?;
+                let descriptor = p.expect_word("field descriptor")?;
+                p.expect_newline()?;
+                class.fields.push(IrField { name, descriptor, access_flags });
+            },
+            Some(TokenKind::Word(word)) if word == "function" => class.functions.push(function(p)?),
+            Some(other) => return p.error(format!("expected `field`, `function` or `}}`, found {}", describe(other))),
+            None => return p.error(format!("missing `}}` for class `{}`", class.name)),
+        }
+    }
+}
+
+/// `function <flags> <name><descriptor>`, followed by a body in braces when the method has code.
+fn function(p: &mut Parser) -> Result<Function, AssemblyError> {
+    p.expect_keyword("function")?;
+    let access_flags = flags(p, METHOD_FLAGS)?;
+    let (line, column) = p.location();
+    let signature = p.expect_word("function name")?;
+    let paren = match signature.find('(') {
+        Some(paren) if paren > 0 => paren,
+        _ => return Err(AssemblyError::Syntax { line, column, message: format!("expected `name(descriptor)`, found `{}`", signature) }),
+    };
+    let function = Function::new(&signature[..paren], &signature[paren..], access_flags);
+    if p.peek() != Some(&TokenKind::LBrace) {
+        p.expect_newline()?;
+        return Ok(function);
+    }
+    p.next();
+    p.expect_newline()?;
+    Body { p, function, types: Vec::new(), uses: Vec::new(), labels: BTreeMap::new(), targets: Vec::new(), header: line }.parse()
+}
+
+/// The state of a function body while it is parsed. Values and blocks keep the numbers they
+/// are written with.
+struct Body<'a> {
+    p: &'a mut Parser,
+    function: Function,
+    /// Type of each value defined so far
+    types: Vec<Option<IrType>>,
+    /// Values read, with where they were read, checked against the definitions at the end
+    uses: Vec<(IrValue, usize, usize)>,
+    /// Line of each block label
+    labels: BTreeMap<u32, usize>,
+    /// Blocks jumped to, with the line of the jump
+    targets: Vec<(BlockId, usize)>,
+    header: usize,
+}
+
+impl Body<'_> {
+    fn parse(mut self) -> Result<Function, AssemblyError> {
+        let mut current = None;
+        loop {
+            self.p.skip_newlines();
+            let (line, column) = self.p.location();
+            match self.p.peek() {
+                Some(TokenKind::RBrace) => {
+                    self.p.next();
+                    self.p.expect_newline()?;
+                    break;
+                },
+                Some(TokenKind::LabelDef(label)) => {
+                    let label = label.clone();
+                    let block = block_number(&label)
+                        .ok_or_else(|| AssemblyError::Syntax { line, column, message: format!("expected a block label like `bb0:`, found `{}:`", label) })?;
+                    if self.labels.insert(block, line).is_some() {
+                        return Err(AssemblyError::DuplicateLabel { line, label });
+                    }
+                    while self.function.blocks.len() <= block as usize {
+                        self.function.add_block();
+                    }
+                    current = Some(BlockId(block));
+                    self.p.next();
+                    if self.p.peek() == Some(&TokenKind::Word("try".to_string())) {
+                        self.p.next();
+                        loop {
+                            let (line, column) = self.p.location();
+                            let class = match self.p.next() {
+                                Some(TokenKind::Word(word)) if word == "any" => None,
+                                Some(TokenKind::Word(class) | TokenKind::Str(class)) => Some(class),
+                                other => return Err(AssemblyError::Syntax {
+                                    line,
+                                    column,
+                                    message: format!("expected a handler's class, found {}", other.as_ref().map(describe).unwrap_or_else(|| "end of file".to_string())),
+                                }),
+                            };
+                            let target = self.block()?;
+                            self.function.block_mut(BlockId(block)).handlers.push(Handler { class, target });
+                            if self.p.peek() != Some(&TokenKind::Comma) {
+                                break;
+                            }
+                            self.p.next();
+                        }
+                    }
+                    self.p.expect_newline()?;
+                },
+                Some(_) => {
+                    let block = match current {
+                        Some(block) => block,
+                        None => return self.p.error("expected a block label before the first statement"),
+                    };
+                    let statement = self.statement()?;
+                    self.function.block_mut(block).statements.push(statement);
+                },
+                None => return self.p.error(format!("missing `}}` for function `{}{}`", self.function.name, self.function.descriptor)),
+            }
+        }
+        for (target, line) in &self.targets {
+            if !self.labels.contains_key(&target.0) {
+                return Err(AssemblyError::UndefinedLabel { line: *line, label: target.to_string() });
+            }
+        }
+        if let Some(missing) = self.function.block_ids().find(|block| !self.labels.contains_key(&block.0)) {
+            return Err(AssemblyError::UndefinedLabel { line: self.header, label: missing.to_string() });
+        }
+        for (value, line, column) in &self.uses {
+            if self.types.get(value.0 as usize).copied().flatten().is_none() {
+                return Err(AssemblyError::Syntax { line: *line, column: *column, message: format!("`{}` is never defined", value) });
+            }
+        }
+        // numbers nothing defines keep a placeholder type
+        self.function.values = self.types.iter().map(|ty| ty.unwrap_or(IrType::Int)).collect();
+        Ok(self.function)
+    }
+
+    /// `[%N = <type>] <mnemonic> <operands>`
+    fn statement(&mut self) -> Result<Statement, AssemblyError> {
+        let mut result = None;
+        if let Some(TokenKind::Word(word)) = self.p.peek() {
+            if word.starts_with('%') {
+                let (line, column) = self.p.location();
+                let value = self.value_number()?;
+                self.p.expect_token(TokenKind::Equals)?;
+                let ty = self.ir_type()?;
+                let slot = value.0 as usize;
+                if self.types.len() <= slot {
+                    self.types.resize(slot + 1, None);
+                }
+                match self.types[slot] {
+                    Some(defined) if defined != ty => return Err(AssemblyError::Syntax {
+                        line,
+                        column,
+                        message: format!("`{}` is already defined as {}", value, defined),
+                    }),
+                    _ => self.types[slot] = Some(ty),
+                }
+                result = Some(value);
+            }
+        }
+        let instruction = self.instruction()?;
+        self.p.expect_newline()?;
+        Ok(Statement { result, instruction })
+    }
+
+    fn instruction(&mut self) -> Result<Instruction<IrValue>, AssemblyError> {
+        use Instruction as I;
+        let (line, column) = self.p.location();
+        let mnemonic = match self.p.next() {
+            Some(TokenKind::Word(word)) => word,
+            other => return Err(AssemblyError::Syntax {
+                line,
+                column,
+                message: format!("expected an instruction, found {}", other.as_ref().map(describe).unwrap_or_else(|| "end of file".to_string())),
+            }),
+        };
+        if let Some(op) = ArithmeticOp::from_name(&mnemonic) {
+            let ty = self.operation_type()?;
+            let l = self.value()?;
+            let r = self.comma_value()?;
+            return Ok(I::Arithmetic { l, r, op, ty });
+        }
+        if let Some(op) = CompareOp::from_name(&mnemonic) {
+            let ty = self.operation_type()?;
+            let l = self.value()?;
+            let r = self.comma_value()?;
+            return Ok(I::Compare { l, r, op, ty });
+        }
+        Ok(match mnemonic.as_str() {
+            "param" => I::Param { index: self.p.expect_int_in("parameter index")? },
+            "const" => I::Const { value: self.constant()? },
+            "getvar" => I::GetVar { var: self.variable()? },
+            "setvar" => {
+                let var = self.variable()?;
+                I::SetVar { var, value: self.comma_value()? }
+            },
+            "neg" => {
+                let ty = self.operation_type()?;
+                I::Negate { value: self.value()?, ty }
+            },
+            "convert" => {
+                let from = self.operation_type()?;
+                let to = self.operation_type()?;
+                I::Convert { value: self.value()?, from, to }
+            },
+            "newsizedarray" => {
+                let element = self.field_type()?;
+                I::NewSizedArray { element, size: self.p.expect_int_in("array size")? }
+            },
+            "newarray" => {
+                let element = self.field_type()?;
+                I::NewDynamicArray { element, length: self.value()? }
+            },
+            "multianewarray" => {
+                let class = self.field_type()?;
+                I::NewMultiArray { class, dimensions: self.values()? }
+            },
+            "new" => I::New { class: self.p.expect_word("class name")? },
+            "arraylength" => I::ArrayLength { array: self.value()? },
+            "aload" => {
+                let element = self.element_type()?;
+                let array = self.value()?;
+                I::ArrayLoad { array, index: self.comma_value()?, element }
+            },
+            "astore" => {
+                let element = self.element_type()?;
+                let array = self.value()?;
+                let index = self.comma_value()?;
+                I::ArrayStore { array, index, value: self.comma_value()?, element }
+            },
+            "getfield" => {
+                let field = member(self.p.parse_field_ref(false)?);
+                let mut values = self.values()?;
+                if values.len() > 1 {
+                    return self.p.error("`getfield` takes at most an object");
+                }
+                I::GetField { field, object: values.pop() }
+            },
+            "putfield" => {
+                let field = member(self.p.parse_field_ref(false)?);
+                let mut values = self.values()?;
+                let value = match (values.pop(), values.len()) {
+                    (Some(value), 0 | 1) => value,
+                    _ => return self.p.error("`putfield` takes an optional object and a value"),
+                };
+                I::PutField { field, object: values.pop(), value }
+            },
+            "invoke" => {
+                let (line, column) = self.p.location();
+                let kind_name = self.p.expect_word("invoke kind")?;
+                let kind = InvokeKind::from_name(&kind_name)
+                    .ok_or_else(|| AssemblyError::Syntax { line, column, message: format!("unknown invoke kind `{}`", kind_name) })?;
+                let interface = self.p.eat_keyword("interface") || kind == InvokeKind::Interface;
+                let method = member(self.p.parse_method_ref(interface)?);
+                let num_args = match self.p.eat_keyword("argc") {
+                    true => Some(self.p.expect_int_in("argument count")?),
+                    false => None,
+                };
+                let args = self.values()?;
+                I::Invoke { kind, method, num_args: num_args.unwrap_or(args.len()), args }
+            },
+            "invokedynamic" => {
+                let site = self.call_site()?;
+                I::InvokeDynamic { site, args: self.values()? }
+            },
+            "checkcast" => {
+                let class = self.p.expect_word("class name")?;
+                I::CheckCast { value: self.value()?, class }
+            },
+            "instanceof" => {
+                let class = self.p.expect_word("class name")?;
+                I::InstanceOf { value: self.value()?, class }
+            },
+            "nullcheck" => I::NullCheck { value: self.value()? },
+            "monitorenter" => I::MonitorEnter { object: self.value()? },
+            "monitorexit" => I::MonitorExit { object: self.value()? },
+            "focus" => I::Focus { value: self.value()? },
+            "catch" => I::Catch,
+            "phi" => {
+                let mut inputs = Vec::new();
+                if self.p.peek() != Some(&TokenKind::Newline) {
+                    let block = self.block()?;
+                    inputs.push((block, self.value()?));
+                    while self.p.peek() == Some(&TokenKind::Comma) {
+                        self.p.next();
+                        let block = self.block()?;
+                        inputs.push((block, self.value()?));
+                    }
+                }
+                I::Phi { inputs }
+            },
+            "goto" => I::Goto { target: self.block()? },
+            "br" => {
+                let (line, column) = self.p.location();
+                let cond_name = self.p.expect_word("condition")?;
+                let cond = Condition::from_name(&cond_name)
+                    .ok_or_else(|| AssemblyError::Syntax { line, column, message: format!("unknown condition `{}`", cond_name) })?;
+                let l = self.value()?;
+                let r = self.comma_value()?;
+                self.p.expect_token(TokenKind::Comma)?;
+                let if_true = self.block()?;
+                self.p.expect_token(TokenKind::Comma)?;
+                I::Branch { l, r, cond, if_true, if_false: self.block()? }
+            },
+            "switch" => {
+                let value = self.value()?;
+                self.p.expect_token(TokenKind::Comma)?;
+                self.p.expect_keyword("default")?;
+                let default = self.block()?;
+                let mut cases = Vec::new();
+                while self.p.peek() == Some(&TokenKind::Comma) {
+                    self.p.next();
+                    let key = self.p.expect_int_in("case key")?;
+                    cases.push((key, self.block()?));
+                }
+                I::Switch { value, cases, default }
+            },
+            "indirectjump" => {
+                let address = self.value()?;
+                let mut targets = Vec::new();
+                while self.p.peek() == Some(&TokenKind::Comma) {
+                    self.p.next();
+                    targets.push(self.block()?);
+                }
+                I::IndirectJump { address, targets }
+            },
+            "return" => {
+                let mut values = self.values()?;
+                if values.len() > 1 {
+                    return self.p.error("`return` takes at most a value");
+                }
+                I::Return { value: values.pop() }
+            },
+            "throw" => I::Throw { value: self.value()? },
+            _ => return Err(AssemblyError::UnknownMnemonic { line, column, mnemonic }),
+        })
+    }
+
+    fn value_number(&mut self) -> Result<IrValue, AssemblyError> {
+        let (line, column) = self.p.location();
+        let word = self.p.expect_word("value")?;
+        match word.strip_prefix('%').and_then(|number| number.parse().ok()) {
+            Some(number) => Ok(IrValue(number)),
+            None => Err(AssemblyError::Syntax { line, column, message: format!("expected a value like `%0`, found `{}`", word) }),
+        }
+    }
+    /// A value read by the instruction.
+    fn value(&mut self) -> Result<IrValue, AssemblyError> {
+        let (line, column) = self.p.location();
+        let value = self.value_number()?;
+        self.uses.push((value, line, column));
+        Ok(value)
+    }
+    fn comma_value(&mut self) -> Result<IrValue, AssemblyError> {
+        self.p.expect_token(TokenKind::Comma)?;
+        self.value()
+    }
+    /// Values separated by commas, possibly none.
+    fn values(&mut self) -> Result<Vec<IrValue>, AssemblyError> {
+        let mut values = Vec::new();
+        if matches!(self.p.peek(), Some(TokenKind::Word(word)) if word.starts_with('%')) {
+            values.push(self.value()?);
+            while self.p.peek() == Some(&TokenKind::Comma) {
+                values.push(self.comma_value()?);
+            }
+        }
+        Ok(values)
+    }
+    /// A block jumped to or flowing into a phi.
+    fn block(&mut self) -> Result<BlockId, AssemblyError> {
+        let (line, column) = self.p.location();
+        let word = self.p.expect_word("block")?;
+        match block_number(&word) {
+            Some(number) => {
+                self.targets.push((BlockId(number), line));
+                Ok(BlockId(number))
+            },
+            None => Err(AssemblyError::Syntax { line, column, message: format!("expected a block like `bb0`, found `{}`", word) }),
+        }
+    }
+    fn ir_type(&mut self) -> Result<IrType, AssemblyError> {
+        self.named("type", IrType::from_name)
+    }
+    fn operation_type(&mut self) -> Result<ArithmeticOpType, AssemblyError> {
+        self.named("operation type", ArithmeticOpType::from_name)
+    }
+    fn element_type(&mut self) -> Result<ElementType, AssemblyError> {
+        self.named("element type", ElementType::from_name)
+    }
+    fn named<T>(&mut self, what: &str, from_name: impl Fn(&str) -> Option<T>) -> Result<T, AssemblyError> {
+        let (line, column) = self.p.location();
+        let word = self.p.expect_word(what)?;
+        from_name(&word).ok_or_else(|| AssemblyError::Syntax { line, column, message: format!("unknown {} `{}`", what, word) })
+    }
+    fn field_type(&mut self) -> Result<FieldType, AssemblyError> {
+        let (line, column) = self.p.location();
+        let descriptor = self.p.expect_word("field descriptor")?;
+        FieldType::parse(&descriptor).map_err(|_| AssemblyError::Syntax { line, column, message: format!("bad field descriptor `{}`", descriptor) })
+    }
+    /// `local<N>:<type>` or `stack<N>:<type>`
+    fn variable(&mut self) -> Result<Variable, AssemblyError> {
+        let (line, column) = self.p.location();
+        let word = self.p.expect_word("variable")?;
+        let parsed = word.split_once(':').and_then(|(slot, ty)| {
+            let ty = IrType::from_name(ty)?;
+            match (slot.strip_prefix("local"), slot.strip_prefix("stack")) {
+                (Some(index), _) => Some(Variable::Local(index.parse().ok()?, ty)),
+                (_, Some(depth)) => Some(Variable::Stack(depth.parse().ok()?, ty)),
+                _ => None,
+            }
+        });
+        parsed.ok_or_else(|| AssemblyError::Syntax { line, column, message: format!("expected a variable like `local0:int`, found `{}`", word) })
+    }
+
+    fn constant(&mut self) -> Result<Constant, AssemblyError> {
+        let (line, column) = self.p.location();
+        let kind = self.p.expect_word("constant")?;
+        Ok(match kind.as_str() {
+            "int" => Constant::Int(self.p.expect_int_in("int")?),
+            "long" => Constant::Long(self.take("a long", |token| match token {
+                TokenKind::Int(value) | TokenKind::Long(value) => Some(value),
+                _ => None,
+            })?),
+            "float" => Constant::Float(self.number()? as f32),
+            "double" => Constant::Double(self.number()?),
+            "null" => Constant::Null,
+            "string" => Constant::String(self.take("a quoted string", |token| match token {
+                TokenKind::Str(value) => Some(value),
+                _ => None,
+            })?),
+            "class" => Constant::Class(self.p.expect_word("class name")?),
+            "methodtype" => Constant::MethodType(self.p.expect_word("method descriptor")?),
+            "handle" => Constant::MethodHandle(handle(self.p.parse_handle()?)),
+            "dynamic" => Constant::Dynamic(Box::new(self.call_site()?)),
+            "retaddr" => Constant::ReturnAddress(self.block()?),
+            _ => return Err(AssemblyError::Syntax { line, column, message: format!("unknown constant kind `{}`", kind) }),
+        })
+    }
+    fn number(&mut self) -> Result<f64, AssemblyError> {
+        self.take("a number", |token| match token {
+            TokenKind::Float(value, _) => Some(value),
+            TokenKind::Int(value) => Some(value as f64),
+            _ => None,
+        })
+    }
+    /// Takes the next token if `accept` makes something of it.
+    fn take<T>(&mut self, what: &str, accept: impl FnOnce(TokenKind) -> Option<T>) -> Result<T, AssemblyError> {
+        let found = self.p.peek().map(describe).unwrap_or_else(|| "end of file".to_string());
+        match self.p.peek().cloned().and_then(accept) {
+            Some(value) => {
+                self.p.next();
+                Ok(value)
+            },
+            None => self.p.error(format!("expected {}, found {}", what, found)),
+        }
+    }
+
+    /// `<name> <descriptor> <handle> { <constant>, ... }`
+    fn call_site(&mut self) -> Result<CallSite, AssemblyError> {
+        let name = self.p.expect_word("call site name")?;
+        let descriptor = self.p.expect_word("call site descriptor")?;
+        let bootstrap = handle(self.p.parse_handle()?);
+        self.p.expect_token(TokenKind::LBrace)?;
+        let mut arguments = Vec::new();
+        while self.p.peek() != Some(&TokenKind::RBrace) {
+            if !arguments.is_empty() {
+                self.p.expect_token(TokenKind::Comma)?;
+            }
+            let (line, column) = self.p.location();
+            arguments.push(match self.constant()? {
+                Constant::Int(value) => BootstrapArgument::Int(value),
+                Constant::Float(value) => BootstrapArgument::Float(value),
+                Constant::Long(value) => BootstrapArgument::Long(value),
+                Constant::Double(value) => BootstrapArgument::Double(value),
+                Constant::String(value) => BootstrapArgument::String(value),
+                Constant::Class(value) => BootstrapArgument::Class(value),
+                Constant::MethodType(value) => BootstrapArgument::MethodType(value),
+                Constant::MethodHandle(value) => BootstrapArgument::MethodHandle(value),
+                Constant::Dynamic(value) => BootstrapArgument::Dynamic(value),
+                other => return Err(AssemblyError::Syntax { line, column, message: format!("`{}` is not a bootstrap argument", other) }),
+            });
+        }
+        self.p.next();
+        Ok(CallSite { bootstrap, arguments, name, descriptor })
+    }
+}
+
+/// The number of a `bb<N>` label.
+fn block_number(label: &str) -> Option<u32> {
+    label.strip_prefix("bb").filter(|digits| digits.chars().all(|c| c.is_ascii_digit())).and_then(|digits| digits.parse().ok())
+}
+
+fn member(member: AsmMemberRef) -> MemberRef {
+    MemberRef { class: member.owner, name: member.name, descriptor: member.descriptor, interface: member.interface }
+}
+
+fn handle(handle: AsmHandle) -> MethodHandleRef {
+    MethodHandleRef { kind: handle.kind, member: member(handle.member) }
+}
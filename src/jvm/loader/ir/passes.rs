@@ -0,0 +1,702 @@
+use std::{cmp::Ordering, collections::{BTreeMap, BTreeSet}};
+
+use crate::jvm::reader::access_flags::AccessFlags;
+
+use super::{
+    dominance::DominatorTree,
+    function::{Function, Location},
+    ir_instructions::{ArithmeticOp, ArithmeticOpType, CompareOp, Condition, Instruction},
+    ir_value::{BlockId, Constant, IrValue},
+    ssa::DefUse,
+    verifier::{verify, VerifyError},
+};
+
+/// A transformation of a function in SSA form.
+pub trait Pass {
+    fn name(&self) -> &'static str;
+    /// Transforms `function` once, returning how many changes it made; 0 means it was left as is.
+    fn run(&self, function: &mut Function) -> usize;
+}
+
+/// What one run of a pass changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PassReport {
+    pub pass: &'static str,
+    /// Round of the pipeline, counted from 0
+    pub round: usize,
+    pub changes: usize,
+}
+
+/// Runs passes over functions in SSA form, in the order they were added. With `fixed_point`
+/// the pipeline is repeated until a round changes nothing, or `max_rounds` is reached.
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+    fixed_point: bool,
+    max_rounds: usize,
+    verify: bool,
+}
+
+impl Default for PassManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PassManager {
+    pub fn new() -> Self {
+        Self {
+            passes: Vec::new(),
+            fixed_point: false,
+            max_rounds: 16,
+            verify: false,
+        }
+    }
+    /// Every pass, repeated to a fixed point.
+    pub fn standard() -> Self {
+        let mut manager = Self::new();
+        manager
+            .add(ConstantFolding)
+            .add(CopyPropagation)
+            .add(CommonSubexpressions)
+            .add(NullCheckElimination)
+            .add(SimplifyCfg)
+            .add(DeadCodeElimination)
+            .fixed_point(true);
+        manager
+    }
+    pub fn add(&mut self, pass: impl Pass + 'static) -> &mut Self {
+        self.passes.push(Box::new(pass));
+        self
+    }
+    pub fn fixed_point(&mut self, fixed_point: bool) -> &mut Self {
+        self.fixed_point = fixed_point;
+        self
+    }
+    pub fn max_rounds(&mut self, max_rounds: usize) -> &mut Self {
+        self.max_rounds = max_rounds;
+        self
+    }
+    /// Verifies the function before the first pass and after each pass that changed it.
+    pub fn verify(&mut self, verify: bool) -> &mut Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Runs the pipeline, reporting every pass run in order, and renumbers the values if anything
+    /// changed. Functions without code are left alone.
+    pub fn run(&self, function: &mut Function) -> Result<Vec<PassReport>, VerifyError> {
+        let mut reports = Vec::new();
+        if function.blocks.is_empty() {
+            return Ok(reports);
+        }
+        if self.verify {
+            verify(function)?;
+        }
+        let rounds = if self.fixed_point { self.max_rounds } else { 1 };
+        for round in 0..rounds {
+            let mut changed = false;
+            for pass in &self.passes {
+                let changes = pass.run(function);
+                reports.push(PassReport { pass: pass.name(), round, changes });
+                if changes > 0 && self.verify {
+                    verify(function).map_err(|err| VerifyError { after: Some(pass.name()), ..err })?;
+                }
+                changed |= changes > 0;
+            }
+            if !changed {
+                break;
+            }
+        }
+        if reports.iter().any(|report| report.changes > 0) {
+            function.compact_values();
+        }
+        Ok(reports)
+    }
+}
+
+/// Value replacements and statement removals found by a pass, applied together once it has
+/// looked at the whole function so the locations it collected stay valid.
+struct Rewrite {
+    replacements: Vec<Option<IrValue>>,
+    removals: BTreeSet<Location>,
+    changes: usize,
+}
+
+impl Rewrite {
+    fn new(function: &Function) -> Self {
+        Self { replacements: vec![None; function.values.len()], removals: BTreeSet::new(), changes: 0 }
+    }
+    /// Makes every use of `old` read `new`.
+    fn substitute(&mut self, old: IrValue, new: IrValue) {
+        self.replacements[old.0 as usize] = Some(new);
+    }
+    /// Makes every use of `old` read `new`, and removes the statement at `at` defining `old`.
+    fn replace(&mut self, at: Location, old: IrValue, new: IrValue) {
+        self.substitute(old, new);
+        self.remove(at);
+    }
+    fn remove(&mut self, at: Location) {
+        if self.removals.insert(at) {
+            self.changes += 1;
+        }
+    }
+    /// The value `value` ends up as, following chains of replacements.
+    fn resolve(&self, mut value: IrValue) -> IrValue {
+        for _ in 0..self.replacements.len() {
+            match self.replacements[value.0 as usize] {
+                Some(next) if next != value => value = next,
+                _ => break,
+            }
+        }
+        value
+    }
+    fn apply(self, function: &mut Function) -> usize {
+        for block in function.block_ids().collect::<Vec<_>>() {
+            let statements = std::mem::take(&mut function.block_mut(block).statements);
+            let kept = statements.into_iter().enumerate().filter(|(index, _)| !self.removals.contains(&Location { block, index: *index }));
+            let mut statements: Vec<_> = kept.map(|(_, statement)| statement).collect();
+            for statement in &mut statements {
+                for operand in statement.instruction.operands_mut() {
+                    *operand = self.resolve(*operand);
+                }
+            }
+            function.block_mut(block).statements = statements;
+        }
+        self.changes
+    }
+}
+
+/// The constant each value holds, if it is defined by `Const`.
+fn constants(function: &Function) -> Vec<Option<Constant>> {
+    let mut constants = vec![None; function.values.len()];
+    for statement in function.blocks.iter().flat_map(|block| &block.statements) {
+        if let (Some(result), Instruction::Const { value }) = (statement.result, &statement.instruction) {
+            constants[result.0 as usize] = Some(value.clone());
+        }
+    }
+    constants
+}
+
+/// Whether the instruction can throw, write memory or otherwise be observed beyond its result.
+/// `Param` counts as observable so that every parameter keeps its definition.
+fn has_effects(instruction: &Instruction<IrValue>, constants: &[Option<Constant>]) -> bool {
+    use Instruction as I;
+    match instruction {
+        I::Const { .. } | I::GetVar { .. } | I::Negate { .. } | I::Convert { .. } | I::Compare { .. } | I::Focus { .. }
+        | I::Phi { .. } | I::InstanceOf { .. } | I::ArrayLength { .. } | I::NewSizedArray { .. } => false,
+        // dereferences are guarded by their own NullCheck
+        I::GetField { object: Some(_), .. } => false,
+        // integer division throws unless the divisor is known not to be zero
+        I::Arithmetic { op: ArithmeticOp::Div | ArithmeticOp::Rem, ty: ArithmeticOpType::I32 | ArithmeticOpType::I64, r, .. } => {
+            !matches!(constants[r.0 as usize], Some(Constant::Int(1.. | ..=-1) | Constant::Long(1.. | ..=-1)))
+        },
+        I::Arithmetic { .. } => false,
+        _ => true,
+    }
+}
+
+/// Evaluates instructions whose operands are all constants, following the JVM's arithmetic:
+/// integers wrap, shift distances are masked, float to integer conversions saturate with NaN
+/// becoming 0, and NaN compares as the instruction says. Integer division by zero is left to
+/// throw at run time.
+pub struct ConstantFolding;
+
+impl Pass for ConstantFolding {
+    fn name(&self) -> &'static str {
+        "constant-folding"
+    }
+    fn run(&self, function: &mut Function) -> usize {
+        let mut constants = constants(function);
+        let tree = DominatorTree::new(function);
+        let mut changes = 0;
+        for block in tree.reverse_postorder().to_vec() {
+            for statement in &mut function.block_mut(block).statements {
+                let constant = |value: &IrValue| constants[value.0 as usize].as_ref();
+                let folded = match &statement.instruction {
+                    Instruction::Arithmetic { l, r, op, .. } => constant(l).zip(constant(r)).and_then(|(l, r)| fold_arithmetic(l, r, *op)),
+                    Instruction::Negate { value, .. } => constant(value).and_then(fold_negate),
+                    Instruction::Convert { value, to, .. } => constant(value).and_then(|value| fold_convert(value, *to)),
+                    Instruction::Compare { l, r, op, .. } => constant(l).zip(constant(r)).and_then(|(l, r)| fold_compare(l, r, *op)),
+                    _ => None,
+                };
+                if let (Some(value), Some(result)) = (folded, statement.result) {
+                    constants[result.0 as usize] = Some(value.clone());
+                    statement.instruction = Instruction::Const { value };
+                    changes += 1;
+                }
+            }
+        }
+        changes
+    }
+}
+
+fn fold_arithmetic(l: &Constant, r: &Constant, op: ArithmeticOp) -> Option<Constant> {
+    use ArithmeticOp as Op;
+    Some(match (l, r) {
+        (Constant::Int(l), Constant::Int(r)) => Constant::Int(match op {
+            Op::Add => l.wrapping_add(*r),
+            Op::Sub => l.wrapping_sub(*r),
+            Op::Mul => l.wrapping_mul(*r),
+            Op::Div => l.checked_div(*r).or_else(|| (*r == -1).then(|| l.wrapping_neg()))?,
+            Op::Rem => l.checked_rem(*r).or_else(|| (*r == -1).then_some(0))?,
+            Op::And => l & r,
+            Op::Or => l | r,
+            Op::Xor => l ^ r,
+            Op::Shl => l.wrapping_shl(*r as u32),
+            Op::Shr => l.wrapping_shr(*r as u32),
+            Op::Ushr => (*l as u32).wrapping_shr(*r as u32) as i32,
+        }),
+        (Constant::Long(l), Constant::Int(r)) if op.is_shift() => Constant::Long(match op {
+            Op::Shl => l.wrapping_shl(*r as u32),
+            Op::Shr => l.wrapping_shr(*r as u32),
+            _ => (*l as u64).wrapping_shr(*r as u32) as i64,
+        }),
+        (Constant::Long(l), Constant::Long(r)) => Constant::Long(match op {
+            Op::Add => l.wrapping_add(*r),
+            Op::Sub => l.wrapping_sub(*r),
+            Op::Mul => l.wrapping_mul(*r),
+            Op::Div => l.checked_div(*r).or_else(|| (*r == -1).then(|| l.wrapping_neg()))?,
+            Op::Rem => l.checked_rem(*r).or_else(|| (*r == -1).then_some(0))?,
+            Op::And => l & r,
+            Op::Or => l | r,
+            Op::Xor => l ^ r,
+            Op::Shl | Op::Shr | Op::Ushr => return None,
+        }),
+        // Rust's float remainder truncates like Java's `%`
+        (Constant::Float(l), Constant::Float(r)) => Constant::Float(match op {
+            Op::Add => l + r,
+            Op::Sub => l - r,
+            Op::Mul => l * r,
+            Op::Div => l / r,
+            Op::Rem => l % r,
+            _ => return None,
+        }),
+        (Constant::Double(l), Constant::Double(r)) => Constant::Double(match op {
+            Op::Add => l + r,
+            Op::Sub => l - r,
+            Op::Mul => l * r,
+            Op::Div => l / r,
+            Op::Rem => l % r,
+            _ => return None,
+        }),
+        _ => return None,
+    })
+}
+
+fn fold_negate(value: &Constant) -> Option<Constant> {
+    Some(match value {
+        Constant::Int(value) => Constant::Int(value.wrapping_neg()),
+        Constant::Long(value) => Constant::Long(value.wrapping_neg()),
+        Constant::Float(value) => Constant::Float(-value),
+        Constant::Double(value) => Constant::Double(-value),
+        _ => return None,
+    })
+}
+
+/// Rust's `as` saturates and maps NaN to 0 like `f2i` and friends, and rounds to nearest like `i2f`.
+fn fold_convert(value: &Constant, to: ArithmeticOpType) -> Option<Constant> {
+    use ArithmeticOpType as T;
+    Some(match (value, to) {
+        (Constant::Int(value), T::I64) => Constant::Long(*value as i64),
+        (Constant::Int(value), T::F32) => Constant::Float(*value as f32),
+        (Constant::Int(value), T::F64) => Constant::Double(*value as f64),
+        (Constant::Int(value), T::I8) => Constant::Int(*value as i8 as i32),
+        (Constant::Int(value), T::U16) => Constant::Int(*value as u16 as i32),
+        (Constant::Int(value), T::I16) => Constant::Int(*value as i16 as i32),
+        (Constant::Long(value), T::I32) => Constant::Int(*value as i32),
+        (Constant::Long(value), T::F32) => Constant::Float(*value as f32),
+        (Constant::Long(value), T::F64) => Constant::Double(*value as f64),
+        (Constant::Float(value), T::I32) => Constant::Int(*value as i32),
+        (Constant::Float(value), T::I64) => Constant::Long(*value as i64),
+        (Constant::Float(value), T::F64) => Constant::Double(*value as f64),
+        (Constant::Double(value), T::I32) => Constant::Int(*value as i32),
+        (Constant::Double(value), T::I64) => Constant::Long(*value as i64),
+        (Constant::Double(value), T::F32) => Constant::Float(*value as f32),
+        _ => return None,
+    })
+}
+
+fn fold_compare(l: &Constant, r: &Constant, op: CompareOp) -> Option<Constant> {
+    let ordering = match (l, r) {
+        (Constant::Long(l), Constant::Long(r)) => Some(l.cmp(r)),
+        (Constant::Float(l), Constant::Float(r)) => l.partial_cmp(r),
+        (Constant::Double(l), Constant::Double(r)) => l.partial_cmp(r),
+        _ => return None,
+    };
+    Some(Constant::Int(match ordering {
+        Some(ordering) => ordering as i32,
+        None if op == CompareOp::CmpG => 1,
+        None => -1,
+    }))
+}
+
+/// Replaces copies, and phis merging a single value, with the value they copy.
+pub struct CopyPropagation;
+
+impl Pass for CopyPropagation {
+    fn name(&self) -> &'static str {
+        "copy-propagation"
+    }
+    fn run(&self, function: &mut Function) -> usize {
+        let mut rewrite = Rewrite::new(function);
+        for block in function.block_ids() {
+            for (index, statement) in function.block(block).statements.iter().enumerate() {
+                let Some(result) = statement.result else { continue };
+                let at = Location { block, index };
+                match &statement.instruction {
+                    Instruction::Focus { value } if *value != result => rewrite.replace(at, result, *value),
+                    Instruction::Phi { inputs } => {
+                        let mut merged = inputs.iter().map(|(_, value)| *value).filter(|value| *value != result);
+                        if let Some(first) = merged.next() {
+                            if merged.all(|value| value == first) {
+                                rewrite.replace(at, result, first);
+                            }
+                        }
+                    },
+                    _ => {},
+                }
+            }
+        }
+        rewrite.apply(function)
+    }
+}
+
+/// Removes the statements nothing observable depends on, phis feeding only each other included.
+pub struct DeadCodeElimination;
+
+impl Pass for DeadCodeElimination {
+    fn name(&self) -> &'static str {
+        "dead-code-elimination"
+    }
+    fn run(&self, function: &mut Function) -> usize {
+        let constants = constants(function);
+        let def_use = DefUse::new(function);
+        let mut live = BTreeSet::new();
+        let mut pending = Vec::new();
+        for block in function.block_ids() {
+            for (index, statement) in function.block(block).statements.iter().enumerate() {
+                if has_effects(&statement.instruction, &constants) {
+                    pending.push(Location { block, index });
+                }
+            }
+        }
+        while let Some(at) = pending.pop() {
+            if live.insert(at) {
+                pending.extend(def_use.operand_defs(function, at).into_iter().flatten());
+            }
+        }
+        let mut rewrite = Rewrite::new(function);
+        for block in function.block_ids() {
+            for index in 0..function.block(block).statements.len() {
+                let at = Location { block, index };
+                if !live.contains(&at) {
+                    rewrite.remove(at);
+                }
+            }
+        }
+        rewrite.apply(function)
+    }
+}
+
+/// Replaces computations with an equal one that dominates them. Only instructions whose result
+/// depends on nothing but their operands take part; loads and allocations don't. A landing pad
+/// only sees what was computed before the block unwinding to it.
+pub struct CommonSubexpressions;
+
+impl Pass for CommonSubexpressions {
+    fn name(&self) -> &'static str {
+        "common-subexpressions"
+    }
+    fn run(&self, function: &mut Function) -> usize {
+        let tree = DominatorTree::new(function);
+        let mut rewrite = Rewrite::new(function);
+        // printed instructions tell apart the constants `==` on floats would not, like 0.0 and -0.0
+        let mut available: BTreeMap<String, IrValue> = BTreeMap::new();
+        enum Visit {
+            Enter(BlockId),
+            Body(BlockId),
+            Exit(Vec<String>),
+        }
+        let mut pending = vec![Visit::Enter(BlockId(0))];
+        while let Some(visit) = pending.pop() {
+            let block = match visit {
+                Visit::Enter(block) => {
+                    pending.push(Visit::Body(block));
+                    pending.extend(landing_pads(function, &tree, block).into_iter().rev().map(Visit::Enter));
+                    continue;
+                },
+                Visit::Body(block) => block,
+                Visit::Exit(added) => {
+                    for key in added {
+                        available.remove(&key);
+                    }
+                    continue;
+                },
+            };
+            let mut added = Vec::new();
+            for (index, statement) in function.block(block).statements.iter().enumerate() {
+                let Some(result) = statement.result else { continue };
+                if !matches!(statement.instruction, Instruction::Const { .. } | Instruction::Arithmetic { .. } | Instruction::Negate { .. }
+                    | Instruction::Convert { .. } | Instruction::Compare { .. } | Instruction::InstanceOf { .. } | Instruction::ArrayLength { .. })
+                {
+                    continue;
+                }
+                let mut instruction = statement.instruction.clone();
+                for operand in instruction.operands_mut() {
+                    *operand = rewrite.resolve(*operand);
+                }
+                let key = instruction.to_string();
+                match available.get(&key) {
+                    Some(earlier) => rewrite.replace(Location { block, index }, result, *earlier),
+                    None => {
+                        available.insert(key.clone(), result);
+                        added.push(key);
+                    },
+                }
+            }
+            pending.push(Visit::Exit(added));
+            pending.extend(tree.children(block).iter().rev().filter(|child| !function.block(block).unwinds_to(**child)).map(|child| Visit::Enter(*child)));
+        }
+        rewrite.apply(function)
+    }
+}
+
+/// Removes null checks of values known not to be null: `this`, new objects and arrays, string
+/// and class constants, values already checked by a dominating check, and values tested against
+/// null by the branch leading to the only way into a block. A landing pad only relies on checks
+/// made before the block unwinding to it.
+pub struct NullCheckElimination;
+
+impl Pass for NullCheckElimination {
+    fn name(&self) -> &'static str {
+        "null-check-elimination"
+    }
+    fn run(&self, function: &mut Function) -> usize {
+        let tree = DominatorTree::new(function);
+        let constants = constants(function);
+        let predecessors = function.predecessors();
+        let is_static = function.access_flags & AccessFlags::Static as u16 != 0;
+        let mut non_null = vec![false; function.values.len()];
+        for statement in function.blocks.iter().flat_map(|block| &block.statements) {
+            let Some(result) = statement.result else { continue };
+            non_null[result.0 as usize] = match &statement.instruction {
+                Instruction::Param { index: 0 } => !is_static,
+                Instruction::New { .. } | Instruction::NewSizedArray { .. } | Instruction::NewDynamicArray { .. }
+                | Instruction::NewMultiArray { .. } => true,
+                Instruction::Const { value } => {
+                    matches!(value, Constant::String(_) | Constant::Class(_) | Constant::MethodType(_) | Constant::MethodHandle(_))
+                },
+                _ => false,
+            };
+        }
+        let mut rewrite = Rewrite::new(function);
+        enum Visit {
+            Enter(BlockId),
+            Body(BlockId),
+            Exit(Vec<IrValue>),
+        }
+        let mut pending = vec![Visit::Enter(BlockId(0))];
+        while let Some(visit) = pending.pop() {
+            let block = match visit {
+                Visit::Enter(block) => {
+                    pending.push(Visit::Body(block));
+                    pending.extend(landing_pads(function, &tree, block).into_iter().rev().map(Visit::Enter));
+                    continue;
+                },
+                Visit::Body(block) => block,
+                Visit::Exit(checked) => {
+                    for value in checked {
+                        non_null[value.0 as usize] = false;
+                    }
+                    continue;
+                },
+            };
+            // values learned here, forgotten again when leaving the block's subtree
+            let mut checked = Vec::new();
+            let mut learn = |non_null: &mut [bool], value: IrValue| {
+                if !std::mem::replace(&mut non_null[value.0 as usize], true) {
+                    checked.push(value);
+                }
+            };
+            if let [predecessor] = predecessors[block.0 as usize][..] {
+                if let Some(Instruction::Branch { l, r, cond, if_true, if_false }) = function.block(predecessor).terminator() {
+                    let is_null = |value: &IrValue| matches!(constants[value.0 as usize], Some(Constant::Null));
+                    let tested = if is_null(r) { Some(*l) } else if is_null(l) { Some(*r) } else { None };
+                    let taken = (*cond == Condition::Ne && *if_true == block) || (*cond == Condition::Eq && *if_false == block);
+                    if let (Some(tested), true, true) = (tested, taken, if_true != if_false) {
+                        learn(&mut non_null, tested);
+                    }
+                }
+            }
+            for (index, statement) in function.block(block).statements.iter().enumerate() {
+                match &statement.instruction {
+                    Instruction::NullCheck { value } if non_null[value.0 as usize] => rewrite.remove(Location { block, index }),
+                    Instruction::NullCheck { value } => learn(&mut non_null, *value),
+                    Instruction::Focus { value } if non_null[value.0 as usize] => learn(&mut non_null, statement.result.unwrap()),
+                    _ => {},
+                }
+            }
+            pending.push(Visit::Exit(checked));
+            pending.extend(tree.children(block).iter().rev().filter(|child| !function.block(block).unwinds_to(**child)).map(|child| Visit::Enter(*child)));
+        }
+        rewrite.apply(function)
+    }
+}
+
+/// The children of `block` in the dominator tree that it unwinds to. Walks of the tree visit them
+/// before the statements of `block`, which an exception may have cut short.
+fn landing_pads(function: &Function, tree: &DominatorTree, block: BlockId) -> Vec<BlockId> {
+    tree.children(block).iter().copied().filter(|child| function.block(block).unwinds_to(*child)).collect()
+}
+
+/// Simplifies the control flow graph: branches and switches on constants become gotos, jumps
+/// to blocks that only jump on are threaded through them, a block is merged into its only
+/// predecessor when that predecessor has no other successor and neither is protected by a
+/// handler, and unreachable blocks are removed.
+pub struct SimplifyCfg;
+
+impl Pass for SimplifyCfg {
+    fn name(&self) -> &'static str {
+        "simplify-cfg"
+    }
+    fn run(&self, function: &mut Function) -> usize {
+        let mut changes = fold_branches(function);
+        changes += thread_jumps(function);
+        changes += merge_blocks(function);
+        changes + function.remove_unreachable_blocks()
+    }
+}
+
+/// Whether `ordering` satisfies `cond`.
+fn holds(cond: Condition, ordering: Ordering) -> bool {
+    match cond {
+        Condition::Eq => ordering == Ordering::Equal,
+        Condition::Ne => ordering != Ordering::Equal,
+        Condition::Lt => ordering == Ordering::Less,
+        Condition::Ge => ordering != Ordering::Less,
+        Condition::Gt => ordering == Ordering::Greater,
+        Condition::Le => ordering != Ordering::Greater,
+    }
+}
+
+/// Drops the inputs of `block`'s phis coming from `from`.
+fn remove_phi_inputs(function: &mut Function, block: BlockId, from: BlockId) {
+    for statement in &mut function.block_mut(block).statements {
+        if let Instruction::Phi { inputs } = &mut statement.instruction {
+            inputs.retain(|(predecessor, _)| *predecessor != from);
+        }
+    }
+}
+
+fn fold_branches(function: &mut Function) -> usize {
+    let constants = constants(function);
+    let mut changes = 0;
+    for block in function.block_ids().collect::<Vec<_>>() {
+        let target = match function.block(block).terminator() {
+            Some(Instruction::Branch { l, r, cond, if_true, if_false }) => {
+                let ordering = match (&constants[l.0 as usize], &constants[r.0 as usize]) {
+                    _ if l == r => Some(Ordering::Equal),
+                    (Some(Constant::Int(l)), Some(Constant::Int(r))) => Some(l.cmp(r)),
+                    (Some(Constant::Null), Some(Constant::Null)) => Some(Ordering::Equal),
+                    _ if if_true == if_false => Some(Ordering::Equal),
+                    _ => None,
+                };
+                ordering.map(|ordering| if holds(*cond, ordering) { *if_true } else { *if_false })
+            },
+            Some(Instruction::Switch { value, cases, default }) => match &constants[value.0 as usize] {
+                Some(Constant::Int(key)) => Some(cases.iter().find(|(case, _)| case == key).map(|(_, target)| *target).unwrap_or(*default)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(target) = target else { continue };
+        for successor in function.block(block).normal_successors() {
+            if successor != target {
+                remove_phi_inputs(function, successor, block);
+            }
+        }
+        function.block_mut(block).statements.last_mut().unwrap().instruction = Instruction::Goto { target };
+        changes += 1;
+    }
+    changes
+}
+
+/// Retargets jumps to blocks holding nothing but a goto, unless the goto's target has phis that
+/// would have to tell the two paths apart.
+fn thread_jumps(function: &mut Function) -> usize {
+    let forwards: Vec<Option<BlockId>> = function.block_ids().map(|block| {
+        let has_phis = |target: BlockId| matches!(function.block(target).statements.first().map(|s| &s.instruction), Some(Instruction::Phi { .. }));
+        match function.block(block).statements[..] {
+            [ref only] => match only.instruction {
+                Instruction::Goto { target } if block.0 != 0 && target != block && !has_phis(target) => Some(target),
+                _ => None,
+            },
+            _ => None,
+        }
+    }).collect();
+    let mut changes = 0;
+    for block in &mut function.blocks {
+        let Some(terminator) = block.statements.last_mut() else { continue };
+        for target in terminator.instruction.successors_mut() {
+            // a chain of forwarding blocks is followed one step per run
+            if let Some(forward) = forwards[target.0 as usize] {
+                *target = forward;
+                changes += 1;
+            }
+        }
+    }
+    changes
+}
+
+fn merge_blocks(function: &mut Function) -> usize {
+    let mut changes = 0;
+    let mut predecessors = function.predecessors();
+    for block in function.block_ids().collect::<Vec<_>>() {
+        let Some(Instruction::Goto { target }) = function.block(block).terminator().cloned() else { continue };
+        if target == block || target.0 == 0 || predecessors[target.0 as usize] != [block] {
+            continue;
+        }
+        // landing pads see the values a block was entered with, which differ between the halves
+        if !function.block(block).handlers.is_empty() || !function.block(target).handlers.is_empty() {
+            continue;
+        }
+        let mut merged = std::mem::take(&mut function.block_mut(target).statements);
+        // phis of a block with one predecessor merge nothing
+        let phis = merged.iter().take_while(|statement| matches!(statement.instruction, Instruction::Phi { .. })).count();
+        let mut rewrite = Rewrite::new(function);
+        for phi in merged.drain(..phis) {
+            if let (Some(result), Instruction::Phi { inputs }) = (phi.result, phi.instruction) {
+                match inputs.first() {
+                    Some((_, value)) => rewrite.substitute(result, *value),
+                    None => unreachable!("phi without inputs in a block with a predecessor"),
+                }
+            }
+        }
+        let statements = &mut function.block_mut(block).statements;
+        statements.pop();
+        statements.append(&mut merged);
+        for successor in function.block(block).successors() {
+            for statement in &mut function.block_mut(successor).statements {
+                if let Instruction::Phi { inputs } = &mut statement.instruction {
+                    for (predecessor, _) in inputs {
+                        if *predecessor == target {
+                            *predecessor = block;
+                        }
+                    }
+                }
+            }
+            let entry = &mut predecessors[successor.0 as usize];
+            for predecessor in entry.iter_mut() {
+                if *predecessor == target {
+                    *predecessor = block;
+                }
+            }
+        }
+        // the emptied block is unreachable now and removed with the others
+        function.push(target, Instruction::Goto { target }, None);
+        predecessors[target.0 as usize] = vec![target];
+        rewrite.apply(function);
+        changes += 1;
+    }
+    changes
+}
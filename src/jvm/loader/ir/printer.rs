@@ -0,0 +1,349 @@
+//! The text form of rootir, which [parser](super::parser) reads back into the same module.
+//!
+//! ```text
+//! class public super Counter extends java/lang/Object {
+//!     field private count I
+//!
+//!     function public static abs(I)I {
+//!     bb0:
+//!         %0 = int param 0
+//!         %1 = int const int 0
+//!         br lt %0, %1, bb1, bb2
+//!     bb1:
+//!         %2 = int neg i32 %0
+//!         return %2
+//!     bb2:
+//!         return %0
+//!     }
+//! }
+//! ```
+//!
+//! The handlers of a block follow its label, as in `bb1: try java/lang/ArithmeticException bb4,
+//! any bb5`. A statement defining a value starts with the value and its type. Names that the lexer would
+//! split or read as something else are quoted, member references are written as the assembler
+//! writes them.
+use std::fmt::{Display, Formatter, Result};
+
+use crate::jvm::{
+    asm::{lexer::is_special, parser::{CLASS_FLAGS, FIELD_FLAGS, METHOD_FLAGS}},
+    reader::{bootstrap::{BootstrapArgument, CallSite, MethodHandleRef}, constant_pool::MemberRef, method_handle_kind::MethodHandleKind},
+};
+
+use super::{
+    function::Function,
+    ir_instructions::{Instruction, InvokeKind},
+    ir_value::Constant,
+    module::{IrClass, Module},
+};
+
+/// Names a flag word could be mistaken for, which are quoted wherever a name is expected.
+fn is_reserved(word: &str) -> bool {
+    [CLASS_FLAGS, FIELD_FLAGS, METHOD_FLAGS].iter().any(|table| table.iter().any(|(name, _)| *name == word))
+}
+
+/// Writes a name as is when the lexer reads it back as the same word, quoted otherwise.
+pub(crate) struct Word<'a>(pub &'a str);
+
+impl Display for Word<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let word = self.0;
+        let plain = !word.is_empty()
+            && !word.chars().any(is_special)
+            && !word.starts_with(|c: char| c.is_ascii_digit() || matches!(c, ';' | '.' | '%' | '-' | '+'))
+            && !word.starts_with("NaN")
+            && !word.starts_with("Infinity")
+            && !word.ends_with(':')
+            && !is_reserved(word);
+        match plain {
+            true => write!(f, "{}", word),
+            false => write!(f, "{}", Quoted(word)),
+        }
+    }
+}
+
+/// Writes a string literal with the escapes the lexer understands.
+pub(crate) struct Quoted<'a>(pub &'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => write!(f, "\\\"")?,
+                '\\' => write!(f, "\\\\")?,
+                '\n' => write!(f, "\\n")?,
+                '\t' => write!(f, "\\t")?,
+                '\r' => write!(f, "\\r")?,
+                '\0' => write!(f, "\\0")?,
+                c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+/// Flag words from `table`, followed by any bits the table has no word for.
+pub(crate) struct Flags(pub u16, pub &'static [(&'static str, u16)]);
+
+impl Display for Flags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut rest = self.0;
+        for (name, flag) in self.1 {
+            if rest & flag != 0 {
+                write!(f, "{} ", name)?;
+                rest &= !flag;
+            }
+        }
+        if rest != 0 {
+            write!(f, "{:#06x} ", rest)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shortest form that reads back as the same value; `float` and `double` go through here alike.
+fn float(f: &mut Formatter<'_>, value: impl Into<f64> + std::fmt::Debug + Copy) -> Result {
+    match value.into() {
+        v if v.is_nan() => write!(f, "NaN"),
+        v if v == f64::INFINITY => write!(f, "Infinity"),
+        v if v == f64::NEG_INFINITY => write!(f, "-Infinity"),
+        _ => write!(f, "{:?}", value),
+    }
+}
+
+fn member(f: &mut Formatter<'_>, member: &MemberRef, field: bool) -> Result {
+    if field {
+        write!(f, "{} {}", Word(&format!("{}/{}", member.class, member.name)), Word(&member.descriptor))
+    } else {
+        write!(f, "{}", Word(&format!("{}/{}{}", member.class, member.name, member.descriptor)))
+    }
+}
+
+/// `<kind> [interface] <member>`, as the assembler writes handles.
+fn handle(f: &mut Formatter<'_>, handle: &MethodHandleRef) -> Result {
+    write!(f, "{} ", handle.kind.name())?;
+    if handle.member.interface && handle.kind != MethodHandleKind::InvokeInterface {
+        write!(f, "interface ")?;
+    }
+    let field = matches!(handle.kind, MethodHandleKind::GetField | MethodHandleKind::GetStatic
+        | MethodHandleKind::PutField | MethodHandleKind::PutStatic);
+    member(f, &handle.member, field)
+}
+
+/// `<name> <descriptor> <handle> { <argument>, ... }`
+fn call_site(f: &mut Formatter<'_>, site: &CallSite) -> Result {
+    write!(f, "{} {} ", Word(&site.name), Word(&site.descriptor))?;
+    handle(f, &site.bootstrap)?;
+    write!(f, " {{")?;
+    for (i, argument) in site.arguments.iter().enumerate() {
+        write!(f, "{}", if i == 0 { " " } else { ", " })?;
+        let constant = match argument {
+            BootstrapArgument::Int(value) => Constant::Int(*value),
+            BootstrapArgument::Float(value) => Constant::Float(*value),
+            BootstrapArgument::Long(value) => Constant::Long(*value),
+            BootstrapArgument::Double(value) => Constant::Double(*value),
+            BootstrapArgument::String(value) => Constant::String(value.clone()),
+            BootstrapArgument::Class(value) => Constant::Class(value.clone()),
+            BootstrapArgument::MethodType(value) => Constant::MethodType(value.clone()),
+            BootstrapArgument::MethodHandle(value) => Constant::MethodHandle(value.clone()),
+            BootstrapArgument::Dynamic(value) => Constant::Dynamic(value.clone()),
+        };
+        write!(f, "{}", constant)?;
+    }
+    write!(f, " }}")
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Constant::Int(value) => write!(f, "int {}", value),
+            Constant::Long(value) => write!(f, "long {}", value),
+            Constant::Float(value) => {
+                write!(f, "float ")?;
+                float(f, *value)
+            },
+            Constant::Double(value) => {
+                write!(f, "double ")?;
+                float(f, *value)
+            },
+            Constant::Null => write!(f, "null"),
+            Constant::String(value) => write!(f, "string {}", Quoted(value)),
+            Constant::Class(name) => write!(f, "class {}", Word(name)),
+            Constant::MethodType(descriptor) => write!(f, "methodtype {}", Word(descriptor)),
+            Constant::MethodHandle(value) => {
+                write!(f, "handle ")?;
+                handle(f, value)
+            },
+            Constant::Dynamic(site) => {
+                write!(f, "dynamic ")?;
+                call_site(f, site)
+            },
+            Constant::ReturnAddress(block) => write!(f, "retaddr {}", block),
+        }
+    }
+}
+
+/// Values separated by commas, preceded by a space unless there are none.
+fn values<'a, T: Display + 'a>(f: &mut Formatter<'_>, values: impl IntoIterator<Item = &'a T>) -> Result {
+    for (i, value) in values.into_iter().enumerate() {
+        write!(f, "{}{}", if i == 0 { " " } else { ", " }, value)?;
+    }
+    Ok(())
+}
+
+impl<T: Display> Display for Instruction<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        use Instruction as I;
+        match self {
+            I::Param { index } => write!(f, "param {}", index),
+            I::Const { value } => write!(f, "const {}", value),
+            I::GetVar { var } => write!(f, "getvar {}", var),
+            I::SetVar { var, value } => write!(f, "setvar {}, {}", var, value),
+            I::Arithmetic { l, r, op, ty } => write!(f, "{} {} {}, {}", op.name(), ty.name(), l, r),
+            I::Negate { value, ty } => write!(f, "neg {} {}", ty.name(), value),
+            I::Convert { value, from, to } => write!(f, "convert {} {} {}", from.name(), to.name(), value),
+            I::Compare { l, r, op, ty } => write!(f, "{} {} {}, {}", op.name(), ty.name(), l, r),
+            I::NewSizedArray { element, size } => write!(f, "newsizedarray {} {}", Word(&element.to_string()), size),
+            I::NewDynamicArray { element, length } => write!(f, "newarray {} {}", Word(&element.to_string()), length),
+            I::NewMultiArray { class, dimensions } => {
+                write!(f, "multianewarray {}", Word(&class.to_string()))?;
+                values(f, dimensions)
+            },
+            I::New { class } => write!(f, "new {}", Word(class)),
+            I::ArrayLength { array } => write!(f, "arraylength {}", array),
+            I::ArrayLoad { array, index, element } => write!(f, "aload {} {}, {}", element.name(), array, index),
+            I::ArrayStore { array, index, value, element } => write!(f, "astore {} {}, {}, {}", element.name(), array, index, value),
+            I::GetField { field, object } => {
+                write!(f, "getfield ")?;
+                member(f, field, true)?;
+                values(f, object)
+            },
+            I::PutField { field, object, value } => {
+                write!(f, "putfield ")?;
+                member(f, field, true)?;
+                values(f, object.iter().chain([value]))
+            },
+            I::Invoke { kind, method, num_args, args } => {
+                write!(f, "invoke {} ", kind.name())?;
+                if method.interface && *kind != InvokeKind::Interface {
+                    write!(f, "interface ")?;
+                }
+                member(f, method, false)?;
+                if *num_args != args.len() {
+                    write!(f, " argc {}", num_args)?;
+                }
+                values(f, args)
+            },
+            I::InvokeDynamic { site, args } => {
+                write!(f, "invokedynamic ")?;
+                call_site(f, site)?;
+                values(f, args)
+            },
+            I::CheckCast { value, class } => write!(f, "checkcast {} {}", Word(class), value),
+            I::InstanceOf { value, class } => write!(f, "instanceof {} {}", Word(class), value),
+            I::NullCheck { value } => write!(f, "nullcheck {}", value),
+            I::MonitorEnter { object } => write!(f, "monitorenter {}", object),
+            I::MonitorExit { object } => write!(f, "monitorexit {}", object),
+            I::Focus { value } => write!(f, "focus {}", value),
+            I::Catch => write!(f, "catch"),
+            I::Phi { inputs } => {
+                write!(f, "phi")?;
+                for (i, (block, value)) in inputs.iter().enumerate() {
+                    write!(f, "{}{} {}", if i == 0 { " " } else { ", " }, block, value)?;
+                }
+                Ok(())
+            },
+            I::Goto { target } => write!(f, "goto {}", target),
+            I::Branch { l, r, cond, if_true, if_false } => write!(f, "br {} {}, {}, {}, {}", cond.name(), l, r, if_true, if_false),
+            I::Switch { value, cases, default } => {
+                write!(f, "switch {}, default {}", value, default)?;
+                for (key, target) in cases {
+                    write!(f, ", {} {}", key, target)?;
+                }
+                Ok(())
+            },
+            I::IndirectJump { address, targets } => {
+                write!(f, "indirectjump {}", address)?;
+                for target in targets {
+                    write!(f, ", {}", target)?;
+                }
+                Ok(())
+            },
+            I::Return { value } => {
+                write!(f, "return")?;
+                values(f, value)
+            },
+            I::Throw { value } => write!(f, "throw {}", value),
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "function {}{}", Flags(self.access_flags, METHOD_FLAGS), Word(&format!("{}{}", self.name, self.descriptor)))?;
+        if self.blocks.is_empty() {
+            return writeln!(f);
+        }
+        writeln!(f, " {{")?;
+        for block in self.block_ids() {
+            write!(f, "{}:", block)?;
+            for (i, handler) in self.block(block).handlers.iter().enumerate() {
+                write!(f, "{}", if i == 0 { " try " } else { ", " })?;
+                match &handler.class {
+                    // a class called `any` is quoted to tell it apart
+                    Some(class) if class == "any" => write!(f, "{} {}", Quoted(class), handler.target)?,
+                    Some(class) => write!(f, "{} {}", Word(class), handler.target)?,
+                    None => write!(f, "any {}", handler.target)?,
+                }
+            }
+            writeln!(f)?;
+            for statement in &self.block(block).statements {
+                write!(f, "    ")?;
+                if let Some(result) = statement.result {
+                    write!(f, "{} = {} ", result, self.value_type(result))?;
+                }
+                writeln!(f, "{}", statement.instruction)?;
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl Display for IrClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "class {}{}", Flags(self.access_flags, CLASS_FLAGS), Word(&self.name))?;
+        if let Some(super_name) = &self.super_name {
+            write!(f, " extends {}", Word(super_name))?;
+        }
+        for (i, interface) in self.interfaces.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " implements " } else { ", " }, Word(interface))?;
+        }
+        writeln!(f, " {{")?;
+        for field in &self.fields {
+            writeln!(f, "    field {}{} {}", Flags(field.access_flags, FIELD_FLAGS), Word(&field.name), Word(&field.descriptor))?;
+        }
+        for function in &self.functions {
+            writeln!(f)?;
+            for line in function.to_string().lines() {
+                match line {
+                    "" => writeln!(f)?,
+                    line => writeln!(f, "    {}", line)?,
+                }
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for (i, class) in self.classes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", class)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,776 @@
+//! The register backend: linear-scan allocation of a function in SSA form onto the finite
+//! register file of a virtual register machine, following Wimmer and Mössenböck's linear scan
+//! with interval splitting.
+//!
+//! Blocks are laid out in reverse postorder and their statements numbered two apart, so that a
+//! statement at `2n` reads its operands at `2n` and writes its result at `2n + 1`. Each value
+//! gets a live interval of ranges over these positions, with a lifetime hole wherever a loop
+//! makes it dead for a while. Intervals are allocated in order of their start; when no register
+//! is free, the interval whose next use is furthest away is split and the split-off part waits
+//! in the value's spill slot until just before its next use. Moves are inserted where an
+//! interval was split, and on the control flow edges where a value, or a phi, sits in different
+//! places at the end of the predecessor and the start of the successor.
+//!
+//! The result is [RegisterFunction] code: a flat array of [RegOp]s with jumps resolved to
+//! indices in the array. Instructions read and write registers, except that calls and returns
+//! can take their values straight from spill slots, which only [RegOp::Move] otherwise touches.
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, BinaryHeap},
+    fmt::Display,
+};
+
+use crate::{jvm::asm::parser::METHOD_FLAGS, util::code_err::ClassParseError};
+
+use super::{
+    dominance::DominatorTree,
+    function::Function,
+    ir_instructions::{Condition, Instruction},
+    ir_value::{BlockId, Constant, IrValue},
+    printer::{Flags, Word},
+};
+
+/// The fewest registers the allocator works with: an array store reads three values at once.
+pub const MIN_REGISTERS: u16 = 3;
+
+/// A register of the virtual machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Register(pub u16);
+
+impl Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "r{}", self.0)
+    }
+}
+
+/// Where a value is kept: a register, or a spill slot in the frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Place {
+    Register(Register),
+    Slot(u16),
+}
+
+impl Display for Place {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Place::Register(register) => write!(f, "{}", register),
+            Place::Slot(slot) => write!(f, "s{}", slot),
+        }
+    }
+}
+
+/// An instruction of register code. Jump targets are indices into the code.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegOp {
+    Move {
+        dst: Place,
+        src: Place,
+    },
+    /// An instruction that doesn't end a block, writing its result, if any, to `dst`. Its
+    /// operands are registers, except for the arguments of calls and the value of `focus`.
+    Op {
+        dst: Option<Register>,
+        instruction: Instruction<Place>,
+    },
+    Jump {
+        target: u32,
+    },
+    /// Jumps to `target` when `l cond r` holds, continues with the next instruction otherwise
+    Branch {
+        l: Register,
+        r: Register,
+        cond: Condition,
+        target: u32,
+    },
+    Switch {
+        value: Register,
+        cases: Vec<(i32, u32)>,
+        default: u32,
+    },
+    Return {
+        value: Option<Place>,
+    },
+    Throw {
+        value: Register,
+    },
+}
+
+impl Display for RegOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegOp::Move { dst, src } => write!(f, "move {}, {}", dst, src),
+            RegOp::Op { dst: Some(dst), instruction } => write!(f, "{} = {}", dst, instruction),
+            RegOp::Op { dst: None, instruction } => write!(f, "{}", instruction),
+            RegOp::Jump { target } => write!(f, "jump {}", target),
+            RegOp::Branch { l, r, cond, target } => write!(f, "br {} {}, {}, {}", cond.name(), l, r, target),
+            RegOp::Switch { value, cases, default } => {
+                write!(f, "switch {}, default {}", value, default)?;
+                for (key, target) in cases {
+                    write!(f, ", {} {}", key, target)?;
+                }
+                Ok(())
+            },
+            RegOp::Return { value: Some(value) } => write!(f, "return {}", value),
+            RegOp::Return { value: None } => write!(f, "return"),
+            RegOp::Throw { value } => write!(f, "throw {}", value),
+        }
+    }
+}
+
+/// A method compiled to register code, with the size of the frame it needs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisterFunction {
+    pub name: String,
+    pub descriptor: String,
+    pub access_flags: u16,
+    /// Registers the code uses, numbered from 0
+    pub registers: u16,
+    /// Spill slots the code uses, numbered from 0
+    pub slots: u16,
+    pub code: Vec<RegOp>,
+}
+
+impl Display for RegisterFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let signature = format!("{}{}", self.name, self.descriptor);
+        writeln!(f, "function {}{} registers {} slots {} {{", Flags(self.access_flags, METHOD_FLAGS), Word(&signature), self.registers, self.slots)?;
+        for (pc, op) in self.code.iter().enumerate() {
+            writeln!(f, "{:>4}: {}", pc, op)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// Allocates `function`, which must be in SSA form, onto `registers` registers. Subroutines
+/// (`jsr` and `ret`) are not supported: their return addresses would have to name places in
+/// the register code. Neither are exception handlers, whose landing pads would need their
+/// values in the same places wherever the protected code throws.
+pub fn allocate(function: &Function, registers: u16) -> Result<RegisterFunction, ClassParseError> {
+    let error = |expected: &str, got: String| ClassParseError::BadValue {
+        expected: expected.to_string(),
+        got,
+        for_what: format!("{}{}", function.name, function.descriptor),
+    };
+    if registers < MIN_REGISTERS {
+        return Err(error("at least 3 registers", registers.to_string()));
+    }
+    if function.blocks.iter().any(|block| !block.handlers.is_empty()) {
+        return Err(error("code without exception handlers", "a block with handlers".to_string()));
+    }
+    for instruction in function.blocks.iter().flat_map(|block| &block.statements).map(|statement| &statement.instruction) {
+        match instruction {
+            Instruction::GetVar { .. } | Instruction::SetVar { .. } => return Err(error("a function in SSA form", format!("`{}`", instruction))),
+            Instruction::IndirectJump { .. } | Instruction::Const { value: Constant::ReturnAddress(_) } => {
+                return Err(error("code without subroutines", format!("`{}`", instruction)))
+            },
+            _ => {},
+        }
+    }
+    let mut result = RegisterFunction {
+        name: function.name.clone(),
+        descriptor: function.descriptor.clone(),
+        access_flags: function.access_flags,
+        registers: 0,
+        slots: 0,
+        code: Vec::new(),
+    };
+    if function.blocks.is_empty() {
+        return Ok(result);
+    }
+    let mut function = function.clone();
+    prepare(&mut function);
+    let mut allocator = Allocator::new(&function, registers);
+    allocator.build_intervals();
+    allocator.scan();
+    allocator.emit(&mut result);
+    Ok(result)
+}
+
+/// Turns terminators with a single successor into gotos and splits the critical edges, so every
+/// edge has a block of its own to hold the moves it needs.
+fn prepare(function: &mut Function) {
+    for block in &mut function.blocks {
+        let Some(last) = block.statements.last_mut() else { continue };
+        let successors = last.instruction.successors();
+        if last.instruction.is_terminator() && !successors.is_empty() && successors.iter().all(|target| *target == successors[0]) {
+            last.instruction = Instruction::Goto { target: successors[0] };
+        }
+    }
+    let predecessors = function.predecessors();
+    for block in function.block_ids().collect::<Vec<_>>() {
+        if predecessors[block.0 as usize].len() < 2 {
+            continue;
+        }
+        for predecessor in &predecessors[block.0 as usize] {
+            if function.block(*predecessor).successors().len() > 1 {
+                function.split_edge(*predecessor, block);
+            }
+        }
+    }
+}
+
+/// Whether the instruction needs its operands in registers.
+fn reads_registers(instruction: &Instruction<IrValue>) -> bool {
+    !matches!(instruction, Instruction::Invoke { .. } | Instruction::InvokeDynamic { .. } | Instruction::Return { .. } | Instruction::Focus { .. })
+}
+
+/// A value's lifetime, or the part of it split off at `from`, and where it is kept meanwhile.
+#[derive(Debug, Clone)]
+struct Interval {
+    value: IrValue,
+    /// Where this part takes over from the one before it
+    from: u32,
+    /// Sorted, disjoint and half-open
+    ranges: Vec<(u32, u32)>,
+    /// Positions reading or writing the value, and whether they need it in a register
+    uses: Vec<(u32, bool)>,
+    place: Option<Place>,
+}
+
+impl Interval {
+    fn start(&self) -> u32 {
+        self.ranges[0].0
+    }
+    fn end(&self) -> u32 {
+        self.ranges.last().unwrap().1
+    }
+    fn covers(&self, position: u32) -> bool {
+        self.ranges.iter().any(|(from, to)| (*from..*to).contains(&position))
+    }
+    /// The first position both intervals cover.
+    fn intersection(&self, other: &Interval) -> Option<u32> {
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a, b) = (self.ranges[i], other.ranges[j]);
+            let start = a.0.max(b.0);
+            if start < a.1.min(b.1) {
+                return Some(start);
+            }
+            if a.1 <= b.1 {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        None
+    }
+    /// The first use at or after `position` that needs a register.
+    fn next_register_use(&self, position: u32) -> Option<u32> {
+        self.uses.iter().find(|(at, register)| *at >= position && *register).map(|(at, _)| *at)
+    }
+    /// Cuts the interval at `at`, which must lie before its end, returning the part from `at` on.
+    fn split(&mut self, at: u32) -> Interval {
+        let mut ranges = Vec::new();
+        let mut kept = Vec::new();
+        for (from, to) in self.ranges.drain(..) {
+            if to <= at {
+                kept.push((from, to));
+            } else if from >= at {
+                ranges.push((from, to));
+            } else {
+                kept.push((from, at));
+                ranges.push((at, to));
+            }
+        }
+        self.ranges = kept;
+        let split = self.uses.partition_point(|(position, _)| *position < at);
+        Interval { value: self.value, from: at, ranges, uses: self.uses.split_off(split), place: None }
+    }
+}
+
+struct Allocator<'a> {
+    function: &'a Function,
+    registers: u16,
+    /// Reachable blocks in the order their code is laid out
+    order: Vec<BlockId>,
+    /// First and past-the-end position of each block
+    bounds: Vec<(u32, u32)>,
+    /// Position of each statement, phis sharing the start of their block
+    positions: Vec<Vec<u32>>,
+    live_in: Vec<BTreeSet<IrValue>>,
+    intervals: Vec<Interval>,
+    /// The intervals of each value, in the order they take over from each other
+    pieces: Vec<Vec<usize>>,
+    slots: Vec<Option<u16>>,
+    slot_count: u16,
+}
+
+impl<'a> Allocator<'a> {
+    fn new(function: &'a Function, registers: u16) -> Self {
+        let tree = DominatorTree::new(function);
+        let order = tree.reverse_postorder().to_vec();
+        let mut bounds = vec![(u32::MAX, u32::MAX); function.blocks.len()];
+        let mut positions = vec![Vec::new(); function.blocks.len()];
+        let mut next = 0;
+        for block in &order {
+            let from = next;
+            for statement in &function.block(*block).statements {
+                match statement.instruction {
+                    Instruction::Phi { .. } => positions[block.0 as usize].push(from),
+                    _ => {
+                        positions[block.0 as usize].push(next);
+                        next += 2;
+                    },
+                }
+            }
+            bounds[block.0 as usize] = (from, next);
+        }
+        Self {
+            function,
+            registers,
+            order,
+            bounds,
+            positions,
+            live_in: vec![BTreeSet::new(); function.blocks.len()],
+            intervals: Vec::new(),
+            pieces: vec![Vec::new(); function.values.len()],
+            slots: vec![None; function.values.len()],
+            slot_count: 0,
+        }
+    }
+
+    /// Values live at the end of `block`: those live into its successors, and the inputs their
+    /// phis take from `block`.
+    fn live_out(&self, block: BlockId) -> BTreeSet<IrValue> {
+        let mut live = BTreeSet::new();
+        for successor in self.function.block(block).successors() {
+            live.extend(self.live_in[successor.0 as usize].iter().copied());
+            for statement in &self.function.block(successor).statements {
+                if let Instruction::Phi { inputs } = &statement.instruction {
+                    live.extend(inputs.iter().filter(|(from, _)| *from == block).map(|(_, value)| *value));
+                }
+            }
+        }
+        live
+    }
+
+    /// Solves liveness, then builds the intervals walking the blocks and statements backwards.
+    fn build_intervals(&mut self) {
+        let function = self.function;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in self.order.iter().rev() {
+                let mut live = self.live_out(*block);
+                for statement in function.block(*block).statements.iter().rev() {
+                    if let Some(result) = statement.result {
+                        live.remove(&result);
+                    }
+                    if !matches!(statement.instruction, Instruction::Phi { .. }) {
+                        live.extend(statement.instruction.operands().into_iter().copied());
+                    }
+                }
+                if live != self.live_in[block.0 as usize] {
+                    self.live_in[block.0 as usize] = live;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut ranges: Vec<Vec<(u32, u32)>> = vec![Vec::new(); function.values.len()];
+        let mut uses: Vec<Vec<(u32, bool)>> = vec![Vec::new(); function.values.len()];
+        // ranges are added back to front, so each new one starts at or before the first
+        let add_range = |ranges: &mut Vec<(u32, u32)>, from: u32, to: u32| match ranges.first_mut() {
+            Some(first) if first.0 <= to => *first = (from.min(first.0), to.max(first.1)),
+            _ => ranges.insert(0, (from, to)),
+        };
+        for block in self.order.iter().rev() {
+            let (from, to) = self.bounds[block.0 as usize];
+            for value in self.live_out(*block) {
+                add_range(&mut ranges[value.0 as usize], from, to);
+            }
+            let statements = &function.block(*block).statements;
+            for (statement, position) in statements.iter().zip(&self.positions[block.0 as usize]).rev() {
+                let is_phi = matches!(statement.instruction, Instruction::Phi { .. });
+                if let Some(result) = statement.result {
+                    let ranges = &mut ranges[result.0 as usize];
+                    let start = if is_phi { *position } else { position + 1 };
+                    match ranges.first_mut() {
+                        Some(first) if first.0 <= start => first.0 = start,
+                        _ => ranges.insert(0, (start, start + 1)),
+                    }
+                    if !is_phi {
+                        uses[result.0 as usize].push((start, true));
+                    }
+                }
+                if !is_phi {
+                    let register = reads_registers(&statement.instruction);
+                    for operand in statement.instruction.operands() {
+                        add_range(&mut ranges[operand.0 as usize], from, position + 1);
+                        uses[operand.0 as usize].push((*position, register));
+                    }
+                }
+            }
+        }
+        for (value, (ranges, mut uses)) in ranges.into_iter().zip(uses).enumerate() {
+            if ranges.is_empty() {
+                continue;
+            }
+            uses.sort();
+            self.pieces[value].push(self.intervals.len());
+            self.intervals.push(Interval { value: IrValue(value as u32), from: ranges[0].0, ranges, uses, place: None });
+        }
+    }
+
+    fn slot(&mut self, value: IrValue) -> Place {
+        let slot = *self.slots[value.0 as usize].get_or_insert_with(|| {
+            self.slot_count += 1;
+            self.slot_count - 1
+        });
+        Place::Slot(slot)
+    }
+    fn register(&self, interval: usize) -> Option<u16> {
+        match self.intervals[interval].place {
+            Some(Place::Register(register)) => Some(register.0),
+            _ => None,
+        }
+    }
+    fn split(&mut self, interval: usize, at: u32) -> usize {
+        let part = self.intervals[interval].split(at);
+        let value = part.value.0 as usize;
+        self.intervals.push(part);
+        let index = self.intervals.len() - 1;
+        let pieces = &mut self.pieces[value];
+        let position = pieces.iter().position(|piece| *piece == interval).unwrap();
+        pieces.insert(position + 1, index);
+        index
+    }
+
+    fn scan(&mut self) {
+        let mut unhandled: BinaryHeap<Reverse<(u32, usize)>> = (0..self.intervals.len()).map(|i| Reverse((self.intervals[i].start(), i))).collect();
+        let mut active: Vec<usize> = Vec::new();
+        let mut inactive: Vec<usize> = Vec::new();
+        while let Some(Reverse((position, current))) = unhandled.pop() {
+            let mut still_active = Vec::new();
+            for interval in active.drain(..) {
+                if self.intervals[interval].end() <= position {
+                    continue;
+                }
+                match self.intervals[interval].covers(position) {
+                    true => still_active.push(interval),
+                    false => inactive.push(interval),
+                }
+            }
+            active = still_active;
+            let mut still_inactive = Vec::new();
+            for interval in inactive.drain(..) {
+                if self.intervals[interval].end() <= position {
+                    continue;
+                }
+                match self.intervals[interval].covers(position) {
+                    true => active.push(interval),
+                    false => still_inactive.push(interval),
+                }
+            }
+            inactive = still_inactive;
+
+            let split = match self.allocate_free(current, &active, &inactive) {
+                Some(split) => split,
+                None => self.allocate_blocked(current, &mut active, &mut inactive),
+            };
+            for part in split {
+                unhandled.push(Reverse((self.intervals[part].start(), part)));
+            }
+            if self.register(current).is_some() {
+                active.push(current);
+            }
+        }
+    }
+
+    /// Gives `current` the register free the longest, if one is free at its start, splitting it
+    /// where that register is taken again. Returns the split-off parts to allocate later.
+    fn allocate_free(&mut self, current: usize, active: &[usize], inactive: &[usize]) -> Option<Vec<usize>> {
+        let mut free_until = vec![u32::MAX; self.registers as usize];
+        for interval in active {
+            free_until[self.register(*interval).unwrap() as usize] = 0;
+        }
+        for interval in inactive {
+            if let Some(at) = self.intervals[*interval].intersection(&self.intervals[current]) {
+                let register = self.register(*interval).unwrap() as usize;
+                free_until[register] = free_until[register].min(at);
+            }
+        }
+        let (register, until) = free_until.iter().enumerate().fold((0, 0), |best, (register, until)| if *until > best.1 { (register, *until) } else { best });
+        let start = self.intervals[current].start();
+        // parts split off get their place from a move in front of a statement
+        let at = until & !1;
+        if until < self.intervals[current].end() && at <= start {
+            return None;
+        }
+        self.intervals[current].place = Some(Place::Register(Register(register as u16)));
+        if until >= self.intervals[current].end() {
+            return Some(Vec::new());
+        }
+        Some(vec![self.split(current, at)])
+    }
+
+    /// Frees a register for `current` by spilling the intervals holding it, or spills `current`
+    /// itself until its first use if every register is needed sooner.
+    fn allocate_blocked(&mut self, current: usize, active: &mut Vec<usize>, inactive: &mut Vec<usize>) -> Vec<usize> {
+        let start = self.intervals[current].start();
+        let mut next_use = vec![u32::MAX; self.registers as usize];
+        for interval in active.iter() {
+            let register = self.register(*interval).unwrap() as usize;
+            next_use[register] = next_use[register].min(self.intervals[*interval].next_register_use(start).unwrap_or(u32::MAX));
+        }
+        for interval in inactive.iter() {
+            if self.intervals[*interval].intersection(&self.intervals[current]).is_some() {
+                let register = self.register(*interval).unwrap() as usize;
+                next_use[register] = next_use[register].min(self.intervals[*interval].next_register_use(start).unwrap_or(u32::MAX));
+            }
+        }
+        let (register, furthest) = next_use.iter().enumerate().fold((0, 0), |best, (register, at)| if *at > best.1 { (register, *at) } else { best });
+        let first_use = self.intervals[current].next_register_use(start);
+        match first_use {
+            None => {
+                let value = self.intervals[current].value;
+                self.intervals[current].place = Some(self.slot(value));
+                return Vec::new();
+            },
+            Some(first_use) if first_use > furthest && first_use & !1 > start => {
+                let value = self.intervals[current].value;
+                self.intervals[current].place = Some(self.slot(value));
+                return vec![self.split(current, first_use & !1)];
+            },
+            Some(_) => {},
+        }
+        self.intervals[current].place = Some(Place::Register(Register(register as u16)));
+        let mut split = Vec::new();
+        let holders: Vec<usize> = active.iter().chain(inactive.iter()).copied().filter(|interval| {
+            self.register(*interval) == Some(register as u16) && (active.contains(interval) || self.intervals[*interval].intersection(&self.intervals[current]).is_some())
+        }).collect();
+        for holder in holders {
+            active.retain(|interval| *interval != holder);
+            inactive.retain(|interval| *interval != holder);
+            let spilled = self.split(holder, start);
+            split.extend(self.spill(spilled));
+        }
+        split
+    }
+
+    /// Keeps `interval` in its value's slot until just before its next use needing a register,
+    /// returning the part from there on.
+    fn spill(&mut self, interval: usize) -> Option<usize> {
+        let value = self.intervals[interval].value;
+        let from = self.intervals[interval].from;
+        match self.intervals[interval].next_register_use(from) {
+            Some(at) if at & !1 > from => {
+                self.intervals[interval].place = Some(self.slot(value));
+                Some(self.split(interval, at & !1))
+            },
+            // needed in a register right away, so it competes for one again
+            Some(_) => Some(interval),
+            None => {
+                self.intervals[interval].place = Some(self.slot(value));
+                None
+            },
+        }
+    }
+
+    /// Where `value` is kept at `position`.
+    fn place(&self, value: IrValue, position: u32) -> Place {
+        let pieces = &self.pieces[value.0 as usize];
+        let piece = pieces.iter().rev().find(|piece| self.intervals[**piece].from <= position).unwrap_or(&pieces[0]);
+        self.intervals[*piece].place.unwrap()
+    }
+    fn register_at(&self, value: IrValue, position: u32) -> Register {
+        match self.place(value, position) {
+            Place::Register(register) => register,
+            Place::Slot(slot) => unreachable!("{} is in s{} where it needs a register", value, slot),
+        }
+    }
+
+    /// The moves making the values live into `to` available where it expects them, its phis included.
+    fn edge_moves(&self, from: BlockId, to: BlockId) -> Vec<(Place, Place)> {
+        let end = self.bounds[from.0 as usize].1 - 1;
+        let start = self.bounds[to.0 as usize].0;
+        let mut moves = Vec::new();
+        for value in &self.live_in[to.0 as usize] {
+            moves.push((self.place(*value, start), self.place(*value, end)));
+        }
+        for statement in &self.function.block(to).statements {
+            if let (Some(result), Instruction::Phi { inputs }) = (statement.result, &statement.instruction) {
+                if let Some((_, input)) = inputs.iter().find(|(predecessor, _)| *predecessor == from) {
+                    moves.push((self.place(result, start), self.place(*input, end)));
+                }
+            }
+        }
+        moves
+    }
+
+    fn emit(&mut self, result: &mut RegisterFunction) {
+        let function = self.function;
+        let block_starts: BTreeSet<u32> = self.order.iter().map(|block| self.bounds[block.0 as usize].0).collect();
+        // moves where an interval was split inside a block, by the statement they go before
+        let mut split_moves: BTreeMap<u32, Vec<(Place, Place)>> = BTreeMap::new();
+        for pieces in &self.pieces {
+            for pair in pieces.windows(2) {
+                let (before, after) = (&self.intervals[pair[0]], &self.intervals[pair[1]]);
+                if !block_starts.contains(&after.from) && after.covers(after.from) {
+                    split_moves.entry(after.from / 2).or_default().push((after.place.unwrap(), before.place.unwrap()));
+                }
+            }
+        }
+        let scratch = Place::Slot(self.slot_count);
+        let mut scratch_used = false;
+        let mut code = Vec::new();
+        let mut moves = |code: &mut Vec<RegOp>, moves: Vec<(Place, Place)>| {
+            for (dst, src) in sequentialize(moves, scratch) {
+                scratch_used |= dst == scratch;
+                code.push(RegOp::Move { dst, src });
+            }
+        };
+        let predecessors = function.predecessors();
+        let mut starts = vec![0; function.blocks.len()];
+        for (i, block) in self.order.iter().enumerate() {
+            starts[block.0 as usize] = code.len() as u32;
+            if let [predecessor] = predecessors[block.0 as usize][..] {
+                if !matches!(function.block(predecessor).terminator(), Some(Instruction::Goto { .. })) {
+                    moves(&mut code, self.edge_moves(predecessor, *block));
+                }
+            }
+            let next = self.order.get(i + 1).copied();
+            let statements = &function.block(*block).statements;
+            for (statement, position) in statements.iter().zip(&self.positions[block.0 as usize]) {
+                if matches!(statement.instruction, Instruction::Phi { .. }) {
+                    continue;
+                }
+                if let Some(split) = split_moves.remove(&(position / 2)) {
+                    moves(&mut code, split);
+                }
+                let register = |value: &IrValue| self.register_at(*value, *position);
+                // jump targets are block numbers until every block has its start
+                match &statement.instruction {
+                    Instruction::Goto { target } => {
+                        moves(&mut code, self.edge_moves(*block, *target));
+                        if Some(*target) != next {
+                            code.push(RegOp::Jump { target: target.0 });
+                        }
+                    },
+                    Instruction::Branch { l, r, cond, if_true, if_false } => {
+                        let (l, r) = (register(l), register(r));
+                        if Some(*if_true) == next {
+                            code.push(RegOp::Branch { l, r, cond: cond.negate(), target: if_false.0 });
+                        } else {
+                            code.push(RegOp::Branch { l, r, cond: *cond, target: if_true.0 });
+                            if Some(*if_false) != next {
+                                code.push(RegOp::Jump { target: if_false.0 });
+                            }
+                        }
+                    },
+                    Instruction::Switch { value, cases, default } => code.push(RegOp::Switch {
+                        value: register(value),
+                        cases: cases.iter().map(|(key, target)| (*key, target.0)).collect(),
+                        default: default.0,
+                    }),
+                    Instruction::Return { value } => code.push(RegOp::Return { value: value.map(|value| self.place(value, *position)) }),
+                    Instruction::Throw { value } => code.push(RegOp::Throw { value: register(value) }),
+                    instruction => {
+                        let mut operands = instruction.clone();
+                        let mut places = Vec::new();
+                        for operand in operands.operands_mut() {
+                            places.push(self.place(*operand, *position));
+                        }
+                        let instruction = map_operands(operands, &mut places.into_iter());
+                        let dst = statement.result.map(|result| self.register_at(result, position + 1));
+                        code.push(RegOp::Op { dst, instruction });
+                    },
+                }
+            }
+        }
+        for op in &mut code {
+            match op {
+                RegOp::Jump { target } | RegOp::Branch { target, .. } => *target = starts[*target as usize],
+                RegOp::Switch { cases, default, .. } => {
+                    for (_, target) in cases {
+                        *target = starts[*target as usize];
+                    }
+                    *default = starts[*default as usize];
+                },
+                _ => {},
+            }
+        }
+        let mut registers = 0;
+        for op in &code {
+            let mut note = |place: &Place| {
+                if let Place::Register(register) = place {
+                    registers = registers.max(register.0 + 1);
+                }
+            };
+            match op {
+                RegOp::Move { dst, src } => [dst, src].into_iter().for_each(&mut note),
+                RegOp::Op { dst, instruction } => {
+                    dst.iter().for_each(|dst| note(&Place::Register(*dst)));
+                    instruction.operands().into_iter().for_each(&mut note);
+                },
+                RegOp::Branch { l, r, .. } => [l, r].into_iter().for_each(|register| note(&Place::Register(*register))),
+                RegOp::Switch { value, .. } | RegOp::Throw { value } => note(&Place::Register(*value)),
+                RegOp::Return { value } => value.iter().for_each(&mut note),
+                RegOp::Jump { .. } => {},
+            }
+        }
+        result.registers = registers;
+        result.slots = self.slot_count + scratch_used as u16;
+        result.code = code;
+    }
+}
+
+/// The same instruction over the places taking the place of its operands, in operand order.
+fn map_operands(instruction: Instruction<IrValue>, places: &mut impl Iterator<Item = Place>) -> Instruction<Place> {
+    use Instruction as I;
+    let mut next = || places.next().unwrap();
+    match instruction {
+        I::Param { index } => I::Param { index },
+        I::Const { value } => I::Const { value },
+        I::GetVar { var } => I::GetVar { var },
+        I::SetVar { var, .. } => I::SetVar { var, value: next() },
+        I::Arithmetic { op, ty, .. } => I::Arithmetic { l: next(), r: next(), op, ty },
+        I::Negate { ty, .. } => I::Negate { value: next(), ty },
+        I::Convert { from, to, .. } => I::Convert { value: next(), from, to },
+        I::Compare { op, ty, .. } => I::Compare { l: next(), r: next(), op, ty },
+        I::NewSizedArray { element, size } => I::NewSizedArray { element, size },
+        I::NewDynamicArray { element, .. } => I::NewDynamicArray { element, length: next() },
+        I::NewMultiArray { class, dimensions } => I::NewMultiArray { class, dimensions: dimensions.iter().map(|_| next()).collect() },
+        I::New { class } => I::New { class },
+        I::ArrayLength { .. } => I::ArrayLength { array: next() },
+        I::ArrayLoad { element, .. } => I::ArrayLoad { array: next(), index: next(), element },
+        I::ArrayStore { element, .. } => I::ArrayStore { array: next(), index: next(), value: next(), element },
+        I::GetField { field, object } => I::GetField { field, object: object.map(|_| next()) },
+        I::PutField { field, object, .. } => I::PutField { field, object: object.map(|_| next()), value: next() },
+        I::Invoke { kind, method, num_args, args } => I::Invoke { kind, method, num_args, args: args.iter().map(|_| next()).collect() },
+        I::InvokeDynamic { site, args } => I::InvokeDynamic { site, args: args.iter().map(|_| next()).collect() },
+        I::CheckCast { class, .. } => I::CheckCast { value: next(), class },
+        I::InstanceOf { class, .. } => I::InstanceOf { value: next(), class },
+        I::NullCheck { .. } => I::NullCheck { value: next() },
+        I::MonitorEnter { .. } => I::MonitorEnter { object: next() },
+        I::MonitorExit { .. } => I::MonitorExit { object: next() },
+        I::Focus { .. } => I::Focus { value: next() },
+        I::Phi { inputs } => I::Phi { inputs: inputs.into_iter().map(|(block, _)| (block, next())).collect() },
+        I::Catch => I::Catch,
+        I::Goto { target } => I::Goto { target },
+        I::Branch { cond, if_true, if_false, .. } => I::Branch { l: next(), r: next(), cond, if_true, if_false },
+        I::Switch { cases, default, .. } => I::Switch { value: next(), cases, default },
+        I::IndirectJump { targets, .. } => I::IndirectJump { address: next(), targets },
+        I::Return { value } => I::Return { value: value.map(|_| next()) },
+        I::Throw { .. } => I::Throw { value: next() },
+    }
+}
+
+/// Orders moves meant to happen all at once so that none overwrites a place another still has
+/// to read, parking a value in `scratch` to break each cycle.
+fn sequentialize(moves: Vec<(Place, Place)>, scratch: Place) -> Vec<(Place, Place)> {
+    let mut pending: Vec<(Place, Place)> = moves.into_iter().filter(|(dst, src)| dst != src).collect();
+    pending.dedup();
+    let mut ordered = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        match pending.iter().position(|(dst, _)| !pending.iter().any(|(_, src)| src == dst)) {
+            Some(ready) => ordered.push(pending.remove(ready)),
+            None => {
+                let (dst, src) = pending[0];
+                ordered.push((scratch, src));
+                pending[0] = (dst, scratch);
+            },
+        }
+    }
+    ordered
+}
@@ -0,0 +1,291 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::util::code_err::ClassParseError;
+
+use super::{
+    dominance::DominatorTree,
+    function::{Function, Location, Statement},
+    ir_instructions::Instruction,
+    ir_value::{BlockId, IrValue, Variable},
+};
+
+/// Rewrites a function from variables into SSA form. Phis are placed on the iterated dominance
+/// frontiers of each variable's writes, for the variables read in a block other than the one
+/// writing them, and reads are renamed to the value that reaches them. Phis nothing uses are
+/// dropped again. Blocks the entry can't reach are removed first, and the values are renumbered
+/// at the end to close the gaps left by the variable reads.
+///
+/// A landing pad gets a phi for every variable its protected blocks write, even with a single
+/// predecessor, and the input from a protected block is the value the variable had when the
+/// block was entered.
+pub fn construct(function: &mut Function) -> Result<(), ClassParseError> {
+    if function.blocks.is_empty() {
+        return Ok(());
+    }
+    function.remove_unreachable_blocks();
+    let tree = DominatorTree::new(function);
+    let frontiers = tree.frontiers(function);
+
+    let mut writes: BTreeMap<Variable, BTreeSet<BlockId>> = BTreeMap::new();
+    let mut global = BTreeSet::new();
+    for block in function.block_ids() {
+        let mut written = BTreeSet::new();
+        for statement in &function.block(block).statements {
+            match &statement.instruction {
+                Instruction::GetVar { var } if !written.contains(var) => {
+                    global.insert(*var);
+                },
+                Instruction::SetVar { var, .. } => {
+                    written.insert(*var);
+                    writes.entry(*var).or_default().insert(block);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    // phis of each block, with the variable they merge
+    let mut phis: Vec<Vec<(Variable, IrValue)>> = vec![Vec::new(); function.blocks.len()];
+    for var in &global {
+        let mut pending: Vec<BlockId> = writes.get(var).map(|blocks| blocks.iter().copied().collect()).unwrap_or_default();
+        // landing pads of blocks writing the variable, which may have thrown before the write
+        let mut landing_pads: Vec<BlockId> = pending.iter().flat_map(|block| function.block(*block).handlers.iter().map(|handler| handler.target)).collect();
+        landing_pads.sort();
+        landing_pads.dedup();
+        let mut placed = BTreeSet::new();
+        for pad in landing_pads {
+            placed.insert(pad);
+            let value = function.new_value(var.ty());
+            phis[pad.0 as usize].push((*var, value));
+            pending.push(pad);
+        }
+        while let Some(block) = pending.pop() {
+            for frontier in &frontiers[block.0 as usize] {
+                if placed.insert(*frontier) {
+                    let value = function.new_value(var.ty());
+                    phis[frontier.0 as usize].push((*var, value));
+                    pending.push(*frontier);
+                }
+            }
+        }
+    }
+
+    let mut replacement: Vec<Option<IrValue>> = vec![None; function.values.len()];
+    let mut inputs: BTreeMap<IrValue, Vec<(BlockId, Option<IrValue>)>> = BTreeMap::new();
+    let mut reaching: BTreeMap<Variable, Vec<IrValue>> = BTreeMap::new();
+    enum Visit {
+        Enter(BlockId),
+        Exit(Vec<Variable>),
+    }
+    let mut pending = vec![Visit::Enter(BlockId(0))];
+    while let Some(visit) = pending.pop() {
+        let block = match visit {
+            Visit::Enter(block) => block,
+            Visit::Exit(pushed) => {
+                for var in pushed {
+                    reaching.get_mut(&var).unwrap().pop();
+                }
+                continue;
+            },
+        };
+        let mut pushed = Vec::new();
+        for (var, value) in &phis[block.0 as usize] {
+            reaching.entry(*var).or_default().push(*value);
+            pushed.push(*var);
+        }
+        let landing_pads: BTreeSet<BlockId> = function.block(block).handlers.iter().map(|handler| handler.target).collect();
+        for pad in landing_pads {
+            for (var, phi) in &phis[pad.0 as usize] {
+                let value = reaching.get(var).and_then(|values| values.last()).copied();
+                inputs.entry(*phi).or_default().push((block, value));
+            }
+        }
+        let statements = std::mem::take(&mut function.block_mut(block).statements);
+        let mut kept = Vec::with_capacity(statements.len());
+        for mut statement in statements {
+            for operand in statement.instruction.operands_mut() {
+                *operand = replacement[operand.0 as usize].unwrap_or(*operand);
+            }
+            match statement.instruction {
+                Instruction::GetVar { var } => {
+                    let value = reaching.get(&var).and_then(|values| values.last()).copied()
+                        .ok_or_else(|| undefined(function, var, block))?;
+                    replacement[statement.result.unwrap().0 as usize] = Some(value);
+                },
+                Instruction::SetVar { var, value } => {
+                    reaching.entry(var).or_default().push(value);
+                    pushed.push(var);
+                },
+                _ => kept.push(statement),
+            }
+        }
+        function.block_mut(block).statements = kept;
+        let successors: BTreeSet<BlockId> = function.block(block).normal_successors().into_iter().collect();
+        for successor in successors {
+            for (var, phi) in &phis[successor.0 as usize] {
+                let value = reaching.get(var).and_then(|values| values.last()).copied();
+                inputs.entry(*phi).or_default().push((block, value));
+            }
+        }
+        pending.push(Visit::Exit(pushed));
+        pending.extend(tree.children(block).iter().rev().map(|child| Visit::Enter(*child)));
+    }
+
+    // keep the phis that something other than a dead phi uses
+    let mut live = BTreeSet::new();
+    let mut worklist: Vec<IrValue> = function.blocks.iter()
+        .flat_map(|block| block.statements.iter().flat_map(|statement| statement.instruction.operands()))
+        .copied()
+        .collect();
+    while let Some(value) = worklist.pop() {
+        if let Some(phi_inputs) = inputs.get(&value) {
+            if live.insert(value) {
+                worklist.extend(phi_inputs.iter().filter_map(|(_, input)| *input));
+            }
+        }
+    }
+    for (block, block_phis) in phis.into_iter().enumerate() {
+        let block = BlockId(block as u32);
+        let mut placed = Vec::new();
+        for (var, phi) in block_phis.into_iter().filter(|(_, phi)| live.contains(phi)) {
+            let mut phi_inputs = Vec::new();
+            for (from, input) in inputs.remove(&phi).unwrap_or_default() {
+                phi_inputs.push((from, input.ok_or_else(|| undefined(function, var, from))?));
+            }
+            phi_inputs.sort_by_key(|(from, _)| *from);
+            placed.push(Statement { result: Some(phi), instruction: Instruction::Phi { inputs: phi_inputs } });
+        }
+        function.block_mut(block).statements.splice(0..0, placed);
+    }
+    function.compact_values();
+    Ok(())
+}
+
+fn undefined(function: &Function, var: Variable, block: BlockId) -> ClassParseError {
+    ClassParseError::BadValue {
+        expected: format!("a write to {} on every path", var),
+        got: format!("a path through {} without one", block),
+        for_what: format!("{}{}", function.name, function.descriptor),
+    }
+}
+
+/// Takes a function out of SSA form for code generation. Critical edges into blocks with phis
+/// are split, and each phi becomes a copy from a temporary that every predecessor sets just
+/// before its terminator. The copies of one edge read all their sources before any phi is
+/// written, so swapped and lost values are kept apart. The temporaries are defined once per
+/// predecessor, which is why the result is no longer in SSA form.
+///
+/// A block unwinding to a landing pad sets the temporaries of the pad's phis right after its
+/// own phis instead, before anything in it can throw, and the pad reads them after its catch.
+pub fn destruct(function: &mut Function) {
+    let phi_count = |function: &Function, block: BlockId| {
+        function.block(block).statements.iter().take_while(|statement| matches!(statement.instruction, Instruction::Phi { .. })).count()
+    };
+    let predecessors = function.predecessors();
+    for block in function.block_ids().collect::<Vec<_>>() {
+        if phi_count(function, block) == 0 {
+            continue;
+        }
+        for predecessor in &predecessors[block.0 as usize] {
+            let successors = function.block(*predecessor).normal_successors();
+            if successors.len() > 1 && successors.contains(&block) {
+                function.split_edge(*predecessor, block);
+            }
+        }
+    }
+    // where the copies into a landing pad go, which the phis turning into copies don't move
+    let entries: Vec<usize> = function.block_ids().map(|block| phi_count(function, block)).collect();
+    for block in function.block_ids().collect::<Vec<_>>() {
+        let statements = &mut function.block_mut(block).statements;
+        let count = statements.iter().take_while(|statement| matches!(statement.instruction, Instruction::Phi { .. })).count();
+        let phis: Vec<Statement> = statements.drain(..count).collect();
+        let mut copies = Vec::with_capacity(phis.len());
+        for phi in phis {
+            let (result, inputs) = match phi {
+                Statement { result: Some(result), instruction: Instruction::Phi { inputs } } => (result, inputs),
+                _ => unreachable!(),
+            };
+            let temporary = function.new_value(function.value_type(result));
+            for (from, value) in inputs {
+                let copy = Statement { result: Some(temporary), instruction: Instruction::Focus { value } };
+                let unwinds = function.block(from).unwinds_to(block) && !function.block(from).normal_successors().contains(&block);
+                let statements = &mut function.block_mut(from).statements;
+                match unwinds {
+                    true => statements.insert(entries[from.0 as usize], copy),
+                    false => statements.insert(statements.len() - 1, copy),
+                }
+            }
+            copies.push(Statement { result: Some(result), instruction: Instruction::Focus { value: temporary } });
+        }
+        // a landing pad keeps its catch first
+        let statements = &mut function.block_mut(block).statements;
+        let at = statements.first().map_or(0, |statement| matches!(statement.instruction, Instruction::Catch) as usize);
+        statements.splice(at..at, copies);
+    }
+}
+
+/// Def-use and use-def chains of a function in SSA form, answering in constant time where a
+/// value is defined and where it is used. Parameters, constants and every other value are
+/// defined by exactly one statement.
+#[derive(Debug, Clone)]
+pub struct DefUse {
+    defs: Vec<Option<Location>>,
+    uses: Vec<Vec<Location>>,
+}
+
+impl DefUse {
+    pub fn new(function: &Function) -> Self {
+        let mut defs = vec![None; function.values.len()];
+        let mut uses = vec![Vec::new(); function.values.len()];
+        for block in function.block_ids() {
+            for (index, statement) in function.block(block).statements.iter().enumerate() {
+                let at = Location { block, index };
+                if let Some(result) = statement.result {
+                    defs[result.0 as usize] = Some(at);
+                }
+                for operand in statement.instruction.operands() {
+                    let uses: &mut Vec<Location> = &mut uses[operand.0 as usize];
+                    if uses.last() != Some(&at) {
+                        uses.push(at);
+                    }
+                }
+            }
+        }
+        Self { defs, uses }
+    }
+    /// The statement defining `value`.
+    pub fn def(&self, value: IrValue) -> Option<Location> {
+        self.defs.get(value.0 as usize).copied().flatten()
+    }
+    /// The statements reading `value`, each listed once.
+    pub fn uses(&self, value: IrValue) -> &[Location] {
+        self.uses.get(value.0 as usize).map(Vec::as_slice).unwrap_or_default()
+    }
+    pub fn is_used(&self, value: IrValue) -> bool {
+        !self.uses(value).is_empty()
+    }
+    /// The definitions of the operands of the statement at `at`, in operand order.
+    pub fn operand_defs(&self, function: &Function, at: Location) -> Vec<Option<Location>> {
+        function.statement(at).instruction.operands().into_iter().map(|operand| self.def(*operand)).collect()
+    }
+    /// Makes every use of `old` read `new` instead, keeping the chains up to date.
+    pub fn replace_uses(&mut self, function: &mut Function, old: IrValue, new: IrValue) {
+        if self.uses.len() <= new.0 as usize {
+            self.uses.resize(new.0 as usize + 1, Vec::new());
+            self.defs.resize(new.0 as usize + 1, None);
+        }
+        let uses = std::mem::take(&mut self.uses[old.0 as usize]);
+        for at in &uses {
+            let statement = &mut function.block_mut(at.block).statements[at.index];
+            for operand in statement.instruction.operands_mut() {
+                if *operand == old {
+                    *operand = new;
+                }
+            }
+        }
+        let new_uses = &mut self.uses[new.0 as usize];
+        new_uses.extend(uses);
+        new_uses.sort();
+        new_uses.dedup();
+    }
+}
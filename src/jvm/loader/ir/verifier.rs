@@ -0,0 +1,327 @@
+//! Checks the invariants the rest of the IR relies on, as a safety net behind lowering and every
+//! optimization pass. The verifier takes a function whose values are each defined once: lowered
+//! code, which still reads and writes variables, or code in SSA form. Functions taken out of SSA
+//! form define the phi temporaries once per predecessor and are not verified.
+use std::{collections::{BTreeMap, BTreeSet}, fmt::Display};
+
+use crate::jvm::reader::{access_flags::AccessFlags, descriptor::MethodDescriptor};
+
+use super::{
+    dominance::DominatorTree,
+    function::{Function, Location},
+    ir_instructions::{Instruction, InvokeKind, OperandType},
+    ir_value::{BlockId, IrType, IrValue},
+};
+
+/// A broken invariant of a function's IR, found at `at` when it concerns one statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub function: String,
+    /// The pass that left the function this way, when verifying between passes
+    pub after: Option<&'static str>,
+    pub at: Option<Location>,
+    pub message: String,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.function)?;
+        if let Some(at) = self.at {
+            write!(f, " at {}[{}]", at.block, at.index)?;
+        }
+        write!(f, ": {}", self.message)?;
+        if let Some(pass) = self.after {
+            write!(f, " (after {})", pass)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Verifies `function`, failing with the first problem [diagnose] finds.
+pub fn verify(function: &Function) -> Result<(), VerifyError> {
+    match diagnose(function).into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Every problem with `function`. The checks are:
+///
+/// - each block ends in its only terminator, and phis come before anything else in a block
+/// - each value is defined exactly once, before its uses in the same block and in a block
+///   dominating the others; a phi input must be available at the end of its predecessor
+/// - operands and results have the types the instruction declares, parameters and returned
+///   values the types the descriptor declares
+/// - the inputs of each phi come from exactly the block's predecessors
+/// - `Invoke { num_args }` counts the arguments the callee's descriptor takes, and `args` holds
+///   that many values
+/// - handlers land in blocks that exist, and `catch` only comes right after the phis of a block
+///   that every predecessor unwinds to and none jumps to
+///
+/// A value defined in a protected block is not available where an exception out of the block
+/// leads before the block has been left normally, and a phi in a landing pad reads its input
+/// from a protected block as that block was entered. Dominance is not checked in blocks the
+/// entry can't reach.
+pub fn diagnose(function: &Function) -> Vec<VerifyError> {
+    Verifier::new(function).run()
+}
+
+struct Verifier<'a> {
+    function: &'a Function,
+    errors: Vec<VerifyError>,
+    defs: Vec<Option<Location>>,
+    /// For protected blocks, the blocks reachable without leaving them through their terminator
+    bypassed: BTreeMap<BlockId, Vec<bool>>,
+}
+
+impl<'a> Verifier<'a> {
+    fn new(function: &'a Function) -> Self {
+        Self { function, errors: Vec::new(), defs: vec![None; function.values.len()], bypassed: BTreeMap::new() }
+    }
+    fn error(&mut self, at: Option<Location>, message: String) {
+        self.errors.push(VerifyError {
+            function: format!("{}{}", self.function.name, self.function.descriptor),
+            after: None,
+            at,
+            message,
+        });
+    }
+    fn ty(&self, value: IrValue) -> Option<IrType> {
+        self.function.values.get(value.0 as usize).copied()
+    }
+
+    fn run(mut self) -> Vec<VerifyError> {
+        let function = self.function;
+        self.definitions();
+        let tree = DominatorTree::new(function);
+        let predecessors = function.predecessors();
+        let descriptor = MethodDescriptor::parse(&function.descriptor).ok();
+        if descriptor.is_none() {
+            self.error(None, format!("`{}` is not a method descriptor", function.descriptor));
+        }
+        for block in function.block_ids() {
+            let statements = &function.block(block).statements;
+            if function.block(block).terminator().is_none() {
+                self.error(None, format!("{} does not end in a terminator", block));
+            }
+            for handler in &function.block(block).handlers {
+                if handler.target.0 as usize >= function.blocks.len() {
+                    self.error(None, format!("{} unwinds to {}, which does not exist", block, handler.target));
+                }
+            }
+            let mut in_phis = true;
+            for (index, statement) in statements.iter().enumerate() {
+                let at = Location { block, index };
+                let instruction = &statement.instruction;
+                if instruction.is_terminator() && index + 1 != statements.len() {
+                    self.error(Some(at), format!("terminator `{}` in the middle of {}", instruction, block));
+                }
+                match instruction {
+                    Instruction::Phi { inputs } if in_phis => self.phi(at, inputs, &predecessors[block.0 as usize], &tree),
+                    Instruction::Phi { .. } => self.error(Some(at), format!("phi in {} after a statement that is not a phi", block)),
+                    Instruction::Catch => {
+                        if !in_phis {
+                            self.error(Some(at), format!("catch in {} after a statement that is not a phi", block));
+                        }
+                        in_phis = false;
+                        let predecessors = &predecessors[block.0 as usize];
+                        if predecessors.is_empty() {
+                            self.error(Some(at), format!("catch in {}, which no block unwinds to", block));
+                        }
+                        for predecessor in predecessors {
+                            if function.block(*predecessor).normal_successors().contains(&block) {
+                                self.error(Some(at), format!("catch in {}, which {} jumps to", block, predecessor));
+                            }
+                        }
+                    },
+                    _ => {
+                        in_phis = false;
+                        for operand in instruction.operands() {
+                            self.available(at, *operand, block, &tree, instruction);
+                        }
+                    },
+                }
+                self.operand_types(at, instruction);
+                self.result_type(at, statement.result, instruction, descriptor.as_ref());
+                match instruction {
+                    Instruction::Invoke { kind, method, num_args, args } => {
+                        let receiver = (*kind != InvokeKind::Static) as usize;
+                        match MethodDescriptor::parse(&method.descriptor) {
+                            Ok(callee) if callee.params.len() + receiver != *num_args => self.error(Some(at), format!(
+                                "`{}` passes {} arguments to {}/{}{}, which takes {}",
+                                instruction, num_args, method.class, method.name, method.descriptor, callee.params.len() + receiver,
+                            )),
+                            Ok(_) => {},
+                            Err(_) => self.error(Some(at), format!("`{}` is not a method descriptor", method.descriptor)),
+                        }
+                        if args.len() != *num_args {
+                            self.error(Some(at), format!("`{}` has {} arguments but num_args is {}", instruction, args.len(), num_args));
+                        }
+                    },
+                    Instruction::InvokeDynamic { site, args } => match MethodDescriptor::parse(&site.descriptor) {
+                        Ok(callee) if callee.params.len() != args.len() => self.error(Some(at), format!(
+                            "`{}` passes {} arguments to a call site of type {}",
+                            instruction, args.len(), site.descriptor,
+                        )),
+                        Ok(_) => {},
+                        Err(_) => self.error(Some(at), format!("`{}` is not a method descriptor", site.descriptor)),
+                    },
+                    Instruction::Return { value } => {
+                        let Some(descriptor) = &descriptor else { continue };
+                        match (value.and_then(|value| self.ty(value)), descriptor.ret.as_ref().map(IrType::of)) {
+                            (Some(_), None) => self.error(Some(at), format!("`{}` returns a value from a void method", instruction)),
+                            (None, Some(ty)) if value.is_none() => {
+                                self.error(Some(at), format!("`{}` returns nothing from a method returning {}", instruction, ty))
+                            },
+                            (Some(got), Some(ty)) if got != ty => {
+                                self.error(Some(at), format!("`{}` returns {} from a method returning {}", instruction, got, ty))
+                            },
+                            _ => {},
+                        }
+                    },
+                    _ => {},
+                }
+                for target in instruction.successors() {
+                    if target.0 as usize >= function.blocks.len() {
+                        self.error(Some(at), format!("`{}` jumps to {}, which does not exist", instruction, target));
+                    }
+                }
+            }
+        }
+        self.errors
+    }
+
+    /// Records where each value is defined, reporting values defined twice or without a type.
+    fn definitions(&mut self) {
+        let function = self.function;
+        for block in function.block_ids() {
+            for (index, statement) in function.block(block).statements.iter().enumerate() {
+                let at = Location { block, index };
+                let Some(result) = statement.result else { continue };
+                match self.defs.get(result.0 as usize).copied() {
+                    None => self.error(Some(at), format!("{} has no type", result)),
+                    Some(Some(first)) => self.error(Some(at), format!("{} is defined again, first in {}[{}]", result, first.block, first.index)),
+                    Some(None) => self.defs[result.0 as usize] = Some(at),
+                }
+            }
+        }
+    }
+
+    /// Checks that `value` is defined before the statement at `at` on every path to it.
+    fn available(&mut self, at: Location, value: IrValue, block: BlockId, tree: &DominatorTree, instruction: &Instruction<IrValue>) {
+        let Some(def) = self.defs.get(value.0 as usize).copied().flatten() else {
+            return self.error(Some(at), format!("`{}` reads {}, which is never defined", instruction, value));
+        };
+        if !tree.is_reachable(block) {
+            return;
+        }
+        if def.block == block && def.index >= at.index {
+            self.error(Some(at), format!("`{}` reads {} before its definition in {}[{}]", instruction, value, def.block, def.index));
+        } else if def.block != block && !tree.dominates(def.block, block) {
+            self.error(Some(at), format!("`{}` reads {}, defined in {}, which does not dominate {}", instruction, value, def.block, block));
+        } else if def.block != block && !self.is_phi(def) && self.bypasses(def.block, block) {
+            self.error(Some(at), format!("`{}` reads {}, defined in {}, which {} can be reached from by unwinding", instruction, value, def.block, block));
+        }
+    }
+    fn is_phi(&self, at: Location) -> bool {
+        matches!(self.function.statement(at).instruction, Instruction::Phi { .. })
+    }
+    /// Whether `block` can be reached without leaving `from` through its terminator, which only
+    /// happens when `from` unwinds.
+    fn bypasses(&mut self, from: BlockId, block: BlockId) -> bool {
+        let function = self.function;
+        if function.block(from).handlers.is_empty() {
+            return false;
+        }
+        let reachable = self.bypassed.entry(from).or_insert_with(|| {
+            let mut reachable = vec![false; function.blocks.len()];
+            let mut pending = vec![BlockId(0)];
+            while let Some(current) = pending.pop() {
+                if std::mem::replace(&mut reachable[current.0 as usize], true) {
+                    continue;
+                }
+                match current == from {
+                    true => pending.extend(function.block(current).handlers.iter().map(|handler| handler.target)),
+                    false => pending.extend(function.block(current).successors()),
+                }
+            }
+            reachable
+        });
+        reachable[block.0 as usize]
+    }
+
+    fn phi(&mut self, at: Location, inputs: &[(BlockId, IrValue)], predecessors: &[BlockId], tree: &DominatorTree) {
+        let instruction = Instruction::Phi { inputs: inputs.to_vec() };
+        let from: BTreeSet<BlockId> = inputs.iter().map(|(from, _)| *from).collect();
+        let expected: BTreeSet<BlockId> = predecessors.iter().copied().collect();
+        if from.len() != inputs.len() {
+            self.error(Some(at), format!("`{}` has more than one input from the same block", instruction));
+        }
+        if from != expected {
+            let list = |blocks: &BTreeSet<BlockId>| blocks.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ");
+            self.error(Some(at), format!("`{}` has inputs from [{}] but the predecessors of {} are [{}]", instruction, list(&from), at.block, list(&expected)));
+        }
+        for (predecessor, value) in inputs {
+            if (predecessor.0 as usize) >= self.function.blocks.len() {
+                continue;
+            }
+            // the input is read at the end of the predecessor, or after its phis when it unwinds here
+            let from = self.function.block(*predecessor);
+            let index = match from.unwinds_to(at.block) && !from.normal_successors().contains(&at.block) {
+                true => from.statements.iter().take_while(|statement| matches!(statement.instruction, Instruction::Phi { .. })).count(),
+                false => from.statements.len(),
+            };
+            self.available(Location { block: *predecessor, index }, *value, *predecessor, tree, &instruction);
+        }
+    }
+
+    fn operand_types(&mut self, at: Location, instruction: &Instruction<IrValue>) {
+        let operands = instruction.operands();
+        let mut same: Option<(IrValue, IrType)> = None;
+        for (position, (value, accepted)) in operands.iter().zip(instruction.operand_types()).enumerate() {
+            let Some(ty) = self.ty(**value) else { continue };
+            match accepted {
+                OperandType::Exactly(expected) if ty != expected => self.error(Some(at), format!(
+                    "operand {} of `{}` is {} of type {}, expected {}",
+                    position, instruction, value, ty, expected,
+                )),
+                OperandType::Same => match same {
+                    Some((first, first_ty)) if first_ty != ty => self.error(Some(at), format!(
+                        "`{}` mixes {} of type {} with {} of type {}",
+                        instruction, first, first_ty, value, ty,
+                    )),
+                    Some(_) => {},
+                    None => same = Some((**value, ty)),
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn result_type(&mut self, at: Location, result: Option<IrValue>, instruction: &Instruction<IrValue>, descriptor: Option<&MethodDescriptor>) {
+        let Some(result) = result else { return };
+        let Some(ty) = self.ty(result) else { return };
+        let expected = match instruction {
+            Instruction::Param { index } => {
+                let is_static = self.function.access_flags & AccessFlags::Static as u16 != 0;
+                let Some(descriptor) = descriptor else { return };
+                let params: Vec<IrType> = (!is_static).then_some(IrType::Reference).into_iter().chain(descriptor.params.iter().map(IrType::of)).collect();
+                match params.get(*index) {
+                    Some(ty) => Some(*ty),
+                    None => return self.error(Some(at), format!("`{}` but the method takes {} parameters", instruction, params.len())),
+                }
+            },
+            Instruction::Focus { value } => self.ty(*value),
+            Instruction::Phi { inputs } => inputs.first().and_then(|(_, value)| self.ty(*value)),
+            _ => match instruction.result_type() {
+                Some(ty) => Some(ty),
+                None => return self.error(Some(at), format!("`{}` produces no value but defines {}", instruction, result)),
+            },
+        };
+        if let Some(expected) = expected.filter(|expected| *expected != ty) {
+            self.error(Some(at), format!("{} has type {} but `{}` produces {}", result, ty, instruction, expected));
+        }
+    }
+}
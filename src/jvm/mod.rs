@@ -1,2 +1,4 @@
 pub mod loader;
-pub mod reader;
\ No newline at end of file
+pub mod reader;
+pub mod asm;
+pub mod analysis;
\ No newline at end of file
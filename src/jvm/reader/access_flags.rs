@@ -11,8 +11,10 @@ pub enum AccessFlags {
     Super = 0x0020,
     Volatile = 0x0040,
     Transient = 0x0080,
+    Native = 0x0100,
     Interface = 0x0200,
     Abstract = 0x0400,
+    Strict = 0x0800,
     Synthetic = 0x1000,
     Annotation = 0x2000,
     Enum = 0x4000,
@@ -1,4 +1,4 @@
-use crate::{io::BufferReadable, util::code_err::ClassParseError};
+use crate::{io::{BufferReadable, BufferWritable}, util::code_err::ClassParseError};
 
 use super::constant_pool::{ConstantPool, ConstantPoolInfo};
 
@@ -15,6 +15,13 @@ impl Attributes {
         }
         Ok(Self(attributes))
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.0.len() as u16)?;
+        for attribute in &self.0 {
+            attribute.write(buf)?;
+        }
+        Ok(())
+    }
     pub fn find_by_name(&self, name: &str, pool: &ConstantPool) -> Result<Option<&AttributeInfo>, ClassParseError> {
         for attribute in &self.0 {
             if attribute.name(pool)? == name {
@@ -32,6 +39,13 @@ pub struct AttributeInfo {
 }
 
 impl AttributeInfo {
+    pub fn new(attribute_name_index: u16, info: Vec<u8>) -> Self {
+        Self {
+            attribute_name_index,
+            attribute_length: info.len() as u32,
+            info,
+        }
+    }
     pub fn load<R: BufferReadable>(buf: &mut R) -> Result<Self, ClassParseError> {
         let attribute_name_index = buf.read_u2()?;
         let attribute_length = buf.read_u4()?;
@@ -45,6 +59,11 @@ impl AttributeInfo {
             info,
         })
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.attribute_name_index)?;
+        buf.write_u4(self.info.len() as u32)?;
+        buf.write_bytes(&self.info)
+    }
     pub fn name(&self, constant_pool: &ConstantPool) -> Result<String, ClassParseError> {
         match constant_pool.get_java_aligned(self.attribute_name_index as usize) {
             Some(name) => {
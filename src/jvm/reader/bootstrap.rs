@@ -0,0 +1,307 @@
+use crate::{io::{BufferReadable, BufferWritable, Prebuffer}, util::code_err::ClassParseError};
+
+use super::{
+    constant_pool::{ConstantPool, ConstantPoolInfo, MemberRef},
+    descriptor::{FieldType, MethodDescriptor},
+    method_handle_kind::MethodHandleKind,
+    raw_class::RawClass,
+};
+
+const LAMBDA_METAFACTORY: &str = "java/lang/invoke/LambdaMetafactory";
+const STRING_CONCAT_FACTORY: &str = "java/lang/invoke/StringConcatFactory";
+const OBJECT_METHODS: &str = "java/lang/runtime/ObjectMethods";
+const SWITCH_BOOTSTRAPS: &str = "java/lang/runtime/SwitchBootstraps";
+/// `LambdaMetafactory.FLAG_SERIALIZABLE`, passed to `altMetafactory`
+const FLAG_SERIALIZABLE: i32 = 1;
+/// `Dynamic` constants may take other `Dynamic` constants as arguments, but never themselves
+const MAX_DYNAMIC_DEPTH: usize = 16;
+
+// https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.23
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootstrapMethod {
+    /// Index of the MethodHandle constant that is invoked to link the call site
+    pub method_ref: u16,
+    /// Indices of the loadable constants passed as static arguments
+    pub arguments: Vec<u16>,
+}
+
+/// The `BootstrapMethods` attribute of a class, referenced by index from `Dynamic` and
+/// `InvokeDynamic` constants.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BootstrapMethods(pub Vec<BootstrapMethod>);
+
+impl BootstrapMethods {
+    pub fn load<R: BufferReadable>(buf: &mut R) -> Result<Self, ClassParseError> {
+        let count = buf.read_u2()?;
+        let mut methods = Vec::new();
+        for _ in 0..count {
+            let method_ref = buf.read_u2()?;
+            let argument_count = buf.read_u2()?;
+            let mut arguments = Vec::new();
+            for _ in 0..argument_count {
+                arguments.push(buf.read_u2()?);
+            }
+            methods.push(BootstrapMethod { method_ref, arguments });
+        }
+        Ok(Self(methods))
+    }
+    /// Decodes the attribute of `class`, a class without one has no bootstrap methods.
+    pub fn of(class: &RawClass) -> Result<Self, ClassParseError> {
+        match class.attributes.find_by_name("BootstrapMethods", &class.cp)? {
+            Some(attribute) => Self::load(&mut Prebuffer::copy_from_vec(&attribute.info)),
+            None => Ok(Self::default()),
+        }
+    }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.0.len() as u16)?;
+        for method in &self.0 {
+            buf.write_u2(method.method_ref)?;
+            buf.write_u2(method.arguments.len() as u16)?;
+            for argument in &method.arguments {
+                buf.write_u2(*argument)?;
+            }
+        }
+        Ok(())
+    }
+    pub fn get(&self, index: u16) -> Result<&BootstrapMethod, ClassParseError> {
+        self.0.get(index as usize).ok_or_else(|| ClassParseError::BadValue {
+            expected: format!("bootstrap method index below {}", self.0.len()),
+            got: index.to_string(),
+            for_what: "BootstrapMethods".to_string(),
+        })
+    }
+}
+
+/// A `MethodHandle` constant with the member it refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodHandleRef {
+    pub kind: MethodHandleKind,
+    pub member: MemberRef,
+}
+
+impl MethodHandleRef {
+    pub fn load(cp: &ConstantPool, index: u16) -> Result<Self, ClassParseError> {
+        let (kind, member) = cp.get_method_handle(index)?;
+        Ok(Self { kind, member })
+    }
+}
+
+/// A static argument of a bootstrap method, which is any loadable constant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BootstrapArgument {
+    Int(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(String),
+    /// Internal name or array descriptor
+    Class(String),
+    MethodType(String),
+    MethodHandle(MethodHandleRef),
+    /// A dynamically-computed constant, itself produced by a bootstrap method
+    Dynamic(Box<CallSite>),
+}
+
+impl BootstrapArgument {
+    fn load(cp: &ConstantPool, methods: &BootstrapMethods, index: u16, depth: usize) -> Result<Self, ClassParseError> {
+        Ok(match cp.get_info(index)? {
+            ConstantPoolInfo::Integer(value) => BootstrapArgument::Int(*value),
+            ConstantPoolInfo::Float(value) => BootstrapArgument::Float(*value),
+            ConstantPoolInfo::Long(value) => BootstrapArgument::Long(*value),
+            ConstantPoolInfo::Double(value) => BootstrapArgument::Double(*value),
+            ConstantPoolInfo::StringRef(value) => BootstrapArgument::String(String::from_utf16_lossy(&cp.get_utf16(*value)?)),
+            ConstantPoolInfo::ClassRef(_) => BootstrapArgument::Class(cp.get_class_name(index)?.to_string()),
+            ConstantPoolInfo::MethodType(descriptor) => BootstrapArgument::MethodType(cp.get_utf8(*descriptor)?.to_string()),
+            ConstantPoolInfo::MethodHandle { .. } => BootstrapArgument::MethodHandle(MethodHandleRef::load(cp, index)?),
+            ConstantPoolInfo::Dynamic { .. } => BootstrapArgument::Dynamic(Box::new(CallSite::load(cp, methods, index, depth + 1)?)),
+            other => return Err(ClassParseError::BadValue {
+                expected: "loadable constant".to_string(),
+                got: format!("{:?}", other),
+                for_what: "bootstrap method argument".to_string(),
+            }),
+        })
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BootstrapArgument::String(value) => Some(value),
+            _ => None,
+        }
+    }
+    pub fn as_method_type(&self) -> Option<&str> {
+        match self {
+            BootstrapArgument::MethodType(descriptor) => Some(descriptor),
+            _ => None,
+        }
+    }
+    pub fn as_method_handle(&self) -> Option<&MethodHandleRef> {
+        match self {
+            BootstrapArgument::MethodHandle(handle) => Some(handle),
+            _ => None,
+        }
+    }
+}
+
+/// An `invokedynamic` call site, or a `Dynamic` constant, with its bootstrap method resolved
+/// from the `BootstrapMethods` attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSite {
+    pub bootstrap: MethodHandleRef,
+    pub arguments: Vec<BootstrapArgument>,
+    pub name: String,
+    /// A method descriptor for call sites, a field descriptor for dynamic constants
+    pub descriptor: String,
+}
+
+impl CallSite {
+    /// Resolves the `InvokeDynamic` or `Dynamic` constant at `index`.
+    pub fn resolve(cp: &ConstantPool, methods: &BootstrapMethods, index: u16) -> Result<Self, ClassParseError> {
+        Self::load(cp, methods, index, 0)
+    }
+    fn load(cp: &ConstantPool, methods: &BootstrapMethods, index: u16, depth: usize) -> Result<Self, ClassParseError> {
+        if depth > MAX_DYNAMIC_DEPTH {
+            return Err(ClassParseError::BadValue {
+                expected: format!("at most {} nested Dynamic constants", MAX_DYNAMIC_DEPTH),
+                got: format!("more below index {}", index),
+                for_what: "call site".to_string(),
+            });
+        }
+        let (attribute_index, name_and_type) = match cp.get_info(index)? {
+            ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index }
+            | ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                (*bootstrap_method_attr_index, *name_and_type_index)
+            },
+            other => return Err(ClassParseError::BadValue {
+                expected: "InvokeDynamic or Dynamic".to_string(),
+                got: format!("{:?}", other),
+                for_what: "call site".to_string(),
+            }),
+        };
+        let method = methods.get(attribute_index)?;
+        let (name, descriptor) = cp.get_name_and_type(name_and_type)?;
+        Ok(Self {
+            bootstrap: MethodHandleRef::load(cp, method.method_ref)?,
+            arguments: method.arguments.iter()
+                .map(|argument| BootstrapArgument::load(cp, methods, *argument, depth))
+                .collect::<Result<_, _>>()?,
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+        })
+    }
+    /// Recognizes the bootstrap methods javac emits.
+    pub fn classify(&self) -> Bootstrap {
+        let bootstrap = &self.bootstrap.member;
+        let recognized = match (bootstrap.class.as_str(), bootstrap.name.as_str()) {
+            (LAMBDA_METAFACTORY, "metafactory" | "altMetafactory") => self.lambda(),
+            (STRING_CONCAT_FACTORY, "makeConcatWithConstants" | "makeConcat") => self.string_concat(),
+            (OBJECT_METHODS, "bootstrap") => self.object_methods(),
+            (SWITCH_BOOTSTRAPS, "typeSwitch") => Some(Bootstrap::Switch { kind: SwitchKind::Type, labels: self.arguments.clone() }),
+            (SWITCH_BOOTSTRAPS, "enumSwitch") => Some(Bootstrap::Switch { kind: SwitchKind::Enum, labels: self.arguments.clone() }),
+            _ => None,
+        };
+        recognized.unwrap_or(Bootstrap::Other)
+    }
+    // (erased signature, implementation, instantiated signature, [flags, ...])
+    fn lambda(&self) -> Option<Bootstrap> {
+        let interface = MethodDescriptor::parse(&self.descriptor).ok()?.ret?.element_class()?.to_string();
+        let serializable = match self.arguments.get(3) {
+            Some(BootstrapArgument::Int(flags)) => flags & FLAG_SERIALIZABLE != 0,
+            _ => false,
+        };
+        Some(Bootstrap::Lambda {
+            interface,
+            method: self.name.clone(),
+            erased: self.arguments.first()?.as_method_type()?.to_string(),
+            implementation: self.arguments.get(1)?.as_method_handle()?.clone(),
+            instantiated: self.arguments.get(2)?.as_method_type()?.to_string(),
+            serializable,
+        })
+    }
+    fn string_concat(&self) -> Option<Bootstrap> {
+        let params = MethodDescriptor::parse(&self.descriptor).ok()?.params;
+        if self.bootstrap.member.name == "makeConcat" {
+            return Some(Bootstrap::StringConcat(params.into_iter().map(ConcatPart::Argument).collect()));
+        }
+        let mut params = params.into_iter();
+        let mut constants = self.arguments.iter().skip(1);
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        for c in self.arguments.first()?.as_str()?.chars() {
+            let part = match c {
+                '\u{1}' => ConcatPart::Argument(params.next()?),
+                '\u{2}' => ConcatPart::Constant(constants.next()?.clone()),
+                c => {
+                    literal.push(c);
+                    continue;
+                },
+            };
+            if !literal.is_empty() {
+                parts.push(ConcatPart::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(part);
+        }
+        if !literal.is_empty() {
+            parts.push(ConcatPart::Literal(literal));
+        }
+        Some(Bootstrap::StringConcat(parts))
+    }
+    // (record class, component names joined by ';', getters...)
+    fn object_methods(&self) -> Option<Bootstrap> {
+        let record = match self.arguments.first()? {
+            BootstrapArgument::Class(name) => name.clone(),
+            _ => return None,
+        };
+        let names = self.arguments.get(1)?.as_str()?;
+        Some(Bootstrap::ObjectMethods {
+            method: self.name.clone(),
+            record,
+            components: names.split(';').filter(|name| !name.is_empty()).map(str::to_string).collect(),
+            getters: self.arguments.iter().skip(2).map(|getter| getter.as_method_handle().cloned()).collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// A piece of a string built by `StringConcatFactory`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConcatPart {
+    Literal(String),
+    /// The next argument of the call site, of the given type
+    Argument(FieldType),
+    Constant(BootstrapArgument),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchKind {
+    /// `SwitchBootstraps.typeSwitch`, for patterns on any reference type
+    Type,
+    /// `SwitchBootstraps.enumSwitch`
+    Enum,
+}
+
+/// What a call site's bootstrap method does, for the bootstraps javac emits.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bootstrap {
+    /// A lambda or method reference, implementing `method` of `interface` by calling `implementation`
+    Lambda {
+        interface: String,
+        method: String,
+        erased: String,
+        implementation: MethodHandleRef,
+        instantiated: String,
+        serializable: bool,
+    },
+    /// String concatenation, in order
+    StringConcat(Vec<ConcatPart>),
+    /// `equals`, `hashCode` or `toString` of a record
+    ObjectMethods {
+        method: String,
+        record: String,
+        components: Vec<String>,
+        getters: Vec<MethodHandleRef>,
+    },
+    /// A `switch` on patterns or enum constants, the labels being the static arguments
+    Switch {
+        kind: SwitchKind,
+        labels: Vec<BootstrapArgument>,
+    },
+    Other,
+}
@@ -1,6 +1,6 @@
 use log::warn;
 
-use crate::{io::{BufferReadable, Prebuffer}, util::code_err::ClassParseError};
+use crate::{io::{BufferReadable, BufferWritable, Prebuffer}, util::code_err::ClassParseError};
 
 use super::{raw_class::RawClass};
 
@@ -13,9 +13,9 @@ pub struct ClassFile {
 }
 #[derive(Debug)]
 pub struct ClassFileMetadata {
-    magic: u32,
-    minor_version: u16,
-    major_version: u16,
+    pub magic: u32,
+    pub minor_version: u16,
+    pub major_version: u16,
 }
 
 impl ClassFile {
@@ -25,6 +25,9 @@ impl ClassFile {
             Err(_) => Err(ClassParseError::Silly("cant open file, fix this error message later :3".to_owned())),
         } 
     }
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, ClassParseError> {
+        Self::new(Prebuffer::new(data.into_boxed_slice()))
+    }
     pub fn new<R: BufferReadable>(mut reader:  R) -> Result<Self, ClassParseError> {
         let metadata = ClassFileMetadata::new(&mut reader)?;
         let class = RawClass::load(&mut reader)?;
@@ -35,9 +38,25 @@ impl ClassFile {
             class,
         })
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        self.metadata.write(buf)?;
+        self.class.write(buf)
+    }
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ClassParseError> {
+        let mut buf = Vec::new();
+        self.write(&mut buf)?;
+        Ok(buf)
+    }
 }
 
 impl ClassFileMetadata {
+    pub fn with_version(major_version: u16, minor_version: u16) -> Self {
+        Self {
+            magic: 0xCAFEBABE,
+            minor_version,
+            major_version,
+        }
+    }
     pub fn new<R: BufferReadable>(reader: &mut R) -> Result<Self, ClassParseError> {
         let magic = reader.read_u4()?;
         if magic != 0xCAFEBABE {
@@ -52,4 +71,9 @@ impl ClassFileMetadata {
             major_version,
         })
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u4(self.magic)?;
+        buf.write_u2(self.minor_version)?;
+        buf.write_u2(self.major_version)
+    }
 }
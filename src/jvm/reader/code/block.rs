@@ -1,4 +1,6 @@
-use crate::{jvm::reader::attribute::{Attributes}, io::BufferReadable, util::code_err::ClassParseError};
+use std::io::Seek;
+
+use crate::{jvm::reader::{attribute::Attributes, constant_pool::ConstantPool}, io::{BufferReadable, BufferWritable, Prebuffer}, util::code_err::ClassParseError};
 
 use super::{instruction::Instruction, exception_table::ExceptionTable};
 
@@ -17,8 +19,9 @@ impl CodeBlock {
         let max_locals = buf.read_u2()?;
         let code_length = buf.read_u4()?;
         let mut code = Vec::new();
-        let start_code = buf.stream_position().unwrap() + 1;
-        for _ in 0..code_length {
+        let start_code = buf.stream_position().unwrap();
+        let end_code = start_code + code_length as u64;
+        while buf.stream_position().unwrap() < end_code {
             code.push(Instruction::load(buf, start_code)?);
         }
         
@@ -33,4 +36,52 @@ impl CodeBlock {
             attributes,
         })
     }
-}
\ No newline at end of file
+    /// The `(start_pc, line_number)` entries of the `LineNumberTable` attributes, sorted by pc.
+    pub fn line_numbers(&self, constant_pool: &ConstantPool) -> Result<Vec<(u16, u16)>, ClassParseError> {
+        let mut lines = Vec::new();
+        for attribute in &self.attributes.0 {
+            if attribute.name(constant_pool)? != "LineNumberTable" {
+                continue;
+            }
+            let entries = match attribute.info.split_first_chunk::<2>() {
+                Some((count, entries)) if entries.len() == u16::from_be_bytes(*count) as usize * 4 => entries,
+                _ => return Err(ClassParseError::BadValue {
+                    expected: "a count of 4 byte entries".to_string(),
+                    got: format!("{} bytes", attribute.info.len()),
+                    for_what: "LineNumberTable attribute".to_string(),
+                }),
+            };
+            lines.extend(entries.chunks(4).map(|entry| (u16::from_be_bytes([entry[0], entry[1]]), u16::from_be_bytes([entry[2], entry[3]]))));
+        }
+        lines.sort_unstable();
+        Ok(lines)
+    }
+    /// Decodes a bare bytecode array, as found in the `code` item of a `Code` attribute.
+    pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, ClassParseError> {
+        let mut buf = Prebuffer::copy_from_vec(&bytes.to_vec());
+        let mut code = Vec::new();
+        while (buf.stream_position().unwrap() as usize) < bytes.len() {
+            code.push(Instruction::load(&mut buf, 0)?);
+        }
+        Ok(code)
+    }
+    /// Encodes the instructions back into a bytecode array.
+    pub fn encode(&self) -> Result<Vec<u8>, ClassParseError> {
+        let mut bytes = Vec::new();
+        for instruction in &self.code {
+            let pc = bytes.len() as u32;
+            instruction.write(&mut bytes, pc)?;
+        }
+        Ok(bytes)
+    }
+    /// Writes the body of a `Code` attribute (everything after `attribute_length`).
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        let bytes = self.encode()?;
+        buf.write_u2(self.max_stack)?;
+        buf.write_u2(self.max_locals)?;
+        buf.write_u4(bytes.len() as u32)?;
+        buf.write_bytes(&bytes)?;
+        self.exception_table.write(buf)?;
+        self.attributes.write(buf)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{io::BufferReadable, util::code_err::ClassParseError};
+use crate::{io::{BufferReadable, BufferWritable}, util::code_err::ClassParseError};
 
 #[derive(Debug)]
 pub struct ExceptionTable(pub Vec<ExceptionTableEntry>);
@@ -12,6 +12,13 @@ impl ExceptionTable {
         }
         Ok(Self(exception_table))
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.0.len() as u16)?;
+        for entry in &self.0 {
+            entry.write(buf)?;
+        }
+        Ok(())
+    }
 }
 #[derive(Debug)]
 pub struct ExceptionTableEntry {
@@ -34,4 +41,10 @@ impl ExceptionTableEntry {
             catch_type,
         })
     }
-}
\ No newline at end of file
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.start_pc)?;
+        buf.write_u2(self.end_pc)?;
+        buf.write_u2(self.handler_pc)?;
+        buf.write_u2(self.catch_type)
+    }
+}
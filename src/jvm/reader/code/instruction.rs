@@ -1,6 +1,12 @@
 use std::io::SeekFrom;
 
-use crate::{io::BufferReadable, util::code_err::{CodeParseError, ClassParseError}};
+use crate::{
+    io::{BufferReadable, BufferWritable},
+    jvm::reader::{constant_pool::{ConstantPool, ConstantPoolInfo}, descriptor::{FieldType, MethodDescriptor}},
+    util::code_err::{CodeParseError, ClassParseError},
+};
+
+use super::opcode::{Flow, OpcodeInfo, StackEffect, Throws, OPCODES};
 
 #[repr(u8)]
 #[derive(Debug, Clone)]
@@ -195,7 +201,7 @@ pub enum Instruction {
     Invokeinterface(u16, u8, u8), // InvokeInterface(_, _, Y) => Y must always be 0
     Invokedynamic(u16, u16), // InvokeDynamic(_, X) => X must always be 0
     New(u16),
-    Newarray(u8),
+    Newarray(ArrayType),
     Arraylength,
     ANewarray(u16),
     Athrow,
@@ -203,7 +209,7 @@ pub enum Instruction {
     Instanceof(u16),
     Monitorenter,
     Monitorexit,
-    Wide(u8, u16, u16), // Wide(inst, _, X) => If inst is Iinc, X must be a signed 16-bit integer, otherwise X must be 0 as it should not be read. 
+    Wide(WideOp),
     Multianewarray(u16, u8),
     Ifnull(i16),
     Ifnonnull(i16),
@@ -404,7 +410,13 @@ impl Instruction {
             185 => Ok(Instruction::Invokeinterface(buf.read_u2()?, buf.read_byte()?, buf.read_byte()?)),
             186 => Ok(Instruction::Invokedynamic(buf.read_u2()?, buf.read_u2()?)),
             187 => Ok(Instruction::New(buf.read_u2()?)),
-            188 => Ok(Instruction::Newarray(buf.read_byte()?)),
+            188 => {
+                let code = buf.read_byte()?;
+                match ArrayType::from_code(code) {
+                    Some(atype) => Ok(Instruction::Newarray(atype)),
+                    None => Err(invalid_bytecode("newarray", format!("array type {}", code))),
+                }
+            },
             189 => Ok(Instruction::ANewarray(buf.read_u2()?)),
             190 => Ok(Instruction::Arraylength),
             191 => Ok(Instruction::Athrow),
@@ -412,75 +424,617 @@ impl Instruction {
             193 => Ok(Instruction::Instanceof(buf.read_u2()?)),
             194 => Ok(Instruction::Monitorenter),
             195 => Ok(Instruction::Monitorexit),
-            196 => {
-                let opcode = buf.read_byte()?;
-                if opcode != 132 {
-                    return Ok(Instruction::Wide(opcode, buf.read_u2()?, 0));
-                } else {
-                    return Ok(Instruction::Wide(opcode, buf.read_u2()?, buf.read_u2()?));
-                }
-            },
+            196 => Ok(Instruction::Wide(WideOp::load(buf)?)),
             197 => Ok(Instruction::Multianewarray(buf.read_u2()?, buf.read_byte()?)),
             198 => Ok(Instruction::Ifnull(buf.read_u2()? as i16)),
             199 => Ok(Instruction::Ifnonnull(buf.read_u2()? as i16)),
             200 => Ok(Instruction::GotoW(buf.read_u4()? as i32)),
             201 => Ok(Instruction::JsrW(buf.read_u4()? as i32)),
 
-            x => {
-                return Err(ClassParseError::CodeParseError { internal: CodeParseError::InvalidBytecode {
-                    at: "here".to_string(),
-                    what: format!("{}", x).to_string(),
-                }, classpath: None, signature: None})
-            }
+            x => Err(invalid_bytecode("here", format!("{}", x))),
+        }
+    }
+    /// The opcode byte this instruction is encoded with.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Nop => 0,
+            Instruction::AconstNull => 1,
+            Instruction::IconstM1 => 2,
+            Instruction::Iconst0 => 3,
+            Instruction::Iconst1 => 4,
+            Instruction::Iconst2 => 5,
+            Instruction::Iconst3 => 6,
+            Instruction::Iconst4 => 7,
+            Instruction::Iconst5 => 8,
+            Instruction::Lconst0 => 9,
+            Instruction::Lconst1 => 10,
+            Instruction::Fconst0 => 11,
+            Instruction::Fconst1 => 12,
+            Instruction::Fconst2 => 13,
+            Instruction::Dconst0 => 14,
+            Instruction::Dconst1 => 15,
+            Instruction::Bipush(..) => 16,
+            Instruction::Sipush(..) => 17,
+            Instruction::Ldc(..) => 18,
+            Instruction::LdcW(..) => 19,
+            Instruction::Ldc2W(..) => 20,
+            Instruction::Iload(..) => 21,
+            Instruction::Lload(..) => 22,
+            Instruction::Fload(..) => 23,
+            Instruction::Dload(..) => 24,
+            Instruction::Aload(..) => 25,
+            Instruction::Iload0 => 26,
+            Instruction::Iload1 => 27,
+            Instruction::Iload2 => 28,
+            Instruction::Iload3 => 29,
+            Instruction::Lload0 => 30,
+            Instruction::Lload1 => 31,
+            Instruction::Lload2 => 32,
+            Instruction::Lload3 => 33,
+            Instruction::Fload0 => 34,
+            Instruction::Fload1 => 35,
+            Instruction::Fload2 => 36,
+            Instruction::Fload3 => 37,
+            Instruction::Dload0 => 38,
+            Instruction::Dload1 => 39,
+            Instruction::Dload2 => 40,
+            Instruction::Dload3 => 41,
+            Instruction::Aload0 => 42,
+            Instruction::Aload1 => 43,
+            Instruction::Aload2 => 44,
+            Instruction::Aload3 => 45,
+            Instruction::Iaload => 46,
+            Instruction::Laload => 47,
+            Instruction::Faload => 48,
+            Instruction::Daload => 49,
+            Instruction::Aaload => 50,
+            Instruction::Baload => 51,
+            Instruction::Caload => 52,
+            Instruction::Saload => 53,
+            Instruction::Istore(..) => 54,
+            Instruction::Lstore(..) => 55,
+            Instruction::Fstore(..) => 56,
+            Instruction::Dstore(..) => 57,
+            Instruction::Astore(..) => 58,
+            Instruction::Istore0 => 59,
+            Instruction::Istore1 => 60,
+            Instruction::Istore2 => 61,
+            Instruction::Istore3 => 62,
+            Instruction::Lstore0 => 63,
+            Instruction::Lstore1 => 64,
+            Instruction::Lstore2 => 65,
+            Instruction::Lstore3 => 66,
+            Instruction::Fstore0 => 67,
+            Instruction::Fstore1 => 68,
+            Instruction::Fstore2 => 69,
+            Instruction::Fstore3 => 70,
+            Instruction::Dstore0 => 71,
+            Instruction::Dstore1 => 72,
+            Instruction::Dstore2 => 73,
+            Instruction::Dstore3 => 74,
+            Instruction::Astore0 => 75,
+            Instruction::Astore1 => 76,
+            Instruction::Astore2 => 77,
+            Instruction::Astore3 => 78,
+            Instruction::Iastore => 79,
+            Instruction::Lastore => 80,
+            Instruction::Fastore => 81,
+            Instruction::Dastore => 82,
+            Instruction::Aastore => 83,
+            Instruction::Bastore => 84,
+            Instruction::Castore => 85,
+            Instruction::Sastore => 86,
+            Instruction::Pop => 87,
+            Instruction::Pop2 => 88,
+            Instruction::Dup => 89,
+            Instruction::DupX1 => 90,
+            Instruction::DupX2 => 91,
+            Instruction::Dup2 => 92,
+            Instruction::Dup2X1 => 93,
+            Instruction::Dup2X2 => 94,
+            Instruction::Swap => 95,
+            Instruction::Iadd => 96,
+            Instruction::Ladd => 97,
+            Instruction::Fadd => 98,
+            Instruction::Dadd => 99,
+            Instruction::Isub => 100,
+            Instruction::Lsub => 101,
+            Instruction::Fsub => 102,
+            Instruction::Dsub => 103,
+            Instruction::Imul => 104,
+            Instruction::Lmul => 105,
+            Instruction::Fmul => 106,
+            Instruction::Dmul => 107,
+            Instruction::Idiv => 108,
+            Instruction::Ldiv => 109,
+            Instruction::Fdiv => 110,
+            Instruction::Ddiv => 111,
+            Instruction::Irem => 112,
+            Instruction::Lrem => 113,
+            Instruction::Frem => 114,
+            Instruction::Drem => 115,
+            Instruction::Ineg => 116,
+            Instruction::Lneg => 117,
+            Instruction::Fneg => 118,
+            Instruction::Dneg => 119,
+            Instruction::Ishl => 120,
+            Instruction::Lshl => 121,
+            Instruction::Ishr => 122,
+            Instruction::Lshr => 123,
+            Instruction::Iushr => 124,
+            Instruction::Lushr => 125,
+            Instruction::Iand => 126,
+            Instruction::Land => 127,
+            Instruction::Ior => 128,
+            Instruction::Lor => 129,
+            Instruction::Ixor => 130,
+            Instruction::Lxor => 131,
+            Instruction::Iinc(..) => 132,
+            Instruction::I2l => 133,
+            Instruction::I2f => 134,
+            Instruction::I2d => 135,
+            Instruction::L2i => 136,
+            Instruction::L2f => 137,
+            Instruction::L2d => 138,
+            Instruction::F2i => 139,
+            Instruction::F2l => 140,
+            Instruction::F2d => 141,
+            Instruction::D2i => 142,
+            Instruction::D2l => 143,
+            Instruction::D2f => 144,
+            Instruction::I2b => 145,
+            Instruction::I2c => 146,
+            Instruction::I2s => 147,
+            Instruction::Lcmp => 148,
+            Instruction::Fcmpl => 149,
+            Instruction::Fcmpg => 150,
+            Instruction::Dcmpl => 151,
+            Instruction::Dcmpg => 152,
+            Instruction::Ifeq(..) => 153,
+            Instruction::Ifne(..) => 154,
+            Instruction::Iflt(..) => 155,
+            Instruction::Ifge(..) => 156,
+            Instruction::Ifgt(..) => 157,
+            Instruction::Ifle(..) => 158,
+            Instruction::IfIcmpeq(..) => 159,
+            Instruction::IfIcmpne(..) => 160,
+            Instruction::IfIcmplt(..) => 161,
+            Instruction::IfIcmpge(..) => 162,
+            Instruction::IfIcmpgt(..) => 163,
+            Instruction::IfIcmple(..) => 164,
+            Instruction::IfAcmpeq(..) => 165,
+            Instruction::IfAcmpne(..) => 166,
+            Instruction::Goto(..) => 167,
+            Instruction::Jsr(..) => 168,
+            Instruction::Ret(..) => 169,
+            Instruction::Tableswitch(..) => 170,
+            Instruction::Lookupswitch(..) => 171,
+            Instruction::Ireturn => 172,
+            Instruction::Lreturn => 173,
+            Instruction::Freturn => 174,
+            Instruction::Dreturn => 175,
+            Instruction::Areturn => 176,
+            Instruction::Return => 177,
+            Instruction::Getstatic(..) => 178,
+            Instruction::Putstatic(..) => 179,
+            Instruction::Getfield(..) => 180,
+            Instruction::Putfield(..) => 181,
+            Instruction::Invokevirtual(..) => 182,
+            Instruction::Invokespecial(..) => 183,
+            Instruction::Invokestatic(..) => 184,
+            Instruction::Invokeinterface(..) => 185,
+            Instruction::Invokedynamic(..) => 186,
+            Instruction::New(..) => 187,
+            Instruction::Newarray(..) => 188,
+            Instruction::ANewarray(..) => 189,
+            Instruction::Arraylength => 190,
+            Instruction::Athrow => 191,
+            Instruction::Checkcast(..) => 192,
+            Instruction::Instanceof(..) => 193,
+            Instruction::Monitorenter => 194,
+            Instruction::Monitorexit => 195,
+            Instruction::Wide(..) => 196,
+            Instruction::Multianewarray(..) => 197,
+            Instruction::Ifnull(..) => 198,
+            Instruction::Ifnonnull(..) => 199,
+            Instruction::GotoW(..) => 200,
+            Instruction::JsrW(..) => 201,
+        }
+    }
+    pub fn info(&self) -> &'static OpcodeInfo {
+        &OPCODES[self.opcode() as usize]
+    }
+    pub fn mnemonic(&self) -> &'static str {
+        self.info().mnemonic
+    }
+    /// Encoded length in bytes when the opcode sits at `pc`.
+    pub fn len_at(&self, pc: u32) -> u32 {
+        match self {
+            Instruction::Tableswitch(table) => 1 + switch_padding(pc) + 12 + 4 * table.offsets.len() as u32,
+            Instruction::Lookupswitch(lookup) => 1 + switch_padding(pc) + 8 + 8 * lookup.matches.len() as u32,
+            Instruction::Wide(WideOp::Iinc(..)) => 6,
+            Instruction::Wide(_) => 4,
+            _ => self.info().length().unwrap(),
+        }
+    }
+    /// The table entry of the instruction `wide` modifies, or this instruction's own.
+    fn effective_info(&self) -> &'static OpcodeInfo {
+        match self {
+            Instruction::Wide(op) => &OPCODES[op.opcode() as usize],
+            _ => self.info(),
+        }
+    }
+    pub fn flow(&self) -> Flow {
+        self.effective_info().flow
+    }
+    pub fn ends_block(&self) -> bool {
+        self.flow().ends_block()
+    }
+    pub fn throws(&self) -> Throws {
+        self.info().throws
+    }
+    pub fn can_throw(&self) -> bool {
+        self.throws().can_throw()
+    }
+    /// Relative offsets of every jump, in the order they appear in the operands.
+    pub fn branch_offsets(&self) -> Vec<i32> {
+        use Instruction as I;
+        match self {
+            I::Ifeq(a) | I::Ifne(a) | I::Iflt(a) | I::Ifge(a) | I::Ifgt(a) | I::Ifle(a)
+            | I::IfIcmpeq(a) | I::IfIcmpne(a) | I::IfIcmplt(a) | I::IfIcmpge(a) | I::IfIcmpgt(a) | I::IfIcmple(a)
+            | I::IfAcmpeq(a) | I::IfAcmpne(a) | I::Goto(a) | I::Jsr(a) | I::Ifnull(a) | I::Ifnonnull(a) => vec![*a as i32],
+            I::GotoW(a) | I::JsrW(a) => vec![*a],
+            I::Tableswitch(table) => table.targets(),
+            I::Lookupswitch(lookup) => lookup.targets(),
+            _ => Vec::new(),
+        }
+    }
+    /// Absolute pcs of every jump, for an instruction at `pc`.
+    pub fn branch_targets(&self, pc: u32) -> Vec<u32> {
+        self.branch_offsets().into_iter().map(|offset| (pc as i64 + offset as i64) as u32).collect()
+    }
+    /// Slots popped from and pushed onto the operand stack. Field accesses and invocations are
+    /// sized by the descriptor their constant pool entry names.
+    pub fn stack_effect(&self, pool: &ConstantPool) -> Result<(u16, u16), ClassParseError> {
+        let info = self.effective_info();
+        Ok(match (info.stack, self) {
+            (StackEffect::Fixed { pops, pushes }, _) => (pops as u16, pushes as u16),
+            (StackEffect::Field { is_static, put }, Instruction::Getstatic(index) | Instruction::Putstatic(index)
+            | Instruction::Getfield(index) | Instruction::Putfield(index)) => {
+                let slots = FieldType::parse(&pool.get_member_ref(*index)?.descriptor)?.slots();
+                let receiver = if is_static { 0 } else { 1 };
+                if put { (receiver + slots, 0) } else { (receiver, slots) }
+            },
+            (StackEffect::Invoke { receiver }, Instruction::Invokedynamic(index, _)) => {
+                let name_and_type = match pool.get_info(*index)? {
+                    ConstantPoolInfo::InvokeDynamic { name_and_type_index, .. } => *name_and_type_index,
+                    other => return Err(ClassParseError::BadValue {
+                        expected: "InvokeDynamic".to_string(),
+                        got: format!("{:?}", other),
+                        for_what: "invokedynamic".to_string(),
+                    }),
+                };
+                let descriptor = MethodDescriptor::parse(pool.get_name_and_type(name_and_type)?.1)?;
+                (descriptor.param_slots() + receiver as u16, descriptor.ret_slots())
+            },
+            (StackEffect::Invoke { receiver }, Instruction::Invokevirtual(index) | Instruction::Invokespecial(index)
+            | Instruction::Invokestatic(index) | Instruction::Invokeinterface(index, _, _)) => {
+                let descriptor = MethodDescriptor::parse(&pool.get_member_ref(*index)?.descriptor)?;
+                (descriptor.param_slots() + receiver as u16, descriptor.ret_slots())
+            },
+            (StackEffect::Dimensions, Instruction::Multianewarray(_, dimensions)) => (*dimensions as u16, 1),
+            (effect, _) => unreachable!("{} has stack effect {:?}", info.mnemonic, effect),
+        })
+    }
+    /// Encodes the instruction, `pc` is the offset of the opcode from the start of the code
+    /// array and is needed to pad `tableswitch` and `lookupswitch`.
+    pub fn write<W: BufferWritable>(&self, buf: &mut W, pc: u32) -> Result<(), ClassParseError> {
+        use Instruction as I;
+        buf.write_byte(self.opcode())?;
+        match self {
+            I::Bipush(a) | I::Ldc(a) | I::Ret(a)
+            | I::Iload(a) | I::Lload(a) | I::Fload(a) | I::Dload(a) | I::Aload(a)
+            | I::Istore(a) | I::Lstore(a) | I::Fstore(a) | I::Dstore(a) | I::Astore(a) => buf.write_byte(*a)?,
+            I::Sipush(a) | I::LdcW(a) | I::Ldc2W(a)
+            | I::Getstatic(a) | I::Putstatic(a) | I::Getfield(a) | I::Putfield(a)
+            | I::Invokevirtual(a) | I::Invokespecial(a) | I::Invokestatic(a)
+            | I::New(a) | I::ANewarray(a) | I::Checkcast(a) | I::Instanceof(a) => buf.write_u2(*a)?,
+            I::Ifeq(a) | I::Ifne(a) | I::Iflt(a) | I::Ifge(a) | I::Ifgt(a) | I::Ifle(a)
+            | I::IfIcmpeq(a) | I::IfIcmpne(a) | I::IfIcmplt(a) | I::IfIcmpge(a) | I::IfIcmpgt(a) | I::IfIcmple(a)
+            | I::IfAcmpeq(a) | I::IfAcmpne(a) | I::Goto(a) | I::Jsr(a) | I::Ifnull(a) | I::Ifnonnull(a) => buf.write_u2(*a as u16)?,
+            I::GotoW(a) | I::JsrW(a) => buf.write_u4(*a as u32)?,
+            I::Iinc(a, b) => {
+                buf.write_byte(*a)?;
+                buf.write_byte(*b as u8)?;
+            },
+            I::Invokeinterface(a, b, c) => {
+                buf.write_u2(*a)?;
+                buf.write_byte(*b)?;
+                buf.write_byte(*c)?;
+            },
+            I::Invokedynamic(a, b) => {
+                buf.write_u2(*a)?;
+                buf.write_u2(*b)?;
+            },
+            I::Multianewarray(a, b) => {
+                buf.write_u2(*a)?;
+                buf.write_byte(*b)?;
+            },
+            I::Tableswitch(table) => table.write(buf, pc)?,
+            I::Lookupswitch(lookup) => lookup.write(buf, pc)?,
+            I::Newarray(atype) => buf.write_byte(atype.to_code())?,
+            I::Wide(op) => op.write(buf)?,
+            _ => {},
         }
+        Ok(())
     }
 }
-#[derive(Debug, Clone)]
+fn invalid_bytecode(at: &str, what: String) -> ClassParseError {
+    ClassParseError::CodeParseError {
+        internal: CodeParseError::InvalidBytecode { at: at.to_string(), what },
+        classpath: None,
+        signature: None,
+    }
+}
+
+/// An instruction modified by `wide`, with its local variable index widened to two bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WideOp {
+    Iload(u16),
+    Lload(u16),
+    Fload(u16),
+    Dload(u16),
+    Aload(u16),
+    Istore(u16),
+    Lstore(u16),
+    Fstore(u16),
+    Dstore(u16),
+    Astore(u16),
+    Ret(u16),
+    /// Local index and signed increment
+    Iinc(u16, i16),
+}
+impl WideOp {
+    pub fn load<R: BufferReadable>(buf: &mut R) -> Result<WideOp, ClassParseError> {
+        let opcode = buf.read_byte()?;
+        let index = buf.read_u2()?;
+        Ok(match opcode {
+            21 => WideOp::Iload(index),
+            22 => WideOp::Lload(index),
+            23 => WideOp::Fload(index),
+            24 => WideOp::Dload(index),
+            25 => WideOp::Aload(index),
+            54 => WideOp::Istore(index),
+            55 => WideOp::Lstore(index),
+            56 => WideOp::Fstore(index),
+            57 => WideOp::Dstore(index),
+            58 => WideOp::Astore(index),
+            169 => WideOp::Ret(index),
+            132 => WideOp::Iinc(index, buf.read_u2()? as i16),
+            other => return Err(invalid_bytecode("wide", format!("opcode {} can not be widened", other))),
+        })
+    }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_byte(self.opcode())?;
+        buf.write_u2(self.index())?;
+        if let WideOp::Iinc(_, increment) = self {
+            buf.write_u2(*increment as u16)?;
+        }
+        Ok(())
+    }
+    /// Opcode of the modified instruction.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            WideOp::Iload(_) => 21,
+            WideOp::Lload(_) => 22,
+            WideOp::Fload(_) => 23,
+            WideOp::Dload(_) => 24,
+            WideOp::Aload(_) => 25,
+            WideOp::Istore(_) => 54,
+            WideOp::Lstore(_) => 55,
+            WideOp::Fstore(_) => 56,
+            WideOp::Dstore(_) => 57,
+            WideOp::Astore(_) => 58,
+            WideOp::Iinc(..) => 132,
+            WideOp::Ret(_) => 169,
+        }
+    }
+    /// The local variable index.
+    pub fn index(&self) -> u16 {
+        match self {
+            WideOp::Iload(index) | WideOp::Lload(index) | WideOp::Fload(index) | WideOp::Dload(index) | WideOp::Aload(index)
+            | WideOp::Istore(index) | WideOp::Lstore(index) | WideOp::Fstore(index) | WideOp::Dstore(index) | WideOp::Astore(index)
+            | WideOp::Ret(index) | WideOp::Iinc(index, _) => *index,
+        }
+    }
+}
+
+/// Element type of a `newarray`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayType {
+    Boolean,
+    Char,
+    Float,
+    Double,
+    Byte,
+    Short,
+    Int,
+    Long,
+}
+impl ArrayType {
+    /// The `atype` operand, `T_BOOLEAN` (4) through `T_LONG` (11).
+    pub fn to_code(&self) -> u8 {
+        match self {
+            ArrayType::Boolean => 4,
+            ArrayType::Char => 5,
+            ArrayType::Float => 6,
+            ArrayType::Double => 7,
+            ArrayType::Byte => 8,
+            ArrayType::Short => 9,
+            ArrayType::Int => 10,
+            ArrayType::Long => 11,
+        }
+    }
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            4 => Some(ArrayType::Boolean),
+            5 => Some(ArrayType::Char),
+            6 => Some(ArrayType::Float),
+            7 => Some(ArrayType::Double),
+            8 => Some(ArrayType::Byte),
+            9 => Some(ArrayType::Short),
+            10 => Some(ArrayType::Int),
+            11 => Some(ArrayType::Long),
+            _ => None,
+        }
+    }
+    /// The Java keyword naming the type, e.g. `boolean`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArrayType::Boolean => "boolean",
+            ArrayType::Char => "char",
+            ArrayType::Float => "float",
+            ArrayType::Double => "double",
+            ArrayType::Byte => "byte",
+            ArrayType::Short => "short",
+            ArrayType::Int => "int",
+            ArrayType::Long => "long",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        (4..=11).filter_map(ArrayType::from_code).find(|atype| atype.name() == name)
+    }
+    pub fn element(&self) -> FieldType {
+        match self {
+            ArrayType::Boolean => FieldType::Boolean,
+            ArrayType::Char => FieldType::Char,
+            ArrayType::Float => FieldType::Float,
+            ArrayType::Double => FieldType::Double,
+            ArrayType::Byte => FieldType::Byte,
+            ArrayType::Short => FieldType::Short,
+            ArrayType::Int => FieldType::Int,
+            ArrayType::Long => FieldType::Long,
+        }
+    }
+}
+
+/// Skips the padding that aligns switch operands to a multiple of four bytes from `start`.
+fn skip_switch_padding<R: BufferReadable>(buf: &mut R, start: u64) -> Result<(), ClassParseError> {
+    let bytes_bffr = (buf.stream_position().map_err(ClassParseError::IOError)? - start) % 4;
+    if bytes_bffr != 0 {
+        buf.seek(SeekFrom::Current(4 - bytes_bffr as i64)).map_err(ClassParseError::IOError)?;
+    }
+    Ok(())
+}
+
+/// `lookupswitch` operands, with `matches` sorted by key and every key distinct.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct LookupSwitch {
-    default: i32,
-    npairs: i32,
-    matches: Vec<(i32, i32)>,
+    pub default: i32,
+    /// (key, offset) pairs
+    pub matches: Vec<(i32, i32)>,
 }
 impl LookupSwitch {
     pub fn load<R: BufferReadable>(buf: &mut R, start: u64) -> Result<LookupSwitch, ClassParseError> {
-        let bytes_bffr = (buf.seek(SeekFrom::Current(0)).unwrap() - start) % 4; 
-        if bytes_bffr != 0 {
-            buf.seek(SeekFrom::Current(4 - bytes_bffr as i64)).unwrap();
-        }
+        skip_switch_padding(buf, start)?;
         let default = buf.read_u4()? as i32;
         let npairs = buf.read_u4()? as i32;
+        if npairs < 0 {
+            return Err(invalid_bytecode("lookupswitch", format!("negative pair count {}", npairs)));
+        }
         let mut matches = Vec::new();
         for _ in 0..npairs {
             let match_ = buf.read_u4()? as i32;
             let offset = buf.read_u4()? as i32;
             matches.push((match_, offset));
         }
-        Ok(LookupSwitch { default, npairs, matches })
-        
+        LookupSwitch::new(default, matches)
+    }
+    /// Fails unless the keys are strictly increasing.
+    pub fn new(default: i32, matches: Vec<(i32, i32)>) -> Result<Self, ClassParseError> {
+        if let Some(pair) = matches.windows(2).find(|pair| pair[0].0 >= pair[1].0) {
+            return Err(invalid_bytecode("lookupswitch", format!("key {} follows key {}, keys must be sorted and distinct", pair[1].0, pair[0].0)));
+        }
+        Ok(LookupSwitch { default, matches })
+    }
+    /// Relative offsets of every jump target, default first.
+    pub fn targets(&self) -> Vec<i32> {
+        std::iter::once(self.default).chain(self.matches.iter().map(|(_, offset)| *offset)).collect()
+    }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W, pc: u32) -> Result<(), ClassParseError> {
+        for _ in 0..switch_padding(pc) {
+            buf.write_byte(0)?;
+        }
+        buf.write_u4(self.default as u32)?;
+        buf.write_u4(self.matches.len() as u32)?;
+        for (match_, offset) in &self.matches {
+            buf.write_u4(*match_ as u32)?;
+            buf.write_u4(*offset as u32)?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Debug, Clone)]
+/// Number of padding bytes following a switch opcode at `pc`, so that its operands are 4-byte aligned.
+pub fn switch_padding(pc: u32) -> u32 {
+    (4 - (pc + 1) % 4) % 4
+}
+
+/// `tableswitch` operands, with one offset for every key from `low` to `high`.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TableSwitch {
-    default: i32,
-    low: i32,
-    high: i32,
-    offsets: Vec<i32>,
+    pub default: i32,
+    pub low: i32,
+    pub high: i32,
+    pub offsets: Vec<i32>,
 }
 impl TableSwitch {
     pub fn load<R: BufferReadable>(buf: &mut R, start: u64) -> Result<TableSwitch, ClassParseError> {
-        let bytes_bffr = (buf.seek(SeekFrom::Current(0)).unwrap() - start) % 4; 
-        if bytes_bffr != 0 {
-            buf.seek(SeekFrom::Current(4 - bytes_bffr as i64)).unwrap();
-        }
+        skip_switch_padding(buf, start)?;
         let default = buf.read_u4()? as i32;
         let low = buf.read_u4()? as i32;
         let high = buf.read_u4()? as i32;
+        if low > high {
+            return Err(invalid_bytecode("tableswitch", format!("low key {} is above high key {}", low, high)));
+        }
         let mut offsets = Vec::new();
-        for _ in 0..(high - low + 1) {
-            let offset = buf.read_u4()? as i32;
-            offsets.push(offset);
+        for _ in low..=high {
+            offsets.push(buf.read_u4()? as i32);
+        }
+        TableSwitch::new(default, low, high, offsets)
+    }
+    /// Fails unless `low <= high` and there is exactly one offset per key.
+    pub fn new(default: i32, low: i32, high: i32, offsets: Vec<i32>) -> Result<Self, ClassParseError> {
+        if low > high {
+            return Err(invalid_bytecode("tableswitch", format!("low key {} is above high key {}", low, high)));
+        }
+        let keys = high as i64 - low as i64 + 1;
+        if offsets.len() as i64 != keys {
+            return Err(invalid_bytecode("tableswitch", format!("{} keys but {} offsets", keys, offsets.len())));
         }
         Ok(TableSwitch { default, low, high, offsets })
     }
-}
\ No newline at end of file
+    /// Relative offsets of every jump target, default first.
+    pub fn targets(&self) -> Vec<i32> {
+        std::iter::once(self.default).chain(self.offsets.iter().copied()).collect()
+    }
+    /// Relative offset taken for `key`.
+    pub fn offset_for(&self, key: i32) -> i32 {
+        if key < self.low || key > self.high {
+            return self.default;
+        }
+        self.offsets[(key as i64 - self.low as i64) as usize]
+    }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W, pc: u32) -> Result<(), ClassParseError> {
+        for _ in 0..switch_padding(pc) {
+            buf.write_byte(0)?;
+        }
+        buf.write_u4(self.default as u32)?;
+        buf.write_u4(self.low as u32)?;
+        buf.write_u4(self.high as u32)?;
+        for offset in &self.offsets {
+            buf.write_u4(*offset as u32)?;
+        }
+        Ok(())
+    }
+}
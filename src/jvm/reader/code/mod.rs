@@ -1,3 +1,4 @@
 pub mod instruction;
+pub mod opcode;
 pub mod block;
 pub mod exception_table;
\ No newline at end of file
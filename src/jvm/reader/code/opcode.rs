@@ -0,0 +1,398 @@
+//! Static facts about every JVM opcode (JVMS §6.5), kept in a single table indexed by the
+//! opcode byte. [Instruction](super::instruction::Instruction) answers its metadata
+//! questions through this table instead of matching on every variant.
+
+/// Constant pool tags, as stored in [ConstantPoolEntry::tag](crate::jvm::reader::constant_pool::ConstantPoolEntry).
+pub mod tag {
+    pub const INTEGER: u8 = 3;
+    pub const FLOAT: u8 = 4;
+    pub const LONG: u8 = 5;
+    pub const DOUBLE: u8 = 6;
+    pub const CLASS: u8 = 7;
+    pub const STRING: u8 = 8;
+    pub const FIELD_REF: u8 = 9;
+    pub const METHOD_REF: u8 = 10;
+    pub const INTERFACE_METHOD_REF: u8 = 11;
+    pub const METHOD_HANDLE: u8 = 15;
+    pub const METHOD_TYPE: u8 = 16;
+    pub const DYNAMIC: u8 = 17;
+    pub const INVOKE_DYNAMIC: u8 = 18;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    /// Local variable index, one byte (two under `wide`)
+    Local,
+    /// Constant pool index of an entry with one of `tags`, two bytes if `wide`, otherwise one
+    Constant { wide: bool, tags: &'static [u8] },
+    /// Signed jump offset relative to the opcode, four bytes if `wide`, otherwise two
+    Branch { wide: bool },
+    /// Signed immediate value of `bytes` bytes (`iinc` takes two under `wide`)
+    Immediate { bytes: u8 },
+    /// `newarray` element type code
+    ArrayType,
+    /// Unsigned byte count: argument slots of `invokeinterface`, dimensions of `multianewarray`
+    Count,
+    /// Reserved bytes that must be zero
+    Zero { bytes: u8 },
+    /// The padded jump table of `tableswitch` and `lookupswitch`
+    Switch,
+    /// The opcode and operands of the instruction modified by `wide`
+    Widened,
+}
+
+impl OperandKind {
+    /// Encoded size in bytes, `None` when it depends on the position or on the widened instruction.
+    pub fn size(&self) -> Option<u32> {
+        match self {
+            OperandKind::Local | OperandKind::ArrayType | OperandKind::Count => Some(1),
+            OperandKind::Constant { wide, .. } => Some(if *wide { 2 } else { 1 }),
+            OperandKind::Branch { wide } => Some(if *wide { 4 } else { 2 }),
+            OperandKind::Immediate { bytes } | OperandKind::Zero { bytes } => Some(*bytes as u32),
+            OperandKind::Switch | OperandKind::Widened => None,
+        }
+    }
+}
+
+/// Operand stack effect, counted in slots: long and double values take two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackEffect {
+    Fixed { pops: u8, pushes: u8 },
+    /// Field access, sized by the field descriptor, plus the object reference unless static
+    Field { is_static: bool, put: bool },
+    /// Method invocation, sized by the method descriptor, plus the receiver if there is one
+    Invoke { receiver: bool },
+    /// `multianewarray` pops one count per dimension
+    Dimensions,
+    /// `wide` has the effect of the instruction it modifies
+    Widened,
+}
+
+/// Where execution continues after an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// The next instruction
+    Next,
+    /// The next instruction or the branch target
+    Branch,
+    /// Only the branch target
+    Goto,
+    /// One of the switch targets
+    Switch,
+    /// The subroutine, which returns to the next instruction
+    Jsr,
+    /// The address held in a local variable
+    Ret,
+    /// Leaves the method normally
+    Return,
+    /// Leaves through an exception handler
+    Throw,
+}
+
+impl Flow {
+    pub fn falls_through(&self) -> bool {
+        matches!(self, Flow::Next | Flow::Branch | Flow::Jsr)
+    }
+    /// Whether the instruction is the last one of its basic block.
+    pub fn ends_block(&self) -> bool {
+        *self != Flow::Next
+    }
+}
+
+/// Exceptions an instruction can raise besides `VirtualMachineError`, which any instruction may throw.
+/// Symbolic references can fail to resolve or initialize, which is reported as `LinkageError`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Throws {
+    Nothing,
+    Only(&'static [&'static str]),
+    /// Whatever the callee or the thrown value is
+    Anything,
+}
+
+impl Throws {
+    pub fn can_throw(&self) -> bool {
+        *self != Throws::Nothing
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    pub operands: &'static [OperandKind],
+    pub stack: StackEffect,
+    pub throws: Throws,
+    pub flow: Flow,
+}
+
+impl OpcodeInfo {
+    pub fn get(opcode: u8) -> Option<&'static OpcodeInfo> {
+        OPCODES.get(opcode as usize)
+    }
+    pub fn by_mnemonic(mnemonic: &str) -> Option<&'static OpcodeInfo> {
+        OPCODES.iter().find(|info| info.mnemonic == mnemonic)
+    }
+    /// Encoded length including the opcode, `None` for `tableswitch`, `lookupswitch` and `wide`.
+    pub fn length(&self) -> Option<u32> {
+        self.operands.iter().try_fold(1, |length, operand| Some(length + operand.size()?))
+    }
+}
+
+use OperandKind as O;
+use StackEffect as S;
+use Flow as F;
+
+const NONE: &[OperandKind] = &[];
+const LOCAL: &[OperandKind] = &[O::Local];
+const BRANCH: &[OperandKind] = &[O::Branch { wide: false }];
+const BRANCH_W: &[OperandKind] = &[O::Branch { wide: true }];
+const LDC: &[OperandKind] = &[O::Constant {
+    wide: false,
+    tags: &[tag::INTEGER, tag::FLOAT, tag::STRING, tag::CLASS, tag::METHOD_TYPE, tag::METHOD_HANDLE, tag::DYNAMIC],
+}];
+const LDC_W: &[OperandKind] = &[O::Constant {
+    wide: true,
+    tags: &[tag::INTEGER, tag::FLOAT, tag::STRING, tag::CLASS, tag::METHOD_TYPE, tag::METHOD_HANDLE, tag::DYNAMIC],
+}];
+const LDC2_W: &[OperandKind] = &[O::Constant { wide: true, tags: &[tag::LONG, tag::DOUBLE, tag::DYNAMIC] }];
+const CLASS: &[OperandKind] = &[O::Constant { wide: true, tags: &[tag::CLASS] }];
+const FIELD: &[OperandKind] = &[O::Constant { wide: true, tags: &[tag::FIELD_REF] }];
+const METHOD: &[OperandKind] = &[O::Constant { wide: true, tags: &[tag::METHOD_REF] }];
+const ANY_METHOD: &[OperandKind] = &[O::Constant { wide: true, tags: &[tag::METHOD_REF, tag::INTERFACE_METHOD_REF] }];
+
+const NOTHING: Throws = Throws::Nothing;
+const ANYTHING: Throws = Throws::Anything;
+const ARRAY: Throws = Throws::Only(&["java/lang/NullPointerException", "java/lang/ArrayIndexOutOfBoundsException"]);
+const ARITHMETIC: Throws = Throws::Only(&["java/lang/ArithmeticException"]);
+const MONITOR: Throws = Throws::Only(&["java/lang/IllegalMonitorStateException"]);
+const LINKAGE: Throws = Throws::Only(&["java/lang/LinkageError"]);
+const LINKAGE_NULL: Throws = Throws::Only(&["java/lang/LinkageError", "java/lang/NullPointerException"]);
+const NULL: Throws = Throws::Only(&["java/lang/NullPointerException"]);
+
+const fn op(opcode: u8, mnemonic: &'static str, operands: &'static [OperandKind], stack: StackEffect, throws: Throws, flow: Flow) -> OpcodeInfo {
+    OpcodeInfo { opcode, mnemonic, operands, stack, throws, flow }
+}
+
+const fn fixed(pops: u8, pushes: u8) -> StackEffect {
+    StackEffect::Fixed { pops, pushes }
+}
+
+/// Every opcode, indexed by its byte.
+pub static OPCODES: [OpcodeInfo; 202] = [
+    op(0, "nop", NONE, fixed(0, 0), NOTHING, F::Next),
+    op(1, "aconst_null", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(2, "iconst_m1", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(3, "iconst_0", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(4, "iconst_1", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(5, "iconst_2", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(6, "iconst_3", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(7, "iconst_4", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(8, "iconst_5", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(9, "lconst_0", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(10, "lconst_1", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(11, "fconst_0", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(12, "fconst_1", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(13, "fconst_2", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(14, "dconst_0", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(15, "dconst_1", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(16, "bipush", &[O::Immediate { bytes: 1 }], fixed(0, 1), NOTHING, F::Next),
+    op(17, "sipush", &[O::Immediate { bytes: 2 }], fixed(0, 1), NOTHING, F::Next),
+    op(18, "ldc", LDC, fixed(0, 1), LINKAGE, F::Next),
+    op(19, "ldc_w", LDC_W, fixed(0, 1), LINKAGE, F::Next),
+    op(20, "ldc2_w", LDC2_W, fixed(0, 2), LINKAGE, F::Next),
+    op(21, "iload", LOCAL, fixed(0, 1), NOTHING, F::Next),
+    op(22, "lload", LOCAL, fixed(0, 2), NOTHING, F::Next),
+    op(23, "fload", LOCAL, fixed(0, 1), NOTHING, F::Next),
+    op(24, "dload", LOCAL, fixed(0, 2), NOTHING, F::Next),
+    op(25, "aload", LOCAL, fixed(0, 1), NOTHING, F::Next),
+    op(26, "iload_0", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(27, "iload_1", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(28, "iload_2", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(29, "iload_3", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(30, "lload_0", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(31, "lload_1", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(32, "lload_2", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(33, "lload_3", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(34, "fload_0", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(35, "fload_1", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(36, "fload_2", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(37, "fload_3", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(38, "dload_0", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(39, "dload_1", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(40, "dload_2", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(41, "dload_3", NONE, fixed(0, 2), NOTHING, F::Next),
+    op(42, "aload_0", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(43, "aload_1", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(44, "aload_2", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(45, "aload_3", NONE, fixed(0, 1), NOTHING, F::Next),
+    op(46, "iaload", NONE, fixed(2, 1), ARRAY, F::Next),
+    op(47, "laload", NONE, fixed(2, 2), ARRAY, F::Next),
+    op(48, "faload", NONE, fixed(2, 1), ARRAY, F::Next),
+    op(49, "daload", NONE, fixed(2, 2), ARRAY, F::Next),
+    op(50, "aaload", NONE, fixed(2, 1), ARRAY, F::Next),
+    op(51, "baload", NONE, fixed(2, 1), ARRAY, F::Next),
+    op(52, "caload", NONE, fixed(2, 1), ARRAY, F::Next),
+    op(53, "saload", NONE, fixed(2, 1), ARRAY, F::Next),
+    op(54, "istore", LOCAL, fixed(1, 0), NOTHING, F::Next),
+    op(55, "lstore", LOCAL, fixed(2, 0), NOTHING, F::Next),
+    op(56, "fstore", LOCAL, fixed(1, 0), NOTHING, F::Next),
+    op(57, "dstore", LOCAL, fixed(2, 0), NOTHING, F::Next),
+    op(58, "astore", LOCAL, fixed(1, 0), NOTHING, F::Next),
+    op(59, "istore_0", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(60, "istore_1", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(61, "istore_2", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(62, "istore_3", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(63, "lstore_0", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(64, "lstore_1", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(65, "lstore_2", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(66, "lstore_3", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(67, "fstore_0", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(68, "fstore_1", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(69, "fstore_2", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(70, "fstore_3", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(71, "dstore_0", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(72, "dstore_1", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(73, "dstore_2", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(74, "dstore_3", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(75, "astore_0", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(76, "astore_1", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(77, "astore_2", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(78, "astore_3", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(79, "iastore", NONE, fixed(3, 0), ARRAY, F::Next),
+    op(80, "lastore", NONE, fixed(4, 0), ARRAY, F::Next),
+    op(81, "fastore", NONE, fixed(3, 0), ARRAY, F::Next),
+    op(82, "dastore", NONE, fixed(4, 0), ARRAY, F::Next),
+    op(83, "aastore", NONE, fixed(3, 0), Throws::Only(&[
+        "java/lang/NullPointerException",
+        "java/lang/ArrayIndexOutOfBoundsException",
+        "java/lang/ArrayStoreException",
+    ]), F::Next),
+    op(84, "bastore", NONE, fixed(3, 0), ARRAY, F::Next),
+    op(85, "castore", NONE, fixed(3, 0), ARRAY, F::Next),
+    op(86, "sastore", NONE, fixed(3, 0), ARRAY, F::Next),
+    op(87, "pop", NONE, fixed(1, 0), NOTHING, F::Next),
+    op(88, "pop2", NONE, fixed(2, 0), NOTHING, F::Next),
+    op(89, "dup", NONE, fixed(1, 2), NOTHING, F::Next),
+    op(90, "dup_x1", NONE, fixed(2, 3), NOTHING, F::Next),
+    op(91, "dup_x2", NONE, fixed(3, 4), NOTHING, F::Next),
+    op(92, "dup2", NONE, fixed(2, 4), NOTHING, F::Next),
+    op(93, "dup2_x1", NONE, fixed(3, 5), NOTHING, F::Next),
+    op(94, "dup2_x2", NONE, fixed(4, 6), NOTHING, F::Next),
+    op(95, "swap", NONE, fixed(2, 2), NOTHING, F::Next),
+    op(96, "iadd", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(97, "ladd", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(98, "fadd", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(99, "dadd", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(100, "isub", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(101, "lsub", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(102, "fsub", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(103, "dsub", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(104, "imul", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(105, "lmul", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(106, "fmul", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(107, "dmul", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(108, "idiv", NONE, fixed(2, 1), ARITHMETIC, F::Next),
+    op(109, "ldiv", NONE, fixed(4, 2), ARITHMETIC, F::Next),
+    op(110, "fdiv", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(111, "ddiv", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(112, "irem", NONE, fixed(2, 1), ARITHMETIC, F::Next),
+    op(113, "lrem", NONE, fixed(4, 2), ARITHMETIC, F::Next),
+    op(114, "frem", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(115, "drem", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(116, "ineg", NONE, fixed(1, 1), NOTHING, F::Next),
+    op(117, "lneg", NONE, fixed(2, 2), NOTHING, F::Next),
+    op(118, "fneg", NONE, fixed(1, 1), NOTHING, F::Next),
+    op(119, "dneg", NONE, fixed(2, 2), NOTHING, F::Next),
+    op(120, "ishl", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(121, "lshl", NONE, fixed(3, 2), NOTHING, F::Next),
+    op(122, "ishr", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(123, "lshr", NONE, fixed(3, 2), NOTHING, F::Next),
+    op(124, "iushr", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(125, "lushr", NONE, fixed(3, 2), NOTHING, F::Next),
+    op(126, "iand", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(127, "land", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(128, "ior", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(129, "lor", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(130, "ixor", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(131, "lxor", NONE, fixed(4, 2), NOTHING, F::Next),
+    op(132, "iinc", &[O::Local, O::Immediate { bytes: 1 }], fixed(0, 0), NOTHING, F::Next),
+    op(133, "i2l", NONE, fixed(1, 2), NOTHING, F::Next),
+    op(134, "i2f", NONE, fixed(1, 1), NOTHING, F::Next),
+    op(135, "i2d", NONE, fixed(1, 2), NOTHING, F::Next),
+    op(136, "l2i", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(137, "l2f", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(138, "l2d", NONE, fixed(2, 2), NOTHING, F::Next),
+    op(139, "f2i", NONE, fixed(1, 1), NOTHING, F::Next),
+    op(140, "f2l", NONE, fixed(1, 2), NOTHING, F::Next),
+    op(141, "f2d", NONE, fixed(1, 2), NOTHING, F::Next),
+    op(142, "d2i", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(143, "d2l", NONE, fixed(2, 2), NOTHING, F::Next),
+    op(144, "d2f", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(145, "i2b", NONE, fixed(1, 1), NOTHING, F::Next),
+    op(146, "i2c", NONE, fixed(1, 1), NOTHING, F::Next),
+    op(147, "i2s", NONE, fixed(1, 1), NOTHING, F::Next),
+    op(148, "lcmp", NONE, fixed(4, 1), NOTHING, F::Next),
+    op(149, "fcmpl", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(150, "fcmpg", NONE, fixed(2, 1), NOTHING, F::Next),
+    op(151, "dcmpl", NONE, fixed(4, 1), NOTHING, F::Next),
+    op(152, "dcmpg", NONE, fixed(4, 1), NOTHING, F::Next),
+    op(153, "ifeq", BRANCH, fixed(1, 0), NOTHING, F::Branch),
+    op(154, "ifne", BRANCH, fixed(1, 0), NOTHING, F::Branch),
+    op(155, "iflt", BRANCH, fixed(1, 0), NOTHING, F::Branch),
+    op(156, "ifge", BRANCH, fixed(1, 0), NOTHING, F::Branch),
+    op(157, "ifgt", BRANCH, fixed(1, 0), NOTHING, F::Branch),
+    op(158, "ifle", BRANCH, fixed(1, 0), NOTHING, F::Branch),
+    op(159, "if_icmpeq", BRANCH, fixed(2, 0), NOTHING, F::Branch),
+    op(160, "if_icmpne", BRANCH, fixed(2, 0), NOTHING, F::Branch),
+    op(161, "if_icmplt", BRANCH, fixed(2, 0), NOTHING, F::Branch),
+    op(162, "if_icmpge", BRANCH, fixed(2, 0), NOTHING, F::Branch),
+    op(163, "if_icmpgt", BRANCH, fixed(2, 0), NOTHING, F::Branch),
+    op(164, "if_icmple", BRANCH, fixed(2, 0), NOTHING, F::Branch),
+    op(165, "if_acmpeq", BRANCH, fixed(2, 0), NOTHING, F::Branch),
+    op(166, "if_acmpne", BRANCH, fixed(2, 0), NOTHING, F::Branch),
+    op(167, "goto", BRANCH, fixed(0, 0), NOTHING, F::Goto),
+    op(168, "jsr", BRANCH, fixed(0, 1), NOTHING, F::Jsr),
+    op(169, "ret", LOCAL, fixed(0, 0), NOTHING, F::Ret),
+    op(170, "tableswitch", &[O::Switch], fixed(1, 0), NOTHING, F::Switch),
+    op(171, "lookupswitch", &[O::Switch], fixed(1, 0), NOTHING, F::Switch),
+    op(172, "ireturn", NONE, fixed(1, 0), MONITOR, F::Return),
+    op(173, "lreturn", NONE, fixed(2, 0), MONITOR, F::Return),
+    op(174, "freturn", NONE, fixed(1, 0), MONITOR, F::Return),
+    op(175, "dreturn", NONE, fixed(2, 0), MONITOR, F::Return),
+    op(176, "areturn", NONE, fixed(1, 0), MONITOR, F::Return),
+    op(177, "return", NONE, fixed(0, 0), MONITOR, F::Return),
+    op(178, "getstatic", FIELD, S::Field { is_static: true, put: false }, LINKAGE, F::Next),
+    op(179, "putstatic", FIELD, S::Field { is_static: true, put: true }, LINKAGE, F::Next),
+    op(180, "getfield", FIELD, S::Field { is_static: false, put: false }, LINKAGE_NULL, F::Next),
+    op(181, "putfield", FIELD, S::Field { is_static: false, put: true }, LINKAGE_NULL, F::Next),
+    op(182, "invokevirtual", METHOD, S::Invoke { receiver: true }, ANYTHING, F::Next),
+    op(183, "invokespecial", ANY_METHOD, S::Invoke { receiver: true }, ANYTHING, F::Next),
+    op(184, "invokestatic", ANY_METHOD, S::Invoke { receiver: false }, ANYTHING, F::Next),
+    op(185, "invokeinterface", &[
+        O::Constant { wide: true, tags: &[tag::INTERFACE_METHOD_REF] },
+        O::Count,
+        O::Zero { bytes: 1 },
+    ], S::Invoke { receiver: true }, ANYTHING, F::Next),
+    op(186, "invokedynamic", &[
+        O::Constant { wide: true, tags: &[tag::INVOKE_DYNAMIC] },
+        O::Zero { bytes: 2 },
+    ], S::Invoke { receiver: false }, ANYTHING, F::Next),
+    op(187, "new", CLASS, fixed(0, 1), LINKAGE, F::Next),
+    op(188, "newarray", &[O::ArrayType], fixed(1, 1), Throws::Only(&["java/lang/NegativeArraySizeException"]), F::Next),
+    op(189, "anewarray", CLASS, fixed(1, 1), Throws::Only(&["java/lang/LinkageError", "java/lang/NegativeArraySizeException"]), F::Next),
+    op(190, "arraylength", NONE, fixed(1, 1), NULL, F::Next),
+    op(191, "athrow", NONE, fixed(1, 0), ANYTHING, F::Throw),
+    op(192, "checkcast", CLASS, fixed(1, 1), Throws::Only(&["java/lang/LinkageError", "java/lang/ClassCastException"]), F::Next),
+    op(193, "instanceof", CLASS, fixed(1, 1), LINKAGE, F::Next),
+    op(194, "monitorenter", NONE, fixed(1, 0), NULL, F::Next),
+    op(195, "monitorexit", NONE, fixed(1, 0), Throws::Only(&["java/lang/NullPointerException", "java/lang/IllegalMonitorStateException"]), F::Next),
+    op(196, "wide", &[O::Widened], S::Widened, NOTHING, F::Next),
+    op(197, "multianewarray", &[O::Constant { wide: true, tags: &[tag::CLASS] }, O::Count], S::Dimensions, Throws::Only(&[
+        "java/lang/LinkageError",
+        "java/lang/NegativeArraySizeException",
+    ]), F::Next),
+    op(198, "ifnull", BRANCH, fixed(1, 0), NOTHING, F::Branch),
+    op(199, "ifnonnull", BRANCH, fixed(1, 0), NOTHING, F::Branch),
+    op(200, "goto_w", BRANCH_W, fixed(0, 0), NOTHING, F::Goto),
+    op(201, "jsr_w", BRANCH_W, fixed(0, 1), NOTHING, F::Jsr),
+];
@@ -4,27 +4,130 @@ use std::fmt::Debug;
 use log::{error, warn};
 
 
-use crate::io::BufferReadable;
+use crate::io::{decode_modified_utf8, BufferReadable, BufferWritable};
 use crate::util::code_err::ClassParseError;
 
 use super::method_handle_kind::MethodHandleKind;
 
-#[derive(Debug, Clone)]
-pub struct ConstantPool(Vec<ConstantPoolEntry>);
+#[derive(Debug, Clone, Default)]
+pub struct ConstantPool(pub Vec<ConstantPoolEntry>);
 
 impl ConstantPool {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
     pub fn load<R: BufferReadable>(buf: &mut R) -> Result<Self, ClassParseError> {
-        let count_entries = buf.read_u2().expect("failed to read constant pool count")-1;
+        let count_entries = buf.read_u2()?;
         let mut cp: Vec<ConstantPoolEntry> = Vec::with_capacity(count_entries as usize);
-        for _ in 1..count_entries {
-            cp.push(ConstantPoolEntry::load(buf)?);
+        while cp.len() + 1 < count_entries as usize {
+            let entry = ConstantPoolEntry::load(buf)?;
+            let wide = entry.is_wide();
+            cp.push(entry);
+            if wide {
+                // Long and Double take up two slots, the second one is unusable
+                cp.push(ConstantPoolEntry::unusable());
+            }
         }
         Ok(ConstantPool(cp))
 
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.0.len() as u16 + 1)?;
+        for entry in &self.0 {
+            entry.write(buf)?;
+        }
+        Ok(())
+    }
     pub fn get_java_aligned(&self, index: usize) -> Option<&ConstantPoolEntry> {
+        if index == 0 {
+            return None;
+        }
         self.0.get(index-1)
     }
+    /// The `constant_pool_count` as written in the classfile, one more than the number of slots.
+    pub fn count(&self) -> usize {
+        self.0.len() + 1
+    }
+    /// Pushes an entry and returns its java aligned index.
+    pub fn push(&mut self, info: ConstantPoolInfo) -> u16 {
+        let index = self.0.len() as u16 + 1;
+        let entry = ConstantPoolEntry { tag: info.tag(), info };
+        let wide = entry.is_wide();
+        self.0.push(entry);
+        if wide {
+            self.0.push(ConstantPoolEntry::unusable());
+        }
+        index
+    }
+
+    fn bad_entry(&self, index: u16, expected: &str, for_what: &str) -> ClassParseError {
+        ClassParseError::BadValue {
+            expected: format!("{} in CP @ {} (jvm index: {})", expected, index as i32 - 1, index),
+            got: match self.get_java_aligned(index as usize) {
+                Some(entry) => format!("{:?}", entry.info),
+                None => "None @ lookup".to_string(),
+            },
+            for_what: for_what.to_string(),
+        }
+    }
+    pub fn get_info(&self, index: u16) -> Result<&ConstantPoolInfo, ClassParseError> {
+        match self.get_java_aligned(index as usize) {
+            Some(entry) => Ok(&entry.info),
+            None => Err(self.bad_entry(index, "any entry", "Constant Pool Lookup")),
+        }
+    }
+    pub fn get_utf8(&self, index: u16) -> Result<&str, ClassParseError> {
+        match self.get_java_aligned(index as usize).map(|e| &e.info) {
+            Some(ConstantPoolInfo::Utf8(val)) => Ok(val),
+            _ => Err(self.bad_entry(index, "Utf8", "Utf8 Lookup")),
+        }
+    }
+    /// The UTF-16 code units of a Utf8 constant, which unlike [Self::get_utf8] keeps the
+    /// unpaired surrogates a string literal may hold.
+    pub fn get_utf16(&self, index: u16) -> Result<Vec<u16>, ClassParseError> {
+        match self.get_java_aligned(index as usize).map(|e| &e.info) {
+            Some(ConstantPoolInfo::Utf8(val)) => Ok(val.encode_utf16().collect()),
+            Some(ConstantPoolInfo::Utf16(units)) => Ok(units.clone()),
+            _ => Err(self.bad_entry(index, "Utf8", "Utf16 Lookup")),
+        }
+    }
+    /// Resolves a `ClassRef` to its internal name, e.g. `java/lang/Object` or `[I`.
+    pub fn get_class_name(&self, index: u16) -> Result<&str, ClassParseError> {
+        match self.get_java_aligned(index as usize).map(|e| &e.info) {
+            Some(ConstantPoolInfo::ClassRef(name)) => self.get_utf8(*name),
+            _ => Err(self.bad_entry(index, "ClassRef", "Class Name Lookup")),
+        }
+    }
+    /// Resolves a `NameAndType` to its (name, descriptor) pair.
+    pub fn get_name_and_type(&self, index: u16) -> Result<(&str, &str), ClassParseError> {
+        match self.get_java_aligned(index as usize).map(|e| &e.info) {
+            Some(ConstantPoolInfo::NameAndType(name, descriptor)) => Ok((self.get_utf8(*name)?, self.get_utf8(*descriptor)?)),
+            _ => Err(self.bad_entry(index, "NameAndType", "Name And Type Lookup")),
+        }
+    }
+    /// Resolves a `FieldRef`, `MethodRef` or `InterfaceMethodRef`.
+    pub fn get_member_ref(&self, index: u16) -> Result<MemberRef, ClassParseError> {
+        let (class, name_and_type, interface) = match self.get_java_aligned(index as usize).map(|e| &e.info) {
+            Some(ConstantPoolInfo::FieldRef { class, name_and_type }) => (*class, *name_and_type, false),
+            Some(ConstantPoolInfo::MethodRef { class, name_and_type }) => (*class, *name_and_type, false),
+            Some(ConstantPoolInfo::InterfaceMethodRef { class, name_and_type }) => (*class, *name_and_type, true),
+            _ => return Err(self.bad_entry(index, "FieldRef, MethodRef or InterfaceMethodRef", "Member Lookup")),
+        };
+        let (name, descriptor) = self.get_name_and_type(name_and_type)?;
+        Ok(MemberRef {
+            class: self.get_class_name(class)?.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            interface,
+        })
+    }
+    /// Resolves a `MethodHandle` to its kind and the member it refers to.
+    pub fn get_method_handle(&self, index: u16) -> Result<(MethodHandleKind, MemberRef), ClassParseError> {
+        match self.get_java_aligned(index as usize).map(|e| &e.info) {
+            Some(ConstantPoolInfo::MethodHandle { kind, index }) => Ok((*kind, self.get_member_ref(*index)?)),
+            _ => Err(self.bad_entry(index, "MethodHandle", "Method Handle Lookup")),
+        }
+    }
 
     pub fn verify(&self) -> bool {
         todo!();
@@ -37,6 +140,12 @@ pub struct ConstantPoolEntry {
 } 
 
 impl ConstantPoolEntry {
+    fn unusable() -> Self {
+        Self { tag: 0, info: ConstantPoolInfo::Unusable }
+    }
+    pub fn is_wide(&self) -> bool {
+        matches!(self.info, ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_))
+    }
     pub fn load<R: BufferReadable>(buf: &mut R) -> Result<Self, ClassParseError> {
         let tag = buf.read_byte()?;
         let info = match tag {
@@ -44,7 +153,11 @@ impl ConstantPoolEntry {
                 let length = buf.read_u2()?;
                 let mut bytes = vec![0; length as usize];
                 buf.read_exact(&mut bytes).map_err(|e| ClassParseError::IOError(e))?;
-                ConstantPoolInfo::Utf8(String::from_utf8(bytes).expect("failed to convert bytes to string"))
+                let units = decode_modified_utf8(&bytes)?;
+                match String::from_utf16(&units) {
+                    Ok(value) => ConstantPoolInfo::Utf8(value),
+                    Err(_) => ConstantPoolInfo::Utf16(units),
+                }
             },
             3 => {
                 let bytes = buf.read_u4()?;
@@ -145,11 +258,63 @@ impl ConstantPoolEntry {
             info,
         })
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        if let ConstantPoolInfo::Unusable = self.info {
+            return Ok(());
+        }
+        buf.write_byte(self.tag)?;
+        match &self.info {
+            ConstantPoolInfo::Utf8(val) => buf.write_string(val)?,
+            ConstantPoolInfo::Utf16(units) => buf.write_utf16(units)?,
+            ConstantPoolInfo::Integer(val) => buf.write_u4(*val as u32)?,
+            ConstantPoolInfo::Float(val) => buf.write_u4(val.to_bits())?,
+            ConstantPoolInfo::Long(val) => buf.write_u8(*val as u64)?,
+            ConstantPoolInfo::Double(val) => buf.write_u8(val.to_bits())?,
+            ConstantPoolInfo::ClassRef(index)
+            | ConstantPoolInfo::StringRef(index)
+            | ConstantPoolInfo::MethodType(index)
+            | ConstantPoolInfo::Module(index)
+            | ConstantPoolInfo::Package(index) => buf.write_u2(*index)?,
+            ConstantPoolInfo::FieldRef { class, name_and_type }
+            | ConstantPoolInfo::MethodRef { class, name_and_type }
+            | ConstantPoolInfo::InterfaceMethodRef { class, name_and_type } => {
+                buf.write_u2(*class)?;
+                buf.write_u2(*name_and_type)?;
+            },
+            ConstantPoolInfo::NameAndType(name, descriptor) => {
+                buf.write_u2(*name)?;
+                buf.write_u2(*descriptor)?;
+            },
+            ConstantPoolInfo::MethodHandle { kind, index } => {
+                buf.write_byte(kind.to_ordinal())?;
+                buf.write_u2(*index)?;
+            },
+            ConstantPoolInfo::Dynamic { bootstrap_method_attr_index, name_and_type_index }
+            | ConstantPoolInfo::InvokeDynamic { bootstrap_method_attr_index, name_and_type_index } => {
+                buf.write_u2(*bootstrap_method_attr_index)?;
+                buf.write_u2(*name_and_type_index)?;
+            },
+            ConstantPoolInfo::Unusable => {},
+        }
+        Ok(())
+    }
+}
+
+/// A resolved `FieldRef`, `MethodRef` or `InterfaceMethodRef`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MemberRef {
+    pub class: String,
+    pub name: String,
+    pub descriptor: String,
+    pub interface: bool,
 }
 
 #[derive(Debug, Clone)]
 pub enum ConstantPoolInfo {
     Utf8(String), // 1
+    /// A Utf8 constant that isn't valid Unicode, like a string literal holding an unpaired
+    /// surrogate, as its UTF-16 code units
+    Utf16(Vec<u16>), // 1
     Integer(i32), // 3
     Float(f32), // 4
     Long(i64), // 5
@@ -186,4 +351,31 @@ pub enum ConstantPoolInfo {
     }, // 18
     Module(u16), // 19
     Package(u16), // 20
+    /// The slot following a Long or Double, which the JVM never uses
+    Unusable,
+}
+
+impl ConstantPoolInfo {
+    pub fn tag(&self) -> u8 {
+        match self {
+            ConstantPoolInfo::Utf8(_) | ConstantPoolInfo::Utf16(_) => 1,
+            ConstantPoolInfo::Integer(_) => 3,
+            ConstantPoolInfo::Float(_) => 4,
+            ConstantPoolInfo::Long(_) => 5,
+            ConstantPoolInfo::Double(_) => 6,
+            ConstantPoolInfo::ClassRef(_) => 7,
+            ConstantPoolInfo::StringRef(_) => 8,
+            ConstantPoolInfo::FieldRef { .. } => 9,
+            ConstantPoolInfo::MethodRef { .. } => 10,
+            ConstantPoolInfo::InterfaceMethodRef { .. } => 11,
+            ConstantPoolInfo::NameAndType(..) => 12,
+            ConstantPoolInfo::MethodHandle { .. } => 15,
+            ConstantPoolInfo::MethodType(_) => 16,
+            ConstantPoolInfo::Dynamic { .. } => 17,
+            ConstantPoolInfo::InvokeDynamic { .. } => 18,
+            ConstantPoolInfo::Module(_) => 19,
+            ConstantPoolInfo::Package(_) => 20,
+            ConstantPoolInfo::Unusable => 0,
+        }
+    }
 }
\ No newline at end of file
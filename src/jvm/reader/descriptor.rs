@@ -0,0 +1,180 @@
+use std::fmt::Display;
+
+use crate::util::code_err::ClassParseError;
+
+/// A parsed field descriptor, see JVMS §4.3.2
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Byte, // B
+    Char, // C
+    Double, // D
+    Float, // F
+    Int, // I
+    Long, // J
+    Short, // S
+    Boolean, // Z
+    /// Internal name of the class, e.g. `java/lang/String`
+    Object(String), // L<name>;
+    Array(Box<FieldType>), // [<component>
+}
+
+impl FieldType {
+    pub fn parse(descriptor: &str) -> Result<Self, ClassParseError> {
+        let mut pos = 0;
+        match Self::parse_from(descriptor.as_bytes(), &mut pos) {
+            Some(ty) if pos == descriptor.len() => Ok(ty),
+            _ => Err(bad_descriptor("field descriptor", descriptor)),
+        }
+    }
+    fn parse_from(bytes: &[u8], pos: &mut usize) -> Option<Self> {
+        let c = *bytes.get(*pos)?;
+        *pos += 1;
+        Some(match c {
+            b'B' => FieldType::Byte,
+            b'C' => FieldType::Char,
+            b'D' => FieldType::Double,
+            b'F' => FieldType::Float,
+            b'I' => FieldType::Int,
+            b'J' => FieldType::Long,
+            b'S' => FieldType::Short,
+            b'Z' => FieldType::Boolean,
+            b'L' => {
+                let start = *pos;
+                while *bytes.get(*pos)? != b';' {
+                    *pos += 1;
+                }
+                let name = std::str::from_utf8(&bytes[start..*pos]).ok()?;
+                *pos += 1;
+                if name.is_empty() {
+                    return None;
+                }
+                FieldType::Object(name.to_string())
+            },
+            b'[' => FieldType::Array(Box::new(Self::parse_from(bytes, pos)?)),
+            _ => return None,
+        })
+    }
+    /// Parses the name stored in a `ClassRef`, which is either an internal name or an array descriptor.
+    pub fn from_class_name(name: &str) -> Result<Self, ClassParseError> {
+        if name.starts_with('[') {
+            Self::parse(name)
+        } else {
+            Ok(FieldType::Object(name.to_string()))
+        }
+    }
+    /// The number of local variable or operand stack slots a value of this type takes.
+    pub fn slots(&self) -> u16 {
+        match self {
+            FieldType::Long | FieldType::Double => 2,
+            _ => 1,
+        }
+    }
+    pub fn is_reference(&self) -> bool {
+        matches!(self, FieldType::Object(_) | FieldType::Array(_))
+    }
+    /// The class of an object, or of the innermost elements of an array of objects.
+    pub fn element_class(&self) -> Option<&str> {
+        match self {
+            FieldType::Object(name) => Some(name),
+            FieldType::Array(component) => component.element_class(),
+            _ => None,
+        }
+    }
+    /// The name a `ClassRef` to this type would carry: the internal name for classes, the descriptor for arrays.
+    pub fn class_name(&self) -> Option<String> {
+        match self {
+            FieldType::Object(name) => Some(name.clone()),
+            FieldType::Array(_) => Some(self.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl Display for FieldType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldType::Byte => write!(f, "B"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Double => write!(f, "D"),
+            FieldType::Float => write!(f, "F"),
+            FieldType::Int => write!(f, "I"),
+            FieldType::Long => write!(f, "J"),
+            FieldType::Short => write!(f, "S"),
+            FieldType::Boolean => write!(f, "Z"),
+            FieldType::Object(name) => write!(f, "L{};", name),
+            FieldType::Array(component) => write!(f, "[{}", component),
+        }
+    }
+}
+
+/// A parsed method descriptor, see JVMS §4.3.3
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub params: Vec<FieldType>,
+    /// `None` for `void`
+    pub ret: Option<FieldType>,
+}
+
+impl MethodDescriptor {
+    pub fn parse(descriptor: &str) -> Result<Self, ClassParseError> {
+        let bytes = descriptor.as_bytes();
+        if bytes.first() != Some(&b'(') {
+            return Err(bad_descriptor("method descriptor", descriptor));
+        }
+        let mut pos = 1;
+        let mut params = Vec::new();
+        while bytes.get(pos) != Some(&b')') {
+            match FieldType::parse_from(bytes, &mut pos) {
+                Some(ty) => params.push(ty),
+                None => return Err(bad_descriptor("method descriptor", descriptor)),
+            }
+        }
+        pos += 1;
+        let ret = if bytes.get(pos) == Some(&b'V') {
+            pos += 1;
+            None
+        } else {
+            match FieldType::parse_from(bytes, &mut pos) {
+                Some(ty) => Some(ty),
+                None => return Err(bad_descriptor("method descriptor", descriptor)),
+            }
+        };
+        if pos != bytes.len() {
+            return Err(bad_descriptor("method descriptor", descriptor));
+        }
+        Ok(Self { params, ret })
+    }
+    /// Slots taken by the parameters, not counting `this`.
+    pub fn param_slots(&self) -> u16 {
+        self.params.iter().map(|p| p.slots()).sum()
+    }
+    pub fn ret_slots(&self) -> u16 {
+        self.ret.as_ref().map(|r| r.slots()).unwrap_or(0)
+    }
+    /// Classes named by the parameter and return types.
+    pub fn classes(&self) -> impl Iterator<Item = &str> {
+        self.params.iter().chain(self.ret.iter()).filter_map(FieldType::element_class)
+    }
+}
+
+impl Display for MethodDescriptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for param in &self.params {
+            write!(f, "{}", param)?;
+        }
+        write!(f, ")")?;
+        match &self.ret {
+            Some(ret) => write!(f, "{}", ret),
+            None => write!(f, "V"),
+        }
+    }
+}
+
+fn bad_descriptor(expected: &str, got: &str) -> ClassParseError {
+    ClassParseError::BadValue {
+        expected: expected.to_string(),
+        got: got.to_string(),
+        for_what: "Descriptor".to_string(),
+    }
+}
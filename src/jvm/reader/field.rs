@@ -1,6 +1,6 @@
-use crate::{io::BufferReadable, util::code_err::ClassParseError};
+use crate::{io::{BufferReadable, BufferWritable}, util::code_err::ClassParseError};
 
-use super::attribute::Attributes;
+use super::{access_flags::AccessFlags, attribute::Attributes, constant_pool::ConstantPool};
 
 #[derive(Debug)]
 pub struct Fields(pub Vec<FieldInfo>);
@@ -14,6 +14,13 @@ impl Fields {
         }
         Ok(Self(fields))
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.0.len() as u16)?;
+        for field in &self.0 {
+            field.write(buf)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -36,5 +43,35 @@ impl FieldInfo {
             attributes,
         })
     }
+    pub fn name<'a>(&self, constant_pool: &'a ConstantPool) -> Result<&'a str, ClassParseError> {
+        constant_pool.get_utf8(self.name_index)
+    }
+    pub fn descriptor<'a>(&self, constant_pool: &'a ConstantPool) -> Result<&'a str, ClassParseError> {
+        constant_pool.get_utf8(self.descriptor_index)
+    }
+    pub fn has_flag(&self, flag: AccessFlags) -> bool {
+        self.access_flags & flag as u16 != 0
+    }
+    /// Constant pool index of the value in the `ConstantValue` attribute, if the field has one.
+    pub fn constant_value(&self, constant_pool: &ConstantPool) -> Result<Option<u16>, ClassParseError> {
+        let attribute = match self.attributes.find_by_name("ConstantValue", constant_pool)? {
+            Some(attribute) => attribute,
+            None => return Ok(None),
+        };
+        match attribute.info[..] {
+            [high, low] => Ok(Some(u16::from_be_bytes([high, low]))),
+            _ => Err(ClassParseError::BadValue {
+                expected: "2 bytes".to_string(),
+                got: format!("{} bytes", attribute.info.len()),
+                for_what: "ConstantValue attribute".to_string(),
+            }),
+        }
+    }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.access_flags)?;
+        buf.write_u2(self.name_index)?;
+        buf.write_u2(self.descriptor_index)?;
+        self.attributes.write(buf)
+    }
 }
 
@@ -1,7 +1,7 @@
-use crate::{io::BufferReadable, util::code_err::ClassParseError};
+use crate::{io::{BufferReadable, BufferWritable}, util::code_err::ClassParseError};
 
 #[derive(Debug)]
-pub struct Interfaces(Vec<u16>);
+pub struct Interfaces(pub Vec<u16>);
 
 impl Interfaces {
     pub fn load<R: BufferReadable>(buf: &mut R) -> Result<Self, ClassParseError> {
@@ -12,4 +12,11 @@ impl Interfaces {
         }
         Ok(Self(interfaces))
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.0.len() as u16)?;
+        for interface in &self.0 {
+            buf.write_u2(*interface)?;
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
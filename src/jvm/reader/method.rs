@@ -1,6 +1,6 @@
-use crate::{io::{BufferReadable, Prebuffer}, util::code_err::{ClassParseError, CodeParseError}};
+use crate::{io::{BufferReadable, BufferWritable, Prebuffer}, util::code_err::{ClassParseError, CodeParseError}};
 
-use super::{attribute::Attributes, code::block::CodeBlock, constant_pool::ConstantPool};
+use super::{access_flags::AccessFlags, attribute::Attributes, code::block::CodeBlock, constant_pool::ConstantPool};
 
 #[derive(Debug)]
 pub struct Methods(pub Vec<MethodInfo>);
@@ -14,6 +14,13 @@ impl Methods {
         }
         Ok(Self(methods))
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.0.len() as u16)?;
+        for method in &self.0 {
+            method.write(buf)?;
+        }
+        Ok(())
+    }
     pub fn load_code(&mut self, constant_pool: &ConstantPool) -> Result<(), ClassParseError> {
         for method in &mut self.0 {
             method.load_code(constant_pool)?;
@@ -49,6 +56,10 @@ impl MethodInfo {
             Some(attr) => {
                 self.code = Some(CodeBlock::load(&mut Prebuffer::copy_from_vec(&attr.info))?);
             }
+            None if self.access_flags & (AccessFlags::Abstract as u16 | AccessFlags::Native as u16) != 0 => {
+                // abstract and native methods have no code to load
+                self.code = None;
+            }
             None => {
                 return Err(
                     ClassParseError::CodeParseError {
@@ -60,5 +71,22 @@ impl MethodInfo {
         }
         Ok(())
     }
+    pub fn name<'a>(&self, constant_pool: &'a ConstantPool) -> Result<&'a str, ClassParseError> {
+        constant_pool.get_utf8(self.name_index)
+    }
+    pub fn descriptor<'a>(&self, constant_pool: &'a ConstantPool) -> Result<&'a str, ClassParseError> {
+        constant_pool.get_utf8(self.descriptor_index)
+    }
+    pub fn has_flag(&self, flag: AccessFlags) -> bool {
+        self.access_flags & flag as u16 != 0
+    }
+    /// Writes the method as it is described by its attributes, a decoded `code` block
+    /// is expected to also be present as a `Code` attribute.
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.access_flags)?;
+        buf.write_u2(self.name_index)?;
+        buf.write_u2(self.descriptor_index)?;
+        self.attributes.write(buf)
+    }
     
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MethodHandleKind {
     GetField,
     GetStatic,
@@ -11,6 +11,20 @@ pub enum MethodHandleKind {
     InvokeInterface,
 }
 impl MethodHandleKind {
+    /// The name the assembler writes the kind with.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MethodHandleKind::GetField => "getfield",
+            MethodHandleKind::GetStatic => "getstatic",
+            MethodHandleKind::PutField => "putfield",
+            MethodHandleKind::PutStatic => "putstatic",
+            MethodHandleKind::InvokeVirtual => "invokevirtual",
+            MethodHandleKind::InvokeStatic => "invokestatic",
+            MethodHandleKind::InvokeSpecial => "invokespecial",
+            MethodHandleKind::NewInvokeSpecial => "newinvokespecial",
+            MethodHandleKind::InvokeInterface => "invokeinterface",
+        }
+    }
     pub fn to_ordinal(&self) -> u8 {
         match self {
             MethodHandleKind::GetField => 1,
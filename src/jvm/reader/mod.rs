@@ -6,5 +6,9 @@ pub mod method_handle_kind;
 pub mod field;
 pub mod method;
 pub mod attribute;
+pub mod bootstrap;
 pub mod interface;
-pub mod code;
\ No newline at end of file
+pub mod code;
+pub mod descriptor;
+pub mod signature;
+pub mod stack_map;
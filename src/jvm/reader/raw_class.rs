@@ -1,22 +1,22 @@
-use crate::{io::BufferReadable, util::code_err::ClassParseError};
+use crate::{io::{BufferReadable, BufferWritable}, util::code_err::ClassParseError};
 
 use super::{
-    constant_pool::{ConstantPool}, interface::Interfaces, field::Fields, method::Methods, attribute::Attributes,
+    access_flags::AccessFlags, constant_pool::{ConstantPool}, interface::Interfaces, field::{FieldInfo, Fields}, method::{MethodInfo, Methods}, attribute::Attributes,
     //  Fileish, FileReadUtility
     };
 
 
 #[derive(Debug)]
 pub struct RawClass {
-    access_flags: u16,
-    this_class: u16,
-    super_class: u16,
+    pub access_flags: u16,
+    pub this_class: u16,
+    pub super_class: u16,
 
-    cp: ConstantPool,
-    interfaces: Interfaces,
-    fields: Fields,
-    methods: Methods,
-    attributes: Attributes,
+    pub cp: ConstantPool,
+    pub interfaces: Interfaces,
+    pub fields: Fields,
+    pub methods: Methods,
+    pub attributes: Attributes,
 
 }
 
@@ -49,6 +49,58 @@ impl RawClass {
             attributes,
         })?)
     }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        self.cp.write(buf)?;
+        buf.write_u2(self.access_flags)?;
+        buf.write_u2(self.this_class)?;
+        buf.write_u2(self.super_class)?;
+        self.interfaces.write(buf)?;
+        self.fields.write(buf)?;
+        self.methods.write(buf)?;
+        self.attributes.write(buf)
+    }
+    /// Internal name of this class, e.g. `java/lang/String`.
+    pub fn name(&self) -> Result<&str, ClassParseError> {
+        self.cp.get_class_name(self.this_class)
+    }
+    /// Internal name of the super class, `None` only for `java/lang/Object` and modules.
+    pub fn super_name(&self) -> Result<Option<&str>, ClassParseError> {
+        match self.super_class {
+            0 => Ok(None),
+            index => self.cp.get_class_name(index).map(Some),
+        }
+    }
+    pub fn interface_names(&self) -> Result<Vec<&str>, ClassParseError> {
+        self.interfaces.0.iter().map(|index| self.cp.get_class_name(*index)).collect()
+    }
+    pub fn has_flag(&self, flag: AccessFlags) -> bool {
+        self.access_flags & flag as u16 != 0
+    }
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&MethodInfo> {
+        self.methods.0.iter().find(|method| {
+            method.name(&self.cp).is_ok_and(|n| n == name) && method.descriptor(&self.cp).is_ok_and(|d| d == descriptor)
+        })
+    }
+    pub fn find_field(&self, name: &str, descriptor: &str) -> Option<&FieldInfo> {
+        self.fields.0.iter().find(|field| {
+            field.name(&self.cp).is_ok_and(|n| n == name) && field.descriptor(&self.cp).is_ok_and(|d| d == descriptor)
+        })
+    }
+    /// The file the class was compiled from, as its `SourceFile` attribute names it.
+    pub fn source_file(&self) -> Result<Option<&str>, ClassParseError> {
+        let attribute = match self.attributes.find_by_name("SourceFile", &self.cp)? {
+            Some(attribute) => attribute,
+            None => return Ok(None),
+        };
+        match attribute.info[..] {
+            [high, low] => self.cp.get_utf8(u16::from_be_bytes([high, low])).map(Some),
+            _ => Err(ClassParseError::BadValue {
+                expected: "2 bytes".to_string(),
+                got: format!("{} bytes", attribute.info.len()),
+                for_what: "SourceFile attribute".to_string(),
+            }),
+        }
+    }
     fn verify(self) -> Result<Self, ClassParseError> {
         // TODO: Verify class
         Ok(self)
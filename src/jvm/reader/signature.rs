@@ -0,0 +1,122 @@
+use crate::util::code_err::ClassParseError;
+
+/// Collects the classes named by a generic `Signature` attribute (JVMS §4.7.9.1), for any of
+/// the class, method and field forms. Inner classes come out under their binary name, e.g.
+/// `java/util/Map$Entry` for `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`.
+pub fn signature_classes(signature: &str) -> Result<Vec<String>, ClassParseError> {
+    let mut scanner = Scanner { bytes: signature.as_bytes(), pos: 0, classes: Vec::new() };
+    match scanner.signature() {
+        Some(()) if scanner.pos == scanner.bytes.len() => Ok(scanner.classes),
+        _ => Err(ClassParseError::BadValue {
+            expected: "generic signature".to_string(),
+            got: signature.to_string(),
+            for_what: "Signature".to_string(),
+        }),
+    }
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    classes: Vec<String>,
+}
+
+impl<'a> Scanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+    fn eat(&mut self, c: u8) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+    /// Consumes an identifier up to, but not including, one of `ends`.
+    fn identifier(&mut self, ends: &[u8]) -> Option<&'a str> {
+        let start = self.pos;
+        while !ends.contains(&self.peek()?) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).ok().filter(|name| !name.is_empty())
+    }
+    fn signature(&mut self) -> Option<()> {
+        if self.eat(b'<') {
+            while !self.eat(b'>') {
+                self.identifier(b":")?;
+                // the class bound may be empty, interface bounds follow it
+                while self.eat(b':') {
+                    if !matches!(self.peek()?, b':' | b'>') {
+                        self.reference()?;
+                    }
+                }
+            }
+        }
+        if self.eat(b'(') {
+            while !self.eat(b')') {
+                self.java_type()?;
+            }
+            if !self.eat(b'V') {
+                self.java_type()?;
+            }
+            while self.eat(b'^') {
+                self.reference()?;
+            }
+            return Some(());
+        }
+        // a class signature is a super class followed by interfaces, a field signature is one type
+        while self.pos < self.bytes.len() {
+            self.reference()?;
+        }
+        Some(())
+    }
+    fn java_type(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'B' | b'C' | b'D' | b'F' | b'I' | b'J' | b'S' | b'Z' => {
+                self.pos += 1;
+                Some(())
+            },
+            _ => self.reference(),
+        }
+    }
+    fn reference(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'L' => {
+                self.pos += 1;
+                let mut name = self.identifier(b"<.;")?.to_string();
+                loop {
+                    if self.eat(b'<') {
+                        while !self.eat(b'>') {
+                            if !self.eat(b'*') {
+                                self.eat(b'+');
+                                self.eat(b'-');
+                                self.reference()?;
+                            }
+                        }
+                    }
+                    if self.eat(b'.') {
+                        name.push('$');
+                        name.push_str(self.identifier(b"<.;")?);
+                    } else if self.eat(b';') {
+                        break;
+                    } else {
+                        return None;
+                    }
+                }
+                self.classes.push(name);
+                Some(())
+            },
+            b'T' => {
+                self.pos += 1;
+                self.identifier(b";")?;
+                self.eat(b';');
+                Some(())
+            },
+            b'[' => {
+                self.pos += 1;
+                self.java_type()
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{io::{BufferReadable, BufferWritable}, util::code_err::ClassParseError};
+
+// https://docs.oracle.com/javase/specs/jvms/se17/html/jvms-4.html#jvms-4.7.4
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationType {
+    Top, // 0
+    Integer, // 1
+    Float, // 2
+    Double, // 3
+    Long, // 4
+    Null, // 5
+    UninitializedThis, // 6
+    /// <Self>.0 is the index of a ClassRef in the constant pool
+    Object(u16), // 7
+    /// <Self>.0 is the offset of the `new` instruction that created the value
+    Uninitialized(u16), // 8
+}
+
+impl VerificationType {
+    pub fn load<R: BufferReadable>(buf: &mut R) -> Result<Self, ClassParseError> {
+        Ok(match buf.read_byte()? {
+            0 => VerificationType::Top,
+            1 => VerificationType::Integer,
+            2 => VerificationType::Float,
+            3 => VerificationType::Double,
+            4 => VerificationType::Long,
+            5 => VerificationType::Null,
+            6 => VerificationType::UninitializedThis,
+            7 => VerificationType::Object(buf.read_u2()?),
+            8 => VerificationType::Uninitialized(buf.read_u2()?),
+            x => return Err(ClassParseError::BadValue {
+                expected: "verification type tag (0-8)".to_string(),
+                got: x.to_string(),
+                for_what: "StackMapTable".to_string(),
+            }),
+        })
+    }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        match self {
+            VerificationType::Top => buf.write_byte(0),
+            VerificationType::Integer => buf.write_byte(1),
+            VerificationType::Float => buf.write_byte(2),
+            VerificationType::Double => buf.write_byte(3),
+            VerificationType::Long => buf.write_byte(4),
+            VerificationType::Null => buf.write_byte(5),
+            VerificationType::UninitializedThis => buf.write_byte(6),
+            VerificationType::Object(index) => {
+                buf.write_byte(7)?;
+                buf.write_u2(*index)
+            },
+            VerificationType::Uninitialized(offset) => {
+                buf.write_byte(8)?;
+                buf.write_u2(*offset)
+            },
+        }
+    }
+    /// Long and Double take up two local variable slots, but only one entry in a frame.
+    pub fn is_wide(&self) -> bool {
+        matches!(self, VerificationType::Long | VerificationType::Double)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StackMapFrame {
+    Same { offset_delta: u16 }, // 0-63, 251
+    SameLocals1StackItem { offset_delta: u16, stack: VerificationType }, // 64-127, 247
+    Chop { offset_delta: u16, k: u8 }, // 248-250
+    Append { offset_delta: u16, locals: Vec<VerificationType> }, // 252-254
+    Full { offset_delta: u16, locals: Vec<VerificationType>, stack: Vec<VerificationType> }, // 255
+}
+
+impl StackMapFrame {
+    pub fn load<R: BufferReadable>(buf: &mut R) -> Result<Self, ClassParseError> {
+        let frame_type = buf.read_byte()?;
+        Ok(match frame_type {
+            0..=63 => StackMapFrame::Same { offset_delta: frame_type as u16 },
+            64..=127 => StackMapFrame::SameLocals1StackItem {
+                offset_delta: frame_type as u16 - 64,
+                stack: VerificationType::load(buf)?,
+            },
+            247 => StackMapFrame::SameLocals1StackItem {
+                offset_delta: buf.read_u2()?,
+                stack: VerificationType::load(buf)?,
+            },
+            248..=250 => StackMapFrame::Chop { offset_delta: buf.read_u2()?, k: 251 - frame_type },
+            251 => StackMapFrame::Same { offset_delta: buf.read_u2()? },
+            252..=254 => {
+                let offset_delta = buf.read_u2()?;
+                let mut locals = Vec::new();
+                for _ in 0..(frame_type - 251) {
+                    locals.push(VerificationType::load(buf)?);
+                }
+                StackMapFrame::Append { offset_delta, locals }
+            },
+            255 => {
+                let offset_delta = buf.read_u2()?;
+                let mut locals = Vec::new();
+                for _ in 0..buf.read_u2()? {
+                    locals.push(VerificationType::load(buf)?);
+                }
+                let mut stack = Vec::new();
+                for _ in 0..buf.read_u2()? {
+                    stack.push(VerificationType::load(buf)?);
+                }
+                StackMapFrame::Full { offset_delta, locals, stack }
+            },
+            x => return Err(ClassParseError::BadValue {
+                expected: "stack map frame type".to_string(),
+                got: x.to_string(),
+                for_what: "StackMapTable".to_string(),
+            }),
+        })
+    }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        match self {
+            StackMapFrame::Same { offset_delta } if *offset_delta <= 63 => buf.write_byte(*offset_delta as u8),
+            StackMapFrame::Same { offset_delta } => {
+                buf.write_byte(251)?;
+                buf.write_u2(*offset_delta)
+            },
+            StackMapFrame::SameLocals1StackItem { offset_delta, stack } => {
+                if *offset_delta <= 63 {
+                    buf.write_byte(64 + *offset_delta as u8)?;
+                } else {
+                    buf.write_byte(247)?;
+                    buf.write_u2(*offset_delta)?;
+                }
+                stack.write(buf)
+            },
+            StackMapFrame::Chop { offset_delta, k } => {
+                buf.write_byte(251 - k)?;
+                buf.write_u2(*offset_delta)
+            },
+            StackMapFrame::Append { offset_delta, locals } => {
+                buf.write_byte(251 + locals.len() as u8)?;
+                buf.write_u2(*offset_delta)?;
+                for local in locals {
+                    local.write(buf)?;
+                }
+                Ok(())
+            },
+            StackMapFrame::Full { offset_delta, locals, stack } => {
+                buf.write_byte(255)?;
+                buf.write_u2(*offset_delta)?;
+                buf.write_u2(locals.len() as u16)?;
+                for local in locals {
+                    local.write(buf)?;
+                }
+                buf.write_u2(stack.len() as u16)?;
+                for item in stack {
+                    item.write(buf)?;
+                }
+                Ok(())
+            },
+        }
+    }
+    pub fn offset_delta(&self) -> u16 {
+        match self {
+            StackMapFrame::Same { offset_delta }
+            | StackMapFrame::SameLocals1StackItem { offset_delta, .. }
+            | StackMapFrame::Chop { offset_delta, .. }
+            | StackMapFrame::Append { offset_delta, .. }
+            | StackMapFrame::Full { offset_delta, .. } => *offset_delta,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StackMapTable(pub Vec<StackMapFrame>);
+
+impl StackMapTable {
+    pub fn load<R: BufferReadable>(buf: &mut R) -> Result<Self, ClassParseError> {
+        let number_of_entries = buf.read_u2()?;
+        let mut frames = Vec::new();
+        for _ in 0..number_of_entries {
+            frames.push(StackMapFrame::load(buf)?);
+        }
+        Ok(Self(frames))
+    }
+    pub fn write<W: BufferWritable>(&self, buf: &mut W) -> Result<(), ClassParseError> {
+        buf.write_u2(self.0.len() as u16)?;
+        for frame in &self.0 {
+            frame.write(buf)?;
+        }
+        Ok(())
+    }
+    /// Builds the compressed table from full frames, given as (offset, locals, stack).
+    /// `initial` are the locals of the implicit frame derived from the method descriptor.
+    /// Locals and stack are one entry per value, Long and Double are not followed by Top.
+    pub fn compress(initial: &[VerificationType], frames: &[(u16, Vec<VerificationType>, Vec<VerificationType>)]) -> Self {
+        let mut table = Vec::new();
+        let mut previous_locals = initial.to_vec();
+        let mut previous_offset: Option<u16> = None;
+        for (offset, locals, stack) in frames {
+            let offset_delta = match previous_offset {
+                Some(previous) => offset - previous - 1,
+                None => *offset,
+            };
+            let frame = if stack.is_empty() && *locals == previous_locals {
+                StackMapFrame::Same { offset_delta }
+            } else if stack.len() == 1 && *locals == previous_locals {
+                StackMapFrame::SameLocals1StackItem { offset_delta, stack: stack[0].clone() }
+            } else if stack.is_empty() && locals.len() < previous_locals.len()
+                && previous_locals.len() - locals.len() <= 3
+                && previous_locals.starts_with(locals) {
+                StackMapFrame::Chop { offset_delta, k: (previous_locals.len() - locals.len()) as u8 }
+            } else if stack.is_empty() && locals.len() > previous_locals.len()
+                && locals.len() - previous_locals.len() <= 3
+                && locals.starts_with(&previous_locals) {
+                StackMapFrame::Append { offset_delta, locals: locals[previous_locals.len()..].to_vec() }
+            } else {
+                StackMapFrame::Full { offset_delta, locals: locals.clone(), stack: stack.clone() }
+            };
+            table.push(frame);
+            previous_locals = locals.clone();
+            previous_offset = Some(*offset);
+        }
+        Self(table)
+    }
+}
@@ -0,0 +1,449 @@
+//! A minimal class library, enough to run small programs without a JDK.
+//!
+//! The classes are assembled from the sources next to this module, and their native methods
+//! are bound by [register_natives]. A few natives need the heap and are run by the
+//! interpreter itself: `Object.getClass`, `Class.getName`, `System.arraycopy` and
+//! `System.identityHashCode`, as are those of `Thread` and the monitor methods of `Object`,
+//! which need its threads. Exceptions only get the usual constructors and
+//! `Throwable`'s methods, so they are generated rather than written out.
+mod unicode;
+
+use std::{cmp::Ordering, io::Write, sync::{Arc, Mutex}, time::{Instant, SystemTime, UNIX_EPOCH}};
+
+use crate::{
+    core::typings::{j_string::JString, j_value::JValue},
+    jvm::{analysis::classpath::ClassPath, asm::assemble, reader::classfile::ClassFile},
+    util::code_err::JavaThrowable,
+};
+
+use super::{interpreter::java_float_string, native::NativeRegistry};
+
+/// [ClassFile::classpath] of the library classes.
+pub const ORIGIN: &str = "<classlib>";
+
+const SOURCES: [&str; 26] = [
+    include_str!("java/lang/Object.j"),
+    include_str!("java/lang/Class.j"),
+    include_str!("java/lang/CharSequence.j"),
+    include_str!("java/lang/String.j"),
+    include_str!("java/lang/StringBuilder.j"),
+    include_str!("java/lang/System.j"),
+    include_str!("java/lang/Math.j"),
+    include_str!("java/lang/Number.j"),
+    include_str!("java/lang/Integer.j"),
+    include_str!("java/lang/Long.j"),
+    include_str!("java/lang/Double.j"),
+    include_str!("java/lang/Boolean.j"),
+    include_str!("java/lang/Character.j"),
+    include_str!("java/lang/Throwable.j"),
+    include_str!("java/lang/Runnable.j"),
+    include_str!("java/lang/Thread.j"),
+    include_str!("java/lang/Iterable.j"),
+    include_str!("java/io/PrintStream.j"),
+    include_str!("java/util/Iterator.j"),
+    include_str!("java/util/Collection.j"),
+    include_str!("java/util/List.j"),
+    include_str!("java/util/ArrayList.j"),
+    include_str!("java/util/ArrayList$Itr.j"),
+    include_str!("java/util/Map.j"),
+    include_str!("java/util/HashMap.j"),
+    include_str!("java/util/HashMap$Node.j"),
+];
+
+/// Exceptions and errors with the class they extend, super classes first.
+const THROWABLES: [(&str, &str); 33] = [
+    ("java/lang/Exception", "java/lang/Throwable"),
+    ("java/lang/Error", "java/lang/Throwable"),
+    ("java/lang/RuntimeException", "java/lang/Exception"),
+    ("java/lang/InterruptedException", "java/lang/Exception"),
+    ("java/lang/CloneNotSupportedException", "java/lang/Exception"),
+    ("java/io/IOException", "java/lang/Exception"),
+    ("java/lang/IllegalArgumentException", "java/lang/RuntimeException"),
+    ("java/lang/NumberFormatException", "java/lang/IllegalArgumentException"),
+    ("java/lang/IllegalThreadStateException", "java/lang/IllegalArgumentException"),
+    ("java/lang/IllegalStateException", "java/lang/RuntimeException"),
+    ("java/lang/IllegalMonitorStateException", "java/lang/RuntimeException"),
+    ("java/lang/ArithmeticException", "java/lang/RuntimeException"),
+    ("java/lang/NullPointerException", "java/lang/RuntimeException"),
+    ("java/lang/ClassCastException", "java/lang/RuntimeException"),
+    ("java/lang/ArrayStoreException", "java/lang/RuntimeException"),
+    ("java/lang/NegativeArraySizeException", "java/lang/RuntimeException"),
+    ("java/lang/IndexOutOfBoundsException", "java/lang/RuntimeException"),
+    ("java/lang/ArrayIndexOutOfBoundsException", "java/lang/IndexOutOfBoundsException"),
+    ("java/lang/StringIndexOutOfBoundsException", "java/lang/IndexOutOfBoundsException"),
+    ("java/lang/UnsupportedOperationException", "java/lang/RuntimeException"),
+    ("java/util/NoSuchElementException", "java/lang/RuntimeException"),
+    ("java/lang/LinkageError", "java/lang/Error"),
+    ("java/lang/NoClassDefFoundError", "java/lang/LinkageError"),
+    ("java/lang/UnsatisfiedLinkError", "java/lang/LinkageError"),
+    ("java/lang/ExceptionInInitializerError", "java/lang/LinkageError"),
+    ("java/lang/IncompatibleClassChangeError", "java/lang/LinkageError"),
+    ("java/lang/AbstractMethodError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/InstantiationError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/NoSuchFieldError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/NoSuchMethodError", "java/lang/IncompatibleClassChangeError"),
+    ("java/lang/VirtualMachineError", "java/lang/Error"),
+    ("java/lang/OutOfMemoryError", "java/lang/VirtualMachineError"),
+    ("java/lang/StackOverflowError", "java/lang/VirtualMachineError"),
+];
+
+/// The source of an exception class with the constructors of `Throwable`.
+fn throwable_source(name: &str, super_name: &str) -> String {
+    let mut source = format!(".class public {}\n.super {}\n", name, super_name);
+    for (descriptor, loads) in [("()V", ""), ("(Ljava/lang/String;)V", "aload_1\n"), ("(Ljava/lang/String;Ljava/lang/Throwable;)V", "aload_1\naload_2\n"), ("(Ljava/lang/Throwable;)V", "aload_1\n")] {
+        source += &format!(
+            ".method public <init>{descriptor}\naload_0\n{loads}invokespecial {super_name}/<init>{descriptor}\nreturn\n.end method\n",
+        );
+    }
+    source
+}
+
+/// The classes of the library.
+pub fn classes() -> impl Iterator<Item = ClassFile> {
+    let generated = THROWABLES.iter().map(|(name, super_name)| throwable_source(name, super_name));
+    SOURCES.iter().map(|source| source.to_string()).chain(generated).map(|source| {
+        let mut class = assemble(&source).expect("the class library assembles");
+        class.path = format!("{}/{}.class", ORIGIN, class.class.name().unwrap_or_default());
+        class.classpath = ORIGIN.to_string();
+        class
+    })
+}
+
+/// A class path holding the library, for the classes of a program to be added to. As the
+/// first definition of a class wins, a program can't replace the library's classes, like
+/// with the bootstrap class path of the JVM.
+pub fn bootstrap_classpath() -> ClassPath {
+    let mut classpath = ClassPath::new();
+    for class in classes() {
+        classpath.add(class).expect("the class library has valid names");
+    }
+    classpath
+}
+
+/// The natives are only called with arguments of the types they declare.
+fn mismatch(args: &[JValue]) -> JavaThrowable {
+    JavaThrowable::new("java/lang/InternalError", format!("native called with {:?}", args))
+}
+
+fn null_pointer() -> JavaThrowable {
+    JavaThrowable::new("java/lang/NullPointerException", None)
+}
+
+fn number_format(input: &str) -> JavaThrowable {
+    JavaThrowable::new("java/lang/NumberFormatException", format!("For input string: \"{}\"", input))
+}
+
+fn string(value: impl Into<JString>) -> JValue {
+    JValue::String(value.into())
+}
+
+/// Where `needle` first appears in `haystack` from `from` on.
+fn find(haystack: &[u16], needle: &[u16], from: usize) -> Option<usize> {
+    (from..=haystack.len().checked_sub(needle.len())?).find(|start| haystack[*start..].starts_with(needle))
+}
+
+/// Maps the characters of `value`, leaving unpaired surrogates as they are.
+fn map_chars<I: IntoIterator<Item = char>>(value: &JString, f: impl Fn(char) -> I) -> JString {
+    let mut units = Vec::with_capacity(value.len());
+    for decoded in char::decode_utf16(value.units()) {
+        match decoded {
+            Ok(c) => f(c).into_iter().for_each(|c| units.extend(c.encode_utf16(&mut [0; 2]).iter())),
+            Err(unpaired) => units.push(unpaired.unpaired_surrogate()),
+        }
+    }
+    JString::from_utf16(units)
+}
+
+/// The `char` mapped to a single `char` by `f`, unchanged when it maps to several.
+fn map_char<I: Iterator<Item = char>>(unit: u16, f: impl Fn(char) -> I) -> u16 {
+    let c = match char::from_u32(unit as u32) {
+        Some(c) => c,
+        None => return unit,
+    };
+    let mut mapped = f(c);
+    match (mapped.next(), mapped.next()) {
+        (Some(single), None) if (single as u32) <= 0xffff => single as u32 as u16,
+        _ => unit,
+    }
+}
+
+fn char_test(test: fn(char) -> bool) -> impl Fn(&[JValue]) -> Result<JValue, JavaThrowable> {
+    move |args| match args {
+        [JValue::UShort(unit)] => Ok(JValue::Boolean(char::from_u32(*unit as u32).is_some_and(test))),
+        _ => Err(mismatch(args)),
+    }
+}
+
+fn math(f: fn(f64) -> f64) -> impl Fn(&[JValue]) -> Result<JValue, JavaThrowable> {
+    move |args| match args {
+        [JValue::Double(x)] => Ok(JValue::Double(f(*x))),
+        _ => Err(mismatch(args)),
+    }
+}
+
+fn math2(f: fn(f64, f64) -> f64) -> impl Fn(&[JValue]) -> Result<JValue, JavaThrowable> {
+    move |args| match args {
+        [JValue::Double(x), JValue::Double(y)] => Ok(JValue::Double(f(*x, *y))),
+        _ => Err(mismatch(args)),
+    }
+}
+
+/// `Math.max` and `Math.min` of doubles, keeping `x` when it compares to `y` as `kept`. NaN
+/// wins and -0.0 is less than 0.0, as [f64::total_cmp] orders them.
+fn min_max(x: f64, y: f64, kept: Ordering) -> f64 {
+    match (x.is_nan() || y.is_nan(), x.total_cmp(&y) == kept) {
+        (true, _) => f64::NAN,
+        (false, true) => x,
+        (false, false) => y,
+    }
+}
+
+fn parse_int(args: &[JValue]) -> Result<JValue, JavaThrowable> {
+    let (input, radix) = match args {
+        [JValue::String(input)] => (input, 10),
+        [JValue::String(input), JValue::Int(radix)] => (input, *radix),
+        [JValue::Null, ..] => return Err(JavaThrowable::new("java/lang/NumberFormatException", "Cannot parse null string: null".to_string())),
+        _ => return Err(mismatch(args)),
+    };
+    if !(2..=36).contains(&radix) {
+        return Err(JavaThrowable::new("java/lang/NumberFormatException", format!("radix {} out of range", radix)));
+    }
+    let input = input.to_string_lossy();
+    i32::from_str_radix(&input, radix as u32).map(JValue::Int).map_err(|_| match radix {
+        10 => number_format(&input),
+        radix => JavaThrowable::new("java/lang/NumberFormatException", format!("For input string: \"{}\" under radix {}", input, radix)),
+    })
+}
+
+/// Binds the natives of the library, `System.out` writing to `out` and `System.err` to `err`.
+pub fn register_natives(natives: &mut NativeRegistry, out: Arc<Mutex<dyn Write + Send>>, err: Arc<Mutex<dyn Write + Send>>) {
+    natives.register("java/lang/Object", "hashCode", "()I", |args| match args {
+        [JValue::Object(object)] => Ok(JValue::Int(object.index() as i32)),
+        [JValue::String(value)] => Ok(JValue::Int(value.hash_code())),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/io/PrintStream", "write", "(ILjava/lang/String;)V", move |args| {
+        let (fd, text) = match args {
+            [JValue::Int(fd), JValue::String(text)] => (*fd, text.to_string_lossy()),
+            [JValue::Int(fd), JValue::Null] => (*fd, "null".to_string()),
+            _ => return Err(mismatch(args)),
+        };
+        let stream = if fd == 2 { &err } else { &out };
+        // like a PrintStream, failing to write is not an exception
+        let _ = stream.lock().unwrap().write_all(text.as_bytes());
+        Ok(JValue::Null)
+    });
+    natives.register("java/lang/System", "currentTimeMillis", "()J", |_| {
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        Ok(JValue::Long(since_epoch.as_millis() as i64))
+    });
+    let start = Instant::now();
+    natives.register("java/lang/System", "nanoTime", "()J", move |_| Ok(JValue::Long(start.elapsed().as_nanos() as i64)));
+
+    register_strings(natives);
+    register_numbers(natives);
+    natives.register("java/lang/Character", "isDigit", "(C)Z", char_test(unicode::is_decimal_digit));
+    natives.register("java/lang/Character", "isLetter", "(C)Z", char_test(unicode::is_letter));
+    natives.register("java/lang/Character", "isLetterOrDigit", "(C)Z", char_test(unicode::is_letter_or_digit));
+    natives.register("java/lang/Character", "isWhitespace", "(C)Z", char_test(unicode::is_whitespace));
+    natives.register("java/lang/Character", "isUpperCase", "(C)Z", char_test(char::is_uppercase));
+    natives.register("java/lang/Character", "isLowerCase", "(C)Z", char_test(char::is_lowercase));
+    natives.register("java/lang/Character", "toUpperCase", "(C)C", |args| match args {
+        [JValue::UShort(unit)] => Ok(JValue::UShort(map_char(*unit, char::to_uppercase))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Character", "toLowerCase", "(C)C", |args| match args {
+        [JValue::UShort(unit)] => Ok(JValue::UShort(map_char(*unit, char::to_lowercase))),
+        _ => Err(mismatch(args)),
+    });
+    register_math(natives);
+}
+
+/// The methods of `java/lang/String` the interpreter doesn't run itself.
+fn register_strings(natives: &mut NativeRegistry) {
+    const STRING: &str = "java/lang/String";
+    natives.register(STRING, "valueOf", "(C)Ljava/lang/String;", |args| match args {
+        [JValue::UShort(unit)] => Ok(JValue::String(JString::from_utf16(vec![*unit]))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register(STRING, "valueOf", "(F)Ljava/lang/String;", |args| match args {
+        [JValue::Float(value)] => Ok(string(java_float_string(*value as f64, format!("{:e}", value)))),
+        _ => Err(mismatch(args)),
+    });
+    // the other methods are called on a string, with strings for arguments
+    natives.register_class(STRING, |name, descriptor, args| {
+        let (value, args) = match args {
+            [JValue::String(value), args @ ..] => (value, args),
+            _ => return Err(mismatch(args)),
+        };
+        let units: Vec<u16> = value.units().collect();
+        let other = |index: usize| match args.get(index) {
+            Some(JValue::String(other)) => Ok(other.units().collect::<Vec<_>>()),
+            Some(JValue::Null) => Err(null_pointer()),
+            _ => Err(JavaThrowable::new("java/lang/UnsupportedOperationException", format!("String.{}{} of {:?}", name, descriptor, args))),
+        };
+        let substring = |begin: i32, end: i32| match (usize::try_from(begin), usize::try_from(end)) {
+            (Ok(begin), Ok(end)) if begin <= end && end <= units.len() => Ok(JValue::String(JString::from_utf16(units[begin..end].to_vec()))),
+            _ => Err(JavaThrowable::new("java/lang/StringIndexOutOfBoundsException", format!(
+                "begin {}, end {}, length {}", begin, end, units.len(),
+            ))),
+        };
+        let index = |found: Option<usize>| JValue::Int(found.map_or(-1, |index| index as i32));
+        Ok(match (name, descriptor, args) {
+            ("substring", "(I)Ljava/lang/String;", [JValue::Int(begin)]) => substring(*begin, units.len() as i32)?,
+            ("substring", "(II)Ljava/lang/String;", [JValue::Int(begin), JValue::Int(end)]) => substring(*begin, *end)?,
+            ("indexOf", "(I)I", [JValue::Int(c)]) => index(units.iter().position(|unit| *unit as i32 == *c)),
+            ("lastIndexOf", "(I)I", [JValue::Int(c)]) => index(units.iter().rposition(|unit| *unit as i32 == *c)),
+            ("indexOf", "(Ljava/lang/String;)I", _) => index(find(&units, &other(0)?, 0)),
+            ("contains", "(Ljava/lang/CharSequence;)Z", _) => JValue::Boolean(find(&units, &other(0)?, 0).is_some()),
+            ("startsWith", "(Ljava/lang/String;)Z", _) => JValue::Boolean(units.starts_with(&other(0)?)),
+            ("endsWith", "(Ljava/lang/String;)Z", _) => JValue::Boolean(units.ends_with(&other(0)?)),
+            ("compareTo", "(Ljava/lang/String;)I", _) => {
+                let other = other(0)?;
+                let differing = units.iter().zip(&other).find(|(a, b)| a != b);
+                JValue::Int(differing.map_or(units.len() as i32 - other.len() as i32, |(a, b)| *a as i32 - *b as i32))
+            },
+            ("equalsIgnoreCase", "(Ljava/lang/String;)Z", [JValue::Null]) => JValue::Boolean(false),
+            ("equalsIgnoreCase", "(Ljava/lang/String;)Z", _) => {
+                let upper = |units: &[u16]| units.iter().map(|unit| map_char(map_char(*unit, char::to_uppercase), char::to_lowercase)).collect::<Vec<_>>();
+                JValue::Boolean(upper(&units) == upper(&other(0)?))
+            },
+            ("toUpperCase", "()Ljava/lang/String;", []) => JValue::String(map_chars(value, char::to_uppercase)),
+            ("toLowerCase", "()Ljava/lang/String;", []) => JValue::String(map_chars(value, char::to_lowercase)),
+            ("trim", "()Ljava/lang/String;", []) => {
+                let start = units.iter().position(|unit| *unit > b' ' as u16).unwrap_or(units.len());
+                let end = units.iter().rposition(|unit| *unit > b' ' as u16).map_or(start, |end| end + 1);
+                JValue::String(JString::from_utf16(units[start..end].to_vec()))
+            },
+            ("replace", "(CC)Ljava/lang/String;", [JValue::UShort(from), JValue::UShort(to)]) => {
+                JValue::String(JString::from_utf16(units.iter().map(|unit| if unit == from { *to } else { *unit }).collect()))
+            },
+            _ => return Err(JavaThrowable::new("java/lang/UnsatisfiedLinkError", format!("String.{}{}", name, descriptor))),
+        })
+    });
+}
+
+/// Parsing and printing of the boxing classes.
+fn register_numbers(natives: &mut NativeRegistry) {
+    natives.register("java/lang/Integer", "parseInt", "(Ljava/lang/String;)I", parse_int);
+    natives.register("java/lang/Integer", "parseInt", "(Ljava/lang/String;I)I", parse_int);
+    natives.register("java/lang/Integer", "toString", "(I)Ljava/lang/String;", |args| match args {
+        [JValue::Int(value)] => Ok(string(value.to_string())),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Integer", "toHexString", "(I)Ljava/lang/String;", |args| match args {
+        [JValue::Int(value)] => Ok(string(format!("{:x}", value))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Integer", "toBinaryString", "(I)Ljava/lang/String;", |args| match args {
+        [JValue::Int(value)] => Ok(string(format!("{:b}", value))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Long", "parseLong", "(Ljava/lang/String;)J", |args| match args {
+        [JValue::String(input)] => {
+            let input = input.to_string_lossy();
+            input.parse().map(JValue::Long).map_err(|_| number_format(&input))
+        },
+        [JValue::Null] => Err(JavaThrowable::new("java/lang/NumberFormatException", "Cannot parse null string: null".to_string())),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Long", "toString", "(J)Ljava/lang/String;", |args| match args {
+        [JValue::Long(value)] => Ok(string(value.to_string())),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Double", "parseDouble", "(Ljava/lang/String;)D", |args| match args {
+        [JValue::String(input)] => {
+            let input = input.to_string_lossy();
+            let trimmed = input.trim_matches(|c: char| c <= ' ');
+            let number = trimmed.strip_suffix(['d', 'D', 'f', 'F']).unwrap_or(trimmed);
+            match number {
+                "" => Err(JavaThrowable::new("java/lang/NumberFormatException", "empty String".to_string())),
+                "Infinity" | "+Infinity" => Ok(JValue::Double(f64::INFINITY)),
+                "-Infinity" => Ok(JValue::Double(f64::NEG_INFINITY)),
+                "NaN" => Ok(JValue::Double(f64::NAN)),
+                // Rust also reads `inf` and `nan`, which Java doesn't
+                number if number.bytes().any(|b| b.is_ascii_alphabetic() && !matches!(b, b'e' | b'E')) => Err(number_format(&input)),
+                number => number.parse().map(JValue::Double).map_err(|_| number_format(&input)),
+            }
+        },
+        [JValue::Null] => Err(null_pointer()),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Double", "toString", "(D)Ljava/lang/String;", |args| match args {
+        [JValue::Double(value)] => Ok(string(java_float_string(*value, format!("{:e}", value)))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Double", "doubleToLongBits", "(D)J", |args| match args {
+        [JValue::Double(value)] if value.is_nan() => Ok(JValue::Long(0x7ff8_0000_0000_0000)),
+        [JValue::Double(value)] => Ok(JValue::Long(value.to_bits() as i64)),
+        _ => Err(mismatch(args)),
+    });
+    natives.register("java/lang/Double", "isNaN", "(D)Z", |args| match args {
+        [JValue::Double(value)] => Ok(JValue::Boolean(value.is_nan())),
+        _ => Err(mismatch(args)),
+    });
+}
+
+fn register_math(natives: &mut NativeRegistry) {
+    const MATH: &str = "java/lang/Math";
+    natives.register(MATH, "abs", "(I)I", |args| match args {
+        [JValue::Int(x)] => Ok(JValue::Int(x.wrapping_abs())),
+        _ => Err(mismatch(args)),
+    });
+    natives.register(MATH, "abs", "(J)J", |args| match args {
+        [JValue::Long(x)] => Ok(JValue::Long(x.wrapping_abs())),
+        _ => Err(mismatch(args)),
+    });
+    natives.register(MATH, "abs", "(F)F", |args| match args {
+        [JValue::Float(x)] => Ok(JValue::Float(x.abs())),
+        _ => Err(mismatch(args)),
+    });
+    natives.register(MATH, "abs", "(D)D", math(f64::abs));
+    natives.register(MATH, "max", "(II)I", |args| match args {
+        [JValue::Int(x), JValue::Int(y)] => Ok(JValue::Int(*x.max(y))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register(MATH, "max", "(JJ)J", |args| match args {
+        [JValue::Long(x), JValue::Long(y)] => Ok(JValue::Long(*x.max(y))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register(MATH, "max", "(DD)D", math2(|x, y| min_max(x, y, Ordering::Greater)));
+    natives.register(MATH, "min", "(II)I", |args| match args {
+        [JValue::Int(x), JValue::Int(y)] => Ok(JValue::Int(*x.min(y))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register(MATH, "min", "(JJ)J", |args| match args {
+        [JValue::Long(x), JValue::Long(y)] => Ok(JValue::Long(*x.min(y))),
+        _ => Err(mismatch(args)),
+    });
+    natives.register(MATH, "min", "(DD)D", math2(|x, y| min_max(x, y, Ordering::Less)));
+    natives.register(MATH, "sqrt", "(D)D", math(f64::sqrt));
+    natives.register(MATH, "cbrt", "(D)D", math(f64::cbrt));
+    natives.register(MATH, "pow", "(DD)D", math2(f64::powf));
+    natives.register(MATH, "exp", "(D)D", math(f64::exp));
+    natives.register(MATH, "log", "(D)D", math(f64::ln));
+    natives.register(MATH, "log10", "(D)D", math(f64::log10));
+    natives.register(MATH, "sin", "(D)D", math(f64::sin));
+    natives.register(MATH, "cos", "(D)D", math(f64::cos));
+    natives.register(MATH, "tan", "(D)D", math(f64::tan));
+    natives.register(MATH, "atan2", "(DD)D", math2(f64::atan2));
+    natives.register(MATH, "hypot", "(DD)D", math2(f64::hypot));
+    natives.register(MATH, "floor", "(D)D", math(f64::floor));
+    natives.register(MATH, "ceil", "(D)D", math(f64::ceil));
+    // the floor of `x + 0.5`, computed without rounding `x + 0.5`
+    natives.register(MATH, "round", "(D)J", |args| match args {
+        [JValue::Double(x)] => {
+            let floor = x.floor();
+            Ok(JValue::Long(if x - floor >= 0.5 { floor + 1.0 } else { floor } as i64))
+        },
+        _ => Err(mismatch(args)),
+    });
+    natives.register(MATH, "floorMod", "(II)I", |args| match args {
+        [JValue::Int(_), JValue::Int(0)] => Err(JavaThrowable::new("java/lang/ArithmeticException", "/ by zero".to_string())),
+        [JValue::Int(x), JValue::Int(y)] => {
+            let remainder = x.wrapping_rem(*y);
+            Ok(JValue::Int(if remainder != 0 && (remainder ^ y) < 0 { remainder + y } else { remainder }))
+        },
+        _ => Err(mismatch(args)),
+    });
+}
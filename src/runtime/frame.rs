@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use crate::{jvm::reader::constant_pool::ConstantPool, util::code_err::RuntimeError};
+
+use super::{heap::ObjectRef, loader::Method, value::Value};
+
+/// The activation of a method: its local variables, its operand stack and where it is in its
+/// code. Values on the operand stack take one entry whatever their size, but are counted in
+/// slots against `max_stack` like the JVM does.
+#[derive(Debug)]
+pub struct Frame<'a> {
+    pub method: Arc<Method<'a>>,
+    /// Index of the next instruction to run, one past the running one while it runs
+    pub pc: usize,
+    /// Sized to `max_locals`, a `long` or `double` takes two slots, the second of which is `Top`
+    pub locals: Vec<Value>,
+    pub stack: Vec<Value>,
+    /// Slots taken on the operand stack
+    depth: usize,
+    max_stack: usize,
+    /// Index of the instruction the caller resumes at, `None` for a frame entered from Rust
+    pub return_address: Option<usize>,
+    /// The object whose monitor a `synchronized` method entered, exited once the frame ends
+    pub monitor: Option<ObjectRef>,
+}
+
+/// A method running when a throwable was created, as its stack trace shows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackTraceElement {
+    /// Binary name of the class, e.g. `java.lang.String`
+    pub class: String,
+    pub method: String,
+    pub file: Option<String>,
+    pub line: Option<u16>,
+}
+
+impl std::fmt::Display for StackTraceElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}(", self.class, self.method)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{})", file, line),
+            (Some(file), None) => write!(f, "{})", file),
+            (None, _) => write!(f, "Unknown Source)"),
+        }
+    }
+}
+
+impl<'a> Frame<'a> {
+    /// A frame for `method` with `args` in its first locals, the receiver first for instance methods.
+    pub fn new(method: Arc<Method<'a>>, args: Vec<Value>, return_address: Option<usize>) -> Result<Self, RuntimeError> {
+        let (max_stack, max_locals) = method.code.map_or((0, 0), |code| (code.max_stack as usize, code.max_locals as usize));
+        let mut frame = Self {
+            method,
+            pc: 0,
+            locals: vec![Value::Top; max_locals],
+            stack: Vec::with_capacity(max_stack),
+            depth: 0,
+            max_stack,
+            return_address,
+            monitor: None,
+        };
+        let mut slot = 0;
+        for arg in args {
+            let slots = arg.slots();
+            frame.store(slot, arg)?;
+            slot += slots;
+        }
+        Ok(frame)
+    }
+    /// The constant pool of the running method's class.
+    pub fn cp(&self) -> &'a ConstantPool {
+        self.method.cp
+    }
+    /// The pc of the running instruction.
+    pub fn current_pc(&self) -> u32 {
+        self.method.pcs.get(self.pc.wrapping_sub(1)).copied().unwrap_or(0)
+    }
+    /// Where the frame is, by the line of the running instruction.
+    pub fn trace_element(&self) -> StackTraceElement {
+        StackTraceElement {
+            class: self.method.class.replace('/', "."),
+            method: self.method.name.to_string(),
+            file: self.method.source_file.map(str::to_string),
+            line: self.method.line_at(self.current_pc()),
+        }
+    }
+    /// An error for the running instruction, which a verifier would have rejected.
+    pub fn invalid(&self, message: impl Into<String>) -> RuntimeError {
+        RuntimeError::InvalidCode {
+            method: self.method.signature_string(),
+            pc: self.current_pc(),
+            message: message.into(),
+        }
+    }
+    pub fn push(&mut self, value: Value) -> Result<(), RuntimeError> {
+        self.depth += value.slots();
+        if self.depth > self.max_stack {
+            return Err(self.invalid(format!("operand stack overflows max_stack {}", self.max_stack)));
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+    /// Empties the operand stack, as entering an exception handler does.
+    pub fn clear_stack(&mut self) {
+        self.stack.clear();
+        self.depth = 0;
+    }
+    pub fn pop(&mut self) -> Result<Value, RuntimeError> {
+        let value = self.stack.pop().ok_or_else(|| self.invalid("pop from an empty operand stack"))?;
+        self.depth -= value.slots();
+        Ok(value)
+    }
+    /// Pops values taking `slots` slots together, returned in the order they were pushed. Fails
+    /// when a `long` or `double` would be split, as the forms of `pop2` and `dup2` require.
+    pub fn pop_slots(&mut self, slots: usize) -> Result<Vec<Value>, RuntimeError> {
+        let mut values = Vec::new();
+        let mut taken = 0;
+        while taken < slots {
+            let value = self.pop()?;
+            taken += value.slots();
+            values.push(value);
+        }
+        if taken != slots {
+            return Err(self.invalid("a long or double would be split"));
+        }
+        values.reverse();
+        Ok(values)
+    }
+    pub fn pop_int(&mut self) -> Result<i32, RuntimeError> {
+        match self.pop()? {
+            Value::Int(value) => Ok(value),
+            other => Err(self.invalid(format!("expected an int, found {:?}", other))),
+        }
+    }
+    pub fn pop_long(&mut self) -> Result<i64, RuntimeError> {
+        match self.pop()? {
+            Value::Long(value) => Ok(value),
+            other => Err(self.invalid(format!("expected a long, found {:?}", other))),
+        }
+    }
+    pub fn pop_float(&mut self) -> Result<f32, RuntimeError> {
+        match self.pop()? {
+            Value::Float(value) => Ok(value),
+            other => Err(self.invalid(format!("expected a float, found {:?}", other))),
+        }
+    }
+    pub fn pop_double(&mut self) -> Result<f64, RuntimeError> {
+        match self.pop()? {
+            Value::Double(value) => Ok(value),
+            other => Err(self.invalid(format!("expected a double, found {:?}", other))),
+        }
+    }
+    /// Pops a reference, `None` being `null`.
+    pub fn pop_reference(&mut self) -> Result<Option<ObjectRef>, RuntimeError> {
+        match self.pop()? {
+            Value::Reference(value) => Ok(value),
+            other => Err(self.invalid(format!("expected a reference, found {:?}", other))),
+        }
+    }
+    pub fn load(&self, index: usize) -> Result<Value, RuntimeError> {
+        match self.locals.get(index) {
+            Some(Value::Top) => Err(self.invalid(format!("local {} is read before it is written", index))),
+            Some(value) => Ok(value.clone()),
+            None => Err(self.invalid(format!("local {} is past max_locals {}", index, self.locals.len()))),
+        }
+    }
+    pub fn store(&mut self, index: usize, value: Value) -> Result<(), RuntimeError> {
+        if index + value.slots() > self.locals.len() {
+            return Err(self.invalid(format!("local {} is past max_locals {}", index + value.slots() - 1, self.locals.len())));
+        }
+        // a wide value written over the second half of another leaves its first half unusable
+        if index > 0 && self.locals[index - 1].is_wide() {
+            self.locals[index - 1] = Value::Top;
+        }
+        if value.is_wide() {
+            self.locals[index + 1] = Value::Top;
+        }
+        self.locals[index] = value;
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{
+    core::typings::{j_object::JObject, j_string::JString, j_value::JValue},
+    jvm::reader::descriptor::FieldType,
+    util::code_err::RuntimeError,
+};
+
+use super::value::{Object, ObjectData, Value};
+
+/// A handle to an object on the [Heap], compared by identity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectRef(u32);
+
+impl ObjectRef {
+    /// Where the object is on its heap, which also serves as its identity hash.
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Owns every object the interpreter allocates, which are reached through [ObjectRef]s.
+///
+/// Objects no longer reachable are freed by a mark-sweep [collection](Heap::collect), their
+/// slots being reused by later allocations. The heap can't tell what Rust code holds, so a
+/// handle kept across code that may collect has to be [pinned](Heap::pin). An allocation past
+/// the limit fails here, the interpreter collecting with its roots and retrying before it
+/// throws `OutOfMemoryError`.
+#[derive(Debug)]
+pub struct Heap {
+    objects: Vec<Option<Object>>,
+    /// Slots of collected objects
+    free: Vec<u32>,
+    pinned: Vec<ObjectRef>,
+    /// Bytes taken by live objects, as [Object::size] counts them
+    used: usize,
+    /// Allocations fail past this many bytes
+    limit: Option<usize>,
+    /// [Heap::should_collect] once this many bytes are used
+    next_collection: usize,
+    stats: GcStats,
+}
+
+/// Counts of what the heap has allocated and collected since it was created.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcStats {
+    pub collections: usize,
+    pub allocated_objects: usize,
+    pub allocated_bytes: usize,
+    pub freed_objects: usize,
+    pub freed_bytes: usize,
+}
+
+/// Bytes used before the first collection, and the least the heap grows by between two
+const MIN_COLLECTION: usize = 1 << 20;
+
+pub(crate) fn out_of_memory() -> RuntimeError {
+    RuntimeError::exception("java/lang/OutOfMemoryError", Some("Java heap space".to_string()))
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+            free: Vec::new(),
+            pinned: Vec::new(),
+            used: 0,
+            limit: None,
+            next_collection: MIN_COLLECTION,
+            stats: GcStats::default(),
+        }
+    }
+    /// A heap failing allocations with `OutOfMemoryError` once `limit` bytes are used.
+    pub fn with_limit(limit: usize) -> Self {
+        let mut heap = Self::new();
+        heap.set_limit(Some(limit));
+        heap
+    }
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+        self.schedule_collection();
+    }
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+    /// Fails with `OutOfMemoryError` when `bytes` more would pass the limit.
+    pub fn reserve(&self, bytes: usize) -> Result<(), RuntimeError> {
+        match self.limit {
+            Some(limit) if self.used.saturating_add(bytes) > limit => Err(out_of_memory()),
+            _ => Ok(()),
+        }
+    }
+    pub fn allocate(&mut self, object: Object) -> Result<ObjectRef, RuntimeError> {
+        let size = object.size();
+        self.reserve(size)?;
+        self.used += size;
+        self.stats.allocated_objects += 1;
+        self.stats.allocated_bytes += size;
+        Ok(match self.free.pop() {
+            Some(slot) => {
+                self.objects[slot as usize] = Some(object);
+                ObjectRef(slot)
+            },
+            None => {
+                self.objects.push(Some(object));
+                ObjectRef(self.objects.len() as u32 - 1)
+            },
+        })
+    }
+    pub fn get(&self, object: ObjectRef) -> &Object {
+        self.objects[object.index()].as_ref().expect("the object was collected")
+    }
+    pub fn get_mut(&mut self, object: ObjectRef) -> &mut Object {
+        self.objects[object.index()].as_mut().expect("the object was collected")
+    }
+    /// Internal name of the class of `object`, or its descriptor for an array.
+    pub fn class(&self, object: ObjectRef) -> &str {
+        &self.get(object).class
+    }
+    /// The contents of a `java/lang/String`.
+    pub fn string(&self, object: ObjectRef) -> Option<&JString> {
+        match &self.get(object).data {
+            ObjectData::String(value) => Some(value),
+            _ => None,
+        }
+    }
+    /// Number of live objects.
+    pub fn len(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Bytes taken by live objects.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+    pub fn stats(&self) -> &GcStats {
+        &self.stats
+    }
+    /// Keeps `object` alive until it is unpinned as many times as it was pinned.
+    pub fn pin(&mut self, object: ObjectRef) {
+        self.pinned.push(object);
+    }
+    pub fn unpin(&mut self, object: ObjectRef) {
+        if let Some(index) = self.pinned.iter().rposition(|pinned| *pinned == object) {
+            self.pinned.swap_remove(index);
+        }
+    }
+    /// Pins the objects `values` refer to.
+    pub fn pin_values(&mut self, values: &[Value]) {
+        for value in values {
+            if let Value::Reference(Some(object)) = value {
+                self.pin(*object);
+            }
+        }
+    }
+    pub fn unpin_values(&mut self, values: &[Value]) {
+        for value in values {
+            if let Value::Reference(Some(object)) = value {
+                self.unpin(*object);
+            }
+        }
+    }
+    /// Whether `object` is still on the heap, that is wasn't collected.
+    pub fn is_live(&self, object: ObjectRef) -> bool {
+        self.objects.get(object.index()).is_some_and(Option::is_some)
+    }
+    /// Whether enough was allocated since the last collection for another to be worth it.
+    pub fn should_collect(&self) -> bool {
+        self.used >= self.next_collection
+    }
+    /// Frees every object that can't be reached from `roots` or a pinned object, returning
+    /// how many were.
+    pub fn collect(&mut self, roots: impl IntoIterator<Item = ObjectRef>) -> usize {
+        let mut marked = vec![false; self.objects.len()];
+        let mut pending: Vec<ObjectRef> = roots.into_iter().chain(self.pinned.iter().copied()).collect();
+        while let Some(object) = pending.pop() {
+            if std::mem::replace(&mut marked[object.index()], true) {
+                continue;
+            }
+            self.get(object).references(|reference| if !marked[reference.index()] {
+                pending.push(reference);
+            });
+        }
+        let mut freed = 0;
+        for (index, slot) in self.objects.iter_mut().enumerate() {
+            if marked[index] {
+                continue;
+            }
+            if let Some(object) = slot.take() {
+                let size = object.size();
+                self.used -= size;
+                self.stats.freed_bytes += size;
+                self.free.push(index as u32);
+                freed += 1;
+            }
+        }
+        self.stats.freed_objects += freed;
+        self.stats.collections += 1;
+        self.schedule_collection();
+        freed
+    }
+    /// Lets the heap double before the next collection, but collects halfway to the limit
+    /// at the latest, so that garbage rarely makes an allocation fail.
+    fn schedule_collection(&mut self) {
+        let mut next = self.used + self.used.max(MIN_COLLECTION);
+        if let Some(limit) = self.limit {
+            next = next.min(self.used + limit.saturating_sub(self.used) / 2);
+        }
+        self.next_collection = next;
+    }
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where the instance fields of a class live in its objects. The fields of its super classes
+/// come first, in the slots they take in objects of the super class, then the fields the class
+/// declares in the order of its class file.
+#[derive(Debug, Clone, Default)]
+pub struct FieldLayout {
+    pub slots: Vec<FieldSlot>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldSlot {
+    /// The class declaring the field
+    pub class: String,
+    pub name: String,
+    pub ty: FieldType,
+}
+
+impl FieldLayout {
+    /// The layout of a class declaring `fields` and extending the class laid out as `parent`.
+    pub fn extend(parent: &FieldLayout, class: &str, fields: impl IntoIterator<Item = (String, FieldType)>) -> Self {
+        let mut slots = parent.slots.clone();
+        slots.extend(fields.into_iter().map(|(name, ty)| FieldSlot { class: class.to_string(), name, ty }));
+        Self { slots }
+    }
+    /// Slot of the field `name` declared by `class`.
+    pub fn slot(&self, class: &str, name: &str) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.class == class && slot.name == name)
+    }
+    /// Slot of the field `name` as the class itself sees it, hiding fields of the same name
+    /// declared by its super classes.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.slots.iter().rposition(|slot| slot.name == name)
+    }
+    /// The fields of a new object.
+    pub fn defaults(&self) -> Vec<JValue> {
+        self.slots.iter().map(|slot| JValue::default_of(&slot.ty)).collect()
+    }
+}
+
+impl JValue {
+    /// The value a field of type `ty` starts out with.
+    pub fn default_of(ty: &FieldType) -> Self {
+        Value::default_of(ty).to_field(ty).unwrap_or(JValue::Null)
+    }
+}
+
+/// Fields are looked up by name as the class of the object sees them, see [FieldLayout::find].
+/// Objects other than class instances have no fields.
+impl JObject for Object {
+    fn identify(&self) -> String {
+        self.class.clone()
+    }
+    fn get(&self, key: &str) -> Option<JValue> {
+        match &self.data {
+            ObjectData::Instance(instance) => instance.fields.get(instance.layout.find(key)?).cloned(),
+            _ => None,
+        }
+    }
+    fn get_mut(&mut self, key: &str) -> Option<&mut JValue> {
+        match &mut self.data {
+            ObjectData::Instance(instance) => instance.fields.get_mut(instance.layout.find(key)?),
+            _ => None,
+        }
+    }
+    /// Fails when there is no such field or `value` is not of its type.
+    fn set(&mut self, key: &str, value: JValue) -> Option<()> {
+        let instance = match &mut self.data {
+            ObjectData::Instance(instance) => instance,
+            _ => return None,
+        };
+        let slot = instance.layout.find(key)?;
+        if Value::from_field(&value)?.to_field(&instance.layout.slots[slot].ty)? != value {
+            return None;
+        }
+        instance.fields[slot] = value;
+        Some(())
+    }
+}
@@ -0,0 +1,2060 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    mem,
+    ops::{Deref, DerefMut},
+    sync::{mpsc::{self, Sender}, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    jvm::{
+        analysis::classpath::ClassPath,
+        reader::{
+            access_flags::AccessFlags,
+            bootstrap::{Bootstrap, BootstrapArgument, CallSite, ConcatPart},
+            code::instruction::{Instruction, WideOp},
+            constant_pool::{ConstantPool, ConstantPoolInfo, MemberRef},
+            descriptor::{FieldType, MethodDescriptor},
+            method_handle_kind::MethodHandleKind,
+        },
+    },
+    core::typings::{j_string::JString, j_value::JValue},
+    util::code_err::{ClassParseError, ResolutionError, RuntimeError},
+};
+
+use super::{
+    classlib,
+    frame::{Frame, StackTraceElement},
+    loader::{InitState, Loader, Method, RuntimeClass},
+    heap::{out_of_memory, Heap, ObjectRef},
+    native::NativeRegistry,
+    thread::{InterpreterLock, Monitor, ThreadState, ThreadStatus},
+    value::{Array, Instance, Lambda, Object, ObjectData, Value, HEADER_SIZE},
+};
+
+const OBJECT: &str = "java/lang/Object";
+const STRING: &str = "java/lang/String";
+const THROWABLE: &str = "java/lang/Throwable";
+const THREAD: &str = "java/lang/Thread";
+/// Calls nested deeper than this throw `StackOverflowError`
+const MAX_FRAMES: usize = 2048;
+/// Bytes the heap of an interpreter may take, like the `-Xmx` of a JVM
+const HEAP_LIMIT: usize = 256 << 20;
+/// Frames past [MAX_FRAMES] left to the constructor of a throwable the interpreter throws, so
+/// that a `StackOverflowError` can still be made
+const RESERVED_FRAMES: usize = 64;
+/// Instructions a thread runs before it lets the others waiting for the interpreter lock run
+const TIME_SLICE: u32 = 1000;
+/// The id of the thread an interpreter is created on, `#1` in thread dumps like with the JVM
+const MAIN_THREAD: usize = 1;
+
+/// What the running frame does after an instruction.
+enum Step {
+    Next,
+    /// The frame returned, with a value unless its method is `void`
+    Return(Option<Value>),
+}
+
+/// Runs bytecode on an explicit stack of [Frame]s, loading classes from a class path as they
+/// are first used.
+///
+/// Objects live on a [Heap] owned by the interpreter, which is collected between two
+/// instructions once enough was allocated. A Java exception unwinds the frames to the first
+/// handler their exception tables have for it, and ends the call into the interpreter with
+/// [RuntimeError::Exception] when none catches it. Strings
+/// are objects holding a [JString], native methods run the Rust bound to them in a
+/// [NativeRegistry], and the
+/// bootstrap methods of `invokedynamic` are run by the interpreter itself for string
+/// concatenation and lambdas.
+///
+/// An interpreter is one Java thread, the one it is created on running what it is asked to.
+/// `Thread.start` runs the new thread on a thread of the OS with an interpreter of its own,
+/// which shares the [state](Shared) of the first behind an [InterpreterLock]. A thread only
+/// runs bytecode while it holds the lock, so fields are read and written one thread at a time,
+/// which makes every access, `volatile` or not, sequentially consistent as the JMM requires of
+/// `volatile` fields. The threads started by a call into the interpreter have all ended when
+/// it returns.
+///
+/// `synchronized` methods and blocks enter the monitor of their object, which a thread may
+/// enter again while it owns it. A thread blocked on a monitor, in `Object.wait`, `Thread.join`
+/// or waiting for another to initialize a class releases the lock. Once every thread waits for
+/// another with no deadline, they all fail with [RuntimeError::Deadlock], which holds the
+/// [thread dump](Interpreter::thread_dump) showing who waits for whom.
+pub struct Interpreter<'a> {
+    lock: Arc<InterpreterLock<Shared<'a>>>,
+    /// Id of the thread, its key in [Shared::threads]
+    thread: usize,
+    /// Whether the thread holds the lock, without which it can't reach the shared state
+    holding: bool,
+    frames: Vec<Frame<'a>>,
+    /// Whether a throwable is being made, which may use the [RESERVED_FRAMES]
+    throwing: bool,
+    /// Instructions run since the thread last let others run
+    ticks: u32,
+}
+
+/// The state the threads of an [Interpreter] share, which a thread reaches through it while it
+/// holds the interpreter lock.
+pub struct Shared<'a> {
+    loader: Loader<'a>,
+    heap: Heap,
+    /// `java/lang/Class` objects by the name of the class they stand for
+    mirrors: HashMap<String, ObjectRef>,
+    /// The string objects `ldc` and `String.intern` return, by their contents
+    interned: HashMap<JString, ObjectRef>,
+    natives: NativeRegistry,
+    /// The frames running when each throwable was created, innermost first
+    traces: HashMap<ObjectRef, Vec<StackTraceElement>>,
+    /// Messages of the throwables made without a constructor, as their class isn't on the class path
+    messages: HashMap<ObjectRef, String>,
+    /// The threads that haven't ended, by id
+    threads: BTreeMap<usize, ThreadState<'a>>,
+    next_thread: usize,
+    /// The monitors threads own, by their object
+    monitors: HashMap<ObjectRef, Monitor>,
+    /// Threads in `Object.wait` that weren't notified yet with their object, in the order they
+    /// started waiting
+    wait_set: Vec<(usize, ObjectRef)>,
+    /// The threads running the initialization of a class, by its name
+    initializers: HashMap<String, usize>,
+    /// Where `Thread.start` sends the threads to run, while a call into the interpreter lasts
+    launcher: Option<Sender<Interpreter<'a>>>,
+    /// The thread dump taken once every thread waited for another, which ends them all
+    deadlock: Option<String>,
+}
+
+impl<'a> Deref for Interpreter<'a> {
+    type Target = Shared<'a>;
+
+    fn deref(&self) -> &Shared<'a> {
+        assert!(self.holding, "thread {} doesn't hold the interpreter lock", self.thread);
+        // SAFETY: the thread holds the lock, which it can't release while the reference lives
+        // as that takes `&mut self`
+        unsafe { &*self.lock.get() }
+    }
+}
+
+impl<'a> DerefMut for Interpreter<'a> {
+    fn deref_mut(&mut self) -> &mut Shared<'a> {
+        self.parts().1
+    }
+}
+
+fn null_pointer() -> RuntimeError {
+    RuntimeError::exception("java/lang/NullPointerException", None)
+}
+
+fn illegal_monitor_state() -> RuntimeError {
+    RuntimeError::exception("java/lang/IllegalMonitorStateException", Some("current thread is not owner".to_string()))
+}
+
+fn interrupted(message: Option<&str>) -> RuntimeError {
+    RuntimeError::exception("java/lang/InterruptedException", message.map(str::to_string))
+}
+
+/// The deadline of a `wait` or `join` of `millis`, which is none for 0 as they then wait for good.
+fn deadline_after(millis: i64) -> Result<Option<Instant>, RuntimeError> {
+    match millis {
+        ..=-1 => Err(RuntimeError::exception("java/lang/IllegalArgumentException", Some("timeout value is negative".to_string()))),
+        0 => Ok(None),
+        millis => Ok(Some(Instant::now() + Duration::from_millis(millis as u64))),
+    }
+}
+
+impl<'a> Interpreter<'a> {
+    /// An interpreter running the classes of `classpath`, with the natives of the
+    /// [class library](classlib) bound to the standard output and error.
+    pub fn new(classpath: &'a ClassPath) -> Result<Self, ClassParseError> {
+        let mut natives = NativeRegistry::new();
+        classlib::register_natives(&mut natives, Arc::new(Mutex::new(io::stdout())), Arc::new(Mutex::new(io::stderr())));
+        let shared = Shared {
+            loader: Loader::new(classpath)?,
+            heap: Heap::with_limit(HEAP_LIMIT),
+            mirrors: HashMap::new(),
+            interned: HashMap::new(),
+            natives,
+            traces: HashMap::new(),
+            messages: HashMap::new(),
+            threads: BTreeMap::from([(MAIN_THREAD, ThreadState::new(None))]),
+            next_thread: MAIN_THREAD + 1,
+            monitors: HashMap::new(),
+            wait_set: Vec::new(),
+            initializers: HashMap::new(),
+            launcher: None,
+            deadlock: None,
+        };
+        Ok(Self::on_thread(Arc::new(InterpreterLock::new(shared, MAIN_THREAD)), MAIN_THREAD, true))
+    }
+    fn on_thread(lock: Arc<InterpreterLock<Shared<'a>>>, thread: usize, holding: bool) -> Self {
+        Self { lock, thread, holding, frames: Vec::new(), throwing: false, ticks: 0 }
+    }
+    /// The frames of the thread along with the shared state.
+    fn parts(&mut self) -> (&mut Vec<Frame<'a>>, &mut Shared<'a>) {
+        assert!(self.holding, "thread {} doesn't hold the interpreter lock", self.thread);
+        // SAFETY: as for `deref`
+        (&mut self.frames, unsafe { &mut *self.lock.get() })
+    }
+    pub fn loader(&mut self) -> &mut Loader<'a> {
+        &mut self.loader
+    }
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+    pub fn heap_mut(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+    /// Where native methods are bound.
+    pub fn natives(&mut self) -> &mut NativeRegistry {
+        &mut self.natives
+    }
+    /// The frames of the methods running, the innermost last.
+    pub fn frames(&self) -> &[Frame<'a>] {
+        &self.frames
+    }
+    /// Runs `public static void main(String[])` of `class`. An exception it doesn't catch ends
+    /// it with its stack trace reported on `System.err`, as it ends a thread of the JVM.
+    pub fn run_main(&mut self, class: &str, args: &[&str]) -> Result<(), RuntimeError> {
+        self.with_threads(|this| {
+            // the array keeps the strings made before the next one alive
+            let array = this.new_array(&FieldType::Array(Box::new(FieldType::Object(STRING.to_string()))), &[args.len() as i32])?;
+            this.heap.pin(array);
+            let filled: Result<(), RuntimeError> = args.iter().enumerate().try_for_each(|(index, arg)| {
+                let string = this.new_string(*arg)?;
+                if let ObjectData::Array(Array::Reference(elements)) = &mut this.heap.get_mut(array).data {
+                    elements[index] = Some(string);
+                }
+                Ok(())
+            });
+            this.heap.unpin(array);
+            filled?;
+            match this.call_static(class, "main", "([Ljava/lang/String;)V", vec![Value::Reference(Some(array))]) {
+                Err(RuntimeError::Thrown(exception)) => {
+                    let report = format!("Exception in thread \"main\" {}", this.stack_trace(exception));
+                    this.print_error(report)?;
+                    Err(this.uncaught(RuntimeError::Thrown(exception)))
+                },
+                result => result,
+            }
+        }).map(drop)
+    }
+    /// Initializes `class` and runs its static method `name`, returning what it returns.
+    pub fn invoke_static(&mut self, class: &str, name: &str, descriptor: &str, args: Vec<Value>) -> Result<Option<Value>, RuntimeError> {
+        self.with_threads(|this| this.call_static(class, name, descriptor, args).map_err(|err| this.uncaught(err)))
+    }
+    /// Runs `method` with `args`, the receiver first for instance methods, until it returns.
+    /// An exception it doesn't catch is returned as [RuntimeError::Exception].
+    pub fn invoke(&mut self, method: Arc<Method<'a>>, args: Vec<Value>) -> Result<Option<Value>, RuntimeError> {
+        self.with_threads(|this| this.execute(method, args).map_err(|err| this.uncaught(err)))
+    }
+    /// What every thread is doing, like `jstack` prints it, followed by the cycles of threads
+    /// waiting for each other.
+    pub fn thread_dump(&self) -> String {
+        let mut dump = String::from("Full thread dump:\n");
+        for (thread, state) in &self.threads {
+            dump += &format!("\n\"{}\" #{} {}\n", self.thread_name(*thread), thread, state.status);
+            let frames = if *thread == self.thread { &self.frames } else { &state.frames };
+            for (depth, frame) in frames.iter().rev().enumerate() {
+                dump += &format!("\tat {}\n", frame.trace_element());
+                match &state.status {
+                    ThreadStatus::Blocked(object) if depth == 0 => dump += &format!("\t- waiting to lock {}\n", self.describe(*object)),
+                    ThreadStatus::Waiting(object, _) if depth == 0 => dump += &format!("\t- waiting on {}\n", self.describe(*object)),
+                    _ => {},
+                }
+            }
+        }
+        for cycle in self.deadlocks() {
+            dump += "\nFound one Java-level deadlock:\n=============================\n";
+            for thread in cycle {
+                let (other, waiting) = self.waits_for(&self.threads[&thread]).expect("threads of a cycle wait");
+                dump += &format!("\"{}\":\n  {}\"{}\"\n", self.thread_name(thread), waiting, self.thread_name(other));
+            }
+        }
+        dump
+    }
+    /// Runs `call` on this thread, then waits for the threads it started to end. What it
+    /// returns is kept from the collector meanwhile.
+    fn with_threads(&mut self, call: impl FnOnce(&mut Self) -> Result<Option<Value>, RuntimeError>) -> Result<Option<Value>, RuntimeError> {
+        std::thread::scope(|scope| {
+            let (launcher, started) = mpsc::channel::<Interpreter<'a>>();
+            scope.spawn(move || {
+                for thread in started {
+                    scope.spawn(move || thread.run_thread());
+                }
+            });
+            self.launcher = Some(launcher);
+            let result = call(self);
+            let values: Vec<Value> = result.iter().flatten().cloned().collect();
+            self.heap.pin_values(&values);
+            let ended = self.join_threads();
+            self.heap.unpin_values(&values);
+            self.launcher = None;
+            self.deadlock = None;
+            result.and_then(|value| ended.map(|()| value))
+        })
+    }
+    /// Waits for every other thread to end, through a deadlock as they then end too.
+    fn join_threads(&mut self) -> Result<(), RuntimeError> {
+        let mut ended = Ok(());
+        while let Some(thread) = self.threads.keys().copied().find(|thread| *thread != self.thread) {
+            // being interrupted doesn't end this wait
+            self.thread_state().interrupted = false;
+            if let Err(err) = self.block(ThreadStatus::Joining(thread, None)) {
+                ended = ended.and(Err(err));
+                self.wait_for_change(None);
+            }
+        }
+        ended
+    }
+    /// Runs the thread [started](Self::start_thread) with the `run` method of its object, on
+    /// a thread of the OS of its own. An exception it doesn't catch is reported like the JVM
+    /// does, a deadlock is left to the main thread to report.
+    fn run_thread(mut self) {
+        self.lock.acquire(self.thread);
+        self.holding = true;
+        match self.run_started() {
+            Ok(()) | Err(RuntimeError::Deadlock(_)) => {},
+            Err(err) => {
+                let report = match err {
+                    RuntimeError::Thrown(exception) => self.stack_trace(exception),
+                    err => format!("{}\n", err),
+                };
+                let report = format!("Exception in thread \"{}\" {}", self.thread_name(self.thread), report);
+                // like the JVM, failing to report is not an error of the thread
+                let _ = self.print_error(report);
+            },
+        }
+        let thread = self.thread;
+        self.monitors.retain(|_, monitor| monitor.owner != thread);
+        self.threads.remove(&thread);
+        self.holding = false;
+        self.lock.release(thread);
+    }
+    fn run_started(&mut self) -> Result<(), RuntimeError> {
+        let object = self.thread_state().object.expect("a started thread has its object");
+        let member = MemberRef { class: THREAD.to_string(), name: "run".to_string(), descriptor: "()V".to_string(), interface: false };
+        let resolved = self.loader.resolve_method(&member)?;
+        let class = self.heap.class(object).to_string();
+        let method = self.loader.select(&resolved, &class)?;
+        self.execute(method, vec![Value::Reference(Some(object))]).map(drop)
+    }
+    /// The text `printStackTrace` prints for `exception`: what it is, where it was created, then
+    /// the same for its causes without the frames they share with what they caused.
+    pub fn stack_trace(&self, exception: ObjectRef) -> String {
+        let mut text = String::new();
+        let mut seen = Vec::new();
+        let mut enclosing: &[StackTraceElement] = &[];
+        let mut next = Some(exception);
+        while let Some(exception) = next.filter(|exception| !seen.contains(exception)) {
+            if !seen.is_empty() {
+                text.push_str("Caused by: ");
+            }
+            seen.push(exception);
+            text.push_str(&format!("{}\n", self.uncaught(RuntimeError::Thrown(exception))));
+            let trace = self.traces.get(&exception).map_or(&[][..], Vec::as_slice);
+            let common = trace.iter().rev().zip(enclosing.iter().rev()).take_while(|(frame, other)| frame == other).count();
+            for element in &trace[..trace.len() - common] {
+                text.push_str(&format!("\tat {}\n", element));
+            }
+            if common > 0 {
+                text.push_str(&format!("\t... {} more\n", common));
+            }
+            enclosing = trace;
+            next = self.throwable_field(exception, "cause");
+        }
+        text
+    }
+    fn call_static(&mut self, class: &str, name: &str, descriptor: &str, args: Vec<Value>) -> Result<Option<Value>, RuntimeError> {
+        let member = MemberRef {
+            class: class.to_string(),
+            name: name.to_string(),
+            descriptor: descriptor.to_string(),
+            interface: self.loader.hierarchy().is_interface(class),
+        };
+        let method = self.loader.resolve_method(&member)?;
+        if !method.is_static() {
+            return Err(RuntimeError::exception("java/lang/IncompatibleClassChangeError", Some(format!("{} is not static", method.signature_string()))));
+        }
+        // initialization may collect, while nothing else refers to the arguments
+        self.heap.pin_values(&args);
+        let initialized = self.initialize(method.class);
+        self.heap.unpin_values(&args);
+        initialized?;
+        self.execute(method, args)
+    }
+    /// Runs `method` until it returns, leaving an exception it doesn't catch
+    /// [thrown](RuntimeError::Thrown).
+    fn execute(&mut self, method: Arc<Method<'a>>, args: Vec<Value>) -> Result<Option<Value>, RuntimeError> {
+        if method.is_native() {
+            return self.call_native(&method, args);
+        }
+        let base = self.frames.len();
+        self.call(method, args, None)?;
+        let result = self.run(base);
+        if result.is_err() {
+            for frame in self.frames.split_off(base) {
+                if let Some(object) = frame.monitor {
+                    self.exit_monitor(object);
+                }
+            }
+        }
+        result
+    }
+    /// A new `java/lang/String` holding `value`.
+    pub fn new_string(&mut self, value: impl Into<JString>) -> Result<ObjectRef, RuntimeError> {
+        self.allocate(Object { class: STRING.to_string(), data: ObjectData::String(value.into()) })
+    }
+    /// Fails with `OutOfMemoryError` when `bytes` more don't fit on the heap, even once it is
+    /// collected. What only Rust code holds has to be pinned.
+    fn reserve(&mut self, bytes: usize) -> Result<(), RuntimeError> {
+        if self.heap.reserve(bytes).is_err() {
+            self.collect();
+        }
+        self.heap.reserve(bytes)
+    }
+    /// Puts `object` on the heap, collecting it first when the object doesn't fit. The objects
+    /// it refers to are kept alive by the collection.
+    fn allocate(&mut self, object: Object) -> Result<ObjectRef, RuntimeError> {
+        if self.heap.reserve(object.size()).is_err() {
+            let mut referenced = Vec::new();
+            object.references(|reference| referenced.push(Value::Reference(Some(reference))));
+            self.heap.pin_values(&referenced);
+            self.collect();
+            self.heap.unpin_values(&referenced);
+        }
+        self.heap.allocate(object)
+    }
+    /// An array of type `ty` with `lengths[0]` elements, each an array of `lengths[1]` elements
+    /// and so on, as `multianewarray` makes it.
+    fn new_array(&mut self, ty: &FieldType, lengths: &[i32]) -> Result<ObjectRef, RuntimeError> {
+        let size = array_size(ty, lengths)?;
+        self.reserve(size)?;
+        new_dimension(&mut self.heap, ty, lengths)
+    }
+    /// The `java/lang/String` holding `value` that `ldc` pushes, the same one for equal strings.
+    pub fn intern(&mut self, value: impl Into<JString>) -> Result<ObjectRef, RuntimeError> {
+        let value = value.into();
+        if let Some(string) = self.interned.get(&value) {
+            return Ok(*string);
+        }
+        let string = self.new_string(value.clone())?;
+        self.interned.insert(value, string);
+        Ok(string)
+    }
+    /// The `java/lang/Class` object of a class or array descriptor, the same one every time.
+    pub fn mirror(&mut self, name: &str) -> Result<ObjectRef, RuntimeError> {
+        if let Some(mirror) = self.mirrors.get(name) {
+            return Ok(*mirror);
+        }
+        let mirror = self.allocate(Object { class: "java/lang/Class".to_string(), data: ObjectData::Class(name.to_string()) })?;
+        self.mirrors.insert(name.to_string(), mirror);
+        Ok(mirror)
+    }
+    /// Collects the heap, with the frames and objects of every thread, static fields, class
+    /// mirrors, interned strings and monitors as roots. Returns how many objects were freed.
+    pub fn collect(&mut self) -> usize {
+        let mut roots: Vec<ObjectRef> = self.mirrors.values().chain(self.interned.values()).chain(self.monitors.keys()).copied().collect();
+        roots.extend(self.wait_set.iter().map(|(_, object)| *object));
+        for state in self.threads.values() {
+            roots.extend(state.object);
+            if let ThreadStatus::Blocked(object) | ThreadStatus::Waiting(object, _) = state.status {
+                roots.push(object);
+            }
+        }
+        let parked = self.threads.values().flat_map(|state| &state.frames);
+        let values = self.frames.iter().chain(parked).flat_map(|frame| frame.locals.iter().chain(&frame.stack));
+        let statics: Vec<Value> = self.loader.classes().flat_map(|class| class.statics.lock().unwrap().values().cloned().collect::<Vec<_>>()).collect();
+        for value in values.chain(&statics) {
+            if let Value::Reference(Some(object)) = value {
+                roots.push(*object);
+            }
+        }
+        let freed = self.heap.collect(roots);
+        let shared = &mut **self;
+        shared.traces.retain(|exception, _| shared.heap.is_live(*exception));
+        shared.messages.retain(|exception, _| shared.heap.is_live(*exception));
+        freed
+    }
+    /// The value of a static field of a loaded class.
+    pub fn static_field(&mut self, class: &str, name: &str) -> Option<Value> {
+        self.loader.class(class).ok()?.statics.lock().unwrap().get(name).cloned()
+    }
+    /// Initializes `class` the first time it is used, as JVMS §5.5 describes. A class whose
+    /// initialization failed stays unusable.
+    fn initialize(&mut self, name: &str) -> Result<(), RuntimeError> {
+        let class = self.loader.class(name)?;
+        let state = *class.state.lock().unwrap();
+        match state {
+            // another thread initializing the class is waited for (step 2), then the class is
+            // seen as it left it
+            InitState::InProgress if self.initializers.get(name).is_some_and(|thread| *thread != self.thread) => {
+                self.block(ThreadStatus::Initializing(name.to_string()))?;
+                return self.initialize(name);
+            },
+            // a request from within the initialization sees the class as it is (step 3)
+            InitState::InProgress | InitState::Initialized => return Ok(()),
+            InitState::Erroneous => return Err(RuntimeError::exception("java/lang/NoClassDefFoundError", Some(format!(
+                "Could not initialize class {}", name.replace('/', "."),
+            )))),
+            InitState::Uninitialized => {},
+        }
+        *class.state.lock().unwrap() = InitState::InProgress;
+        let thread = self.thread;
+        self.initializers.insert(name.to_string(), thread);
+        let result = self.run_initializer(&class);
+        self.initializers.remove(name);
+        *class.state.lock().unwrap() = if result.is_ok() { InitState::Initialized } else { InitState::Erroneous };
+        self.lock.notify();
+        result
+    }
+    /// Initializes the super class of `class` and its super interfaces declaring default
+    /// methods, then its constant fields, then runs its `<clinit>`. A failure of the super
+    /// types is the failure of the class, an exception thrown by `<clinit>` other than an
+    /// `Error` is wrapped in `ExceptionInInitializerError`, whose message names it.
+    fn run_initializer(&mut self, class: &RuntimeClass<'a>) -> Result<(), RuntimeError> {
+        if !class.is_interface() {
+            let (classpath, hierarchy) = (self.loader.classpath(), self.loader.hierarchy());
+            let mut supers: Vec<&str> = hierarchy.super_name(class.name).into_iter().collect();
+            let mut pending = hierarchy.interfaces(class.name).to_vec();
+            while let Some(interface) = pending.pop() {
+                let declares_default = classpath.get(interface).is_some_and(|file| file.class.methods.0.iter()
+                    .any(|method| !method.has_flag(AccessFlags::Abstract) && !method.has_flag(AccessFlags::Static)));
+                if declares_default && !supers.contains(&interface) {
+                    supers.push(interface);
+                }
+                pending.extend(hierarchy.interfaces(interface));
+            }
+            for super_name in supers.into_iter().filter(|name| classpath.contains(name)) {
+                self.initialize(super_name)?;
+            }
+        }
+        let raw = class.raw;
+        for field in raw.fields.0.iter().filter(|field| field.has_flag(AccessFlags::Static) && field.has_flag(AccessFlags::Final)) {
+            if let Some(index) = field.constant_value(&raw.cp)? {
+                let value = self.constant(&raw.cp, index)?;
+                class.statics.lock().unwrap().insert(field.name(&raw.cp)?.to_string(), value);
+            }
+        }
+        let clinit = match self.loader.find_method(class.name, "<clinit>", "()V")? {
+            Some(clinit) => clinit,
+            None => return Ok(()),
+        };
+        let exception = match self.execute(clinit, Vec::new()) {
+            Ok(_) => return Ok(()),
+            Err(err) => self.throwable_of(err)?,
+        };
+        if self.is_error(self.heap.class(exception)) {
+            return Err(RuntimeError::Thrown(exception));
+        }
+        self.heap.pin(exception);
+        let wrapped = self.new_throwable("java/lang/ExceptionInInitializerError", None, Some(exception));
+        self.heap.unpin(exception);
+        Err(RuntimeError::Thrown(wrapped?))
+    }
+    /// Whether `class` is a `java/lang/Error`, going by its name when the class path lacks it.
+    fn is_error(&self, class: &str) -> bool {
+        match self.loader.classpath().contains(class) {
+            true => self.loader.hierarchy().is_subtype(class, "java/lang/Error"),
+            false => class.ends_with("Error"),
+        }
+    }
+    /// Pushes a frame for `method`, which starts running with the next instruction. A native
+    /// method runs at once instead, its result pushed on the running frame.
+    fn call(&mut self, method: Arc<Method<'a>>, args: Vec<Value>, return_address: Option<usize>) -> Result<(), RuntimeError> {
+        if method.is_native() {
+            if let Some(result) = self.call_native(&method, args)? {
+                self.frame().push(result)?;
+            }
+            return Ok(());
+        }
+        if method.is_abstract() {
+            return Err(RuntimeError::exception("java/lang/AbstractMethodError", Some(method.signature_string())));
+        }
+        if self.frames.len() >= MAX_FRAMES + if self.throwing { RESERVED_FRAMES } else { 0 } {
+            return Err(RuntimeError::exception("java/lang/StackOverflowError", None));
+        }
+        let monitor = self.monitor_of(&method, &args)?;
+        let mut frame = Frame::new(method, args, return_address)?;
+        if let Some(object) = monitor {
+            self.synchronize(object, &frame.locals)?;
+            frame.monitor = Some(object);
+        }
+        self.frames.push(frame);
+        Ok(())
+    }
+    /// The object whose monitor `method` runs in if it is `synchronized`: its receiver, or
+    /// the mirror of its class for a static method.
+    fn monitor_of(&mut self, method: &Method<'a>, args: &[Value]) -> Result<Option<ObjectRef>, RuntimeError> {
+        match (method.is_synchronized(), method.is_static()) {
+            (false, _) => Ok(None),
+            (true, true) => self.mirror(method.class).map(Some),
+            (true, false) => Self::receiver(args).map(Some),
+        }
+    }
+    /// Enters the monitor of a `synchronized` method about to run with `args`, which nothing
+    /// else refers to while the thread blocks.
+    fn synchronize(&mut self, object: ObjectRef, args: &[Value]) -> Result<(), RuntimeError> {
+        self.heap.pin_values(args);
+        let entered = self.enter_monitor(object);
+        self.heap.unpin_values(args);
+        entered
+    }
+    /// Runs a native method, in the monitor of [Self::monitor_of] if it is `synchronized`.
+    fn call_native(&mut self, method: &Method<'a>, args: Vec<Value>) -> Result<Option<Value>, RuntimeError> {
+        let monitor = self.monitor_of(method, &args)?;
+        if let Some(object) = monitor {
+            self.synchronize(object, &args)?;
+        }
+        let result = self.run_native(method, args);
+        if let Some(object) = monitor {
+            self.exit_monitor(object);
+        }
+        result
+    }
+    /// Runs the binding of a native method, converting `args` to the types it declares and its
+    /// result back.
+    fn run_native(&mut self, method: &Method<'a>, args: Vec<Value>) -> Result<Option<Value>, RuntimeError> {
+        // the natives of the class library that need the heap
+        match (method.class, method.name, method.descriptor, &args[..]) {
+            (OBJECT, "getClass", "()Ljava/lang/Class;", [receiver]) | ("java/lang/System", "identityHashCode", "(Ljava/lang/Object;)I", [receiver]) => {
+                let object = match receiver {
+                    Value::Reference(Some(object)) => *object,
+                    Value::Reference(None) if method.name == "identityHashCode" => return Ok(Some(Value::Int(0))),
+                    _ => return Err(null_pointer()),
+                };
+                let class = self.heap.class(object).to_string();
+                return Ok(Some(match method.name {
+                    "getClass" => Value::Reference(Some(self.mirror(&class)?)),
+                    _ => Value::Int(object.index() as i32),
+                }));
+            },
+            ("java/lang/Class", "getName", "()Ljava/lang/String;", [receiver]) => {
+                let name = match &self.heap.get(Self::receiver(std::slice::from_ref(receiver))?).data {
+                    ObjectData::Class(name) => name.replace('/', "."),
+                    _ => return Err(self.invalid("getName of a class without a mirror")),
+                };
+                return Ok(Some(Value::Reference(Some(self.new_string(name)?))));
+            },
+            (THROWABLE, "fillInStackTrace", "()Ljava/lang/Throwable;", [receiver]) => {
+                let exception = Self::receiver(std::slice::from_ref(receiver))?;
+                let trace = self.capture_stack_trace(exception);
+                self.traces.insert(exception, trace);
+                return Ok(Some(receiver.clone()));
+            },
+            (THROWABLE, "printStackTrace", "()V", [receiver]) => {
+                let text = self.stack_trace(Self::receiver(std::slice::from_ref(receiver))?);
+                self.print_error(text)?;
+                return Ok(None);
+            },
+            ("java/lang/System", "arraycopy", "(Ljava/lang/Object;ILjava/lang/Object;II)V", [source, Value::Int(from), destination, Value::Int(to), Value::Int(length)]) => {
+                self.array_copy(source, *from, destination, *to, *length)?;
+                return Ok(None);
+            },
+            (OBJECT, "wait", "(J)V", [receiver, Value::Long(millis)]) => {
+                self.wait(Self::receiver(std::slice::from_ref(receiver))?, *millis)?;
+                return Ok(None);
+            },
+            (OBJECT, "notify" | "notifyAll", "()V", [receiver]) => {
+                self.notify(Self::receiver(std::slice::from_ref(receiver))?, method.name == "notifyAll")?;
+                return Ok(None);
+            },
+            (THREAD, "start0", "()V", [receiver]) => {
+                self.start_thread(Self::receiver(std::slice::from_ref(receiver))?);
+                return Ok(None);
+            },
+            (THREAD, "join", "(J)V", [receiver, Value::Long(millis)]) => {
+                self.join(Self::receiver(std::slice::from_ref(receiver))?, *millis)?;
+                return Ok(None);
+            },
+            (THREAD, "sleep", "(J)V", [Value::Long(millis)]) => {
+                self.sleep(*millis)?;
+                return Ok(None);
+            },
+            (THREAD, "currentThread", "()Ljava/lang/Thread;", []) => return Ok(Some(Value::Reference(Some(self.current_thread()?)))),
+            (THREAD, "interrupt" | "isInterrupted" | "isAlive", _, [receiver]) => {
+                let thread = self.thread_of(Self::receiver(std::slice::from_ref(receiver))?);
+                let state = thread.and_then(|thread| self.threads.get_mut(&thread));
+                return Ok(match method.name {
+                    "interrupt" => {
+                        if let Some(state) = state {
+                            state.interrupted = true;
+                            self.lock.notify();
+                        }
+                        None
+                    },
+                    "isInterrupted" => Some(Value::Int(state.is_some_and(|state| state.interrupted) as i32)),
+                    _ => Some(Value::Int(state.is_some() as i32)),
+                });
+            },
+            (THREAD, "interrupted", "()Z", []) => return Ok(Some(Value::Int(mem::take(&mut self.thread_state().interrupted) as i32))),
+            _ => {},
+        }
+        if !self.natives.contains(method.class, method.name, method.descriptor) {
+            return Err(RuntimeError::exception("java/lang/UnsatisfiedLinkError", Some(format!(
+                "no native binding for {}.{}{}", method.class.replace('/', "."), method.name, method.descriptor,
+            ))));
+        }
+        let invalid = |message: String| RuntimeError::InvalidNative { method: method.signature_string(), message };
+        let receiver = (!method.is_static()).then(|| FieldType::Object(method.class.to_string()));
+        let args = receiver.iter().chain(&method.signature.params).zip(args)
+            .map(|(ty, value)| match value {
+                Value::Reference(Some(object)) => match self.heap.string(object) {
+                    Some(string) => Ok(JValue::String(string.clone())),
+                    None => Ok(JValue::Object(object)),
+                },
+                value => value.clone().to_field(ty).ok_or_else(|| invalid(format!("{:?} passed as {}", value, ty))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self.natives.call(method.class, method.name, method.descriptor, &args).expect("the method is bound")?;
+        let ty = match &method.signature.ret {
+            Some(ty) => ty,
+            None => return Ok(None),
+        };
+        Ok(Some(match result {
+            JValue::String(string) if matches!(ty, FieldType::Object(_)) => Value::Reference(Some(self.new_string(string)?)),
+            result => Value::from_field(&result).filter(|value| value.clone().to_field(ty).is_some())
+                .ok_or_else(|| invalid(format!("returned {:?} as {}", result, ty)))?,
+        }))
+    }
+    /// `System.arraycopy`, copying through a temporary as the two arrays may be the same.
+    fn array_copy(&mut self, source: &Value, from: i32, destination: &Value, to: i32, length: i32) -> Result<(), RuntimeError> {
+        let (source, destination) = match (source, destination) {
+            (Value::Reference(Some(source)), Value::Reference(Some(destination))) => (*source, *destination),
+            _ => return Err(null_pointer()),
+        };
+        let (source_class, destination_class) = (self.heap.class(source).to_string(), self.heap.class(destination).to_string());
+        let array_store = |message: String| RuntimeError::exception("java/lang/ArrayStoreException", Some(message));
+        let source_length = match &self.heap.get(source).data {
+            ObjectData::Array(elements) => elements.len(),
+            _ => return Err(array_store(format!("arraycopy: source type {} is not an array", source_class.replace('/', ".")))),
+        };
+        let destination_length = match &self.heap.get(destination).data {
+            ObjectData::Array(elements) => elements.len(),
+            _ => return Err(array_store(format!("arraycopy: destination type {} is not an array", destination_class.replace('/', ".")))),
+        };
+        if from < 0 || to < 0 || length < 0 || from as usize + length as usize > source_length || to as usize + length as usize > destination_length {
+            return Err(RuntimeError::exception("java/lang/ArrayIndexOutOfBoundsException", Some(format!(
+                "arraycopy: copying {} elements from {} of a length {} array to {} of a length {} array", length, from, source_length, to, destination_length,
+            ))));
+        }
+        let elements: Vec<Value> = match &self.heap.get(source).data {
+            ObjectData::Array(elements) => (from as usize..(from + length) as usize).filter_map(|index| elements.get(index)).collect(),
+            _ => unreachable!(),
+        };
+        // references are checked against the component type of the destination one by one
+        let component = destination_class.strip_prefix('[').and_then(|component| FieldType::parse(component).ok()).and_then(|component| component.class_name());
+        for (offset, element) in elements.into_iter().enumerate() {
+            if let (Some(component), Value::Reference(Some(object))) = (&component, &element) {
+                if !self.is_assignable(self.heap.class(*object), component) {
+                    return Err(array_store(format!("arraycopy: element type mismatch, {} stored into {}", self.heap.class(*object).replace('/', "."), destination_class)));
+                }
+            }
+            if let ObjectData::Array(elements) = &mut self.heap.get_mut(destination).data {
+                elements.set(to as usize + offset, element).map_err(|_| array_store(format!(
+                    "arraycopy: type mismatch, can not copy {} into {}", source_class, destination_class,
+                )))?;
+            }
+        }
+        Ok(())
+    }
+    /// Runs instructions until the frame at `base` returns.
+    fn run(&mut self, base: usize) -> Result<Option<Value>, RuntimeError> {
+        loop {
+            self.ticks += 1;
+            if self.ticks == TIME_SLICE {
+                self.ticks = 0;
+                self.yield_lock();
+            }
+            // between two instructions, every live object is reachable from a root
+            if self.heap.should_collect() {
+                self.collect();
+            }
+            let frame = self.frame();
+            let method = frame.method.clone();
+            let instruction = match method.instructions().get(frame.pc) {
+                Some(instruction) => instruction,
+                None => return Err(frame.invalid("execution falls off the end of the code")),
+            };
+            frame.pc += 1;
+            let step = match self.step(instruction) {
+                Ok(step) => step,
+                Err(err) => {
+                    let exception = self.throwable_of(err)?;
+                    self.unwind(base, exception)?;
+                    continue;
+                },
+            };
+            if let Step::Return(value) = step {
+                let frame = self.frames.pop().unwrap();
+                // the monitor entered by a `synchronized` method must still be owned (JVMS §6.5 areturn)
+                if frame.monitor.is_some_and(|object| !self.exit_monitor(object)) {
+                    let exception = self.throwable_of(illegal_monitor_state())?;
+                    self.unwind(base, exception)?;
+                    continue;
+                }
+                if self.frames.len() == base {
+                    return Ok(value);
+                }
+                let caller = self.frame();
+                if let Some(address) = frame.return_address {
+                    caller.pc = address;
+                }
+                if let Some(value) = value {
+                    caller.push(value)?;
+                }
+            }
+        }
+    }
+    /// Pops frames down to `base` until one has a handler for `exception`, which it resumes
+    /// at with only the exception on its operand stack. The exception is left
+    /// [thrown](RuntimeError::Thrown) when no frame catches it.
+    fn unwind(&mut self, base: usize, exception: ObjectRef) -> Result<(), RuntimeError> {
+        if !self.traces.contains_key(&exception) {
+            let trace = self.capture_stack_trace(exception);
+            self.traces.insert(exception, trace);
+        }
+        while self.frames.len() > base {
+            if let Some(handler) = self.handler(exception)? {
+                let frame = self.frame();
+                frame.clear_stack();
+                frame.pc = handler;
+                return frame.push(Value::Reference(Some(exception)));
+            }
+            if let Some(object) = self.frames.pop().and_then(|frame| frame.monitor) {
+                self.exit_monitor(object);
+            }
+        }
+        Err(RuntimeError::Thrown(exception))
+    }
+    /// Index of the first instruction of the handler the running frame has for `exception` at
+    /// its current pc, from the exception table of its code (JVMS §2.10).
+    fn handler(&self, exception: ObjectRef) -> Result<Option<usize>, RuntimeError> {
+        let frame = self.frames.last().expect("no frame is running");
+        let (pc, class) = (frame.current_pc(), self.heap.class(exception));
+        for entry in frame.method.code.map_or(&[][..], |code| &code.exception_table.0) {
+            if pc < entry.start_pc as u32 || pc >= entry.end_pc as u32 {
+                continue;
+            }
+            if entry.catch_type == 0 || self.is_assignable(class, frame.cp().get_class_name(entry.catch_type)?) {
+                return frame.method.index_of(entry.handler_pc as u32).map(Some)
+                    .ok_or_else(|| frame.invalid(format!("handler at pc {} is not an instruction", entry.handler_pc)));
+            }
+        }
+        Ok(None)
+    }
+    /// The object thrown for `err`, made for an exception the interpreter or a native method
+    /// throws. An error that isn't a Java exception is returned as it is.
+    fn throwable_of(&mut self, err: RuntimeError) -> Result<ObjectRef, RuntimeError> {
+        match err {
+            RuntimeError::Thrown(exception) => Ok(exception),
+            RuntimeError::Exception { class, message } => self.new_throwable(&class, message, None),
+            err => Err(err),
+        }
+    }
+    /// A new throwable of `class` made by its constructor taking `cause`, `message` or nothing.
+    /// Without the class or such a constructor, its fields are left unset and the message is
+    /// remembered by the interpreter.
+    fn new_throwable(&mut self, class: &str, message: Option<String>, cause: Option<ObjectRef>) -> Result<ObjectRef, RuntimeError> {
+        let throwing = std::mem::replace(&mut self.throwing, true);
+        let result = self.construct_throwable(class, message, cause);
+        self.throwing = throwing;
+        result
+    }
+    fn construct_throwable(&mut self, class: &str, message: Option<String>, cause: Option<ObjectRef>) -> Result<ObjectRef, RuntimeError> {
+        let descriptor = match (cause, &message) {
+            (Some(_), _) => "(Ljava/lang/Throwable;)V",
+            (None, Some(_)) => "(Ljava/lang/String;)V",
+            (None, None) => "()V",
+        };
+        let (object, constructor) = match self.loader.classpath().contains(class) {
+            true => (self.instantiate(class)?, self.loader.find_method(class, "<init>", descriptor)?),
+            false => (self.allocate(Object { class: class.to_string(), data: ObjectData::Instance(Instance::new(Arc::default())) })?, None),
+        };
+        let constructor = match constructor {
+            Some(constructor) => constructor,
+            None => {
+                let message = message.or_else(|| cause.map(|cause| self.uncaught(RuntimeError::Thrown(cause)).to_string()));
+                self.messages.extend(message.map(|message| (object, message)));
+                let trace = self.capture_stack_trace(object);
+                self.traces.insert(object, trace);
+                return Ok(object);
+            },
+        };
+        let argument = match (cause, message) {
+            (Some(cause), _) => Some(cause),
+            (None, Some(message)) => {
+                self.heap.pin(object);
+                let message = self.new_string(message);
+                self.heap.unpin(object);
+                Some(message?)
+            },
+            (None, None) => None,
+        };
+        let args: Vec<Value> = std::iter::once(Some(object)).chain(argument.map(Some)).map(Value::Reference).collect();
+        self.heap.pin_values(&args);
+        let constructed = self.execute(constructor, args.clone());
+        self.heap.unpin_values(&args);
+        constructed?;
+        Ok(object)
+    }
+    /// The frames running, innermost first, less those constructing `exception`.
+    fn capture_stack_trace(&self, exception: ObjectRef) -> Vec<StackTraceElement> {
+        let class = self.heap.class(exception);
+        self.frames.iter().rev()
+            .skip_while(|frame| frame.method.name == "<init>" && self.is_assignable(class, frame.method.class))
+            .map(Frame::trace_element)
+            .collect()
+    }
+    /// `err` as the code calling into the interpreter sees it, a thrown object by its class and message.
+    fn uncaught(&self, err: RuntimeError) -> RuntimeError {
+        match err {
+            RuntimeError::Thrown(exception) => RuntimeError::Exception {
+                class: self.heap.class(exception).to_string(),
+                message: match self.messages.get(&exception) {
+                    Some(message) => Some(message.clone()),
+                    None => self.throwable_field(exception, "detailMessage").and_then(|message| self.heap.string(message)).map(JString::to_string),
+                },
+            },
+            err => err,
+        }
+    }
+    /// A reference field `java/lang/Throwable` declares, of an instance of one of its subclasses.
+    fn throwable_field(&self, exception: ObjectRef, name: &str) -> Option<ObjectRef> {
+        self.reference_field(exception, THROWABLE, name).filter(|object| *object != exception)
+    }
+    /// A reference field `class` declares, of an instance of it or one of its subclasses.
+    fn reference_field(&self, object: ObjectRef, class: &str, name: &str) -> Option<ObjectRef> {
+        match &self.heap.get(object).data {
+            ObjectData::Instance(instance) => match instance.fields.get(instance.layout.slot(class, name)?) {
+                Some(JValue::Object(object)) => Some(*object),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    /// Writes `text` to `System.err` with the native method of the class library, or to the
+    /// standard error if it isn't bound.
+    fn print_error(&mut self, text: String) -> Result<(), RuntimeError> {
+        let args = [JValue::Int(2), JValue::String(text.as_str().into())];
+        match self.natives.call("java/io/PrintStream", "write", "(ILjava/lang/String;)V", &args) {
+            Some(written) => written.map(drop).map_err(RuntimeError::from),
+            None => {
+                eprint!("{}", text);
+                Ok(())
+            },
+        }
+    }
+
+    /// What the other threads know of the thread the interpreter runs.
+    fn thread_state(&mut self) -> &mut ThreadState<'a> {
+        let thread = self.thread;
+        self.threads.get_mut(&thread).expect("a running thread is registered")
+    }
+    /// Leaves the frames of the thread where the collector finds them while another thread
+    /// holds the lock, which it then no longer reaches.
+    fn park(&mut self) {
+        let frames = mem::take(&mut self.frames);
+        self.thread_state().frames = frames;
+        self.holding = false;
+    }
+    fn unpark(&mut self) {
+        self.holding = true;
+        self.frames = mem::take(&mut self.thread_state().frames);
+    }
+    /// Lets the other threads run until one of them signals a change or `deadline` passes.
+    fn wait_for_change(&mut self, deadline: Option<Instant>) {
+        self.park();
+        self.lock.wait(self.thread, deadline);
+        self.unpark();
+    }
+    /// Lets a thread waiting for the lock run before this one goes on.
+    fn yield_lock(&mut self) {
+        if self.lock.is_contended() {
+            self.park();
+            self.lock.yield_now(self.thread);
+            self.unpark();
+        }
+    }
+    /// Gives the thread `status` until it no longer has to wait, letting the others run
+    /// meanwhile. Fails with [RuntimeError::Deadlock] once no thread can go on by itself.
+    fn block(&mut self, status: ThreadStatus) -> Result<(), RuntimeError> {
+        let deadline = status.deadline();
+        self.thread_state().status = status;
+        let result = loop {
+            // a deadlock ends every thread that waits, even for what a thread it ended released
+            if let Some(dump) = &self.deadlock {
+                break Err(RuntimeError::Deadlock(dump.clone()));
+            }
+            if !self.must_wait(self.thread, &self.threads[&self.thread]) {
+                break Ok(());
+            }
+            if self.threads.iter().all(|(thread, state)| !state.status.is_timed() && self.must_wait(*thread, state)) {
+                self.deadlock = Some(self.thread_dump());
+                self.lock.notify();
+                continue;
+            }
+            self.wait_for_change(deadline);
+        };
+        self.thread_state().status = ThreadStatus::Runnable;
+        result
+    }
+    /// Whether what the status of `thread` says it waits for didn't happen yet.
+    fn must_wait(&self, thread: usize, state: &ThreadState<'a>) -> bool {
+        let pending = |deadline: Option<Instant>| !state.interrupted && deadline.is_none_or(|deadline| Instant::now() < deadline);
+        match &state.status {
+            ThreadStatus::Runnable => false,
+            ThreadStatus::Blocked(object) => self.monitors.contains_key(object),
+            ThreadStatus::Waiting(object, deadline) => pending(*deadline) && self.wait_set.contains(&(thread, *object)),
+            ThreadStatus::Joining(other, deadline) => pending(*deadline) && self.threads.contains_key(other),
+            ThreadStatus::Sleeping(deadline) => pending(Some(*deadline)),
+            ThreadStatus::Initializing(class) => self.initializers.contains_key(class),
+        }
+    }
+    /// Enters the monitor of `object`, blocking while another thread owns it.
+    fn enter_monitor(&mut self, object: ObjectRef) -> Result<(), RuntimeError> {
+        let thread = self.thread;
+        match self.monitors.get_mut(&object) {
+            Some(monitor) if monitor.owner == thread => {
+                monitor.entries += 1;
+                return Ok(());
+            },
+            Some(_) => self.block(ThreadStatus::Blocked(object))?,
+            None => {},
+        }
+        self.monitors.insert(object, Monitor { owner: thread, entries: 1 });
+        Ok(())
+    }
+    /// Exits the monitor of `object` once, releasing it after as many exits as entries.
+    /// Returns whether the thread owned it.
+    fn exit_monitor(&mut self, object: ObjectRef) -> bool {
+        let thread = self.thread;
+        let monitor = match self.monitors.get_mut(&object) {
+            Some(monitor) if monitor.owner == thread => monitor,
+            _ => return false,
+        };
+        monitor.entries -= 1;
+        if monitor.entries == 0 {
+            self.monitors.remove(&object);
+            self.lock.notify();
+        }
+        true
+    }
+    /// The monitor of `object`, which the thread must own to wait on or notify it.
+    fn owned_monitor(&mut self, object: ObjectRef) -> Result<Monitor, RuntimeError> {
+        let thread = self.thread;
+        self.monitors.get(&object).filter(|monitor| monitor.owner == thread).copied().ok_or_else(illegal_monitor_state)
+    }
+    /// `Object.wait`: releases the monitor of `object` until another thread notifies it, the
+    /// thread is interrupted or `millis` pass, then enters it again as many times as it had.
+    fn wait(&mut self, object: ObjectRef, millis: i64) -> Result<(), RuntimeError> {
+        let deadline = deadline_after(millis)?;
+        let monitor = self.owned_monitor(object)?;
+        if mem::take(&mut self.thread_state().interrupted) {
+            return Err(interrupted(None));
+        }
+        let thread = self.thread;
+        self.monitors.remove(&object);
+        self.lock.notify();
+        self.wait_set.push((thread, object));
+        let waited = self.block(ThreadStatus::Waiting(object, deadline));
+        self.wait_set.retain(|waiting| *waiting != (thread, object));
+        waited?;
+        self.enter_monitor(object)?;
+        self.monitors.insert(object, monitor);
+        match mem::take(&mut self.thread_state().interrupted) {
+            true => Err(interrupted(None)),
+            false => Ok(()),
+        }
+    }
+    /// `Object.notify` and `notifyAll`, which wake the thread that waited on `object` the
+    /// longest or all of them.
+    fn notify(&mut self, object: ObjectRef, all: bool) -> Result<(), RuntimeError> {
+        self.owned_monitor(object)?;
+        match all {
+            true => self.wait_set.retain(|(_, waiting)| *waiting != object),
+            false => if let Some(index) = self.wait_set.iter().position(|(_, waiting)| *waiting == object) {
+                self.wait_set.remove(index);
+            },
+        }
+        self.lock.notify();
+        Ok(())
+    }
+    /// `Thread.start`: runs the `run` method of `object` on a new thread.
+    fn start_thread(&mut self, object: ObjectRef) {
+        let thread = self.next_thread;
+        self.next_thread += 1;
+        self.threads.insert(thread, ThreadState::new(Some(object)));
+        let interpreter = Self::on_thread(self.lock.clone(), thread, false);
+        let launcher = self.launcher.as_ref().expect("threads are started within a call into the interpreter");
+        launcher.send(interpreter).expect("the launcher runs while a call into the interpreter lasts");
+    }
+    /// `Thread.join`: waits until the thread of `object` ends or `millis` pass.
+    fn join(&mut self, object: ObjectRef, millis: i64) -> Result<(), RuntimeError> {
+        let deadline = deadline_after(millis)?;
+        if let Some(thread) = self.thread_of(object) {
+            self.block(ThreadStatus::Joining(thread, deadline))?;
+            if mem::take(&mut self.thread_state().interrupted) {
+                return Err(interrupted(None));
+            }
+        }
+        Ok(())
+    }
+    fn sleep(&mut self, millis: i64) -> Result<(), RuntimeError> {
+        let deadline = deadline_after(millis)?.unwrap_or_else(Instant::now);
+        self.block(ThreadStatus::Sleeping(deadline))?;
+        match mem::take(&mut self.thread_state().interrupted) {
+            true => Err(interrupted(Some("sleep interrupted"))),
+            false => Ok(()),
+        }
+    }
+    /// The `java/lang/Thread` of the running thread, which is made the first time it is asked
+    /// for on the main thread.
+    fn current_thread(&mut self) -> Result<ObjectRef, RuntimeError> {
+        if let Some(object) = self.thread_state().object {
+            return Ok(object);
+        }
+        let object = self.instantiate(THREAD)?;
+        self.thread_state().object = Some(object);
+        let constructor = self.loader.find_method(THREAD, "<init>", "(Ljava/lang/String;)V")?
+            .ok_or_else(|| RuntimeError::exception("java/lang/NoSuchMethodError", Some("java.lang.Thread.<init>(Ljava/lang/String;)V".to_string())))?;
+        let args = vec![Value::Reference(Some(object)), Value::Reference(Some(self.new_string("main")?))];
+        self.heap.pin_values(&args);
+        let constructed = self.execute(constructor, args.clone());
+        self.heap.unpin_values(&args);
+        constructed.map(|_| object)
+    }
+    /// The id of the thread of `object`, unless it wasn't started or has ended.
+    fn thread_of(&self, object: ObjectRef) -> Option<usize> {
+        self.threads.iter().find(|(_, state)| state.object == Some(object)).map(|(thread, _)| *thread)
+    }
+    /// The name of a thread, as `Thread.getName` returns it.
+    fn thread_name(&self, thread: usize) -> String {
+        let object = self.threads.get(&thread).and_then(|state| state.object);
+        match object.and_then(|object| self.reference_field(object, THREAD, "name")).and_then(|name| self.heap.string(name)) {
+            Some(name) => name.to_string(),
+            None if thread == MAIN_THREAD => "main".to_string(),
+            None => format!("Thread-{}", thread),
+        }
+    }
+    /// `object` as thread dumps show it.
+    fn describe(&self, object: ObjectRef) -> String {
+        format!("<#{}> (a {})", object.index(), self.heap.class(object).replace('/', "."))
+    }
+    /// The thread that a thread blocked with no deadline waits for, with what it waits for
+    /// it to do up to its name.
+    fn waits_for(&self, state: &ThreadState<'a>) -> Option<(usize, String)> {
+        match &state.status {
+            ThreadStatus::Blocked(object) => self.monitors.get(object)
+                .map(|monitor| (monitor.owner, format!("waiting to lock monitor {},\n  which is held by ", self.describe(*object)))),
+            ThreadStatus::Joining(thread, None) => Some((*thread, "waiting for the end of ".to_string())),
+            ThreadStatus::Initializing(class) => self.initializers.get(class)
+                .map(|thread| (*thread, format!("waiting for the initialization of {} by ", class.replace('/', ".")))),
+            _ => None,
+        }
+    }
+    /// The cycles of threads each waiting for the next, the last for the first.
+    fn deadlocks(&self) -> Vec<Vec<usize>> {
+        let mut cycles: Vec<Vec<usize>> = Vec::new();
+        for start in self.threads.keys() {
+            let mut path = vec![*start];
+            while let Some((next, _)) = path.last().and_then(|thread| self.threads.get(thread)).and_then(|state| self.waits_for(state)) {
+                if let Some(index) = path.iter().position(|thread| *thread == next) {
+                    if !cycles.iter().any(|cycle| cycle.contains(&next)) {
+                        cycles.push(path.split_off(index));
+                    }
+                    break;
+                }
+                path.push(next);
+            }
+        }
+        cycles
+    }
+    fn frame(&mut self) -> &mut Frame<'a> {
+        self.frames.last_mut().expect("no frame is running")
+    }
+    /// See [Frame::invalid].
+    fn invalid(&self, message: impl Into<String>) -> RuntimeError {
+        self.frames.last().expect("no frame is running").invalid(message)
+    }
+
+    fn step(&mut self, instruction: &'a Instruction) -> Result<Step, RuntimeError> {
+        use Instruction as I;
+        match instruction {
+            I::Nop => {},
+            I::AconstNull => self.frame().push(Value::null())?,
+            I::IconstM1 => self.frame().push(Value::Int(-1))?,
+            I::Iconst0 => self.frame().push(Value::Int(0))?,
+            I::Iconst1 => self.frame().push(Value::Int(1))?,
+            I::Iconst2 => self.frame().push(Value::Int(2))?,
+            I::Iconst3 => self.frame().push(Value::Int(3))?,
+            I::Iconst4 => self.frame().push(Value::Int(4))?,
+            I::Iconst5 => self.frame().push(Value::Int(5))?,
+            I::Lconst0 => self.frame().push(Value::Long(0))?,
+            I::Lconst1 => self.frame().push(Value::Long(1))?,
+            I::Fconst0 => self.frame().push(Value::Float(0.0))?,
+            I::Fconst1 => self.frame().push(Value::Float(1.0))?,
+            I::Fconst2 => self.frame().push(Value::Float(2.0))?,
+            I::Dconst0 => self.frame().push(Value::Double(0.0))?,
+            I::Dconst1 => self.frame().push(Value::Double(1.0))?,
+            I::Bipush(value) => self.frame().push(Value::Int(*value as i8 as i32))?,
+            I::Sipush(value) => self.frame().push(Value::Int(*value as i16 as i32))?,
+            I::Ldc(index) => self.ldc(*index as u16)?,
+            I::LdcW(index) | I::Ldc2W(index) => self.ldc(*index)?,
+
+            I::Iload(index) | I::Lload(index) | I::Fload(index) | I::Dload(index) | I::Aload(index) => self.load(*index as usize)?,
+            I::Iload0 | I::Lload0 | I::Fload0 | I::Dload0 | I::Aload0 => self.load(0)?,
+            I::Iload1 | I::Lload1 | I::Fload1 | I::Dload1 | I::Aload1 => self.load(1)?,
+            I::Iload2 | I::Lload2 | I::Fload2 | I::Dload2 | I::Aload2 => self.load(2)?,
+            I::Iload3 | I::Lload3 | I::Fload3 | I::Dload3 | I::Aload3 => self.load(3)?,
+            I::Iaload | I::Laload | I::Faload | I::Daload | I::Aaload | I::Baload | I::Caload | I::Saload => self.array_load()?,
+            I::Istore(index) | I::Lstore(index) | I::Fstore(index) | I::Dstore(index) | I::Astore(index) => self.store(*index as usize)?,
+            I::Istore0 | I::Lstore0 | I::Fstore0 | I::Dstore0 | I::Astore0 => self.store(0)?,
+            I::Istore1 | I::Lstore1 | I::Fstore1 | I::Dstore1 | I::Astore1 => self.store(1)?,
+            I::Istore2 | I::Lstore2 | I::Fstore2 | I::Dstore2 | I::Astore2 => self.store(2)?,
+            I::Istore3 | I::Lstore3 | I::Fstore3 | I::Dstore3 | I::Astore3 => self.store(3)?,
+            I::Iastore | I::Lastore | I::Fastore | I::Dastore | I::Aastore | I::Bastore | I::Castore | I::Sastore => self.array_store()?,
+
+            // the forms of the stack instructions only differ in how many slots each group takes
+            I::Pop => drop(self.frame().pop_slots(1)?),
+            I::Pop2 => drop(self.frame().pop_slots(2)?),
+            I::Dup => self.duplicate(1, 0)?,
+            I::DupX1 => self.duplicate(1, 1)?,
+            I::DupX2 => self.duplicate(1, 2)?,
+            I::Dup2 => self.duplicate(2, 0)?,
+            I::Dup2X1 => self.duplicate(2, 1)?,
+            I::Dup2X2 => self.duplicate(2, 2)?,
+            I::Swap => {
+                let frame = self.frame();
+                let top = frame.pop_slots(1)?;
+                let below = frame.pop_slots(1)?;
+                for value in top.into_iter().chain(below) {
+                    frame.push(value)?;
+                }
+            },
+
+            I::Iadd => self.ints(|a, b| Ok(a.wrapping_add(b)))?,
+            I::Ladd => self.longs(|a, b| Ok(a.wrapping_add(b)))?,
+            I::Fadd => self.floats(|a, b| a + b)?,
+            I::Dadd => self.doubles(|a, b| a + b)?,
+            I::Isub => self.ints(|a, b| Ok(a.wrapping_sub(b)))?,
+            I::Lsub => self.longs(|a, b| Ok(a.wrapping_sub(b)))?,
+            I::Fsub => self.floats(|a, b| a - b)?,
+            I::Dsub => self.doubles(|a, b| a - b)?,
+            I::Imul => self.ints(|a, b| Ok(a.wrapping_mul(b)))?,
+            I::Lmul => self.longs(|a, b| Ok(a.wrapping_mul(b)))?,
+            I::Fmul => self.floats(|a, b| a * b)?,
+            I::Dmul => self.doubles(|a, b| a * b)?,
+            I::Idiv => self.ints(|a, b| if b == 0 { Err(divide_by_zero()) } else { Ok(a.wrapping_div(b)) })?,
+            I::Ldiv => self.longs(|a, b| if b == 0 { Err(divide_by_zero()) } else { Ok(a.wrapping_div(b)) })?,
+            I::Fdiv => self.floats(|a, b| a / b)?,
+            I::Ddiv => self.doubles(|a, b| a / b)?,
+            I::Irem => self.ints(|a, b| if b == 0 { Err(divide_by_zero()) } else { Ok(a.wrapping_rem(b)) })?,
+            I::Lrem => self.longs(|a, b| if b == 0 { Err(divide_by_zero()) } else { Ok(a.wrapping_rem(b)) })?,
+            // Rust's `%` truncates like Java's, unlike IEEE 754 remainder
+            I::Frem => self.floats(|a, b| a % b)?,
+            I::Drem => self.doubles(|a, b| a % b)?,
+            I::Ineg => {
+                let frame = self.frame();
+                let value = frame.pop_int()?;
+                frame.push(Value::Int(value.wrapping_neg()))?;
+            },
+            I::Lneg => {
+                let frame = self.frame();
+                let value = frame.pop_long()?;
+                frame.push(Value::Long(value.wrapping_neg()))?;
+            },
+            I::Fneg => {
+                let frame = self.frame();
+                let value = frame.pop_float()?;
+                frame.push(Value::Float(-value))?;
+            },
+            I::Dneg => {
+                let frame = self.frame();
+                let value = frame.pop_double()?;
+                frame.push(Value::Double(-value))?;
+            },
+            I::Ishl => self.ints(|a, b| Ok(a.wrapping_shl(b as u32 & 0x1f)))?,
+            I::Ishr => self.ints(|a, b| Ok(a.wrapping_shr(b as u32 & 0x1f)))?,
+            I::Iushr => self.ints(|a, b| Ok(((a as u32) >> (b as u32 & 0x1f)) as i32))?,
+            I::Lshl => self.shift(|a, b| a.wrapping_shl(b as u32 & 0x3f))?,
+            I::Lshr => self.shift(|a, b| a.wrapping_shr(b as u32 & 0x3f))?,
+            I::Lushr => self.shift(|a, b| ((a as u64) >> (b as u32 & 0x3f)) as i64)?,
+            I::Iand => self.ints(|a, b| Ok(a & b))?,
+            I::Land => self.longs(|a, b| Ok(a & b))?,
+            I::Ior => self.ints(|a, b| Ok(a | b))?,
+            I::Lor => self.longs(|a, b| Ok(a | b))?,
+            I::Ixor => self.ints(|a, b| Ok(a ^ b))?,
+            I::Lxor => self.longs(|a, b| Ok(a ^ b))?,
+            I::Iinc(index, delta) => self.increment(*index as usize, *delta as i32)?,
+
+            // `as` saturates and takes NaN to 0, as the JVM does
+            I::I2l => self.convert(|frame| frame.pop_int().map(|v| Value::Long(v as i64)))?,
+            I::I2f => self.convert(|frame| frame.pop_int().map(|v| Value::Float(v as f32)))?,
+            I::I2d => self.convert(|frame| frame.pop_int().map(|v| Value::Double(v as f64)))?,
+            I::L2i => self.convert(|frame| frame.pop_long().map(|v| Value::Int(v as i32)))?,
+            I::L2f => self.convert(|frame| frame.pop_long().map(|v| Value::Float(v as f32)))?,
+            I::L2d => self.convert(|frame| frame.pop_long().map(|v| Value::Double(v as f64)))?,
+            I::F2i => self.convert(|frame| frame.pop_float().map(|v| Value::Int(v as i32)))?,
+            I::F2l => self.convert(|frame| frame.pop_float().map(|v| Value::Long(v as i64)))?,
+            I::F2d => self.convert(|frame| frame.pop_float().map(|v| Value::Double(v as f64)))?,
+            I::D2i => self.convert(|frame| frame.pop_double().map(|v| Value::Int(v as i32)))?,
+            I::D2l => self.convert(|frame| frame.pop_double().map(|v| Value::Long(v as i64)))?,
+            I::D2f => self.convert(|frame| frame.pop_double().map(|v| Value::Float(v as f32)))?,
+            I::I2b => self.convert(|frame| frame.pop_int().map(|v| Value::Int(v as i8 as i32)))?,
+            I::I2c => self.convert(|frame| frame.pop_int().map(|v| Value::Int(v as u16 as i32)))?,
+            I::I2s => self.convert(|frame| frame.pop_int().map(|v| Value::Int(v as i16 as i32)))?,
+
+            I::Lcmp => {
+                let frame = self.frame();
+                let b = frame.pop_long()?;
+                let a = frame.pop_long()?;
+                frame.push(Value::Int(a.cmp(&b) as i32))?;
+            },
+            I::Fcmpl | I::Fcmpg => {
+                let frame = self.frame();
+                let b = frame.pop_float()?;
+                let a = frame.pop_float()?;
+                frame.push(Value::Int(compare(a.partial_cmp(&b), matches!(instruction, I::Fcmpg))))?;
+            },
+            I::Dcmpl | I::Dcmpg => {
+                let frame = self.frame();
+                let b = frame.pop_double()?;
+                let a = frame.pop_double()?;
+                frame.push(Value::Int(compare(a.partial_cmp(&b), matches!(instruction, I::Dcmpg))))?;
+            },
+            I::Ifeq(offset) => self.branch_int(*offset, |v| v == 0)?,
+            I::Ifne(offset) => self.branch_int(*offset, |v| v != 0)?,
+            I::Iflt(offset) => self.branch_int(*offset, |v| v < 0)?,
+            I::Ifge(offset) => self.branch_int(*offset, |v| v >= 0)?,
+            I::Ifgt(offset) => self.branch_int(*offset, |v| v > 0)?,
+            I::Ifle(offset) => self.branch_int(*offset, |v| v <= 0)?,
+            I::IfIcmpeq(offset) => self.branch_ints(*offset, |a, b| a == b)?,
+            I::IfIcmpne(offset) => self.branch_ints(*offset, |a, b| a != b)?,
+            I::IfIcmplt(offset) => self.branch_ints(*offset, |a, b| a < b)?,
+            I::IfIcmpge(offset) => self.branch_ints(*offset, |a, b| a >= b)?,
+            I::IfIcmpgt(offset) => self.branch_ints(*offset, |a, b| a > b)?,
+            I::IfIcmple(offset) => self.branch_ints(*offset, |a, b| a <= b)?,
+            I::IfAcmpeq(offset) | I::IfAcmpne(offset) => {
+                let frame = self.frame();
+                let b = frame.pop_reference()?;
+                let a = frame.pop_reference()?;
+                if (a == b) == matches!(instruction, I::IfAcmpeq(_)) {
+                    self.jump(*offset as i32)?;
+                }
+            },
+            I::Ifnull(offset) | I::Ifnonnull(offset) => {
+                let value = self.frame().pop_reference()?;
+                if value.is_none() == matches!(instruction, I::Ifnull(_)) {
+                    self.jump(*offset as i32)?;
+                }
+            },
+            I::Goto(offset) => self.jump(*offset as i32)?,
+            I::GotoW(offset) => self.jump(*offset)?,
+            I::Jsr(offset) => self.subroutine(*offset as i32)?,
+            I::JsrW(offset) => self.subroutine(*offset)?,
+            I::Ret(index) => self.ret(*index as usize)?,
+            I::Tableswitch(table) => {
+                let key = self.frame().pop_int()?;
+                self.jump(table.offset_for(key))?;
+            },
+            I::Lookupswitch(lookup) => {
+                let key = self.frame().pop_int()?;
+                let offset = match lookup.matches.binary_search_by_key(&key, |(key, _)| *key) {
+                    Ok(found) => lookup.matches[found].1,
+                    Err(_) => lookup.default,
+                };
+                self.jump(offset)?;
+            },
+            I::Ireturn | I::Lreturn | I::Freturn | I::Dreturn | I::Areturn => return Ok(Step::Return(Some(self.frame().pop()?))),
+            I::Return => return Ok(Step::Return(None)),
+
+            I::Getstatic(index) => self.static_access(*index, false)?,
+            I::Putstatic(index) => self.static_access(*index, true)?,
+            I::Getfield(index) => self.field_access(*index, false)?,
+            I::Putfield(index) => self.field_access(*index, true)?,
+            I::Invokevirtual(index) | I::Invokeinterface(index, _, _) => self.invoke_virtual(*index)?,
+            I::Invokespecial(index) => self.invoke_special(*index)?,
+            I::Invokestatic(index) => {
+                let member = self.frame().cp().get_member_ref(*index)?;
+                let method = self.loader.resolve_method(&member)?;
+                if !method.is_static() {
+                    return Err(RuntimeError::exception("java/lang/IncompatibleClassChangeError", Some(format!("{} is not static", method.signature_string()))));
+                }
+                self.initialize(method.class)?;
+                let args = self.pop_args(&method.signature, false)?;
+                let return_address = self.frame().pc;
+                self.call(method, args, Some(return_address))?;
+            },
+            I::Invokedynamic(index, _) => self.invoke_dynamic(*index)?,
+            I::New(index) => {
+                let class = self.frame().cp().get_class_name(*index)?;
+                let object = self.instantiate(class)?;
+                self.frame().push(Value::Reference(Some(object)))?;
+            },
+            I::Newarray(atype) => {
+                let length = self.frame().pop_int()?;
+                let array = self.new_array(&FieldType::Array(Box::new(atype.element())), &[length])?;
+                self.frame().push(Value::Reference(Some(array)))?;
+            },
+            I::ANewarray(index) => {
+                let component = FieldType::from_class_name(self.frame().cp().get_class_name(*index)?)?;
+                let length = self.frame().pop_int()?;
+                let array = self.new_array(&FieldType::Array(Box::new(component)), &[length])?;
+                self.frame().push(Value::Reference(Some(array)))?;
+            },
+            I::Multianewarray(index, dimensions) => {
+                let ty = FieldType::from_class_name(self.frame().cp().get_class_name(*index)?)?;
+                let frame = self.frame();
+                let mut lengths = (0..*dimensions).map(|_| frame.pop_int()).collect::<Result<Vec<_>, _>>()?;
+                lengths.reverse();
+                let array = self.new_array(&ty, &lengths)?;
+                self.frame().push(Value::Reference(Some(array)))?;
+            },
+            I::Arraylength => {
+                let array = self.frame().pop_reference()?.ok_or_else(null_pointer)?;
+                let length = match &self.heap.get(array).data {
+                    ObjectData::Array(elements) => elements.len(),
+                    _ => return Err(self.invalid(format!("arraylength of a {}", self.heap.class(array)))),
+                };
+                self.frame().push(Value::Int(length as i32))?;
+            },
+            I::Athrow => {
+                let exception = self.frame().pop_reference()?.ok_or_else(null_pointer)?;
+                return Err(RuntimeError::Thrown(exception));
+            },
+            I::Checkcast(index) => {
+                let frame = self.frame();
+                let target = frame.cp().get_class_name(*index)?;
+                let object = frame.pop_reference()?;
+                if let Some(object) = object {
+                    let class = self.heap.class(object);
+                    if !self.is_assignable(class, target) {
+                        return Err(RuntimeError::exception("java/lang/ClassCastException", Some(format!(
+                            "class {} cannot be cast to class {}", class.replace('/', "."), target.replace('/', "."),
+                        ))));
+                    }
+                }
+                self.frame().push(Value::Reference(object))?;
+            },
+            I::Instanceof(index) => {
+                let frame = self.frame();
+                let target = frame.cp().get_class_name(*index)?;
+                let object = frame.pop_reference()?;
+                let is_instance = object.is_some_and(|object| self.is_assignable(self.heap.class(object), target));
+                self.frame().push(Value::Int(is_instance as i32))?;
+            },
+            I::Monitorenter => {
+                let object = self.frame().pop_reference()?.ok_or_else(null_pointer)?;
+                self.enter_monitor(object)?;
+            },
+            I::Monitorexit => {
+                let object = self.frame().pop_reference()?.ok_or_else(null_pointer)?;
+                if !self.exit_monitor(object) {
+                    return Err(illegal_monitor_state());
+                }
+            },
+            I::Wide(op) => match op {
+                WideOp::Iload(index) | WideOp::Lload(index) | WideOp::Fload(index) | WideOp::Dload(index) | WideOp::Aload(index) => self.load(*index as usize)?,
+                WideOp::Istore(index) | WideOp::Lstore(index) | WideOp::Fstore(index) | WideOp::Dstore(index) | WideOp::Astore(index) => self.store(*index as usize)?,
+                WideOp::Ret(index) => self.ret(*index as usize)?,
+                WideOp::Iinc(index, delta) => self.increment(*index as usize, *delta as i32)?,
+            },
+        }
+        Ok(Step::Next)
+    }
+
+    fn ldc(&mut self, index: u16) -> Result<(), RuntimeError> {
+        let cp = self.frame().cp();
+        let value = self.constant(cp, index)?;
+        self.frame().push(value)
+    }
+    /// The value of a loadable constant, as `ldc` pushes it.
+    fn constant(&mut self, cp: &ConstantPool, index: u16) -> Result<Value, RuntimeError> {
+        Ok(match cp.get_info(index)? {
+            ConstantPoolInfo::Integer(value) => Value::Int(*value),
+            ConstantPoolInfo::Float(value) => Value::Float(*value),
+            ConstantPoolInfo::Long(value) => Value::Long(*value),
+            ConstantPoolInfo::Double(value) => Value::Double(*value),
+            ConstantPoolInfo::StringRef(value) => Value::Reference(Some(self.intern(JString::from_utf16(cp.get_utf16(*value)?))?)),
+            ConstantPoolInfo::ClassRef(_) => Value::Reference(Some(self.mirror(cp.get_class_name(index)?)?)),
+            other => return Err(RuntimeError::Unsupported(format!("ldc of {:?}", other))),
+        })
+    }
+    fn load(&mut self, index: usize) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let value = frame.load(index)?;
+        if let Value::ReturnAddress(_) = value {
+            return Err(frame.invalid(format!("return address in local {} is loaded", index)));
+        }
+        frame.push(value)
+    }
+    fn store(&mut self, index: usize) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let value = frame.pop()?;
+        frame.store(index, value)
+    }
+    fn increment(&mut self, index: usize, delta: i32) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        match frame.load(index)? {
+            Value::Int(value) => frame.store(index, Value::Int(value.wrapping_add(delta))),
+            other => Err(frame.invalid(format!("iinc of {:?}", other))),
+        }
+    }
+    /// Copies the top `count` slots and inserts them below the `below` slots under them.
+    fn duplicate(&mut self, count: usize, below: usize) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let top = frame.pop_slots(count)?;
+        let under = frame.pop_slots(below)?;
+        for value in top.iter().cloned().chain(under).chain(top.iter().cloned()) {
+            frame.push(value)?;
+        }
+        Ok(())
+    }
+    fn ints(&mut self, op: impl FnOnce(i32, i32) -> Result<i32, RuntimeError>) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let b = frame.pop_int()?;
+        let a = frame.pop_int()?;
+        frame.push(Value::Int(op(a, b)?))
+    }
+    fn longs(&mut self, op: impl FnOnce(i64, i64) -> Result<i64, RuntimeError>) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let b = frame.pop_long()?;
+        let a = frame.pop_long()?;
+        frame.push(Value::Long(op(a, b)?))
+    }
+    /// A `long` shifted by an `int`.
+    fn shift(&mut self, op: impl FnOnce(i64, i32) -> i64) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let b = frame.pop_int()?;
+        let a = frame.pop_long()?;
+        frame.push(Value::Long(op(a, b)))
+    }
+    fn floats(&mut self, op: impl FnOnce(f32, f32) -> f32) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let b = frame.pop_float()?;
+        let a = frame.pop_float()?;
+        frame.push(Value::Float(op(a, b)))
+    }
+    fn doubles(&mut self, op: impl FnOnce(f64, f64) -> f64) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let b = frame.pop_double()?;
+        let a = frame.pop_double()?;
+        frame.push(Value::Double(op(a, b)))
+    }
+    fn convert(&mut self, op: impl FnOnce(&mut Frame<'a>) -> Result<Value, RuntimeError>) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let value = op(frame)?;
+        frame.push(value)
+    }
+
+    /// Continues at the instruction `offset` bytes from the running one.
+    fn jump(&mut self, offset: i32) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let target = frame.current_pc() as i64 + offset as i64;
+        frame.pc = u32::try_from(target).ok().and_then(|pc| frame.method.index_of(pc))
+            .ok_or_else(|| frame.invalid(format!("jump to {}, which is not an instruction", target)))?;
+        Ok(())
+    }
+    fn branch_int(&mut self, offset: i16, taken: impl FnOnce(i32) -> bool) -> Result<(), RuntimeError> {
+        let value = self.frame().pop_int()?;
+        match taken(value) {
+            true => self.jump(offset as i32),
+            false => Ok(()),
+        }
+    }
+    fn branch_ints(&mut self, offset: i16, taken: impl FnOnce(i32, i32) -> bool) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let b = frame.pop_int()?;
+        let a = frame.pop_int()?;
+        match taken(a, b) {
+            true => self.jump(offset as i32),
+            false => Ok(()),
+        }
+    }
+    fn subroutine(&mut self, offset: i32) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let next = frame.pc;
+        frame.push(Value::ReturnAddress(next))?;
+        self.jump(offset)
+    }
+    fn ret(&mut self, index: usize) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        match frame.load(index)? {
+            Value::ReturnAddress(address) => frame.pc = address,
+            other => return Err(frame.invalid(format!("ret to {:?}", other))),
+        }
+        Ok(())
+    }
+
+    fn array_load(&mut self) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let index = frame.pop_int()?;
+        let array = frame.pop_reference()?.ok_or_else(null_pointer)?;
+        let value = match &self.heap.get(array).data {
+            ObjectData::Array(elements) => usize::try_from(index).ok().and_then(|index| elements.get(index))
+                .ok_or_else(|| out_of_bounds(index, elements.len()))?,
+            _ => return Err(self.invalid(format!("array load from a {}", self.heap.class(array)))),
+        };
+        self.frame().push(value)
+    }
+    fn array_store(&mut self) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        let value = frame.pop()?;
+        let index = frame.pop_int()?;
+        let array = frame.pop_reference()?.ok_or_else(null_pointer)?;
+        // the index is checked before the element type, as `aastore` lists its exceptions (JVMS §6.5)
+        let slot = match &self.heap.get(array).data {
+            ObjectData::Array(elements) => usize::try_from(index).ok().filter(|&slot| slot < elements.len())
+                .ok_or_else(|| out_of_bounds(index, elements.len()))?,
+            _ => return Err(self.invalid(format!("array store into a {}", self.heap.class(array)))),
+        };
+        let class = self.heap.class(array);
+        let value = match (class, value) {
+            ("[Z", Value::Int(value)) => Value::Int(value & 1),
+            (_, Value::Reference(Some(element))) => {
+                let component = FieldType::parse(&class[1..]).ok().and_then(|component| component.class_name()).unwrap_or_default();
+                let element_class = self.heap.class(element);
+                if !self.is_assignable(element_class, &component) {
+                    return Err(RuntimeError::exception("java/lang/ArrayStoreException", Some(element_class.replace('/', "."))));
+                }
+                Value::Reference(Some(element))
+            },
+            (_, value) => value,
+        };
+        let (frames, shared) = self.parts();
+        let frame = frames.last().expect("no frame is running");
+        let object = shared.heap.get_mut(array);
+        match &mut object.data {
+            ObjectData::Array(elements) => elements.set(slot, value).map_err(|value| frame.invalid(format!("{:?} stored into {}", value, object.class))),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Whether an instance of `from`, a class name or array descriptor, is assignable to `to`
+    /// as `checkcast` decides (JVMS §6.5).
+    fn is_assignable(&self, from: &str, to: &str) -> bool {
+        if from == to || to == OBJECT {
+            return true;
+        }
+        match (from.strip_prefix('['), to.strip_prefix('[')) {
+            (Some(_), None) => to == "java/lang/Cloneable" || to == "java/io/Serializable",
+            (None, Some(_)) => false,
+            (Some(from), Some(to)) => match (FieldType::parse(from), FieldType::parse(to)) {
+                (Ok(from), Ok(to)) => match (from.class_name(), to.class_name()) {
+                    (Some(from), Some(to)) => self.is_assignable(&from, &to),
+                    _ => from == to,
+                },
+                _ => false,
+            },
+            (None, None) => self.loader.hierarchy().is_subtype(from, to),
+        }
+    }
+    /// A new instance of `name` with every field at its default value.
+    fn instantiate(&mut self, name: &str) -> Result<ObjectRef, RuntimeError> {
+        // without a class file, `java/lang/Object` has no fields
+        if name == OBJECT && !self.loader.classpath().contains(OBJECT) {
+            return self.allocate(Object { class: OBJECT.to_string(), data: ObjectData::Instance(Instance::new(Arc::default())) });
+        }
+        let class = self.loader.class(name)?;
+        if class.is_interface() || class.raw.has_flag(AccessFlags::Abstract) {
+            return Err(RuntimeError::exception("java/lang/InstantiationError", Some(name.replace('/', "."))));
+        }
+        self.initialize(name)?;
+        self.allocate(Object {
+            class: name.to_string(),
+            data: ObjectData::Instance(Instance::new(class.layout.clone())),
+        })
+    }
+    fn static_access(&mut self, index: u16, put: bool) -> Result<(), RuntimeError> {
+        let cp = self.frame().cp();
+        let field = self.loader.resolve_field(cp, index)?;
+        if !field.is_static() {
+            return Err(RuntimeError::exception("java/lang/IncompatibleClassChangeError", Some(format!("expected static field {}.{}", field.class, field.name))));
+        }
+        self.initialize(field.class)?;
+        let class = self.loader.class(field.class)?;
+        if put {
+            let value = self.frame().pop()?.narrow(&field.ty);
+            class.statics.lock().unwrap().insert(field.name.to_string(), value);
+            return Ok(());
+        }
+        let value = class.statics.lock().unwrap().get(field.name).cloned();
+        let value = value.ok_or_else(|| self.invalid(format!("{}.{} has no storage", field.class, field.name)))?;
+        self.frame().push(value)
+    }
+    fn field_access(&mut self, index: u16, put: bool) -> Result<(), RuntimeError> {
+        let cp = self.frame().cp();
+        let field = self.loader.resolve_field(cp, index)?;
+        let slot = match field.slot {
+            Some(slot) => slot,
+            None => return Err(RuntimeError::exception("java/lang/IncompatibleClassChangeError", Some(format!("expected non-static field {}.{}", field.class, field.name)))),
+        };
+        let frame = self.frame();
+        let value = match put {
+            true => Some(frame.pop()?),
+            false => None,
+        };
+        let object = frame.pop_reference()?.ok_or_else(null_pointer)?;
+        let (frames, shared) = self.parts();
+        let frame = frames.last().expect("no frame is running");
+        let object = shared.heap.get_mut(object);
+        let fields = match &mut object.data {
+            ObjectData::Instance(instance) if slot < instance.fields.len() => &mut instance.fields,
+            _ => return Err(frame.invalid(format!("{} has no field {}.{}", object.class, field.class, field.name))),
+        };
+        match value {
+            Some(value) => {
+                fields[slot] = value.to_field(&field.ty).ok_or_else(|| frame.invalid(format!("{}.{} is not a {}", field.class, field.name, field.ty)))?;
+                Ok(())
+            },
+            None => {
+                let value = Value::from_field(&fields[slot]).ok_or_else(|| frame.invalid(format!("{}.{} holds {:?}", field.class, field.name, fields[slot])))?;
+                self.frame().push(value)
+            },
+        }
+    }
+
+    /// Pops the arguments of a call, the receiver first when there is one.
+    fn pop_args(&mut self, signature: &MethodDescriptor, receiver: bool) -> Result<Vec<Value>, RuntimeError> {
+        let frame = self.frame();
+        let mut args = (0..signature.params.len() + receiver as usize).map(|_| frame.pop()).collect::<Result<Vec<_>, _>>()?;
+        args.reverse();
+        Ok(args)
+    }
+    fn receiver(args: &[Value]) -> Result<ObjectRef, RuntimeError> {
+        match args.first() {
+            Some(Value::Reference(Some(receiver))) => Ok(*receiver),
+            _ => Err(null_pointer()),
+        }
+    }
+    fn invoke_special(&mut self, index: u16) -> Result<(), RuntimeError> {
+        let member = self.frame().cp().get_member_ref(index)?;
+        // without a class file, `java/lang/Object` declares nothing, so its constructor does nothing
+        if member.class == OBJECT && member.name == "<init>" && !self.loader.classpath().contains(OBJECT) {
+            self.frame().pop_reference()?.ok_or_else(null_pointer)?;
+            return Ok(());
+        }
+        let resolved = self.loader.resolve_method(&member)?;
+        let args = self.pop_args(&resolved.signature, true)?;
+        Self::receiver(&args)?;
+        // `super.m()` selects from the super class of the caller, not the class named
+        let current = self.frame().method.class;
+        let hierarchy = self.loader.hierarchy();
+        let is_super_call = resolved.name != "<init>" && !member.interface && resolved.class != current
+            && !hierarchy.is_interface(resolved.class) && hierarchy.is_subtype(current, resolved.class);
+        let method = match hierarchy.super_name(current) {
+            Some(super_name) if is_super_call => self.loader.select(&resolved, super_name)?,
+            _ => resolved,
+        };
+        let return_address = self.frame().pc;
+        self.call(method, args, Some(return_address))
+    }
+    fn invoke_virtual(&mut self, index: u16) -> Result<(), RuntimeError> {
+        let member = self.frame().cp().get_member_ref(index)?;
+        let signature = MethodDescriptor::parse(&member.descriptor)?;
+        let args = self.pop_args(&signature, true)?;
+        let receiver = Self::receiver(&args)?;
+        let class = self.heap.class(receiver).to_string();
+        let lambda = match &self.heap.get(receiver).data {
+            ObjectData::Lambda(lambda) if lambda.method == member.name && lambda.descriptor == member.descriptor => Some((lambda.implementation.clone(), lambda.captured.clone())),
+            _ => None,
+        };
+        if let Some((implementation, mut captured)) = lambda {
+            captured.extend(args.into_iter().skip(1));
+            let result = self.invoke_handle(&implementation.kind, &implementation.member, captured)?;
+            if let (Some(_), Some(result)) = (&signature.ret, result) {
+                self.frame().push(result)?;
+            }
+            return Ok(());
+        }
+        if class == STRING {
+            if let Some(result) = self.string_method(receiver, &member, &args[1..])? {
+                return self.frame().push(result);
+            }
+        }
+        if class.starts_with('[') && member.name == "clone" && member.descriptor == "()Ljava/lang/Object;" {
+            let copy = match &self.heap.get(receiver).data {
+                ObjectData::Array(elements) => elements.clone(),
+                _ => unreachable!(),
+            };
+            let copy = self.allocate(Object { class, data: ObjectData::Array(copy) })?;
+            return self.frame().push(Value::Reference(Some(copy)));
+        }
+        let resolved = self.loader.resolve_method(&member)?;
+        // arrays have the methods of `java/lang/Object`
+        let selected_from = if class.starts_with('[') { OBJECT } else { &class };
+        let method = self.loader.select(&resolved, selected_from)?;
+        let return_address = self.frame().pc;
+        self.call(method, args, Some(return_address))
+    }
+    /// Runs the methods of `java/lang/String` that depend on how strings are stored, `None` for
+    /// the others.
+    fn string_method(&mut self, receiver: ObjectRef, member: &MemberRef, args: &[Value]) -> Result<Option<Value>, RuntimeError> {
+        let string = match self.heap.string(receiver) {
+            Some(string) => string,
+            None => return Ok(None),
+        };
+        let result = match (member.name.as_str(), member.descriptor.as_str(), args) {
+            ("length", "()I", []) => Value::Int(string.len() as i32),
+            ("isEmpty", "()Z", []) => Value::Int(string.is_empty() as i32),
+            ("hashCode", "()I", []) => Value::Int(string.hash_code()),
+            ("charAt", "(I)C", [Value::Int(index)]) => match usize::try_from(*index).ok().and_then(|index| string.char_at(index)) {
+                Some(unit) => Value::Int(unit as i32),
+                None => return Err(RuntimeError::exception("java/lang/StringIndexOutOfBoundsException", Some(format!(
+                    "Index {} out of bounds for length {}", index, string.len(),
+                )))),
+            },
+            ("equals", "(Ljava/lang/Object;)Z", [Value::Reference(other)]) => {
+                let equal = other.and_then(|other| self.heap.string(other)).is_some_and(|other| other == string);
+                Value::Int(equal as i32)
+            },
+            ("concat", "(Ljava/lang/String;)Ljava/lang/String;", [Value::Reference(other)]) => {
+                let other = self.heap.string(other.ok_or_else(null_pointer)?).ok_or_else(|| self.invalid("concat of a non-string"))?;
+                match other.is_empty() {
+                    true => Value::Reference(Some(receiver)),
+                    false => {
+                        let concatenated = string.concat(other);
+                        Value::Reference(Some(self.new_string(concatenated)?))
+                    },
+                }
+            },
+            ("repeat", "(I)Ljava/lang/String;", [Value::Int(count)]) => {
+                let count = usize::try_from(*count).map_err(|_| RuntimeError::exception("java/lang/IllegalArgumentException", Some(format!("count is negative: {}", count))))?;
+                // the repeated string is as large as the heap allows, not as the process does
+                let length = string.len().checked_mul(count).filter(|length| *length <= i32::MAX as usize).ok_or_else(out_of_memory)?;
+                self.heap.pin(receiver);
+                let reserved = self.reserve(length.saturating_mul(2));
+                self.heap.unpin(receiver);
+                reserved?;
+                let string = self.heap.string(receiver).expect("the receiver is a string");
+                let mut units = Vec::new();
+                units.try_reserve_exact(length).map_err(|_| out_of_memory())?;
+                for _ in 0..count {
+                    units.extend(string.units());
+                }
+                Value::Reference(Some(self.new_string(JString::from_utf16(units))?))
+            },
+            ("intern", "()Ljava/lang/String;", []) => {
+                let string = string.clone();
+                Value::Reference(Some(*self.interned.entry(string).or_insert(receiver)))
+            },
+            ("toString", "()Ljava/lang/String;", []) => Value::Reference(Some(receiver)),
+            _ => return Ok(None),
+        };
+        Ok(Some(result))
+    }
+    /// Runs the method a method handle refers to, as a lambda calls its implementation.
+    fn invoke_handle(&mut self, kind: &MethodHandleKind, member: &MemberRef, mut args: Vec<Value>) -> Result<Option<Value>, RuntimeError> {
+        let resolved = self.loader.resolve_method(member)?;
+        match kind {
+            MethodHandleKind::InvokeStatic => {
+                self.heap.pin_values(&args);
+                let initialized = self.initialize(resolved.class);
+                self.heap.unpin_values(&args);
+                initialized?;
+                self.execute(resolved, args)
+            },
+            MethodHandleKind::InvokeVirtual | MethodHandleKind::InvokeInterface => {
+                let class = self.heap.class(Self::receiver(&args)?).to_string();
+                let method = self.loader.select(&resolved, &class)?;
+                self.execute(method, args)
+            },
+            MethodHandleKind::InvokeSpecial => {
+                Self::receiver(&args)?;
+                self.execute(resolved, args)
+            },
+            MethodHandleKind::NewInvokeSpecial => {
+                self.heap.pin_values(&args);
+                let object = self.instantiate(&member.class);
+                self.heap.unpin_values(&args);
+                let object = object?;
+                args.insert(0, Value::Reference(Some(object)));
+                self.execute(resolved, args)?;
+                Ok(Some(Value::Reference(Some(object))))
+            },
+            other => Err(RuntimeError::Unsupported(format!("lambda implemented by a {} handle", other.name()))),
+        }
+    }
+    fn invoke_dynamic(&mut self, index: u16) -> Result<(), RuntimeError> {
+        let method = self.frame().method.clone();
+        let class = self.loader.class(method.class)?;
+        let site = CallSite::resolve(method.cp, &class.bootstraps, index)?;
+        let signature = MethodDescriptor::parse(&site.descriptor)?;
+        let args = self.pop_args(&signature, false)?;
+        let value = match site.classify() {
+            Bootstrap::StringConcat(parts) => {
+                // `toString` may collect, while nothing else refers to the arguments
+                self.heap.pin_values(&args);
+                let text = self.concat(parts, &args);
+                self.heap.unpin_values(&args);
+                self.new_string(text?)?
+            },
+            Bootstrap::Lambda { interface, method, erased, implementation, .. } => self.allocate(Object {
+                class: interface,
+                data: ObjectData::Lambda(Lambda { method, descriptor: erased, implementation, captured: args }),
+            })?,
+            _ => return Err(RuntimeError::Unsupported(format!(
+                "invokedynamic bootstrapped by {}.{}", site.bootstrap.member.class, site.bootstrap.member.name,
+            ))),
+        };
+        self.frame().push(Value::Reference(Some(value)))
+    }
+    /// The string `makeConcatWithConstants` makes of `parts` and `args`.
+    fn concat(&mut self, parts: Vec<ConcatPart>, args: &[Value]) -> Result<JString, RuntimeError> {
+        let mut args = args.iter();
+        let mut units = Vec::new();
+        for part in parts {
+            match part {
+                ConcatPart::Literal(literal) => units.extend(literal.encode_utf16()),
+                ConcatPart::Constant(constant) => units.extend(constant_string(&constant).encode_utf16()),
+                ConcatPart::Argument(ty) => {
+                    let arg = args.next().ok_or_else(|| self.invalid("too few arguments to concatenate"))?;
+                    units.extend(self.string_of(&ty, arg.clone())?.units());
+                },
+            }
+        }
+        Ok(JString::from_utf16(units))
+    }
+    /// `String.valueOf` of a value of type `ty`, calling `toString` on objects.
+    fn string_of(&mut self, ty: &FieldType, value: Value) -> Result<JString, RuntimeError> {
+        Ok(JString::from(match (ty, value) {
+            (FieldType::Boolean, Value::Int(value)) => (value != 0).to_string(),
+            (FieldType::Char, Value::Int(value)) => return Ok(JString::from_utf16(vec![value as u16])),
+            (_, Value::Int(value)) => value.to_string(),
+            (_, Value::Long(value)) => value.to_string(),
+            (_, Value::Float(value)) => java_float_string(value as f64, format!("{:e}", value)),
+            (_, Value::Double(value)) => java_float_string(value, format!("{:e}", value)),
+            (_, Value::Reference(None)) => "null".to_string(),
+            (_, Value::Reference(Some(object))) => {
+                if let Some(string) = self.heap.string(object) {
+                    return Ok(string.clone());
+                }
+                let class = self.heap.class(object).to_string();
+                let member = MemberRef {
+                    class: if class.starts_with('[') { OBJECT.to_string() } else { class.clone() },
+                    name: "toString".to_string(),
+                    descriptor: "()Ljava/lang/String;".to_string(),
+                    interface: false,
+                };
+                // the identity of the object stands in for `Object.toString` when there is no class file for it
+                let method = match self.loader.resolve_method(&member) {
+                    Ok(resolved) => self.loader.select(&resolved, &member.class)?,
+                    Err(RuntimeError::Resolution(ResolutionError::NoClassDefFound(_) | ResolutionError::NoSuchMethod { .. })) => {
+                        return Ok(format!("{}@{:x}", class.replace('/', "."), object.index()).into());
+                    },
+                    Err(err) => return Err(err),
+                };
+                match self.execute(method, vec![Value::Reference(Some(object))])? {
+                    Some(Value::Reference(Some(string))) => return Ok(self.heap.string(string).cloned().unwrap_or_default()),
+                    _ => "null".to_string(),
+                }
+            },
+            (_, other) => return Err(self.invalid(format!("{:?} concatenated as {}", other, ty))),
+        }))
+    }
+}
+
+fn divide_by_zero() -> RuntimeError {
+    RuntimeError::exception("java/lang/ArithmeticException", Some("/ by zero".to_string()))
+}
+
+fn out_of_bounds(index: i32, length: usize) -> RuntimeError {
+    RuntimeError::exception("java/lang/ArrayIndexOutOfBoundsException", Some(format!("Index {} out of bounds for length {}", index, length)))
+}
+
+/// The result of `fcmp<op>` or `dcmp<op>`, NaN comparing as 1 for `g` and -1 for `l`.
+fn compare(ordering: Option<std::cmp::Ordering>, nan_greater: bool) -> i32 {
+    match ordering {
+        Some(ordering) => ordering as i32,
+        None if nan_greater => 1,
+        None => -1,
+    }
+}
+
+/// Bytes the arrays of type `ty` that `multianewarray` makes with `lengths` take, every
+/// dimension counted before any of them is allocated.
+fn array_size(ty: &FieldType, lengths: &[i32]) -> Result<usize, RuntimeError> {
+    if let Some(negative) = lengths.iter().find(|length| **length < 0) {
+        return Err(RuntimeError::exception("java/lang/NegativeArraySizeException", Some(negative.to_string())));
+    }
+    let (mut array, mut arrays, mut bytes) = (ty, 1usize, 0usize);
+    for &length in lengths {
+        let component = match array {
+            FieldType::Array(component) => component,
+            other => return Err(RuntimeError::Unsupported(format!("array of non-array type {}", other))),
+        };
+        let elements = arrays.checked_mul(length as usize).ok_or_else(out_of_memory)?;
+        bytes = elements.checked_mul(Array::element_size(component))
+            .and_then(|size| size.checked_add(arrays.checked_mul(HEADER_SIZE)?))
+            .and_then(|size| bytes.checked_add(size))
+            .ok_or_else(out_of_memory)?;
+        (array, arrays) = (component, elements);
+    }
+    Ok(bytes)
+}
+/// An array of type `ty` with `lengths[0]` elements, each an array of `lengths[1]` elements
+/// and so on, once the memory [array_size] counts is reserved.
+fn new_dimension(heap: &mut Heap, ty: &FieldType, lengths: &[i32]) -> Result<ObjectRef, RuntimeError> {
+    let component = match ty {
+        FieldType::Array(component) => component,
+        other => return Err(RuntimeError::Unsupported(format!("array of non-array type {}", other))),
+    };
+    let mut elements = Array::try_new(component, lengths[0] as usize).ok_or_else(out_of_memory)?;
+    if let (Array::Reference(elements), [_, inner @ ..]) = (&mut elements, lengths) {
+        if !inner.is_empty() {
+            for element in elements.iter_mut() {
+                *element = Some(new_dimension(heap, component, inner)?);
+            }
+        }
+    }
+    heap.allocate(Object { class: ty.to_string(), data: ObjectData::Array(elements) })
+}
+
+/// A static argument of a string concatenation, as it is inserted.
+fn constant_string(constant: &BootstrapArgument) -> String {
+    match constant {
+        BootstrapArgument::Int(value) => value.to_string(),
+        BootstrapArgument::Long(value) => value.to_string(),
+        BootstrapArgument::Float(value) => java_float_string(*value as f64, format!("{:e}", value)),
+        BootstrapArgument::Double(value) => java_float_string(*value, format!("{:e}", value)),
+        BootstrapArgument::String(value) => value.clone(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Formats a `float` or `double` like `Double.toString`, from `value` and the shortest
+/// scientific notation Rust gives for it: plain notation from 10^-3 up to 10^7, computerized
+/// scientific notation outside of that, and always a digit after the point.
+///
+/// Java never picks a single digit: when one is enough to tell `value` apart, it prints the
+/// two digits closest to it instead, so that `Float.MIN_VALUE` is `1.4E-45` rather than `1.0E-45`.
+pub(crate) fn java_float_string(value: f64, scientific: String) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    let split = |scientific: &str| {
+        let (mantissa, exponent) = scientific.split_once('e').unwrap_or((scientific, "0"));
+        let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+        (digits, exponent.parse::<i32>().unwrap_or(0))
+    };
+    let sign = if scientific.starts_with('-') { "-" } else { "" };
+    let (mut digits, mut exponent) = split(&scientific);
+    if digits.len() == 1 && value != 0.0 {
+        (digits, exponent) = split(&format!("{:.1e}", value.abs()));
+        digits.truncate(if digits.ends_with('0') { 1 } else { 2 });
+    }
+    if !(-3..7).contains(&exponent) {
+        let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+        return format!("{}{}.{}E{}", sign, &digits[..1], fraction, exponent);
+    }
+    if exponent < 0 {
+        return format!("{}0.{}{}", sign, "0".repeat((-exponent - 1) as usize), digits);
+    }
+    let point = exponent as usize + 1;
+    let padded = format!("{:0<width$}", digits, width = point);
+    let fraction = if padded.len() > point { &padded[point..] } else { "0" };
+    format!("{}{}.{}", sign, &padded[..point], fraction)
+}
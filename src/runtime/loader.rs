@@ -0,0 +1,261 @@
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use crate::{
+    jvm::{
+        analysis::{classpath::ClassPath, hierarchy::{ClassHierarchy, ResolvedMethod}},
+        reader::{
+            access_flags::AccessFlags, bootstrap::BootstrapMethods, code::{block::CodeBlock, instruction::Instruction},
+            constant_pool::{ConstantPool, MemberRef}, descriptor::{FieldType, MethodDescriptor}, field::FieldInfo,
+            method::MethodInfo, raw_class::RawClass,
+        },
+    },
+    util::code_err::{ClassParseError, ResolutionError, RuntimeError},
+};
+
+use super::{heap::FieldLayout, value::Value};
+
+/// Classes and methods as the interpreter runs them, loaded from a [ClassPath] the first time
+/// they are needed. Symbolic references resolve through a [ClassHierarchy] over the same class
+/// path, so a class missing from it fails with `NoClassDefFoundError`.
+pub struct Loader<'a> {
+    hierarchy: ClassHierarchy<'a>,
+    classes: HashMap<&'a str, Arc<RuntimeClass<'a>>>,
+    /// Methods by the address of their `MethodInfo`
+    methods: HashMap<usize, Arc<Method<'a>>>,
+    /// Resolved `Fieldref`s by the address of their constant pool and their index in it
+    fields: HashMap<(usize, u16), Arc<Field<'a>>>,
+}
+
+/// A loaded class along with its static fields.
+#[derive(Debug)]
+pub struct RuntimeClass<'a> {
+    pub name: &'a str,
+    pub raw: &'a RawClass,
+    /// Static fields by name
+    pub statics: Mutex<HashMap<String, Value>>,
+    pub state: Mutex<InitState>,
+    /// Where the instance fields of this class and its super classes are in its objects
+    pub layout: Arc<FieldLayout>,
+    pub bootstraps: BootstrapMethods,
+}
+
+/// Where a class is in its initialization (JVMS §5.5).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitState {
+    Uninitialized,
+    /// `<clinit>` or the initialization of a super type is running
+    InProgress,
+    Initialized,
+    /// Initialization failed, so every later use fails with `NoClassDefFoundError`
+    Erroneous,
+}
+
+impl<'a> RuntimeClass<'a> {
+    pub fn is_interface(&self) -> bool {
+        self.raw.has_flag(AccessFlags::Interface)
+    }
+}
+
+/// A resolved field, with the slot it takes in objects when it isn't static.
+#[derive(Debug)]
+pub struct Field<'a> {
+    /// The class declaring the field
+    pub class: &'a str,
+    pub name: &'a str,
+    pub ty: FieldType,
+    pub info: &'a FieldInfo,
+    /// `None` for static fields
+    pub slot: Option<usize>,
+}
+
+impl<'a> Field<'a> {
+    pub fn is_static(&self) -> bool {
+        self.info.has_flag(AccessFlags::Static)
+    }
+}
+
+/// A method ready to run, with the pc of every instruction of its code.
+#[derive(Debug)]
+pub struct Method<'a> {
+    pub class: &'a str,
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    pub signature: MethodDescriptor,
+    pub info: &'a MethodInfo,
+    /// The constant pool of the declaring class, which the code's operands index
+    pub cp: &'a ConstantPool,
+    /// `None` for abstract and native methods
+    pub code: Option<&'a CodeBlock>,
+    pub pcs: Vec<u32>,
+    /// Instruction index of each pc
+    index: HashMap<u32, usize>,
+    /// The `SourceFile` of the declaring class
+    pub source_file: Option<&'a str>,
+    /// `(start_pc, line_number)` from the `LineNumberTable`, sorted by pc
+    lines: Vec<(u16, u16)>,
+}
+
+impl<'a> Method<'a> {
+    fn new(class: &'a str, raw: &'a RawClass, info: &'a MethodInfo) -> Result<Self, ClassParseError> {
+        let descriptor = info.descriptor(&raw.cp)?;
+        let code = info.code.as_ref();
+        let mut pcs = Vec::new();
+        let mut pc = 0;
+        for instruction in code.map_or(&[][..], |code| &code.code) {
+            pcs.push(pc);
+            pc += instruction.len_at(pc);
+        }
+        Ok(Self {
+            class,
+            name: info.name(&raw.cp)?,
+            descriptor,
+            signature: MethodDescriptor::parse(descriptor)?,
+            info,
+            cp: &raw.cp,
+            code,
+            index: pcs.iter().enumerate().map(|(i, pc)| (*pc, i)).collect(),
+            pcs,
+            source_file: raw.source_file()?,
+            lines: code.map_or(Ok(Vec::new()), |code| code.line_numbers(&raw.cp))?,
+        })
+    }
+    pub fn is_static(&self) -> bool {
+        self.info.has_flag(AccessFlags::Static)
+    }
+    pub fn is_native(&self) -> bool {
+        self.info.has_flag(AccessFlags::Native)
+    }
+    pub fn is_abstract(&self) -> bool {
+        self.info.has_flag(AccessFlags::Abstract)
+    }
+    pub fn is_synchronized(&self) -> bool {
+        // `ACC_SYNCHRONIZED` of methods is `ACC_SUPER` of classes
+        self.info.has_flag(AccessFlags::Super)
+    }
+    pub fn instructions(&self) -> &'a [Instruction] {
+        self.code.map_or(&[], |code| &code.code)
+    }
+    /// Index of the instruction starting at `pc`.
+    pub fn index_of(&self, pc: u32) -> Option<usize> {
+        self.index.get(&pc).copied()
+    }
+    /// The source line of the instruction at `pc`, if the code has a `LineNumberTable`.
+    pub fn line_at(&self, pc: u32) -> Option<u16> {
+        let after = self.lines.partition_point(|(start, _)| *start as u32 <= pc);
+        after.checked_sub(1).map(|entry| self.lines[entry].1)
+    }
+    /// `class.name(descriptor)`, for errors
+    pub fn signature_string(&self) -> String {
+        format!("{}.{}{}", self.class, self.name, self.descriptor)
+    }
+}
+
+impl<'a> Loader<'a> {
+    pub fn new(classpath: &'a ClassPath) -> Result<Self, ClassParseError> {
+        Ok(Self {
+            hierarchy: ClassHierarchy::new(classpath)?,
+            classes: HashMap::new(),
+            methods: HashMap::new(),
+            fields: HashMap::new(),
+        })
+    }
+    pub fn hierarchy(&self) -> &ClassHierarchy<'a> {
+        &self.hierarchy
+    }
+    pub fn classpath(&self) -> &'a ClassPath {
+        self.hierarchy.classpath()
+    }
+    /// The classes loaded so far.
+    pub fn classes(&self) -> impl Iterator<Item = &Arc<RuntimeClass<'a>>> {
+        self.classes.values()
+    }
+    /// The class named `name`, loading it and its super classes if they aren't yet.
+    pub fn class(&mut self, name: &str) -> Result<Arc<RuntimeClass<'a>>, RuntimeError> {
+        if let Some(class) = self.classes.get(name) {
+            return Ok(class.clone());
+        }
+        let (name, file) = self.classpath().get_key_value(name)
+            .ok_or_else(|| ResolutionError::NoClassDefFound(name.to_string()))?;
+        let raw = &file.class;
+        let parent = match raw.super_name()? {
+            Some(super_name) if self.classpath().contains(super_name) => self.class(super_name)?.layout.clone(),
+            _ => Arc::default(),
+        };
+        let mut statics = HashMap::new();
+        let mut fields = Vec::new();
+        for field in &raw.fields.0 {
+            let ty = FieldType::parse(field.descriptor(&raw.cp)?)?;
+            let field_name = field.name(&raw.cp)?.to_string();
+            if field.has_flag(AccessFlags::Static) {
+                statics.insert(field_name, Value::default_of(&ty));
+            } else {
+                fields.push((field_name, ty));
+            }
+        }
+        let class = Arc::new(RuntimeClass {
+            name,
+            raw,
+            statics: Mutex::new(statics),
+            state: Mutex::new(InitState::Uninitialized),
+            layout: Arc::new(FieldLayout::extend(&parent, name, fields)),
+            bootstraps: BootstrapMethods::of(raw)?,
+        });
+        self.classes.insert(name, class.clone());
+        Ok(class)
+    }
+    fn method(&mut self, resolved: ResolvedMethod<'a>) -> Result<Arc<Method<'a>>, RuntimeError> {
+        let key = resolved.method as *const MethodInfo as usize;
+        if let Some(method) = self.methods.get(&key) {
+            return Ok(method.clone());
+        }
+        let raw = self.class(resolved.class)?.raw;
+        let method = Arc::new(Method::new(resolved.class, raw, resolved.method)?);
+        self.methods.insert(key, method.clone());
+        Ok(method)
+    }
+    /// Resolves a `Methodref` or `InterfaceMethodref`.
+    pub fn resolve_method(&mut self, member: &MemberRef) -> Result<Arc<Method<'a>>, RuntimeError> {
+        let resolved = match member.interface {
+            true => self.hierarchy.resolve_interface_method(&member.class, &member.name, &member.descriptor)?,
+            false => self.hierarchy.resolve_method(&member.class, &member.name, &member.descriptor)?,
+        };
+        self.method(resolved)
+    }
+    /// The method `name` with `descriptor` declared by `class` itself.
+    pub fn find_method(&mut self, class: &str, name: &str, descriptor: &str) -> Result<Option<Arc<Method<'a>>>, RuntimeError> {
+        let class = self.class(class)?;
+        match class.raw.find_method(name, descriptor) {
+            Some(method) => self.method(ResolvedMethod { class: class.name, method }).map(Some),
+            None => Ok(None),
+        }
+    }
+    /// Method selection: the method that runs when `resolved` is invoked on an instance of `class`.
+    pub fn select(&mut self, resolved: &Method<'a>, class: &str) -> Result<Arc<Method<'a>>, RuntimeError> {
+        let selected = self.hierarchy.select(ResolvedMethod { class: resolved.class, method: resolved.info }, class)?;
+        self.method(selected)
+    }
+    /// Resolves the `Fieldref` at `index` of `cp`, once.
+    pub fn resolve_field(&mut self, cp: &'a ConstantPool, index: u16) -> Result<Arc<Field<'a>>, RuntimeError> {
+        let key = (cp as *const ConstantPool as usize, index);
+        if let Some(field) = self.fields.get(&key) {
+            return Ok(field.clone());
+        }
+        let member = cp.get_member_ref(index)?;
+        let resolved = self.hierarchy.resolve_field(&member.class, &member.name, &member.descriptor)?;
+        let class = self.class(resolved.class)?;
+        let name = resolved.field.name(&class.raw.cp)?;
+        let slot = match resolved.field.has_flag(AccessFlags::Static) {
+            true => None,
+            false => class.layout.slot(class.name, name),
+        };
+        let field = Arc::new(Field {
+            class: class.name,
+            name,
+            ty: FieldType::parse(resolved.field.descriptor(&class.raw.cp)?)?,
+            info: resolved.field,
+            slot,
+        });
+        self.fields.insert(key, field.clone());
+        Ok(field)
+    }
+}
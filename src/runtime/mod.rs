@@ -0,0 +1,8 @@
+pub mod value;
+pub mod heap;
+pub mod loader;
+pub mod frame;
+pub mod native;
+pub mod thread;
+pub mod classlib;
+pub mod interpreter;
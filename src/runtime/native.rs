@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use crate::{core::typings::j_value::JValue, util::code_err::JavaThrowable};
+
+/// A Java method implemented in Rust. It is given the receiver first for instance methods, then
+/// the arguments, and returns [JValue::Null] for `void`.
+pub type NativeMethod = Box<dyn Fn(&[JValue]) -> Result<JValue, JavaThrowable> + Send + Sync>;
+
+/// Stands in for every native method of a class without a binding of its own, given the name
+/// and descriptor of the method called.
+pub type ClassStub = Box<dyn Fn(&str, &str, &[JValue]) -> Result<JValue, JavaThrowable> + Send + Sync>;
+
+/// Binds `ACC_NATIVE` methods to Rust, by class, name and descriptor.
+///
+/// Values cross over as [JValue]s of the declared types, `boolean` being [JValue::Boolean] and
+/// `char` [JValue::UShort]. Strings are passed and returned by their contents as
+/// [JValue::String], other objects as handles, `null` as [JValue::Null].
+#[derive(Default)]
+pub struct NativeRegistry {
+    methods: HashMap<(String, String, String), NativeMethod>,
+    stubs: HashMap<String, ClassStub>,
+}
+
+impl NativeRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Binds the native method `name` with `descriptor` of `class`, replacing any binding it had.
+    pub fn register(&mut self, class: &str, name: &str, descriptor: &str, method: impl Fn(&[JValue]) -> Result<JValue, JavaThrowable> + Send + Sync + 'static) {
+        self.methods.insert((class.to_string(), name.to_string(), descriptor.to_string()), Box::new(method));
+    }
+    /// Binds every native method of `class` that isn't [registered](Self::register) on its own.
+    pub fn register_class(&mut self, class: &str, stub: impl Fn(&str, &str, &[JValue]) -> Result<JValue, JavaThrowable> + Send + Sync + 'static) {
+        self.stubs.insert(class.to_string(), Box::new(stub));
+    }
+    pub fn contains(&self, class: &str, name: &str, descriptor: &str) -> bool {
+        self.methods.contains_key(&(class.to_string(), name.to_string(), descriptor.to_string())) || self.stubs.contains_key(class)
+    }
+    /// Runs the binding of a native method, `None` when it has none.
+    pub fn call(&self, class: &str, name: &str, descriptor: &str, args: &[JValue]) -> Option<Result<JValue, JavaThrowable>> {
+        if let Some(method) = self.methods.get(&(class.to_string(), name.to_string(), descriptor.to_string())) {
+            return Some(method(args));
+        }
+        self.stubs.get(class).map(|stub| stub(name, descriptor, args))
+    }
+}
+
+impl std::fmt::Debug for NativeRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NativeRegistry")
+            .field("methods", &self.methods.keys().collect::<Vec<_>>())
+            .field("stubs", &self.stubs.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -0,0 +1,172 @@
+use std::{cell::UnsafeCell, sync::{Condvar, Mutex, MutexGuard}, time::Instant};
+
+use super::{frame::Frame, heap::ObjectRef};
+
+/// The lock a thread of the interpreter holds while it runs bytecode, guarding the state the
+/// threads share.
+///
+/// Unlike a [Mutex], it isn't released when a guard goes out of scope but by the thread
+/// itself, which may do so in the middle of an instruction: when it blocks on a monitor, waits
+/// or sleeps, and every so often to let the other threads run. The state is only reached
+/// through [InterpreterLock::get] by the thread holding the lock.
+#[derive(Debug)]
+pub struct InterpreterLock<T> {
+    state: UnsafeCell<T>,
+    holder: Mutex<Holder>,
+    /// Signalled when the lock is released or a thread changes something others may wait for
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Holder {
+    thread: Option<usize>,
+    /// Threads waiting to take the lock
+    waiting: usize,
+    /// How many times the lock was taken, to tell whether another thread ran
+    turns: u64,
+}
+
+// SAFETY: like the contents of a `Mutex`, the state is only reached by the thread holding the lock
+unsafe impl<T: Send + Sync> Sync for InterpreterLock<T> {}
+
+impl<T> InterpreterLock<T> {
+    /// A lock on `state`, held by `thread`.
+    pub fn new(state: T, thread: usize) -> Self {
+        Self {
+            state: UnsafeCell::new(state),
+            holder: Mutex::new(Holder { thread: Some(thread), ..Holder::default() }),
+            changed: Condvar::new(),
+        }
+    }
+    /// Blocks until `thread` holds the lock.
+    pub fn acquire(&self, thread: usize) {
+        let holder = self.holder.lock().unwrap();
+        self.take(holder, thread);
+    }
+    pub fn release(&self, thread: usize) {
+        let mut holder = self.holder.lock().unwrap();
+        debug_assert_eq!(holder.thread, Some(thread));
+        holder.thread = None;
+        self.changed.notify_all();
+    }
+    /// Releases the lock until another thread signals a change or `deadline` passes, then
+    /// takes it back.
+    pub fn wait(&self, thread: usize, deadline: Option<Instant>) {
+        let mut holder = self.holder.lock().unwrap();
+        debug_assert_eq!(holder.thread, Some(thread));
+        holder.thread = None;
+        self.changed.notify_all();
+        holder = match deadline {
+            Some(deadline) => self.changed.wait_timeout(holder, deadline.saturating_duration_since(Instant::now())).unwrap().0,
+            None => self.changed.wait(holder).unwrap(),
+        };
+        self.take(holder, thread);
+    }
+    /// Lets a thread waiting for the lock take it, if there is one, before `thread` takes it back.
+    pub fn yield_now(&self, thread: usize) {
+        let mut holder = self.holder.lock().unwrap();
+        if holder.waiting == 0 {
+            return;
+        }
+        let turn = holder.turns;
+        holder.thread = None;
+        self.changed.notify_all();
+        while holder.turns == turn && holder.waiting > 0 {
+            holder = self.changed.wait(holder).unwrap();
+        }
+        self.take(holder, thread);
+    }
+    /// Wakes the threads waiting for a change, which check whether what they wait for happened
+    /// once the lock is theirs again.
+    pub fn notify(&self) {
+        let _holder = self.holder.lock().unwrap();
+        self.changed.notify_all();
+    }
+    /// Whether a thread waits to take the lock.
+    pub fn is_contended(&self) -> bool {
+        self.holder.lock().unwrap().waiting > 0
+    }
+    /// The state, which the caller may only dereference while it holds the lock.
+    pub fn get(&self) -> *mut T {
+        self.state.get()
+    }
+    fn take(&self, mut holder: MutexGuard<Holder>, thread: usize) {
+        holder.waiting += 1;
+        while holder.thread.is_some() {
+            holder = self.changed.wait(holder).unwrap();
+        }
+        holder.waiting -= 1;
+        holder.thread = Some(thread);
+        holder.turns += 1;
+    }
+}
+
+/// A thread of the interpreter, as the other threads see it.
+#[derive(Debug)]
+pub struct ThreadState<'a> {
+    /// Its `java/lang/Thread`, which the first thread only has once `Thread.currentThread`
+    /// asked for it
+    pub object: Option<ObjectRef>,
+    pub status: ThreadStatus,
+    pub interrupted: bool,
+    /// Its frames while it doesn't hold the interpreter lock, so that they stay roots of the heap
+    pub frames: Vec<Frame<'a>>,
+}
+
+impl<'a> ThreadState<'a> {
+    pub fn new(object: Option<ObjectRef>) -> Self {
+        Self { object, status: ThreadStatus::Runnable, interrupted: false, frames: Vec::new() }
+    }
+}
+
+/// What a thread is doing, as a thread dump shows it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThreadStatus {
+    /// Running, or about to once it holds the interpreter lock
+    Runnable,
+    /// Waiting to enter the monitor of an object another thread owns
+    Blocked(ObjectRef),
+    /// In `Object.wait`, until notified or the deadline
+    Waiting(ObjectRef, Option<Instant>),
+    /// In `Thread.join`, until the thread ends or the deadline
+    Joining(usize, Option<Instant>),
+    Sleeping(Instant),
+    /// Waiting for another thread to initialize a class
+    Initializing(String),
+}
+
+impl ThreadStatus {
+    /// When the thread stops waiting whatever the others do.
+    pub fn deadline(&self) -> Option<Instant> {
+        match self {
+            ThreadStatus::Waiting(_, deadline) | ThreadStatus::Joining(_, deadline) => *deadline,
+            ThreadStatus::Sleeping(deadline) => Some(*deadline),
+            _ => None,
+        }
+    }
+    /// Whether the thread will run again without another thread doing anything.
+    pub fn is_timed(&self) -> bool {
+        *self == ThreadStatus::Runnable || self.deadline().is_some()
+    }
+}
+
+impl std::fmt::Display for ThreadStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThreadStatus::Runnable => write!(f, "RUNNABLE"),
+            ThreadStatus::Blocked(_) => write!(f, "BLOCKED (on object monitor)"),
+            ThreadStatus::Waiting(_, None) | ThreadStatus::Joining(_, None) => write!(f, "WAITING (on object monitor)"),
+            ThreadStatus::Waiting(_, Some(_)) | ThreadStatus::Joining(_, Some(_)) => write!(f, "TIMED_WAITING (on object monitor)"),
+            ThreadStatus::Sleeping(_) => write!(f, "TIMED_WAITING (sleeping)"),
+            ThreadStatus::Initializing(class) => write!(f, "WAITING (on the initialization of {})", class.replace('/', ".")),
+        }
+    }
+}
+
+/// The monitor of an object while a thread owns it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Monitor {
+    pub owner: usize,
+    /// How many times the owner entered it without exiting
+    pub entries: u32,
+}
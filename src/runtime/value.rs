@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use crate::{
+    core::typings::{j_string::JString, j_value::JValue},
+    jvm::reader::{bootstrap::MethodHandleRef, descriptor::FieldType},
+};
+
+use super::heap::{FieldLayout, ObjectRef};
+
+/// A value in a local variable or on the operand stack. `boolean`, `byte`, `char` and `short`
+/// are held as `Int`, like the JVM does (JVMS §2.11.1).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    /// `None` is `null`
+    Reference(Option<ObjectRef>),
+    /// Pushed by `jsr`, the index of the instruction following it
+    ReturnAddress(usize),
+    /// An unusable slot: a local that was never written, or the second half of a `long` or `double`
+    Top,
+}
+
+impl Value {
+    pub fn null() -> Self {
+        Value::Reference(None)
+    }
+    /// The value a field or array element of type `ty` starts out with.
+    pub fn default_of(ty: &FieldType) -> Self {
+        match ty {
+            FieldType::Long => Value::Long(0),
+            FieldType::Float => Value::Float(0.0),
+            FieldType::Double => Value::Double(0.0),
+            FieldType::Object(_) | FieldType::Array(_) => Value::null(),
+            _ => Value::Int(0),
+        }
+    }
+    /// Whether the value takes two local variable or operand stack slots.
+    pub fn is_wide(&self) -> bool {
+        matches!(self, Value::Long(_) | Value::Double(_))
+    }
+    pub fn slots(&self) -> usize {
+        1 + self.is_wide() as usize
+    }
+    /// Truncates an `int` to what a field or array element of type `ty` holds, as `putfield`
+    /// and `bastore` do.
+    pub fn narrow(self, ty: &FieldType) -> Self {
+        match (ty, self) {
+            (FieldType::Boolean, Value::Int(value)) => Value::Int(value & 1),
+            (FieldType::Byte, Value::Int(value)) => Value::Int(value as i8 as i32),
+            (FieldType::Char, Value::Int(value)) => Value::Int(value as u16 as i32),
+            (FieldType::Short, Value::Int(value)) => Value::Int(value as i16 as i32),
+            (_, value) => value,
+        }
+    }
+    /// How a field of type `ty` holds the value, `None` when it is of another kind.
+    pub fn to_field(self, ty: &FieldType) -> Option<JValue> {
+        Some(match (ty, self) {
+            (FieldType::Boolean, Value::Int(value)) => JValue::Boolean(value & 1 != 0),
+            (FieldType::Byte, Value::Int(value)) => JValue::Byte(value as i8),
+            (FieldType::Char, Value::Int(value)) => JValue::UShort(value as u16),
+            (FieldType::Short, Value::Int(value)) => JValue::Short(value as i16),
+            (FieldType::Int, Value::Int(value)) => JValue::Int(value),
+            (FieldType::Long, Value::Long(value)) => JValue::Long(value),
+            (FieldType::Float, Value::Float(value)) => JValue::Float(value),
+            (FieldType::Double, Value::Double(value)) => JValue::Double(value),
+            (FieldType::Object(_) | FieldType::Array(_), Value::Reference(Some(object))) => JValue::Object(object),
+            (FieldType::Object(_) | FieldType::Array(_), Value::Reference(None)) => JValue::Null,
+            _ => return None,
+        })
+    }
+    /// The value of a field, `None` for the kinds of [JValue] no Java field has.
+    pub fn from_field(value: &JValue) -> Option<Self> {
+        Some(match value {
+            JValue::Boolean(value) => Value::Int(*value as i32),
+            JValue::Byte(value) => Value::Int(*value as i32),
+            JValue::UShort(value) => Value::Int(*value as i32),
+            JValue::Short(value) => Value::Int(*value as i32),
+            JValue::Int(value) => Value::Int(*value),
+            JValue::Long(value) => Value::Long(*value),
+            JValue::Float(value) => Value::Float(*value),
+            JValue::Double(value) => Value::Double(*value),
+            JValue::Object(object) => Value::Reference(Some(*object)),
+            JValue::Null => Value::null(),
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Object {
+    /// Internal name of the class, or the descriptor of an array class such as `[I`
+    pub class: String,
+    pub data: ObjectData,
+}
+
+/// Bytes an object is counted for besides its contents
+pub(crate) const HEADER_SIZE: usize = 16;
+
+impl Object {
+    /// Roughly the bytes the object takes, which is what heap limits count.
+    pub fn size(&self) -> usize {
+        HEADER_SIZE + match &self.data {
+            ObjectData::Instance(instance) => instance.fields.len() * std::mem::size_of::<JValue>(),
+            ObjectData::Array(elements) => elements.size(),
+            ObjectData::String(value) => value.size(),
+            ObjectData::Class(name) => name.len(),
+            ObjectData::Lambda(lambda) => lambda.captured.len() * std::mem::size_of::<Value>(),
+        }
+    }
+    /// Calls `f` with every object this one refers to.
+    pub fn references(&self, mut f: impl FnMut(ObjectRef)) {
+        match &self.data {
+            ObjectData::Instance(instance) => for field in &instance.fields {
+                if let JValue::Object(object) = field {
+                    f(*object);
+                }
+            },
+            ObjectData::Array(Array::Reference(elements)) => elements.iter().flatten().copied().for_each(f),
+            ObjectData::Lambda(lambda) => for value in &lambda.captured {
+                if let Value::Reference(Some(object)) = value {
+                    f(*object);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ObjectData {
+    Instance(Instance),
+    Array(Array),
+    /// The contents of a `java/lang/String`
+    String(JString),
+    /// The `java/lang/Class` object of the named class or array descriptor
+    Class(String),
+    /// An instance of a functional interface made by `LambdaMetafactory`
+    Lambda(Lambda),
+}
+
+/// The fields of an instance of a class, in the slots of its layout.
+#[derive(Debug)]
+pub struct Instance {
+    pub layout: Arc<FieldLayout>,
+    pub fields: Vec<JValue>,
+}
+
+impl Instance {
+    pub fn new(layout: Arc<FieldLayout>) -> Self {
+        Self { fields: layout.defaults(), layout }
+    }
+}
+
+/// What a lambda or method reference calls, and the values it captured.
+#[derive(Debug)]
+pub struct Lambda {
+    /// The method of the functional interface that is implemented
+    pub method: String,
+    /// Its erased descriptor, the `samMethodType` of the call site
+    pub descriptor: String,
+    pub implementation: MethodHandleRef,
+    pub captured: Vec<Value>,
+}
+
+/// The elements of an array, `boolean` arrays being stored as bytes like the JVM does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Array {
+    Byte(Vec<i8>),
+    Char(Vec<u16>),
+    Short(Vec<i16>),
+    Int(Vec<i32>),
+    Long(Vec<i64>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    Reference(Vec<Option<ObjectRef>>),
+}
+
+impl Array {
+    /// An array of `length` default values of type `component`, `None` when the memory for it
+    /// can't be allocated.
+    pub fn try_new(component: &FieldType, length: usize) -> Option<Self> {
+        fn filled<T: Clone>(value: T, length: usize) -> Option<Vec<T>> {
+            let mut elements = Vec::new();
+            elements.try_reserve_exact(length).ok()?;
+            elements.resize(length, value);
+            Some(elements)
+        }
+        Some(match component {
+            FieldType::Boolean | FieldType::Byte => Array::Byte(filled(0, length)?),
+            FieldType::Char => Array::Char(filled(0, length)?),
+            FieldType::Short => Array::Short(filled(0, length)?),
+            FieldType::Int => Array::Int(filled(0, length)?),
+            FieldType::Long => Array::Long(filled(0, length)?),
+            FieldType::Float => Array::Float(filled(0.0, length)?),
+            FieldType::Double => Array::Double(filled(0.0, length)?),
+            FieldType::Object(_) | FieldType::Array(_) => Array::Reference(filled(None, length)?),
+        })
+    }
+    pub fn len(&self) -> usize {
+        match self {
+            Array::Byte(elements) => elements.len(),
+            Array::Char(elements) => elements.len(),
+            Array::Short(elements) => elements.len(),
+            Array::Int(elements) => elements.len(),
+            Array::Long(elements) => elements.len(),
+            Array::Float(elements) => elements.len(),
+            Array::Double(elements) => elements.len(),
+            Array::Reference(elements) => elements.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Bytes each element of type `component` takes.
+    pub fn element_size(component: &FieldType) -> usize {
+        match component {
+            FieldType::Boolean | FieldType::Byte => 1,
+            FieldType::Char | FieldType::Short => 2,
+            FieldType::Int | FieldType::Float => 4,
+            FieldType::Long | FieldType::Double => 8,
+            FieldType::Object(_) | FieldType::Array(_) => std::mem::size_of::<Option<ObjectRef>>(),
+        }
+    }
+    /// Bytes the elements take.
+    pub fn size(&self) -> usize {
+        use std::mem::size_of_val;
+        match self {
+            Array::Byte(elements) => size_of_val(elements.as_slice()),
+            Array::Char(elements) => size_of_val(elements.as_slice()),
+            Array::Short(elements) => size_of_val(elements.as_slice()),
+            Array::Int(elements) => size_of_val(elements.as_slice()),
+            Array::Long(elements) => size_of_val(elements.as_slice()),
+            Array::Float(elements) => size_of_val(elements.as_slice()),
+            Array::Double(elements) => size_of_val(elements.as_slice()),
+            Array::Reference(elements) => size_of_val(elements.as_slice()),
+        }
+    }
+    /// The element at `index`, `None` when it is out of bounds.
+    pub fn get(&self, index: usize) -> Option<Value> {
+        Some(match self {
+            Array::Byte(elements) => Value::Int(*elements.get(index)? as i32),
+            Array::Char(elements) => Value::Int(*elements.get(index)? as i32),
+            Array::Short(elements) => Value::Int(*elements.get(index)? as i32),
+            Array::Int(elements) => Value::Int(*elements.get(index)?),
+            Array::Long(elements) => Value::Long(*elements.get(index)?),
+            Array::Float(elements) => Value::Float(*elements.get(index)?),
+            Array::Double(elements) => Value::Double(*elements.get(index)?),
+            Array::Reference(elements) => Value::Reference(*elements.get(index)?),
+        })
+    }
+    /// Stores `value` at `index`, truncating `int`s to the element type. Fails when `index` is
+    /// out of bounds or `value` is of the wrong kind.
+    pub fn set(&mut self, index: usize, value: Value) -> Result<(), Value> {
+        let slot_missing = index >= self.len();
+        match (self, value) {
+            (_, value) if slot_missing => return Err(value),
+            (Array::Byte(elements), Value::Int(value)) => elements[index] = value as i8,
+            (Array::Char(elements), Value::Int(value)) => elements[index] = value as u16,
+            (Array::Short(elements), Value::Int(value)) => elements[index] = value as i16,
+            (Array::Int(elements), Value::Int(value)) => elements[index] = value,
+            (Array::Long(elements), Value::Long(value)) => elements[index] = value,
+            (Array::Float(elements), Value::Float(value)) => elements[index] = value,
+            (Array::Double(elements), Value::Double(value)) => elements[index] = value,
+            (Array::Reference(elements), Value::Reference(value)) => elements[index] = value,
+            (_, value) => return Err(value),
+        }
+        Ok(())
+    }
+}
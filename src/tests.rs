@@ -1,8 +1,44 @@
 use std::fs::File;
 use std::io::Write;
+use std::sync::{Arc, Mutex};
 
+use crate::jvm::analysis::callgraph::{CallGraph, CallKind, MethodId, Resolution};
+use crate::jvm::analysis::classpath::ClassPath;
+use crate::jvm::analysis::hierarchy::ClassHierarchy;
+use crate::jvm::analysis::dependencies::{Dependency, DependencyReport, Granularity, NOT_FOUND};
+use crate::jvm::asm::{assemble, assemble_to_bytes};
+use crate::jvm::reader::bootstrap::{Bootstrap, BootstrapArgument, BootstrapMethods, CallSite, ConcatPart, SwitchKind};
 use crate::jvm::reader::classfile::ClassFile;
+use crate::jvm::reader::constant_pool::ConstantPoolInfo;
+use crate::jvm::reader::code::block::CodeBlock;
+use crate::jvm::reader::code::instruction::{ArrayType, Instruction, WideOp};
+use crate::jvm::reader::descriptor::FieldType;
+use crate::jvm::reader::method_handle_kind::MethodHandleKind;
+use crate::jvm::reader::code::opcode::{Flow, OpcodeInfo, OPCODES};
+use crate::jvm::reader::signature::signature_classes;
+use crate::jvm::asm::frames::TypeMerger;
+use crate::util::code_err::{AssemblyError, JavaThrowable, ResolutionError, RuntimeError};
+use crate::runtime::{self, interpreter::Interpreter};
+use crate::runtime::heap::Heap;
+use crate::runtime::value::{Object, ObjectData, Value};
+use crate::core::typings::j_object::JObject;
+use crate::core::typings::j_value::JValue;
+use crate::core::typings::j_string::JString;
 use crate::jvm::reader::raw_class::RawClass;
+use crate::jvm::loader::ir::ir_instructions::{ArithmeticOp, ArithmeticOpType, CompareOp, Condition, Instruction as IrInstruction, InvokeKind, OperandType};
+use crate::jvm::loader::ir::ir_value::{Constant, IrType, IrValue, Variable};
+use crate::jvm::loader::ir::lower::{lower_class, lower_class_with_debug};
+use crate::jvm::loader::ir::dominance::DominatorTree;
+use crate::jvm::loader::ir::function::{Function, Handler, Location, Statement};
+use crate::jvm::loader::ir::ir_value::BlockId;
+use crate::jvm::loader::ir::ssa::{self, DefUse};
+use crate::jvm::loader::ir::module::Module;
+use crate::jvm::loader::ir::lower::lower_class_path;
+use crate::jvm::loader::ir::passes::{ConstantFolding, Pass, PassManager, PassReport};
+use crate::jvm::loader::ir::verifier::{diagnose, verify};
+use crate::jvm::loader::ir::regalloc::{allocate, Place, RegOp, RegisterFunction};
+use crate::jvm::loader::ir::binary::{write_module, BinaryModule};
+use crate::jvm::loader::ir::parser::{parse_function, parse_module};
 
 
 #[test]
@@ -12,4 +48,2872 @@ pub fn load_HelloWorld_class() {
 
     let mut debug_file = File::create("java_tests/HelloWorld.class.deserialized").unwrap();
     write!(debug_file, "{:#?}", class).unwrap();
-}
\ No newline at end of file
+}
+
+const ASSEMBLER_FIXTURE: &str = r#"
+; exercises branches, switches, handlers and wide values so frames are needed
+.class public Counter
+.super java/lang/Object
+.source "Counter.java"
+
+.field private static final LIMIT I = 10
+.field private total J
+
+.method public <init>()V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    aload_0
+    lconst_0
+    putfield Counter/total J
+    return
+.end method
+
+.method public static classify(I)Ljava/lang/String;
+    iload_0
+    tableswitch 0
+        Zero
+        One
+        default : Many
+Zero:
+    ldc "zero"
+    areturn
+One:
+    ldc "one"
+    areturn
+Many:
+    iload_0
+    lookupswitch
+        100 : Hundred
+        -1 : Negative
+        default : Other
+Hundred:
+    ldc "hundred"
+    areturn
+Negative:
+    ldc "negative"
+    areturn
+Other:
+    ldc "many"
+    areturn
+.end method
+
+.method public static sum(I)J
+    lconst_0
+    lstore_1
+    iconst_0
+    istore_3
+Loop:
+    iload_3
+    iload_0
+    if_icmpge Done
+    lload_1
+    iload_3
+    i2l
+    ladd
+    lstore_1
+    iinc 3 1
+    goto Loop
+Done:
+    lload_1
+    lreturn
+.end method
+
+.method public static safeDivide(II)I
+Start:
+    iload_0
+    iload_1
+    idiv
+End:
+    ireturn
+Handler:
+    astore_2
+    iconst_m1
+    ireturn
+.catch java/lang/ArithmeticException from Start to End using Handler
+.end method
+
+.method public static main([Ljava/lang/String;)V
+    .line 3
+    getstatic java/lang/System/out Ljava/io/PrintStream;
+    bipush 10
+    invokestatic Counter/sum(I)J
+    invokevirtual java/io/PrintStream/println(J)V
+    getstatic java/lang/System/out Ljava/io/PrintStream;
+    iconst_1
+    invokestatic Counter/classify(I)Ljava/lang/String;
+    invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+    getstatic java/lang/System/out Ljava/io/PrintStream;
+    iconst_1
+    iconst_0
+    invokestatic Counter/safeDivide(II)I
+    invokevirtual java/io/PrintStream/println(I)V
+    return
+.end method
+"#;
+
+#[test]
+pub fn assemble_and_reload_class() {
+    let bytes = assemble_to_bytes(ASSEMBLER_FIXTURE).unwrap();
+    let class = ClassFile::from_bytes(bytes.clone()).unwrap();
+    let cp = &class.class.cp;
+    assert_eq!(cp.get_class_name(class.class.this_class).unwrap(), "Counter");
+    assert_eq!(class.class.methods.0.len(), 5);
+
+    let sum = class.class.methods.0.iter()
+        .find(|m| cp.get_utf8(m.name_index).unwrap() == "sum")
+        .unwrap();
+    let code = sum.code.as_ref().unwrap();
+    assert_eq!((code.max_stack, code.max_locals), (4, 4));
+    assert!(code.attributes.find_by_name("StackMapTable", cp).unwrap().is_some());
+
+    // reading the class back and writing it again must not change a single byte
+    assert_eq!(class.to_bytes().unwrap(), bytes);
+
+    // Utf8 constants are modified UTF-8: NUL takes two bytes, supplementary characters a surrogate pair
+    let bytes = assemble_to_bytes(".class A\n.method static f()Ljava/lang/String;\n    ldc \"a\\u0000\u{1F600}\"\n    areturn\n.end method\n").unwrap();
+    let encoded = [0x00, 0x09, b'a', 0xC0, 0x80, 0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80];
+    assert!(bytes.windows(encoded.len()).any(|window| window == encoded));
+    let class = ClassFile::from_bytes(bytes).unwrap();
+    assert!(class.class.cp.0.iter().any(|entry| matches!(&entry.info, ConstantPoolInfo::Utf8(value) if value == "a\0\u{1F600}")));
+}
+
+#[test]
+pub fn opcode_table_describes_instructions() {
+    for (opcode, info) in OPCODES.iter().enumerate() {
+        assert_eq!(info.opcode as usize, opcode);
+        assert_eq!(OpcodeInfo::by_mnemonic(info.mnemonic).unwrap().opcode, info.opcode);
+    }
+
+    let class = ClassFile::from_bytes(assemble_to_bytes(ASSEMBLER_FIXTURE).unwrap()).unwrap();
+    let cp = &class.class.cp;
+    for method in &class.class.methods.0 {
+        let code = method.code.as_ref().unwrap();
+        let (mut pc, mut depth, mut max_depth) = (0, 0, 0);
+        for instruction in &code.code {
+            assert_eq!(instruction.info().opcode, instruction.opcode());
+            let (pops, pushes) = instruction.stack_effect(cp).unwrap();
+            depth = depth - pops as i32 + pushes as i32;
+            max_depth = max_depth.max(depth);
+            if instruction.flow() != Flow::Next {
+                // blocks of the fixture start on an empty stack, or on a caught exception that is stored right away
+                depth = 0;
+            }
+            pc += instruction.len_at(pc);
+        }
+        assert_eq!(pc as usize, code.encode().unwrap().len());
+        assert_eq!(max_depth as u16, code.max_stack);
+    }
+}
+
+#[test]
+pub fn decoder_rejects_malformed_instructions() {
+    let code = CodeBlock::decode(&[196, 132, 1, 44, 255, 156, 188, 10]).unwrap();
+    assert!(matches!(code[0], Instruction::Wide(WideOp::Iinc(300, -100))));
+    assert!(matches!(code[1], Instruction::Newarray(ArrayType::Int)));
+
+    // wide iadd, newarray of an unknown type
+    assert!(CodeBlock::decode(&[196, 96, 0, 1]).is_err());
+    assert!(CodeBlock::decode(&[188, 3]).is_err());
+    // tableswitch with low 1 above high 0
+    assert!(CodeBlock::decode(&[170, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0]).is_err());
+    // lookupswitch with keys 2, 1
+    assert!(CodeBlock::decode(&[171, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 1]).is_err());
+}
+
+#[test]
+pub fn assembler_reports_located_errors() {
+    let err = assemble_to_bytes(".class A\n.method static f()V\n    iload_0\n    frobnicate\n.end method\n").unwrap_err();
+    assert!(matches!(err, AssemblyError::UnknownMnemonic { line: 4, column: 5, .. }), "{}", err);
+
+    let err = assemble_to_bytes(".class A\n.method static f()V\n    goto Nowhere\n.end method\n").unwrap_err();
+    assert!(matches!(err, AssemblyError::UndefinedLabel { line: 3, .. }), "{}", err);
+
+    let err = assemble_to_bytes(".class A\n.method static f()V\n    iload_0\n    return\n.end method\n").unwrap_err();
+    assert!(matches!(err, AssemblyError::Frames { line: 3, .. }), "{}", err);
+}
+
+const CALL_GRAPH_FIXTURES: [&str; 4] = [
+    ".interface public abstract Shape\n.super java/lang/Object\n.method public abstract area()I\n.end method\n",
+    r#"
+.class public Square
+.super java/lang/Object
+.implements Shape
+.method public <init>()V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+.method public area()I
+    iconst_4
+    ireturn
+.end method
+"#,
+    r#"
+.class public Circle
+.super java/lang/Object
+.implements Shape
+.method public area()I
+    invokestatic Legacy/slowPi()I
+    ireturn
+.end method
+"#,
+    r#"
+.class public Main
+.super java/lang/Object
+.method public static main([Ljava/lang/String;)V
+    new Square
+    dup
+    invokespecial Square/<init>()V
+    invokeinterface Shape/area()I 1
+    pop
+    invokedynamic run()Ljava/lang/Runnable; invokestatic java/lang/invoke/LambdaMetafactory/metafactory(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite; { methodtype ()V, handle invokestatic Main/lambda$main$0()V, methodtype ()V }
+    pop
+    return
+.end method
+.method private static lambda$main$0()V
+    invokestatic Legacy/old()V
+    return
+.end method
+"#,
+];
+
+#[test]
+pub fn call_graph_over_class_path() {
+    let mut classpath = ClassPath::new();
+    for source in CALL_GRAPH_FIXTURES {
+        assert!(classpath.add(assemble(source).unwrap()).unwrap());
+    }
+    let main = MethodId::new("Main", "main", "([Ljava/lang/String;)V");
+    let slow_pi = MethodId::new("Legacy", "slowPi", "()I");
+
+    // Circle is never created, so only class hierarchy analysis dispatches to it
+    let cha = CallGraph::build(&classpath, std::slice::from_ref(&main), Resolution::Cha).unwrap();
+    let rta = CallGraph::build(&classpath, std::slice::from_ref(&main), Resolution::Rta).unwrap();
+    assert!(cha.methods.contains(&slow_pi));
+    assert!(!rta.methods.contains(&slow_pi));
+    assert!(rta.methods.contains(&MethodId::new("Square", "area", "()I")));
+
+    let lambda = MethodId::new("Main", "lambda$main$0", "()V");
+    assert!(rta.callees(&main).any(|edge| edge.callee == lambda && edge.kind == CallKind::Lambda));
+    assert!(rta.callees(&main).any(|edge| edge.callee.class == "java/lang/invoke/LambdaMetafactory" && edge.kind == CallKind::Bootstrap));
+    let paths = rta.entry_points_reaching(|method| method.class == "Legacy");
+    assert_eq!(paths, vec![vec![&main, &lambda, &MethodId::new("Legacy", "old", "()V")]]);
+    assert!(rta.external.contains(&MethodId::new("Legacy", "old", "()V")));
+
+    assert!(rta.to_dot().contains("\"Main.main([Ljava/lang/String;)V\" -> \"Square.area()I\" [label=\"interface\"];"));
+    let json = rta.to_json().to_string();
+    assert!(json.contains("{\"caller\":\"Main.main([Ljava/lang/String;)V\",\"callee\":\"Square.area()I\",\"kind\":\"interface\",\"pc\":7}"), "{}", json);
+}
+
+#[test]
+pub fn dependencies_of_jars() {
+    // built by javac from java_tests/deps, shop-api.jar is the shop.api module
+    let mut classpath = ClassPath::new();
+    assert_eq!(classpath.add_jar(std::path::Path::new("java_tests/shop-api.jar")).unwrap(), 2);
+    assert_eq!(classpath.add_jar(std::path::Path::new("java_tests/shop-impl.jar")).unwrap(), 1);
+    assert_eq!(classpath.module_of("shop/Item"), Some("shop.api"));
+    assert_eq!(classpath.module_of("shop/impl/FileCatalog"), Some("shop.impl"));
+
+    let report = DependencyReport::new(&classpath).unwrap();
+    let catalog = report.classes.iter().find(|class| class.name == "shop/impl/FileCatalog").unwrap();
+    for api in ["shop/Catalog", "shop/Item", "java/util/List", "java/io/IOException", "java/lang/Deprecated"] {
+        assert!(catalog.api.contains(api), "{}", api);
+    }
+    // a private field, a private method, a catch clause and code
+    for implementation in ["java/util/Map", "java/lang/StringBuilder", "java/lang/IllegalStateException", "java/util/ArrayList"] {
+        assert!(catalog.implementation.contains(implementation), "{}", implementation);
+    }
+
+    let dependency = |from: &str, to: &str, api| Dependency { from: from.to_string(), to: to.to_string(), api };
+    assert_eq!(report.dependencies(Granularity::Archive), vec![
+        dependency("java_tests/shop-api.jar", NOT_FOUND, true),
+        dependency("java_tests/shop-impl.jar", "java_tests/shop-api.jar", true),
+        dependency("java_tests/shop-impl.jar", NOT_FOUND, true),
+    ]);
+    assert!(report.dependencies(Granularity::Package).contains(&dependency("shop", "java/lang/runtime", false)));
+    assert!(report.to_json().to_string().contains(r#"{"from":"shop.impl","to":"shop.api","api":true}"#));
+
+    assert_eq!(
+        signature_classes("<K::Ljava/lang/Comparable<-TK;>;V:Ljava/lang/Object;>Ljava/util/AbstractMap<TK;TV;>;Ljava/util/NavigableMap<TK;[TV;>;").unwrap(),
+        vec!["java/lang/Comparable", "java/lang/Object", "java/util/AbstractMap", "java/util/NavigableMap"],
+    );
+    assert_eq!(signature_classes("(Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;)V^TE;").unwrap(), vec!["java/util/Map$Entry"]);
+}
+
+const HIERARCHY_FIXTURES: [&str; 10] = [
+    ".interface public abstract A\n.method public m()V\n    return\n.end method\n",
+    ".interface public abstract B\n.implements A\n.method public m()V\n    return\n.end method\n",
+    ".interface public abstract C\n.method public abstract m()V\n.end method\n",
+    ".interface public abstract D\n.method public m()V\n    return\n.end method\n",
+    ".interface public abstract K\n.field public static final LIMIT I = 3\n",
+    ".class public Base\n.super java/lang/Object\n.implements A\n.implements K\n",
+    ".class public Sub\n.super Base\n.implements B\n.implements C\n",
+    ".class public Clash\n.super Base\n.implements B\n.implements D\n",
+    ".class public p/Top\n.super java/lang/Object\n.method run()V\n    return\n.end method\n",
+    ".class public q/Mid\n.super p/Top\n.method run()V\n    return\n.end method\n",
+];
+
+#[test]
+pub fn class_hierarchy_resolves_members() {
+    let mut classpath = ClassPath::new();
+    for source in HIERARCHY_FIXTURES {
+        classpath.add(assemble(source).unwrap()).unwrap();
+    }
+    let hierarchy = ClassHierarchy::new(&classpath).unwrap();
+    assert!(hierarchy.is_subtype("Sub", "A") && !hierarchy.is_subtype("Base", "B"));
+    assert_eq!(hierarchy.common_superclass("Sub", "Clash"), "Base");
+    assert_eq!(hierarchy.common_superclass("Sub", "B"), "java/lang/Object");
+
+    // the field is found through the interfaces of the super class
+    assert_eq!(hierarchy.resolve_field("Sub", "LIMIT", "I").unwrap().class, "K");
+    assert!(matches!(hierarchy.resolve_field("Sub", "limit", "I"), Err(ResolutionError::NoSuchField { .. })));
+
+    // B redeclares A.m, which leaves B.m and the abstract C.m maximally specific
+    let maximal: Vec<_> = hierarchy.maximally_specific_methods("Sub", "m", "()V").unwrap().iter().map(|m| m.class).collect();
+    assert_eq!(maximal, vec!["B", "C"]);
+    let resolved = hierarchy.resolve_method("Sub", "m", "()V").unwrap();
+    assert_eq!(resolved.class, "B");
+    assert_eq!(hierarchy.select(resolved, "Sub").unwrap().class, "B");
+    assert_eq!(hierarchy.resolve_interface_method("C", "m", "()V").unwrap().class, "C");
+    assert!(matches!(hierarchy.select(hierarchy.resolve_method("Clash", "m", "()V").unwrap(), "Clash"), Err(ResolutionError::IncompatibleClassChange(_))));
+
+    assert!(matches!(hierarchy.resolve_method("A", "m", "()V"), Err(ResolutionError::IncompatibleClassChange(_))));
+    assert!(matches!(hierarchy.resolve_interface_method("Sub", "m", "()V"), Err(ResolutionError::IncompatibleClassChange(_))));
+    assert_eq!(hierarchy.resolve_method("Missing", "m", "()V").unwrap_err().error_class(), "java/lang/NoClassDefFoundError");
+
+    // a package-private method is not overridden from another package
+    let run = hierarchy.resolve_method("p/Top", "run", "()V").unwrap();
+    assert_eq!(hierarchy.select(run, "q/Mid").unwrap().class, "p/Top");
+    assert_eq!(hierarchy.select(hierarchy.resolve_method("q/Mid", "run", "()V").unwrap(), "q/Mid").unwrap().class, "q/Mid");
+}
+
+fn call_sites(path: &str) -> Vec<CallSite> {
+    let class = ClassFile::open_from(path).unwrap();
+    let methods = BootstrapMethods::of(&class.class).unwrap();
+    class.class.methods.0.iter()
+        .flat_map(|method| &method.code.as_ref().unwrap().code)
+        .filter_map(|instruction| match instruction {
+            Instruction::Invokedynamic(index, _) => Some(CallSite::resolve(&class.class.cp, &methods, *index).unwrap()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+pub fn classify_invokedynamic_call_sites() {
+    // javac 17 output, with --enable-preview for the pattern switch
+    let sites = call_sites("java_tests/bootstraps/Bootstraps.class");
+    assert_eq!(sites.len(), 4);
+    match sites[0].classify() {
+        Bootstrap::Lambda { interface, method, implementation, .. } => {
+            assert_eq!((interface.as_str(), method.as_str()), ("java/util/function/Supplier", "get"));
+            assert_eq!(implementation.kind, MethodHandleKind::NewInvokeSpecial);
+            assert_eq!(implementation.member.class, "java/lang/String");
+        },
+        other => panic!("{:?}", other),
+    }
+    assert!(matches!(sites[1].classify(), Bootstrap::Lambda { implementation, .. } if implementation.member.name == "length"));
+    assert_eq!(sites[2].classify(), Bootstrap::Switch {
+        kind: SwitchKind::Type,
+        labels: vec![BootstrapArgument::Class("java/lang/String".to_string()), BootstrapArgument::Class("Bootstraps$Point".to_string())],
+    });
+    match sites[3].classify() {
+        Bootstrap::StringConcat(parts) => {
+            assert_eq!(parts[..3], [ConcatPart::Literal("n=".to_string()), ConcatPart::Argument(FieldType::Int), ConcatPart::Literal(" is ".to_string())]);
+            assert_eq!(parts.len(), 6);
+        },
+        other => panic!("{:?}", other),
+    }
+
+    let sites = call_sites("java_tests/bootstraps/Bootstraps$Point.class");
+    let methods: Vec<_> = sites.iter().map(|site| match site.classify() {
+        Bootstrap::ObjectMethods { method, record, components, getters } => {
+            assert_eq!((record.as_str(), components), ("Bootstraps$Point", vec!["x".to_string(), "y".to_string()]));
+            assert!(getters.iter().all(|getter| getter.kind == MethodHandleKind::GetField));
+            method
+        },
+        other => panic!("{:?}", other),
+    }).collect();
+    assert_eq!(methods, ["toString", "hashCode", "equals"]);
+}
+
+#[test]
+pub fn lower_bytecode_to_ir() {
+    let class = assemble(ASSEMBLER_FIXTURE).unwrap();
+    let ir = lower_class(&class.class).unwrap();
+    assert_eq!((ir.name.as_str(), ir.functions.len(), ir.fields.len()), ("Counter", 5, 2));
+    let instructions = |name: &str| -> Vec<IrInstruction<IrValue>> {
+        let function = ir.functions.iter().find(|f| f.name == name).unwrap();
+        function.blocks.iter().flat_map(|b| b.statements.iter().map(|s| s.instruction.clone())).collect()
+    };
+
+    // every block ends in exactly one terminator
+    for function in &ir.functions {
+        for block in &function.blocks {
+            assert!(block.terminator().is_some(), "{} has an unterminated block", function.name);
+            assert_eq!(block.statements.iter().filter(|s| s.instruction.is_terminator()).count(), 1);
+        }
+    }
+
+    let sum = ir.find_function("sum", "(I)J").unwrap();
+    let code = instructions("sum");
+    assert!(code.iter().any(|i| matches!(i, IrInstruction::Convert { from: ArithmeticOpType::I32, to: ArithmeticOpType::I64, .. })));
+    assert!(code.iter().any(|i| matches!(i, IrInstruction::Branch { cond: Condition::Ge, .. })));
+    assert!(code.iter().any(|i| matches!(i, IrInstruction::SetVar { var: Variable::Local(1, IrType::Long), .. })));
+    let add = sum.blocks.iter().flat_map(|b| &b.statements)
+        .find(|s| matches!(s.instruction, IrInstruction::Arithmetic { op: ArithmeticOp::Add, .. } if sum.value_type(s.result.unwrap()) == IrType::Long));
+    assert!(add.is_some());
+    // the loop header is entered from the entry path and the back edge
+    assert!(sum.predecessors().iter().any(|preds| preds.len() == 2));
+
+    let switches: Vec<Vec<i32>> = instructions("classify").into_iter().filter_map(|i| match i {
+        IrInstruction::Switch { cases, .. } => Some(cases.into_iter().map(|(key, _)| key).collect()),
+        _ => None,
+    }).collect();
+    assert_eq!(switches, vec![vec![0, 1], vec![-1, 100]]);
+
+    // the protected division unwinds to a landing pad, which puts the exception in stack slot 0
+    let divide = ir.find_function("safeDivide", "(II)I").unwrap();
+    let protected = divide.block_ids().find(|id| !divide.block(*id).handlers.is_empty()).unwrap();
+    let pad = divide.block(protected).handlers[0].target;
+    assert_eq!(divide.block(pad).statements[0].instruction, IrInstruction::Catch);
+    assert_eq!(divide.block(pad).statements[1].instruction, IrInstruction::SetVar { var: Variable::Stack(0, IrType::Reference), value: divide.block(pad).statements[0].result.unwrap() });
+    let handler = divide.block(pad).normal_successors()[0];
+    assert_eq!(divide.block(handler).statements[0].instruction, IrInstruction::GetVar { var: Variable::Stack(0, IrType::Reference) });
+
+    let main = instructions("main");
+    let invokes: Vec<(InvokeKind, &str, usize)> = main.iter().filter_map(|i| match i {
+        IrInstruction::Invoke { kind, method, num_args, args } => {
+            assert_eq!(*num_args, args.len());
+            Some((*kind, method.name.as_str(), *num_args))
+        },
+        _ => None,
+    }).collect();
+    assert_eq!(invokes, vec![
+        (InvokeKind::Static, "sum", 1), (InvokeKind::Virtual, "println", 2),
+        (InvokeKind::Static, "classify", 1), (InvokeKind::Virtual, "println", 2),
+        (InvokeKind::Static, "safeDivide", 2), (InvokeKind::Virtual, "println", 2),
+    ]);
+    assert_eq!(main.iter().filter(|i| matches!(i, IrInstruction::NullCheck { .. })).count(), 3);
+
+    // stack shuffles move values, not slots: the long survives dup2_x1 and pop2
+    let shuffle = assemble(r#"
+.class public Shuffle
+.super java/lang/Object
+.method public static shuffle(JI)J
+    iload_2
+    lload_0
+    dup2_x1
+    pop2
+    i2l
+    ladd
+    lreturn
+.end method
+"#).unwrap();
+    let function = &lower_class(&shuffle.class).unwrap().functions[0];
+    let statements: Vec<_> = function.blocks.iter().flat_map(|b| &b.statements).collect();
+    let definition = |value: IrValue| &statements.iter().find(|s| s.result == Some(value)).unwrap().instruction;
+    let returned = match &statements.last().unwrap().instruction {
+        IrInstruction::Return { value: Some(value) } => *value,
+        other => panic!("{:?}", other),
+    };
+    let (l, r) = match definition(returned) {
+        IrInstruction::Arithmetic { l, r, op: ArithmeticOp::Add, ty: ArithmeticOpType::I64 } => (*l, *r),
+        other => panic!("{:?}", other),
+    };
+    assert_eq!(definition(l), &IrInstruction::GetVar { var: Variable::Local(0, IrType::Long) });
+    assert!(matches!(definition(r), IrInstruction::Convert { from: ArithmeticOpType::I32, .. }));
+
+    // real javac output lowers too, invokedynamic included
+    for path in ["java_tests/HelloWorld.class", "java_tests/bootstraps/Bootstraps.class"] {
+        let class = ClassFile::open_from(path).unwrap();
+        let ir = lower_class(&class.class).unwrap();
+        assert_eq!(ir.functions.len(), class.class.methods.0.len());
+    }
+    let bootstraps = lower_class(&ClassFile::open_from("java_tests/bootstraps/Bootstraps.class").unwrap().class).unwrap();
+    assert!(bootstraps.functions.iter().flat_map(|f| f.blocks.iter().flat_map(|b| &b.statements))
+        .any(|s| matches!(s.instruction, IrInstruction::InvokeDynamic { .. })));
+}
+
+#[test]
+pub fn ssa_construction_and_destruction() {
+    let class = assemble(ASSEMBLER_FIXTURE).unwrap();
+    let mut sum = lower_class(&class.class).unwrap().find_function("sum", "(I)J").unwrap().clone();
+    ssa::construct(&mut sum).unwrap();
+
+    let statements = |function: &Function| -> Vec<(BlockId, usize, Statement)> {
+        function.block_ids().flat_map(|block| function.block(block).statements.iter().cloned().enumerate().map(move |(i, s)| (block, i, s))).collect()
+    };
+    assert!(statements(&sum).iter().all(|(_, _, s)| !matches!(s.instruction, IrInstruction::GetVar { .. } | IrInstruction::SetVar { .. })));
+    // the loop header merges the running total and the counter, and nothing else
+    let predecessors = sum.predecessors();
+    let phis: Vec<_> = statements(&sum).into_iter().filter(|(_, _, s)| matches!(s.instruction, IrInstruction::Phi { .. })).collect();
+    let mut types: Vec<IrType> = phis.iter().map(|(_, _, s)| sum.value_type(s.result.unwrap())).collect();
+    types.sort();
+    assert_eq!(types, vec![IrType::Int, IrType::Long]);
+    for (block, index, phi) in &phis {
+        assert!(*index < 2);
+        let IrInstruction::Phi { inputs } = &phi.instruction else { unreachable!() };
+        let mut from: Vec<BlockId> = inputs.iter().map(|(block, _)| *block).collect();
+        from.sort();
+        assert_eq!(from, predecessors[block.0 as usize]);
+    }
+
+    // every value has one definition, which dominates its uses
+    let chains = DefUse::new(&sum);
+    let tree = DominatorTree::new(&sum);
+    let mut defined = std::collections::BTreeSet::new();
+    for (block, index, statement) in statements(&sum) {
+        if let Some(result) = statement.result {
+            assert!(defined.insert(result));
+            assert_eq!(chains.def(result), Some(Location { block, index }));
+        }
+        let operand_defs = chains.operand_defs(&sum, Location { block, index });
+        let inputs: Vec<BlockId> = match &statement.instruction {
+            IrInstruction::Phi { inputs } => inputs.iter().map(|(from, _)| *from).collect(),
+            _ => Vec::new(),
+        };
+        for (i, (operand, def)) in statement.instruction.operands().into_iter().zip(operand_defs).enumerate() {
+            let def = def.unwrap();
+            assert!(chains.uses(*operand).contains(&Location { block, index }));
+            match inputs.get(i) {
+                // a phi input only has to be available at the end of its predecessor
+                Some(from) => assert!(tree.dominates(def.block, *from)),
+                None if def.block == block => assert!(def.index < index),
+                None => assert!(tree.dominates(def.block, block)),
+            }
+        }
+    }
+
+    ssa::destruct(&mut sum);
+    let after = statements(&sum);
+    assert!(after.iter().all(|(_, _, s)| !matches!(s.instruction, IrInstruction::Phi { .. })));
+    // each phi turned into a copy at the top of its block and one copy per predecessor
+    assert_eq!(after.iter().filter(|(_, _, s)| matches!(s.instruction, IrInstruction::Focus { .. })).count(), 2 * (1 + 2));
+    // copies for a phi only run on the edge into its block
+    for block in sum.block_ids() {
+        let statements = &sum.block(block).statements;
+        let leading = statements.iter().take_while(|s| matches!(s.instruction, IrInstruction::Focus { .. })).count();
+        if statements[leading..].iter().any(|s| matches!(s.instruction, IrInstruction::Focus { .. })) {
+            assert_eq!(sum.block(block).successors().len(), 1);
+        }
+    }
+
+    // whole classes go through, javac output and try/catch included
+    for class in [class, ClassFile::open_from("java_tests/HelloWorld.class").unwrap(), ClassFile::open_from("java_tests/bootstraps/Bootstraps.class").unwrap()] {
+        for mut function in lower_class(&class.class).unwrap().functions {
+            ssa::construct(&mut function).unwrap();
+            ssa::destruct(&mut function);
+        }
+    }
+}
+
+#[test]
+pub fn typed_instruction_operands() {
+    let mut classes = vec![assemble(ASSEMBLER_FIXTURE).unwrap(), ClassFile::open_from("java_tests/HelloWorld.class").unwrap()];
+    classes.push(ClassFile::open_from("java_tests/bootstraps/Bootstraps.class").unwrap());
+    for class in classes {
+        for mut function in lower_class(&class.class).unwrap().functions {
+            ssa::construct(&mut function).unwrap();
+            for statement in function.blocks.iter().flat_map(|b| &b.statements) {
+                let instruction = &statement.instruction;
+                if let (Some(result), Some(ty)) = (statement.result, instruction.result_type()) {
+                    assert_eq!(function.values[result.0 as usize], ty, "{} in {}", instruction, function.name);
+                }
+                let operands = instruction.operands();
+                let accepted = instruction.operand_types();
+                assert_eq!(operands.len(), accepted.len(), "{} in {}", instruction, function.name);
+                let same: Vec<_> = operands.iter().zip(&accepted).filter(|(_, ty)| **ty == OperandType::Same).map(|(v, _)| function.values[v.0 as usize]).collect();
+                assert!(same.windows(2).all(|pair| pair[0] == pair[1]), "{} in {}", instruction, function.name);
+                for (value, ty) in operands.iter().zip(&accepted) {
+                    if let OperandType::Exactly(ty) = ty {
+                        assert_eq!(function.values[value.0 as usize], *ty, "{} in {}", instruction, function.name);
+                    }
+                }
+            }
+        }
+    }
+
+    // shifts take an int distance whatever they shift, and lcmp compares longs into an int
+    let shift = IrInstruction::Arithmetic { l: 0u32, r: 1, op: ArithmeticOp::Shl, ty: ArithmeticOpType::I64 };
+    assert_eq!(shift.operand_types(), [OperandType::Exactly(IrType::Long), OperandType::Exactly(IrType::Int)]);
+    assert_eq!(shift.result_type(), Some(IrType::Long));
+    let compare = IrInstruction::Compare { l: 0u32, r: 1, op: CompareOp::Cmp, ty: ArithmeticOpType::I64 };
+    assert_eq!(compare.operand_types(), [OperandType::Exactly(IrType::Long); 2]);
+    assert_eq!(compare.result_type(), Some(IrType::Int));
+}
+
+#[test]
+pub fn rootir_pass_pipeline() {
+    let source = r#"
+function static "fold(Ljava/lang/Object;I)I" {
+bb0:
+    %0 = ref param 0
+    %1 = int param 1
+    %2 = int const int 6
+    %3 = int const int 7
+    %4 = int mul i32 %2, %3
+    %5 = int const int -2147483648
+    %6 = int const int -1
+    %7 = int div i32 %5, %6
+    %8 = int const int 33
+    %9 = int shl i32 %6, %8
+    %10 = float const float NaN
+    %11 = int cmpg f32 %10, %10
+    %12 = int cmpl f32 %10, %10
+    %13 = double const double -3.9
+    %14 = int convert f64 i32 %13
+    %15 = int add i32 %1, %4
+    %16 = int add i32 %1, %4
+    %17 = int const int 0
+    %18 = int div i32 %1, %17
+    %19 = ref const null
+    br ne %0, %19, bb1, bb2
+bb1:
+    nullcheck %0
+    %20 = int focus %16
+    br lt %11, %12, bb3, bb4
+bb2:
+    nullcheck %0
+    return %18
+bb3:
+    return %4
+bb4:
+    %21 = int phi bb1 %20
+    return %21
+}
+"#;
+    let function = parse_function(source).unwrap();
+    let constant = |function: &Function, value: u32| {
+        let statement = function.blocks.iter().flat_map(|b| &b.statements).find(|s| s.result.map(|r| r.0) == Some(value)).unwrap();
+        match &statement.instruction {
+            IrInstruction::Const { value } => Some(value.clone()),
+            _ => None,
+        }
+    };
+
+    // JVM arithmetic: wrapping, masked shift distances, NaN ordering and saturating conversions
+    let mut folded = function.clone();
+    let reports = PassManager::new().add(ConstantFolding).run(&mut folded).unwrap();
+    assert_eq!(reports, [PassReport { pass: "constant-folding", round: 0, changes: 6 }]);
+    assert_eq!(constant(&folded, 4), Some(Constant::Int(42)));
+    assert_eq!(constant(&folded, 7), Some(Constant::Int(i32::MIN)));
+    assert_eq!(constant(&folded, 9), Some(Constant::Int(-2)));
+    assert_eq!(constant(&folded, 11), Some(Constant::Int(1)));
+    assert_eq!(constant(&folded, 12), Some(Constant::Int(-1)));
+    assert_eq!(constant(&folded, 14), Some(Constant::Int(-3)));
+    assert_eq!(constant(&folded, 18), None);
+
+    // the division by zero stays to throw, the copies and the duplicate add go, the branch on
+    // constants is decided and the null checks the null test makes redundant are removed
+    let mut optimized = function.clone();
+    let reports = PassManager::standard().verify(true).run(&mut optimized).unwrap();
+    assert_eq!(optimized.to_string(), r#"function static fold(Ljava/lang/Object;I)I {
+bb0:
+    %0 = ref param 0
+    %1 = int param 1
+    %2 = int const int 42
+    %3 = int add i32 %1, %2
+    %4 = int const int 0
+    %5 = int div i32 %1, %4
+    %6 = ref const null
+    br ne %0, %6, bb2, bb1
+bb1:
+    nullcheck %0
+    return %5
+bb2:
+    return %3
+}
+"#);
+    assert!(reports.iter().all(|report| report.round < 16));
+    let last_round = reports.last().unwrap().round;
+    assert!(reports.iter().filter(|report| report.round == last_round).all(|report| report.changes == 0));
+    for pass in ["constant-folding", "copy-propagation", "common-subexpressions", "null-check-elimination", "simplify-cfg", "dead-code-elimination"] {
+        assert!(reports.iter().any(|report| report.pass == pass && report.changes > 0), "{} changed nothing", pass);
+    }
+
+    // with the verifier on, a pass that breaks the function is named
+    struct Breaking;
+    impl Pass for Breaking {
+        fn name(&self) -> &'static str {
+            "breaking"
+        }
+        fn run(&self, function: &mut Function) -> usize {
+            function.blocks[0].statements.pop();
+            1
+        }
+    }
+    let mut broken = function.clone();
+    assert!(PassManager::new().add(Breaking).run(&mut broken).is_ok());
+    let err = PassManager::new().add(Breaking).verify(true).run(&mut function.clone()).unwrap_err();
+    assert_eq!(err.after, Some("breaking"));
+    assert!(err.to_string().contains("bb0 does not end in a terminator"), "{}", err);
+
+    // lowered code goes through the whole pipeline and stays valid
+    for class in [assemble(ASSEMBLER_FIXTURE).unwrap(), ClassFile::open_from("java_tests/HelloWorld.class").unwrap(), ClassFile::open_from("java_tests/bootstraps/Bootstraps.class").unwrap()] {
+        for mut function in lower_class(&class.class).unwrap().functions {
+            ssa::construct(&mut function).unwrap();
+            PassManager::standard().verify(true).run(&mut function).unwrap();
+        }
+    }
+}
+
+#[test]
+pub fn rootir_verifier() {
+    let source = r#"
+function static "broken(I)J" {
+bb0:
+    %0 = int param 0
+    %1 = long const long 1
+    br lt %0, %1, bb1, bb2
+bb1:
+    %2 = int add i32 %0, %3
+    %4 = int phi bb0 %0
+    goto bb3
+bb2:
+    %3 = int const int 1
+    %5 = long invoke static A/f(IJ)J argc 1 %0
+    goto bb3
+bb3:
+    %6 = long phi bb1 %1, bb0 %1
+    %3 = int const int 2
+    return %0
+}
+"#;
+    let broken = parse_function(source).unwrap();
+    let diagnostics: Vec<String> = diagnose(&broken).iter().map(ToString::to_string).collect();
+    assert_eq!(diagnostics, [
+        "broken(I)J at bb3[1]: %3 is defined again, first in bb2[0]",
+        "broken(I)J at bb0[2]: `br lt %0, %1, bb1, bb2` mixes %0 of type int with %1 of type long",
+        "broken(I)J at bb1[0]: `add i32 %0, %3` reads %3, defined in bb2, which does not dominate bb1",
+        "broken(I)J at bb1[1]: phi in bb1 after a statement that is not a phi",
+        "broken(I)J at bb2[1]: `invoke static A/f(IJ)J %0` passes 1 arguments to A/f(IJ)J, which takes 2",
+        "broken(I)J at bb3[0]: `phi bb1 %1, bb0 %1` has inputs from [bb0, bb1] but the predecessors of bb3 are [bb1, bb2]",
+        "broken(I)J at bb3[2]: `return %0` returns int from a method returning long",
+    ]);
+    let first = verify(&broken).unwrap_err();
+    assert_eq!(first.at, Some(Location { block: BlockId(3), index: 1 }));
+
+    // lowering and SSA construction produce verified code for every method on the class path
+    let mut classpath = ClassPath::new();
+    classpath.add_jar(std::path::Path::new("java_tests/shop-api.jar")).unwrap();
+    classpath.add_jar(std::path::Path::new("java_tests/shop-impl.jar")).unwrap();
+    classpath.add_file(std::path::Path::new("java_tests/HelloWorld.class")).unwrap();
+    classpath.add_file(std::path::Path::new("java_tests/bootstraps/Bootstraps.class")).unwrap();
+    classpath.add(assemble(ASSEMBLER_FIXTURE).unwrap()).unwrap();
+    let (module, _) = lower_class_path(&classpath).unwrap();
+    for class in module.classes {
+        for mut function in class.functions {
+            assert_eq!(diagnose(&function), [], "{}", function);
+            ssa::construct(&mut function).unwrap();
+            assert_eq!(diagnose(&function), [], "{}", function);
+        }
+    }
+}
+
+#[test]
+pub fn rootir_text_round_trip() {
+    let mut module = Module::new();
+    for class in [assemble(ASSEMBLER_FIXTURE).unwrap(), ClassFile::open_from("java_tests/HelloWorld.class").unwrap(), ClassFile::open_from("java_tests/bootstraps/Bootstraps.class").unwrap()] {
+        let lowered = lower_class(&class.class).unwrap();
+        let mut in_ssa = lowered.clone();
+        in_ssa.name.push_str("$ssa");
+        let mut destructed = lowered.clone();
+        destructed.name.push_str("$destructed");
+        for (ssa_function, destructed_function) in in_ssa.functions.iter_mut().zip(&mut destructed.functions) {
+            ssa::construct(ssa_function).unwrap();
+            ssa::construct(destructed_function).unwrap();
+            ssa::destruct(destructed_function);
+        }
+        module.classes.extend([lowered, in_ssa, destructed]);
+    }
+    let text = module.to_string();
+    let parsed = parse_module(&text).unwrap();
+    assert_eq!(parsed, module);
+    assert_eq!(parsed.to_string(), text);
+    assert!(text.contains("invokedynamic makeConcatWithConstants "));
+
+    // hand-written functions, with names that need quoting
+    let source = r#"
+function public static "odd name(I)I" {
+bb0:
+    %0 = int param 0
+    %1 = int const int 0
+    br lt %0, %1, bb1, bb2
+bb1:
+    %2 = int neg i32 %0
+    goto bb2
+bb2:
+    %3 = int phi bb0 %0, bb1 %2
+    %4 = ref const string "a \"quoted\"\nline"
+    %5 = double const double -Infinity
+    return %3
+}
+"#;
+    let function = parse_function(source).unwrap();
+    assert_eq!(function.name, "odd name");
+    assert_eq!(function.access_flags, 0x0009);
+    assert_eq!(function.blocks.len(), 3);
+    assert_eq!(function.block(BlockId(2)).statements[1].instruction, IrInstruction::Const {
+        value: crate::jvm::loader::ir::ir_value::Constant::String("a \"quoted\"\nline".to_string()),
+    });
+    assert_eq!(parse_function(&function.to_string()).unwrap(), function);
+
+    // errors point at the offending token
+    let error = |source: &str| parse_function(source).unwrap_err();
+    match error("function f()V {\nbb0:\n    %0 = int frob %1\n}\n") {
+        AssemblyError::UnknownMnemonic { line, column, mnemonic } => assert_eq!((line, column, mnemonic.as_str()), (3, 14, "frob")),
+        other => panic!("{:?}", other),
+    }
+    match error("function f()V {\nbb0:\n    return %7\n}\n") {
+        AssemblyError::Syntax { line, column, .. } => assert_eq!((line, column), (3, 12)),
+        other => panic!("{:?}", other),
+    }
+    match error("function f()V {\nbb0:\n    goto bb4\n}\n") {
+        AssemblyError::UndefinedLabel { line, label } => assert_eq!((line, label.as_str()), (3, "bb4")),
+        other => panic!("{:?}", other),
+    }
+    match error("function f()V {\nbb0:\n    return\nbb0:\n    return\n}\n") {
+        AssemblyError::DuplicateLabel { line, .. } => assert_eq!(line, 4),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+pub fn rootir_binary_round_trip() {
+    let mut classpath = ClassPath::new();
+    classpath.add_jar(std::path::Path::new("java_tests/shop-api.jar")).unwrap();
+    classpath.add_jar(std::path::Path::new("java_tests/shop-impl.jar")).unwrap();
+    classpath.add_file(std::path::Path::new("java_tests/HelloWorld.class")).unwrap();
+    classpath.add_file(std::path::Path::new("java_tests/bootstraps/Bootstraps.class")).unwrap();
+    classpath.add(assemble(ASSEMBLER_FIXTURE).unwrap()).unwrap();
+    let (mut module, debug) = lower_class_path(&classpath).unwrap();
+    let mut in_ssa = module.classes.clone();
+    for class in &mut in_ssa {
+        class.name.push_str("$ssa");
+        class.functions.iter_mut().for_each(|function| ssa::construct(function).unwrap());
+    }
+    module.classes.extend(in_ssa);
+    let item = module.classes.iter().position(|class| class.name == "shop/Item").unwrap();
+    assert_eq!(debug.classes[item].source.as_deref(), Some("java_tests/shop-api.jar!/shop/Item.class"));
+    let counter = module.find_class("Counter").unwrap();
+    // blocks map back to bytecode, all but the entry block which defines the parameters
+    let counter_debug = &debug.classes[module.classes.iter().position(|class| class.name == "Counter").unwrap()];
+    for (function, offsets) in counter.functions.iter().zip(&counter_debug.block_offsets) {
+        assert_eq!(offsets.len(), function.blocks.len() - 1);
+        assert_eq!(offsets.first(), Some(&(BlockId(1), 0)));
+    }
+
+    let bytes = write_module(&module, Some(&debug));
+    assert_eq!(&bytes[..4], b"\0rir");
+    let binary = BinaryModule::new(bytes.clone()).unwrap();
+    assert_eq!(binary.module().unwrap(), module);
+    assert_eq!(binary.debug_info().unwrap(), Some(debug));
+    // one function at a time, without decoding the rest
+    let class = binary.find_class("Counter").unwrap();
+    assert_eq!(binary.function_signatures(class).count(), counter.functions.len());
+    let index = binary.find_function(class, "sum", "(I)J").unwrap();
+    assert_eq!(&binary.function(class, index).unwrap(), counter.find_function("sum", "(I)J").unwrap());
+    assert_eq!(BinaryModule::new(write_module(&module, None)).unwrap().debug_info().unwrap(), None);
+
+    // sections a later minor version adds are skipped, another major version is refused
+    let mut newer = bytes.clone();
+    newer[6] = 7;
+    newer.extend([0x7F, 3, 1, 2, 3]);
+    assert_eq!(BinaryModule::new(newer).unwrap().module().unwrap(), module);
+    let mut major = bytes.clone();
+    major[4] = 3;
+    assert!(matches!(BinaryModule::new(major), Err(crate::util::code_err::ClassParseError::BadValue { .. })));
+    assert!(BinaryModule::new(b"\xCA\xFE\xBA\xBE\0\0\0\0".to_vec()).is_err());
+    for length in [0, 5, 9, bytes.len() / 2, bytes.len() - 1] {
+        assert!(BinaryModule::new(bytes[..length].to_vec()).and_then(|binary| binary.module()).is_err());
+    }
+}
+
+#[test]
+pub fn register_allocation() {
+    // enough of a register machine to run integer code
+    let run = |function: &RegisterFunction, args: &[i64]| -> Option<i64> {
+        let mut registers = vec![0i64; function.registers as usize];
+        let mut slots = vec![0i64; function.slots as usize];
+        let read = |registers: &[i64], slots: &[i64], place: Place| match place {
+            Place::Register(register) => registers[register.0 as usize],
+            Place::Slot(slot) => slots[slot as usize],
+        };
+        let mut pc = 0;
+        for _ in 0..10_000 {
+            match &function.code[pc] {
+                RegOp::Move { dst, src } => {
+                    let value = read(&registers, &slots, *src);
+                    match dst {
+                        Place::Register(register) => registers[register.0 as usize] = value,
+                        Place::Slot(slot) => slots[*slot as usize] = value,
+                    }
+                },
+                RegOp::Op { dst, instruction } => {
+                    let value = match instruction {
+                        IrInstruction::Param { index } => args[*index],
+                        IrInstruction::Const { value: Constant::Int(value) } => *value as i64,
+                        IrInstruction::Const { value: Constant::Long(value) } => *value,
+                        IrInstruction::Convert { value, .. } => read(&registers, &slots, *value),
+                        IrInstruction::Arithmetic { l, r, op, ty } => {
+                            let (l, r) = (read(&registers, &slots, *l), read(&registers, &slots, *r));
+                            let value = match op {
+                                ArithmeticOp::Add => l.wrapping_add(r),
+                                ArithmeticOp::Sub => l.wrapping_sub(r),
+                                ArithmeticOp::Mul => l.wrapping_mul(r),
+                                ArithmeticOp::Xor => l ^ r,
+                                op => panic!("unexpected {}", op.name()),
+                            };
+                            if *ty == ArithmeticOpType::I32 { value as i32 as i64 } else { value }
+                        },
+                        instruction => panic!("unexpected `{}`", instruction),
+                    };
+                    registers[dst.unwrap().0 as usize] = value;
+                },
+                RegOp::Jump { target } => {
+                    pc = *target as usize;
+                    continue;
+                },
+                RegOp::Branch { l, r, cond, target } => {
+                    let (l, r) = (registers[l.0 as usize], registers[r.0 as usize]);
+                    let holds = match cond {
+                        Condition::Eq => l == r,
+                        Condition::Ne => l != r,
+                        Condition::Lt => l < r,
+                        Condition::Ge => l >= r,
+                        Condition::Gt => l > r,
+                        Condition::Le => l <= r,
+                    };
+                    if holds {
+                        pc = *target as usize;
+                        continue;
+                    }
+                },
+                RegOp::Return { value } => return value.map(|value| read(&registers, &slots, value)),
+                op => panic!("unexpected `{}`", op),
+            }
+            pc += 1;
+        }
+        panic!("{} does not return", function.name)
+    };
+
+    let class = assemble(ASSEMBLER_FIXTURE).unwrap();
+    let mut sum = lower_class(&class.class).unwrap().find_function("sum", "(I)J").unwrap().clone();
+    ssa::construct(&mut sum).unwrap();
+    for registers in [3, 16] {
+        let code = allocate(&sum, registers).unwrap();
+        assert!(code.registers <= registers);
+        assert_eq!(run(&code, &[10]), Some(45));
+        assert_eq!(run(&code, &[0]), Some(0));
+    }
+
+    // more values live across the loop than three registers hold
+    let source = r#"
+function static "pressure(II)I" {
+bb0:
+    %0 = int param 0
+    %1 = int param 1
+    %2 = int const int 0
+    %3 = int const int 1
+    %4 = int add i32 %1, %3
+    %5 = int mul i32 %1, %1
+    %6 = int sub i32 %5, %3
+    goto bb1
+bb1:
+    %7 = int phi bb0 %2, bb2 %12
+    %8 = int phi bb0 %2, bb2 %11
+    %9 = int phi bb0 %6, bb2 %8
+    br lt %7, %0, bb2, bb3
+bb2:
+    %10 = int mul i32 %7, %4
+    %13 = int add i32 %10, %5
+    %14 = int xor i32 %13, %9
+    %11 = int add i32 %8, %14
+    %12 = int add i32 %7, %3
+    goto bb1
+bb3:
+    %15 = int add i32 %8, %4
+    %16 = int add i32 %15, %5
+    %17 = int add i32 %16, %6
+    %18 = int add i32 %17, %9
+    return %18
+}
+"#;
+    let pressure = |n: i64, k: i64| {
+        let (mut i, mut acc, mut prev) = (0, 0, k * k - 1);
+        while i < n {
+            let next = acc + ((i * (k + 1) + k * k) ^ prev);
+            prev = acc;
+            acc = next;
+            i += 1;
+        }
+        acc + (k + 1) + k * k + (k * k - 1) + prev
+    };
+    let function = parse_function(source).unwrap();
+    let roomy = allocate(&function, 16).unwrap();
+    let tight = allocate(&function, 3).unwrap();
+    assert_eq!(tight.registers, 3);
+    // with room to spare only a swap of phis needs a slot, to park one side in
+    assert!(roomy.slots <= 1 && tight.slots > roomy.slots);
+    for code in [&roomy, &tight] {
+        for (n, k) in [(0, 0), (1, 5), (7, 3), (20, -4)] {
+            assert_eq!(run(code, &[n, k]), Some(pressure(n, k)), "pressure({}, {}) in\n{}", n, k, code);
+        }
+    }
+    // the critical edge out of the loop header holds no code of its own, and nothing jumps to the next instruction
+    assert!(tight.code.iter().enumerate().all(|(pc, op)| !matches!(op, RegOp::Jump { target } if *target as usize == pc + 1)));
+
+    assert!(allocate(&function, 2).is_err());
+    assert!(allocate(lower_class(&class.class).unwrap().find_function("sum", "(I)J").unwrap(), 8).is_err());
+}
+
+#[test]
+pub fn exception_regions() {
+    let source = r#"
+.class public Regions
+.super java/lang/Object
+
+.method public static guarded(II)I
+Outer:
+    iconst_0
+    istore_2
+Inner:
+    iload_0
+    iload_1
+    idiv
+    istore_2
+    iload_2
+    iload_1
+    irem
+    istore_2
+InnerEnd:
+    iload_2
+    ireturn
+Caught:
+    astore_3
+    iload_2
+    ireturn
+Finally:
+    astore_3
+    aload_3
+    athrow
+.catch java/lang/ArithmeticException from Inner to InnerEnd using Caught
+.catch all from Outer to InnerEnd using Finally
+.end method
+"#;
+    let class = assemble(source).unwrap();
+    let (lowered, debug) = lower_class_with_debug(&class.class).unwrap();
+    let guarded = lowered.find_function("guarded", "(II)I").unwrap().clone();
+    // the inner region is tried first, and every landing pad starts with the exception
+    let division = guarded.block_ids().find(|id| guarded.block(*id).handlers.len() == 2).unwrap();
+    let handlers = guarded.block(division).handlers.clone();
+    assert_eq!(handlers.iter().map(|handler| handler.class.as_deref()).collect::<Vec<_>>(), [Some("java/lang/ArithmeticException"), None]);
+    for Handler { target, .. } in &handlers {
+        assert_eq!(guarded.block(*target).statements[0].instruction, IrInstruction::Catch);
+        assert!(guarded.predecessors()[target.0 as usize].contains(&division));
+    }
+    // a write to a local ends the block, so landing pads see either value of local 2
+    assert!(guarded.block(division).statements.iter().filter(|s| matches!(s.instruction, IrInstruction::SetVar { var: Variable::Local(2, _), .. })).count() <= 1);
+    assert_eq!(debug.block_offsets[0].len(), guarded.blocks.len() - 1);
+
+    let mut in_ssa = guarded.clone();
+    ssa::construct(&mut in_ssa).unwrap();
+    assert_eq!(diagnose(&in_ssa), []);
+    let pad = in_ssa.block(handlers[0].target);
+    assert!(matches!(pad.statements[0].instruction, IrInstruction::Phi { ref inputs } if inputs.len() >= 2));
+    let mut optimized = in_ssa.clone();
+    PassManager::standard().verify(true).run(&mut optimized).unwrap();
+    assert!(optimized.blocks.iter().any(|block| block.handlers.len() == 2));
+    let mut destructed = in_ssa.clone();
+    ssa::destruct(&mut destructed);
+    for Handler { target, .. } in &handlers {
+        assert_eq!(destructed.block(*target).statements[0].instruction, IrInstruction::Catch);
+    }
+    assert!(allocate(&in_ssa, 8).is_err());
+
+    // handlers survive both formats
+    let mut module = Module::new();
+    let mut ssa_class = lowered.clone();
+    ssa_class.functions = vec![in_ssa.clone()];
+    ssa_class.name.push_str("$ssa");
+    module.classes.extend([lowered, ssa_class]);
+    let text = module.to_string();
+    assert!(text.contains(" try java/lang/ArithmeticException bb"), "{}", text);
+    assert_eq!(parse_module(&text).unwrap(), module);
+    assert_eq!(BinaryModule::new(write_module(&module, None)).unwrap().module().unwrap(), module);
+
+    // a value defined in the body of a protected block is not available in its landing pad
+    let source = r#"
+function static "leak(I)I" {
+bb0:
+    %0 = int param 0
+    goto bb1
+bb1: try any bb2
+    %1 = int const int 1
+    %2 = int div i32 %0, %1
+    return %2
+bb2:
+    %3 = ref catch
+    return %1
+}
+"#;
+    let leak = parse_function(source).unwrap();
+    let diagnostics: Vec<String> = diagnose(&leak).iter().map(ToString::to_string).collect();
+    assert_eq!(diagnostics, ["leak(I)I at bb2[1]: `return %1` reads %1, defined in bb1, which bb2 can be reached from by unwinding"]);
+}
+
+const RUNTIME_FIXTURES: [&str; 6] = [
+    r#"
+; stands in for the class library, keeping what is printed
+.class public java/lang/System
+.super java/lang/Object
+.field public static out Ljava/io/PrintStream;
+.method static <clinit>()V
+    new java/io/PrintStream
+    dup
+    invokespecial java/io/PrintStream/<init>()V
+    putstatic java/lang/System/out Ljava/io/PrintStream;
+    return
+.end method
+"#,
+    r#"
+.class public java/io/PrintStream
+.super java/lang/Object
+.field public static last Ljava/lang/String;
+.method public <init>()V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+.method public println(Ljava/lang/String;)V
+    aload_1
+    putstatic java/io/PrintStream/last Ljava/lang/String;
+    return
+.end method
+"#,
+    r#"
+; a functional interface with a default method of the same name
+.interface public abstract Scaler
+.super java/lang/Object
+.method public abstract scale(I)I
+.end method
+.method public scale(II)I
+    aload_0
+    iload_1
+    invokeinterface Scaler/scale(I)I 2
+    iload_2
+    iadd
+    ireturn
+.end method
+"#,
+    r#"
+.class public abstract Shape
+.super java/lang/Object
+.field protected sides I
+.method public <init>(I)V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    aload_0
+    iload_1
+    putfield Shape/sides I
+    return
+.end method
+.method public abstract area()D
+.end method
+.method public describe()Ljava/lang/String;
+    aload_0
+    getfield Shape/sides I
+    aload_0
+    invokevirtual Shape/area()D
+    invokedynamic makeConcatWithConstants(ID)Ljava/lang/String; invokestatic java/lang/invoke/StringConcatFactory/makeConcatWithConstants(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/String;[Ljava/lang/Object;)Ljava/lang/invoke/CallSite; { "\u0001 sides, area \u0001" }
+    areturn
+.end method
+"#,
+    r#"
+.class public Square
+.super Shape
+.field private side D
+.method public <init>(D)V
+    aload_0
+    iconst_4
+    invokespecial Shape/<init>(I)V
+    aload_0
+    dload_1
+    putfield Square/side D
+    return
+.end method
+.method public area()D
+    aload_0
+    getfield Square/side D
+    dup2
+    dmul
+    dreturn
+.end method
+"#,
+    r#"
+.class public Run
+.super java/lang/Object
+.method public static describe()Ljava/lang/String;
+    new Square
+    dup
+    ldc2_w 1.5
+    invokespecial Square/<init>(D)V
+    invokevirtual Shape/describe()Ljava/lang/String;
+    areturn
+.end method
+.method public static square(D)LShape;
+    new Square
+    dup
+    dload_0
+    invokespecial Square/<init>(D)V
+    areturn
+.end method
+.method public static total(I)D
+    iload_0
+    anewarray Shape
+    astore_1
+    iconst_0
+    istore_2
+Fill:
+    iload_2
+    iload_0
+    if_icmpge Sum
+    aload_1
+    iload_2
+    new Square
+    dup
+    iload_2
+    i2d
+    invokespecial Square/<init>(D)V
+    aastore
+    iinc 2 1
+    goto Fill
+Sum:
+    dconst_0
+    dstore_3
+    aload_1
+    arraylength
+    istore_2
+Next:
+    iinc 2 -1
+    iload_2
+    iflt Done
+    dload_3
+    aload_1
+    iload_2
+    aaload
+    invokevirtual Shape/area()D
+    dadd
+    dstore_3
+    goto Next
+Done:
+    dload_3
+    dreturn
+.end method
+.method public static grid()I
+    iconst_3
+    iconst_4
+    multianewarray [[I 2
+    astore_0
+    aload_0
+    iconst_2
+    aaload
+    iconst_3
+    bipush 7
+    iastore
+    aload_0
+    iconst_2
+    aaload
+    dup
+    arraylength
+    swap
+    iconst_3
+    iaload
+    iadd
+    ireturn
+.end method
+.method public static adder(I)I
+    iload_0
+    invokedynamic applyAsInt(I)Ljava/util/function/IntUnaryOperator; invokestatic java/lang/invoke/LambdaMetafactory/metafactory(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite; { methodtype (I)I, handle invokestatic Run/lambda$0(II)I, methodtype (I)I }
+    bipush 10
+    invokeinterface java/util/function/IntUnaryOperator/applyAsInt(I)I 2
+    ireturn
+.end method
+.method public static scaled(I)I
+    iload_0
+    invokedynamic scale(I)LScaler; invokestatic java/lang/invoke/LambdaMetafactory/metafactory(Ljava/lang/invoke/MethodHandles$Lookup;Ljava/lang/String;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodType;Ljava/lang/invoke/MethodHandle;Ljava/lang/invoke/MethodType;)Ljava/lang/invoke/CallSite; { methodtype (I)I, handle invokestatic Run/lambda$0(II)I, methodtype (I)I }
+    bipush 10
+    iconst_5
+    invokeinterface Scaler/scale(II)I 3
+    ireturn
+.end method
+.method private static lambda$0(II)I
+    iload_0
+    iload_1
+    imul
+    ireturn
+.end method
+.method public static outOfBounds()V
+    iconst_2
+    newarray int
+    iconst_2
+    iaload
+    pop
+    return
+.end method
+.method public static badCast()V
+    ldc "text"
+    checkcast Shape
+    pop
+    return
+.end method
+.method public static abstractShape()V
+    new Shape
+    return
+.end method
+.method public static identities()I
+    ldc "te"
+    ldc "xt"
+    invokevirtual java/lang/String/concat(Ljava/lang/String;)Ljava/lang/String;
+    astore_0
+    iconst_0
+    istore_1
+    aload_0
+    ldc "text"
+    if_acmpeq Interned
+    iinc 1 1
+Interned:
+    aload_0
+    invokevirtual java/lang/String/intern()Ljava/lang/String;
+    ldc "text"
+    if_acmpne Literals
+    iinc 1 2
+Literals:
+    ldc "text"
+    ldc "text"
+    if_acmpne Done
+    iinc 1 4
+Done:
+    iload_1
+    ireturn
+.end method
+.method public static chars()I
+    ldc "h\u00e9llo"
+    dup
+    invokevirtual java/lang/String/length()I
+    swap
+    iconst_1
+    invokevirtual java/lang/String/charAt(I)C
+    iadd
+    ireturn
+.end method
+.method public static smiley()I
+    ldc "😀"
+    dup
+    invokevirtual java/lang/String/length()I
+    swap
+    iconst_1
+    invokevirtual java/lang/String/charAt(I)C
+    iadd
+    ireturn
+.end method
+.method public static suffix(Ljava/lang/String;)Ljava/lang/String;
+    aload_0
+    ldc "b"
+    invokevirtual java/lang/String/concat(Ljava/lang/String;)Ljava/lang/String;
+    invokevirtual java/lang/String/intern()Ljava/lang/String;
+    areturn
+.end method
+.method public static churn(I)V
+Loop:
+    iload_0
+    ifle Done
+    sipush 1000
+    newarray int
+    pop
+    iinc 0 -1
+    goto Loop
+Done:
+    return
+.end method
+.method public static hoard(I)[[I
+    iload_0
+    anewarray [I
+    astore_1
+Loop:
+    iinc 0 -1
+    iload_0
+    iflt Done
+    aload_1
+    iload_0
+    sipush 1000
+    newarray int
+    aastore
+    goto Loop
+Done:
+    aload_1
+    areturn
+.end method
+.method public static recurse()V
+    invokestatic Run/recurse()V
+    return
+.end method
+"#,
+];
+
+/// HelloWorld, the assembler fixture and the runtime fixtures standing in for the class library.
+fn runtime_classpath() -> ClassPath {
+    let mut classpath = ClassPath::new();
+    classpath.add(ClassFile::open_from("java_tests/HelloWorld.class").unwrap()).unwrap();
+    classpath.add(assemble(ASSEMBLER_FIXTURE).unwrap()).unwrap();
+    for source in RUNTIME_FIXTURES {
+        classpath.add(assemble(source).unwrap()).unwrap();
+    }
+    classpath
+}
+
+#[test]
+pub fn interpret_bytecode() {
+    let classpath = runtime_classpath();
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+    interpreter.run_main("HelloWorld", &[]).unwrap();
+    let string = |interpreter: &Interpreter, value: Option<Value>| match value {
+        Some(Value::Reference(Some(string))) => interpreter.heap().string(string).map(JString::to_string),
+        _ => None,
+    };
+    let printed = interpreter.static_field("java/io/PrintStream", "last");
+    assert_eq!(string(&interpreter, printed).as_deref(), Some("Hello World!"));
+
+    assert_eq!(interpreter.invoke_static("Counter", "sum", "(I)J", vec![Value::Int(10)]).unwrap(), Some(Value::Long(45)));
+    assert_eq!(interpreter.invoke_static("Counter", "safeDivide", "(II)I", vec![Value::Int(6), Value::Int(3)]).unwrap(), Some(Value::Int(2)));
+    let classify = interpreter.invoke_static("Counter", "classify", "(I)Ljava/lang/String;", vec![Value::Int(-1)]).unwrap();
+    assert_eq!(string(&interpreter, classify).as_deref(), Some("negative"));
+
+    // virtual dispatch, fields, doubles and string concatenation
+    let describe = interpreter.invoke_static("Run", "describe", "()Ljava/lang/String;", vec![]).unwrap();
+    assert_eq!(string(&interpreter, describe).as_deref(), Some("4 sides, area 2.25"));
+    assert_eq!(interpreter.invoke_static("Run", "total", "(I)D", vec![Value::Int(4)]).unwrap(), Some(Value::Double(14.0)));
+    assert_eq!(interpreter.invoke_static("Run", "grid", "()I", vec![]).unwrap(), Some(Value::Int(11)));
+    assert_eq!(interpreter.invoke_static("Run", "adder", "(I)I", vec![Value::Int(3)]).unwrap(), Some(Value::Int(30)));
+    // only the functional method is the lambda's, an overload of it is the interface's default
+    assert_eq!(interpreter.invoke_static("Run", "scaled", "(I)I", vec![Value::Int(3)]).unwrap(), Some(Value::Int(35)));
+
+    let thrown = |interpreter: &mut Interpreter, name: &str| match interpreter.invoke_static("Run", name, "()V", vec![]) {
+        Err(RuntimeError::Exception { class, message }) => (class, message),
+        other => panic!("{} returned {:?}", name, other),
+    };
+    assert_eq!(thrown(&mut interpreter, "outOfBounds"), ("java/lang/ArrayIndexOutOfBoundsException".to_string(), Some("Index 2 out of bounds for length 2".to_string())));
+    assert_eq!(thrown(&mut interpreter, "badCast").1.as_deref(), Some("class java.lang.String cannot be cast to class Shape"));
+    assert_eq!(thrown(&mut interpreter, "abstractShape").0, "java/lang/InstantiationError");
+    assert_eq!(thrown(&mut interpreter, "recurse").0, "java/lang/StackOverflowError");
+    assert!(interpreter.frames().is_empty());
+}
+
+#[test]
+pub fn java_strings() {
+    // the assembler takes Rust strings, so the lone surrogate javac writes for "\uD800" is patched in
+    let bytes = assemble_to_bytes(".class Lone\n.method public static lone()I\n    ldc \"XYZ\"\n    dup\n    invokevirtual java/lang/String/length()I\n    bipush 16\n    ishl\n    swap\n    iconst_0\n    invokevirtual java/lang/String/charAt(I)C\n    ior\n    ireturn\n.end method\n").unwrap();
+    let at = bytes.windows(5).position(|window| window == [0, 3, b'X', b'Y', b'Z']).unwrap() + 2;
+    let mut patched = bytes.clone();
+    patched[at..at + 3].copy_from_slice(&[0xed, 0xa0, 0x80]);
+    let lone = ClassFile::from_bytes(patched.clone()).unwrap();
+    assert!(lone.class.cp.0.iter().any(|entry| matches!(&entry.info, ConstantPoolInfo::Utf16(units) if units == &[0xd800])));
+    assert_eq!(lone.to_bytes().unwrap(), patched);
+    let mut classpath = runtime_classpath();
+    classpath.add(lone).unwrap();
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+
+    // literals are interned, strings made at run time are new objects until they are
+    assert_eq!(interpreter.invoke_static("Run", "identities", "()I", vec![]).unwrap(), Some(Value::Int(7)));
+    let literal = interpreter.intern("text").unwrap();
+    assert_eq!(interpreter.intern(JString::from("te").concat(&JString::from("xt"))).unwrap(), literal);
+    assert_ne!(interpreter.new_string("text").unwrap(), literal);
+
+    // strings are UTF-16, whatever the class file stores them as
+    assert_eq!(interpreter.invoke_static("Run", "chars", "()I", vec![]).unwrap(), Some(Value::Int(5 + 0xe9)));
+    assert_eq!(interpreter.invoke_static("Run", "smiley", "()I", vec![]).unwrap(), Some(Value::Int(2 + 0xde00)));
+    let latin1 = JString::from("h\u{e9}llo");
+    assert!(latin1.is_latin1() && latin1.size() == 5 && latin1.to_string() == "h\u{e9}llo");
+    assert_eq!(latin1.concat(&JString::from("\u{20ac}")), JString::from("h\u{e9}llo\u{20ac}"));
+    assert_eq!(JString::from("text").hash_code(), 3556653);
+
+    // an unpaired surrogate only turns into U+FFFD when it leaves Java
+    let unpaired = JString::from_utf16(vec![0xd800, 'a' as u16]);
+    assert_eq!((unpaired.len(), unpaired.is_latin1(), unpaired.to_string()), (2, false, "\u{fffd}a".to_string()));
+    let string = interpreter.new_string(unpaired.clone()).unwrap();
+    let suffixed = match interpreter.invoke_static("Run", "suffix", "(Ljava/lang/String;)Ljava/lang/String;", vec![Value::Reference(Some(string))]).unwrap() {
+        Some(Value::Reference(Some(suffixed))) => suffixed,
+        other => panic!("suffix returned {:?}", other),
+    };
+    assert_eq!(interpreter.heap().string(suffixed).unwrap().units().collect::<Vec<_>>(), [0xd800, 'a' as u16, 'b' as u16]);
+    assert_eq!(interpreter.intern(unpaired.concat(&JString::from("b"))).unwrap(), suffixed);
+
+    // and a literal holding one is loaded as it is
+    assert_eq!(interpreter.invoke_static("Lone", "lone", "()I", vec![]).unwrap(), Some(Value::Int(1 << 16 | 0xd800)));
+    assert_eq!(interpreter.intern(JString::from_utf16(vec![0xd800])).unwrap(), interpreter.intern(JString::from_utf16(vec![0xd800])).unwrap());
+}
+
+#[test]
+pub fn heap_object_model() {
+    let classpath = runtime_classpath();
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+    let string = |interpreter: &Interpreter, value: Option<Value>| match value {
+        Some(Value::Reference(Some(string))) => interpreter.heap().string(string).map(JString::to_string),
+        _ => None,
+    };
+
+    // fields of super classes keep their slots, and objects are reachable by field name
+    let layout = interpreter.loader().class("Square").unwrap().layout.clone();
+    assert_eq!((layout.slot("Shape", "sides"), layout.slot("Square", "side")), (Some(0), Some(1)));
+    assert_eq!((layout.find("sides"), layout.find("side"), layout.find("missing")), (Some(0), Some(1), None));
+    let square = match interpreter.invoke_static("Run", "square", "(D)LShape;", vec![Value::Double(2.0)]).unwrap() {
+        Some(Value::Reference(Some(square))) => square,
+        other => panic!("square returned {:?}", other),
+    };
+    interpreter.heap_mut().pin(square);
+    let object = interpreter.heap_mut().get_mut(square);
+    assert_eq!((object.identify(), object.get("sides"), object.get("side")), ("Square".to_string(), Some(JValue::Int(4)), Some(JValue::Double(2.0))));
+    assert_eq!(object.get("missing"), None);
+    assert_eq!(object.set("sides", JValue::Long(3)), None);
+    assert_eq!(object.set("missing", JValue::Int(3)), None);
+    assert_eq!(object.set("sides", JValue::Int(3)), Some(()));
+
+    // what is set from Rust is what bytecode reads
+    let describe = interpreter.loader().find_method("Shape", "describe", "()Ljava/lang/String;").unwrap().unwrap();
+    let described = interpreter.invoke(describe, vec![Value::Reference(Some(square))]).unwrap();
+    assert_eq!(string(&interpreter, described).as_deref(), Some("3 sides, area 4.0"));
+    interpreter.heap_mut().unpin(square);
+}
+
+const OOM_FIXTURE: &str = r#"
+.class public Hog
+.super java/lang/Object
+.method public static huge()I
+Start:
+    ldc 2147483647
+    newarray long
+    arraylength
+    ireturn
+End:
+Handler:
+    pop
+    iconst_m1
+    ireturn
+.catch java/lang/OutOfMemoryError from Start to End using Handler
+.end method
+.method public static cube(I)[[[I
+    iload_0
+    iload_0
+    iload_0
+    multianewarray [[[I 3
+    areturn
+.end method
+.method public static big(I)I
+    iload_0
+    newarray int
+    arraylength
+    ireturn
+.end method
+"#;
+
+#[test]
+pub fn garbage_collection() {
+    // a heap on its own counts what it allocates and frees, and fails past its limit
+    let mut heap = Heap::with_limit(256);
+    let text = |text: &str| Object { class: "java/lang/String".to_string(), data: ObjectData::String(JString::from(text)) };
+    let mut allocated = Vec::new();
+    let failed = loop {
+        match heap.allocate(text("sixteen bytes!!!")) {
+            Ok(object) => allocated.push(object),
+            Err(error) => break error,
+        }
+    };
+    assert!(matches!(failed, RuntimeError::Exception { class, .. } if class == "java/lang/OutOfMemoryError"));
+    assert!(heap.used() <= 256 && allocated.len() > 1);
+    heap.pin(allocated[0]);
+    assert_eq!(heap.collect([allocated[1]]), allocated.len() - 2);
+    assert!(heap.is_live(allocated[0]) && heap.is_live(allocated[1]) && !heap.is_live(allocated[2]));
+    let stats = heap.stats().clone();
+    assert_eq!((stats.collections, stats.allocated_objects, stats.freed_objects), (1, allocated.len(), allocated.len() - 2));
+    assert_eq!(stats.allocated_bytes - stats.freed_bytes, heap.used());
+    // the slots of collected objects are reused
+    assert!(allocated.contains(&heap.allocate(text("again")).unwrap()));
+
+    let classpath = runtime_classpath();
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+    interpreter.run_main("HelloWorld", &[]).unwrap();
+    let string = |interpreter: &Interpreter, value: Option<Value>| match value {
+        Some(Value::Reference(Some(string))) => interpreter.heap().string(string).map(JString::to_string),
+        _ => None,
+    };
+
+    // garbage is collected as it piles up, what is still reachable runs out of memory
+    interpreter.heap_mut().set_limit(Some(1 << 16));
+    interpreter.invoke_static("Run", "churn", "(I)V", vec![Value::Int(1000)]).unwrap();
+    let stats = interpreter.heap().stats().clone();
+    assert!(stats.collections > 0 && stats.freed_bytes > 3_000_000, "{:?}", stats);
+    assert!(interpreter.heap().used() <= 1 << 16);
+    let hoarded = interpreter.invoke_static("Run", "hoard", "(I)[[I", vec![Value::Int(1000)]);
+    assert!(matches!(hoarded, Err(RuntimeError::Exception { class, .. }) if class == "java/lang/OutOfMemoryError"));
+
+    // a forced collection keeps static fields and pinned objects
+    let square = match interpreter.invoke_static("Run", "square", "(D)LShape;", vec![Value::Double(2.0)]).unwrap() {
+        Some(Value::Reference(Some(square))) => square,
+        other => panic!("square returned {:?}", other),
+    };
+    interpreter.heap_mut().pin(square);
+    interpreter.collect();
+    assert_eq!(interpreter.heap().get(square).get("sides"), Some(JValue::Int(4)));
+    let printed = interpreter.static_field("java/io/PrintStream", "last");
+    assert_eq!(string(&interpreter, printed).as_deref(), Some("Hello World!"));
+    let live = interpreter.heap().len();
+    interpreter.heap_mut().unpin(square);
+    assert_eq!(interpreter.collect(), 1);
+    assert_eq!(interpreter.heap().len(), live - 1);
+
+    // arrays too large for the default heap throw instead of taking the memory of the process
+    let mut classpath = runtime::classlib::bootstrap_classpath();
+    classpath.add(assemble(OOM_FIXTURE).unwrap()).unwrap();
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+    assert!(interpreter.heap().limit().is_some());
+    assert_eq!(interpreter.invoke_static("Hog", "huge", "()I", vec![]).unwrap(), Some(Value::Int(-1)));
+    for size in [1 << 12, 1 << 22] {
+        let cube = interpreter.invoke_static("Hog", "cube", "(I)[[[I", vec![Value::Int(size)]);
+        assert!(matches!(cube, Err(RuntimeError::Exception { class, .. }) if class == "java/lang/OutOfMemoryError"));
+    }
+    assert!(interpreter.frames().is_empty());
+
+    // garbage up to near the limit is collected for an allocation that doesn't fit beside it
+    let used = interpreter.heap().used();
+    for _ in 0..9 {
+        interpreter.new_string("x".repeat(100_000)).unwrap();
+    }
+    interpreter.heap_mut().set_limit(Some(used + 1_500_000));
+    let collections = interpreter.heap().stats().collections;
+    assert_eq!(interpreter.invoke_static("Hog", "big", "(I)I", vec![Value::Int(150_000)]).unwrap(), Some(Value::Int(150_000)));
+    assert_eq!(interpreter.heap().stats().collections, collections + 1);
+    assert!(interpreter.heap().used() < used + 1_000_000);
+}
+
+#[test]
+pub fn native_methods() {
+    let source = r#"
+.class public Native
+.super java/lang/Object
+.field public count I
+.method public <init>()V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+.method public static native greet(Ljava/lang/String;)Ljava/lang/String;
+.end method
+.method public native bump(I)Z
+.end method
+.method public static native fail()V
+.end method
+.method public static native unbound()V
+.end method
+.method public static twice(I)I
+    new Native
+    dup
+    invokespecial Native/<init>()V
+    astore_1
+    aload_1
+    iload_0
+    invokevirtual Native/bump(I)Z
+    pop
+    aload_1
+    iload_0
+    invokevirtual Native/bump(I)Z
+    ifeq Done
+    aload_1
+    getfield Native/count I
+    ireturn
+Done:
+    iconst_m1
+    ireturn
+.end method
+"#;
+    let stubbed = r#"
+.class public Stubbed
+.super java/lang/Object
+.method public static native answer()I
+.end method
+.method public static native wide()J
+.end method
+"#;
+    let mut classpath = ClassPath::new();
+    classpath.add(assemble(source).unwrap()).unwrap();
+    classpath.add(assemble(stubbed).unwrap()).unwrap();
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+    let natives = interpreter.natives();
+    natives.register("Native", "greet", "(Ljava/lang/String;)Ljava/lang/String;", |args| match args {
+        [JValue::String(name)] => Ok(JValue::String(JString::from("hello ").concat(name))),
+        _ => Err(JavaThrowable::new("java/lang/NullPointerException", None)),
+    });
+    natives.register("Native", "fail", "()V", |_| Err(JavaThrowable::new("java/lang/IllegalStateException", "from Rust".to_string())));
+    natives.register("Native", "bump", "(I)Z", |args| match args {
+        [JValue::Object(_), JValue::Int(by)] => Ok(JValue::Boolean(*by > 2)),
+        other => panic!("bump got {:?}", other),
+    });
+    natives.register_class("Stubbed", |name, descriptor, _| match (name, descriptor) {
+        ("answer", "()I") => Ok(JValue::Int(42)),
+        _ => Ok(JValue::Int(0)),
+    });
+
+    let name = Value::Reference(Some(interpreter.new_string("world").unwrap()));
+    let greeting = match interpreter.invoke_static("Native", "greet", "(Ljava/lang/String;)Ljava/lang/String;", vec![name]).unwrap() {
+        Some(Value::Reference(Some(string))) => interpreter.heap().string(string).map(JString::to_string),
+        _ => None,
+    };
+    assert_eq!(greeting.as_deref(), Some("hello world"));
+    assert_eq!(interpreter.invoke_static("Native", "twice", "(I)I", vec![Value::Int(3)]).unwrap(), Some(Value::Int(0)));
+    assert_eq!(interpreter.invoke_static("Native", "twice", "(I)I", vec![Value::Int(1)]).unwrap(), Some(Value::Int(-1)));
+    assert_eq!(interpreter.invoke_static("Stubbed", "answer", "()I", vec![]).unwrap(), Some(Value::Int(42)));
+    assert!(matches!(interpreter.invoke_static("Stubbed", "wide", "()J", vec![]), Err(RuntimeError::InvalidNative { .. })));
+
+    let thrown = |interpreter: &mut Interpreter, name: &str| match interpreter.invoke_static("Native", name, "()V", vec![]) {
+        Err(RuntimeError::Exception { class, message }) => (class, message),
+        other => panic!("{} returned {:?}", name, other),
+    };
+    assert_eq!(thrown(&mut interpreter, "fail"), ("java/lang/IllegalStateException".to_string(), Some("from Rust".to_string())));
+    assert_eq!(thrown(&mut interpreter, "unbound"), ("java/lang/UnsatisfiedLinkError".to_string(), Some("no native binding for Native.unbound()V".to_string())));
+    assert!(interpreter.frames().is_empty());
+}
+
+#[test]
+pub fn class_library() {
+    let program = r#"
+.class public Program
+.super java/lang/Object
+.method public static main([Ljava/lang/String;)V
+    getstatic java/lang/System/out Ljava/io/PrintStream;
+    astore_1
+    ; boxing, parsing and Math
+    ldc "41"
+    invokestatic java/lang/Integer/parseInt(Ljava/lang/String;)I
+    iconst_1
+    iadd
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    astore_2
+    aload_1
+    aload_2
+    invokevirtual java/io/PrintStream/println(Ljava/lang/Object;)V
+    aload_1
+    ldc2_w 2.0
+    invokestatic java/lang/Math/sqrt(D)D
+    invokevirtual java/io/PrintStream/println(D)V
+    aload_1
+    bipush -7
+    iconst_3
+    invokestatic java/lang/Math/floorMod(II)I
+    invokevirtual java/io/PrintStream/println(I)V
+    ; a list of boxed numbers, built and iterated
+    new java/util/ArrayList
+    dup
+    invokespecial java/util/ArrayList/<init>()V
+    astore_3
+    iconst_0
+    istore 4
+Fill:
+    iload 4
+    bipush 12
+    if_icmpge Filled
+    aload_3
+    iload 4
+    iload 4
+    imul
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    invokeinterface java/util/List/add(Ljava/lang/Object;)Z 2
+    pop
+    iinc 4 1
+    goto Fill
+Filled:
+    aload_3
+    iconst_0
+    invokeinterface java/util/List/remove(I)Ljava/lang/Object; 2
+    pop
+    iconst_0
+    istore 4
+    aload_3
+    invokeinterface java/util/List/iterator()Ljava/util/Iterator; 1
+    astore 5
+Sum:
+    aload 5
+    invokeinterface java/util/Iterator/hasNext()Z 1
+    ifeq Summed
+    iload 4
+    aload 5
+    invokeinterface java/util/Iterator/next()Ljava/lang/Object; 1
+    checkcast java/lang/Integer
+    invokevirtual java/lang/Integer/intValue()I
+    iadd
+    istore 4
+    goto Sum
+Summed:
+    aload_1
+    new java/lang/StringBuilder
+    dup
+    invokespecial java/lang/StringBuilder/<init>()V
+    ldc "sum "
+    invokevirtual java/lang/StringBuilder/append(Ljava/lang/String;)Ljava/lang/StringBuilder;
+    iload 4
+    invokevirtual java/lang/StringBuilder/append(I)Ljava/lang/StringBuilder;
+    bipush 32
+    invokevirtual java/lang/StringBuilder/append(C)Ljava/lang/StringBuilder;
+    aload_3
+    iconst_2
+    invokeinterface java/util/List/get(I)Ljava/lang/Object; 2
+    invokevirtual java/lang/StringBuilder/append(Ljava/lang/Object;)Ljava/lang/StringBuilder;
+    invokevirtual java/lang/StringBuilder/toString()Ljava/lang/String;
+    invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+    ; a map from words to their lengths, past its first resize
+    new java/util/HashMap
+    dup
+    invokespecial java/util/HashMap/<init>()V
+    astore 6
+    iconst_0
+    istore 4
+Put:
+    iload 4
+    bipush 20
+    if_icmpge Counted
+    aload 6
+    ldc "w"
+    iload 4
+    invokevirtual java/lang/String/repeat(I)Ljava/lang/String;
+    iload 4
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    invokeinterface java/util/Map/put(Ljava/lang/Object;Ljava/lang/Object;)Ljava/lang/Object; 3
+    pop
+    iinc 4 1
+    goto Put
+Counted:
+    aload 6
+    ldc "www"
+    invokeinterface java/util/Map/remove(Ljava/lang/Object;)Ljava/lang/Object; 2
+    pop
+    aload_1
+    aload 6
+    invokeinterface java/util/Map/size()I 1
+    invokevirtual java/io/PrintStream/println(I)V
+    aload_1
+    aload 6
+    ldc "wwwww"
+    invokeinterface java/util/Map/get(Ljava/lang/Object;)Ljava/lang/Object; 2
+    invokevirtual java/io/PrintStream/println(Ljava/lang/Object;)V
+    aload_1
+    aload 6
+    ldc "www"
+    invokeinterface java/util/Map/containsKey(Ljava/lang/Object;)Z 2
+    invokevirtual java/io/PrintStream/println(Z)V
+    ; strings
+    getstatic java/lang/System/err Ljava/io/PrintStream;
+    ldc "  Hello, World  "
+    invokevirtual java/lang/String/trim()Ljava/lang/String;
+    invokevirtual java/lang/String/toUpperCase()Ljava/lang/String;
+    iconst_3
+    invokevirtual java/lang/String/substring(I)Ljava/lang/String;
+    invokevirtual java/io/PrintStream/println(Ljava/lang/String;)V
+    aload_1
+    new java/lang/IllegalStateException
+    dup
+    ldc "broken"
+    invokespecial java/lang/IllegalStateException/<init>(Ljava/lang/String;)V
+    invokevirtual java/io/PrintStream/println(Ljava/lang/Object;)V
+    return
+.end method
+.method public static parse(Ljava/lang/String;)I
+    aload_0
+    invokestatic java/lang/Integer/parseInt(Ljava/lang/String;)I
+    ireturn
+.end method
+.method public static isDigit(C)Z
+    iload_0
+    invokestatic java/lang/Character/isDigit(C)Z
+    ireturn
+.end method
+; a bit for each pair of boxes that is the same object
+.method public static boxes()I
+    iconst_0
+    bipush 127
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    bipush 127
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    if_acmpne Int128
+    iconst_1
+    ior
+Int128:
+    sipush 128
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    sipush 128
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    if_acmpne Min
+    iconst_2
+    ior
+Min:
+    bipush -128
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    bipush -128
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    if_acmpne Long
+    iconst_4
+    ior
+Long:
+    ldc2_w -5L
+    invokestatic java/lang/Long/valueOf(J)Ljava/lang/Long;
+    ldc2_w -5L
+    invokestatic java/lang/Long/valueOf(J)Ljava/lang/Long;
+    if_acmpne Char
+    bipush 8
+    ior
+Char:
+    bipush 97
+    invokestatic java/lang/Character/valueOf(C)Ljava/lang/Character;
+    bipush 97
+    invokestatic java/lang/Character/valueOf(C)Ljava/lang/Character;
+    if_acmpne Done
+    bipush 16
+    ior
+Done:
+    ireturn
+.end method
+.method public static isLetter(C)Z
+    iload_0
+    invokestatic java/lang/Character/isLetter(C)Z
+    ireturn
+.end method
+.method public static isLetterOrDigit(C)Z
+    iload_0
+    invokestatic java/lang/Character/isLetterOrDigit(C)Z
+    ireturn
+.end method
+.method public static isWhitespace(C)Z
+    iload_0
+    invokestatic java/lang/Character/isWhitespace(C)Z
+    ireturn
+.end method
+.method public static repeat(I)Ljava/lang/String;
+    ldc "ab"
+    iload_0
+    invokevirtual java/lang/String/repeat(I)Ljava/lang/String;
+    areturn
+.end method
+.method public static outOfBounds()V
+    new java/util/ArrayList
+    dup
+    invokespecial java/util/ArrayList/<init>()V
+    iconst_1
+    invokevirtual java/util/ArrayList/get(I)Ljava/lang/Object;
+    pop
+    return
+.end method
+"#;
+    let mut classpath = runtime::classlib::bootstrap_classpath();
+    assert_eq!(classpath.origin_of("java/util/HashMap"), Some(runtime::classlib::ORIGIN));
+    assert!(classpath.contains("java/lang/ArrayIndexOutOfBoundsException"));
+    // the library comes first, so a program can't replace its classes
+    assert!(!classpath.add(assemble(".class public java/lang/Math\n.super java/lang/Object\n").unwrap()).unwrap());
+    classpath.add(ClassFile::open_from("java_tests/HelloWorld.class").unwrap()).unwrap();
+    classpath.add(assemble(program).unwrap()).unwrap();
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+    let (out, err) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new())));
+    runtime::classlib::register_natives(interpreter.natives(), out.clone(), err.clone());
+
+    interpreter.run_main("HelloWorld", &[]).unwrap();
+    interpreter.run_main("Program", &[]).unwrap();
+    let printed = String::from_utf8(out.lock().unwrap().clone()).unwrap();
+    assert_eq!(printed, "Hello World!\n42\n1.4142135623730951\n2\nsum 506 9\n19\n5\nfalse\njava.lang.IllegalStateException: broken\n");
+    assert_eq!(String::from_utf8(err.lock().unwrap().clone()).unwrap(), "LO, WORLD\n");
+
+    let parse = |interpreter: &mut Interpreter, input: &str| {
+        let input = Value::Reference(Some(interpreter.new_string(input).unwrap()));
+        interpreter.invoke_static("Program", "parse", "(Ljava/lang/String;)I", vec![input])
+    };
+    assert_eq!(parse(&mut interpreter, "-17").unwrap(), Some(Value::Int(-17)));
+    assert!(matches!(parse(&mut interpreter, "4x"), Err(RuntimeError::Exception { class, message })
+        if class == "java/lang/NumberFormatException" && message.as_deref() == Some("For input string: \"4x\"")));
+    assert!(matches!(interpreter.invoke_static("Program", "outOfBounds", "()V", vec![]), Err(RuntimeError::Exception { class, .. })
+        if class == "java/lang/IndexOutOfBoundsException"));
+
+    // small values are boxed to the same object, as `Integer a = 127, b = 127; a == b` requires
+    assert_eq!(interpreter.invoke_static("Program", "boxes", "()I", vec![]).unwrap(), Some(Value::Int(1 | 4 | 8 | 16)));
+
+    // floats print the digits Float.toString does, two at least when one would tell them apart
+    let mut printed = |descriptor: &str, class: &str, name: &str, value: Value| {
+        let string = interpreter.invoke_static(class, name, descriptor, vec![value]).unwrap();
+        match string {
+            Some(Value::Reference(Some(string))) => interpreter.heap().string(string).unwrap().to_string(),
+            other => panic!("{}.{} returned {:?}", class, name, other),
+        }
+    };
+    let floats = [f32::from_bits(1), f32::from_bits(2), 1.0, 0.1, 1e10, -0.0, 100.0, 3.0e-5]
+        .map(|value| printed("(F)Ljava/lang/String;", "java/lang/String", "valueOf", Value::Float(value)));
+    assert_eq!(floats, ["1.4E-45", "2.8E-45", "1.0", "0.1", "1.0E10", "-0.0", "100.0", "3.0E-5"]);
+    let doubles = [f64::from_bits(1), 1e23, 0.001, 1234567.0]
+        .map(|value| printed("(D)Ljava/lang/String;", "java/lang/Double", "toString", Value::Double(value)));
+    assert_eq!(doubles, ["4.9E-324", "1.0E23", "0.001", "1234567.0"]);
+
+    // -0.0 is less than 0.0 whichever comes first, and NaN wins
+    let mut extremum = |name: &str, x: f64, y: f64| match interpreter.invoke_static("java/lang/Math", name, "(DD)D", vec![Value::Double(x), Value::Double(y)]).unwrap() {
+        Some(Value::Double(result)) => result,
+        other => panic!("Math.{} returned {:?}", name, other),
+    };
+    let zeros = [extremum("min", -0.0, 0.0), extremum("min", 0.0, -0.0), extremum("max", -0.0, 0.0), extremum("max", 0.0, -0.0)];
+    assert_eq!(zeros.map(f64::to_bits), [-0.0, -0.0, 0.0, 0.0].map(f64::to_bits));
+    assert!(extremum("max", f64::NAN, 1.0).is_nan() && extremum("min", 1.0, f64::NAN).is_nan());
+    assert_eq!((extremum("min", 1.0, 2.0), extremum("max", 1.0, 2.0)), (1.0, 2.0));
+
+    // characters are classified by their general category as Java defines the classes, not as Rust does
+    let mut classify = |test: &str, chars: &[char]| -> Vec<bool> {
+        chars.iter().map(|c| match interpreter.invoke_static("Program", test, "(C)Z", vec![Value::Int(*c as i32)]).unwrap() {
+            Some(Value::Int(result)) => result != 0,
+            other => panic!("{} returned {:?}", test, other),
+        }).collect()
+    };
+    assert_eq!(classify("isDigit", &['7', '\u{663}', '\u{ff19}', '\u{bd}', '\u{b2}', '\u{2163}', 'x']), [true, true, true, false, false, false, false]);
+    assert_eq!(classify("isLetter", &['x', '\u{e9}', '\u{3042}', '\u{2b0}', '\u{2160}', '\u{93e}', '7']), [true, true, true, true, false, false, false]);
+    assert_eq!(classify("isLetterOrDigit", &['x', '7', '\u{663}', '\u{b2}', '\u{2160}', '_']), [true, true, true, false, false, false]);
+    assert_eq!(classify("isWhitespace", &[' ', '\t', '\u{1c}', '\u{1f}', '\u{2003}', '\u{2028}', '\u{a0}', '\u{2007}', '\u{202f}', '\u{85}', 'x']),
+        [true, true, true, true, true, true, false, false, false, false, false]);
+
+    // a string repeated past the heap or the length of a string throws instead of aborting
+    let repeated = interpreter.invoke_static("Program", "repeat", "(I)Ljava/lang/String;", vec![Value::Int(3)]).unwrap();
+    assert!(matches!(repeated, Some(Value::Reference(Some(string))) if interpreter.heap().string(string).unwrap().to_string() == "ababab"));
+    for (count, thrown) in [(-1, "java/lang/IllegalArgumentException"), (1 << 29, "java/lang/OutOfMemoryError"), (i32::MAX, "java/lang/OutOfMemoryError")] {
+        let repeated = interpreter.invoke_static("Program", "repeat", "(I)Ljava/lang/String;", vec![Value::Int(count)]);
+        assert!(matches!(repeated, Err(RuntimeError::Exception { ref class, .. }) if class == thrown), "{} repeated {:?}", count, repeated);
+    }
+}
+
+const INITIALIZATION_FIXTURES: [&str; 7] = [
+    r#"
+; records the order initializers run in
+.class public Trail
+.super java/lang/Object
+.field public static trail Ljava/lang/String;
+.method static <clinit>()V
+    ldc ""
+    putstatic Trail/trail Ljava/lang/String;
+    return
+.end method
+.method public static add(Ljava/lang/String;)V
+    getstatic Trail/trail Ljava/lang/String;
+    aload_0
+    invokevirtual java/lang/String/concat(Ljava/lang/String;)Ljava/lang/String;
+    putstatic Trail/trail Ljava/lang/String;
+    return
+.end method
+"#,
+    r#"
+.class public Parent
+.super java/lang/Object
+.field public static final LIMIT I = 7
+.field public static final NAME Ljava/lang/String; = "parent"
+.field public static seen I
+.method public <init>()V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+.method static <clinit>()V
+    ; constants are set before <clinit> runs, and using the class from it doesn't initialize it again
+    invokestatic Parent/limit()I
+    putstatic Parent/seen I
+    ldc "P"
+    invokestatic Trail/add(Ljava/lang/String;)V
+    return
+.end method
+.method public static limit()I
+    getstatic Parent/LIMIT I
+    ireturn
+.end method
+"#,
+    r#"
+.interface public Defaulted
+.super java/lang/Object
+.field public static final MARK Ljava/lang/Object;
+.method static <clinit>()V
+    new java/lang/Object
+    dup
+    invokespecial java/lang/Object/<init>()V
+    putstatic Defaulted/MARK Ljava/lang/Object;
+    ldc "D"
+    invokestatic Trail/add(Ljava/lang/String;)V
+    return
+.end method
+.method public greet()Ljava/lang/String;
+    ldc "hello"
+    areturn
+.end method
+"#,
+    r#"
+.interface public Plain
+.super java/lang/Object
+.field public static final MARK Ljava/lang/Object;
+.method static <clinit>()V
+    ldc "N"
+    invokestatic Trail/add(Ljava/lang/String;)V
+    return
+.end method
+"#,
+    r#"
+.class public Child
+.super Parent
+.implements Plain
+.implements Defaulted
+.method public <init>()V
+    aload_0
+    invokespecial Parent/<init>()V
+    return
+.end method
+.method static <clinit>()V
+    ldc "C"
+    invokestatic Trail/add(Ljava/lang/String;)V
+    return
+.end method
+.method public static make()V
+    new Child
+    dup
+    invokespecial Child/<init>()V
+    pop
+    return
+.end method
+"#,
+    r#"
+.class public Broken
+.super java/lang/Object
+.field public static value I
+.method static <clinit>()V
+    iconst_1
+    iconst_0
+    idiv
+    putstatic Broken/value I
+    return
+.end method
+.method public static read()I
+    getstatic Broken/value I
+    ireturn
+.end method
+"#,
+    r#"
+.class public Fatal
+.super java/lang/Object
+.method static <clinit>()V
+    new java/lang/OutOfMemoryError
+    dup
+    invokespecial java/lang/OutOfMemoryError/<init>()V
+    athrow
+.end method
+.method public static run()V
+    return
+.end method
+"#,
+];
+
+#[test]
+pub fn class_initialization() {
+    let mut classpath = runtime::classlib::bootstrap_classpath();
+    for source in INITIALIZATION_FIXTURES {
+        classpath.add(assemble(source).unwrap()).unwrap();
+    }
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+    let string = |interpreter: &Interpreter, value: Option<Value>| match value {
+        Some(Value::Reference(Some(string))) => interpreter.heap().string(string).map(JString::to_string),
+        _ => None,
+    };
+
+    // super class first, then the super interfaces declaring default methods, once each
+    interpreter.invoke_static("Child", "make", "()V", vec![]).unwrap();
+    interpreter.invoke_static("Child", "make", "()V", vec![]).unwrap();
+    let trail = interpreter.static_field("Trail", "trail");
+    assert_eq!(string(&interpreter, trail).as_deref(), Some("PDC"));
+    assert_eq!(interpreter.static_field("Parent", "seen"), Some(Value::Int(7)));
+    let name = interpreter.static_field("Parent", "NAME");
+    assert_eq!(string(&interpreter, name).as_deref(), Some("parent"));
+    assert_eq!(interpreter.invoke_static("java/lang/Integer", "compare", "(II)I", vec![Value::Int(1), Value::Int(2)]).unwrap(), Some(Value::Int(-1)));
+    assert_eq!(interpreter.static_field("java/lang/Long", "MIN_VALUE"), Some(Value::Long(0)));
+    interpreter.invoke_static("java/lang/Long", "valueOf", "(J)Ljava/lang/Long;", vec![Value::Long(1)]).unwrap();
+    assert_eq!(interpreter.static_field("java/lang/Long", "MIN_VALUE"), Some(Value::Long(i64::MIN)));
+
+    // a failed initialization is wrapped once, then the class can't be used
+    let thrown = |interpreter: &mut Interpreter, class: &str, name: &str, descriptor: &str| match interpreter.invoke_static(class, name, descriptor, vec![]) {
+        Err(RuntimeError::Exception { class, message }) => (class, message),
+        other => panic!("{}.{} returned {:?}", class, name, other),
+    };
+    assert_eq!(thrown(&mut interpreter, "Broken", "read", "()I"), (
+        "java/lang/ExceptionInInitializerError".to_string(),
+        Some("java.lang.ArithmeticException: / by zero".to_string()),
+    ));
+    assert_eq!(thrown(&mut interpreter, "Broken", "read", "()I"), ("java/lang/NoClassDefFoundError".to_string(), Some("Could not initialize class Broken".to_string())));
+    assert_eq!(thrown(&mut interpreter, "Fatal", "run", "()V").0, "java/lang/OutOfMemoryError");
+    assert_eq!(thrown(&mut interpreter, "Fatal", "run", "()V").0, "java/lang/NoClassDefFoundError");
+    assert!(interpreter.frames().is_empty());
+}
+
+const UNWINDING_FIXTURES: [&str; 2] = [
+    r#"
+.class public Failure
+.super java/lang/RuntimeException
+.field public code I
+.method public <init>(I)V
+    aload_0
+    ldc "failure"
+    invokespecial java/lang/RuntimeException/<init>(Ljava/lang/String;)V
+    aload_0
+    iload_1
+    putfield Failure/code I
+    return
+.end method
+"#,
+    r#"
+.class public Unwind
+.super java/lang/Object
+.source "Unwind.java"
+.field public static log Ljava/lang/String;
+
+; one of the exceptions the interpreter throws itself, by kind
+.method public static fail(I)V
+    .line 10
+    iload_0
+    tableswitch 0
+        Null
+        Index
+        Divide
+        Cast
+        Store
+        default : Negative
+Null:
+    .line 11
+    aconst_null
+    arraylength
+    pop
+    return
+Index:
+    .line 12
+    iconst_1
+    newarray int
+    iconst_2
+    iaload
+    pop
+    return
+Divide:
+    .line 13
+    iload_0
+    iconst_0
+    idiv
+    pop
+    return
+Cast:
+    .line 14
+    ldc "text"
+    checkcast java/lang/Integer
+    pop
+    return
+Store:
+    .line 15
+    iconst_1
+    anewarray java/lang/String
+    iconst_5
+    iconst_1
+    invokestatic java/lang/Integer/valueOf(I)Ljava/lang/Integer;
+    aastore
+    return
+Negative:
+    .line 16
+    iconst_m1
+    newarray int
+    pop
+    return
+.end method
+
+.method public static relay(I)V
+    .line 20
+    iload_0
+    invokestatic Unwind/fail(I)V
+    return
+.end method
+
+; catches what fail throws two frames down
+.method public static describe(I)Ljava/lang/String;
+Start:
+    .line 25
+    iload_0
+    invokestatic Unwind/relay(I)V
+End:
+    ldc "nothing"
+    areturn
+Handler:
+    .line 27
+    invokevirtual java/lang/Object/toString()Ljava/lang/String;
+    areturn
+.catch java/lang/RuntimeException from Start to End using Handler
+.end method
+
+.method public static raise(I)V
+    .line 30
+    iload_0
+    ifne Throw
+    return
+Throw:
+    .line 31
+    new Failure
+    dup
+    iload_0
+    invokespecial Failure/<init>(I)V
+    athrow
+.end method
+
+; the first handler whose type matches runs, with only the exception on the stack
+.method public static guarded(I)I
+    iconst_5
+Start:
+    .line 35
+    iload_0
+    invokestatic Unwind/raise(I)V
+End:
+    ireturn
+Arithmetic:
+    pop
+    iconst_m1
+    ireturn
+Caught:
+    getfield Failure/code I
+    ireturn
+.catch java/lang/ArithmeticException from Start to End using Arithmetic
+.catch Failure from Start to End using Caught
+.end method
+
+.method public static rethrow()V
+Start:
+    .line 40
+    iconst_3
+    invokestatic Unwind/raise(I)V
+End:
+    return
+Finally:
+    .line 42
+    astore_0
+    ldc "finally"
+    putstatic Unwind/log Ljava/lang/String;
+    aload_0
+    athrow
+.catch all from Start to End using Finally
+.end method
+
+.method public static chain()V
+Start:
+    .line 45
+    iconst_4
+    invokestatic Unwind/raise(I)V
+End:
+    return
+Wrap:
+    .line 47
+    astore_0
+    new java/lang/IllegalStateException
+    dup
+    ldc "wrapped"
+    aload_0
+    invokespecial java/lang/IllegalStateException/<init>(Ljava/lang/String;Ljava/lang/Throwable;)V
+    invokevirtual java/lang/Throwable/printStackTrace()V
+    return
+.catch Failure from Start to End using Wrap
+.end method
+
+.method public static report()V
+    .line 50
+    invokestatic Unwind/chain()V
+    return
+.end method
+
+.method public static main([Ljava/lang/String;)V
+    .line 55
+    iconst_2
+    invokestatic Unwind/relay(I)V
+    return
+.end method
+"#,
+];
+
+#[test]
+pub fn exception_unwinding() {
+    let mut classpath = runtime::classlib::bootstrap_classpath();
+    for source in UNWINDING_FIXTURES {
+        classpath.add(assemble(source).unwrap()).unwrap();
+    }
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+    let err = Arc::new(Mutex::new(Vec::new()));
+    runtime::classlib::register_natives(interpreter.natives(), Arc::new(Mutex::new(Vec::new())), err.clone());
+    let string = |interpreter: &Interpreter, value: Option<Value>| match value {
+        Some(Value::Reference(Some(string))) => interpreter.heap().string(string).map(JString::to_string),
+        _ => None,
+    };
+
+    // implicit exceptions are objects that handlers frames up catch by a super class
+    let described: Vec<_> = (0..6).map(|kind| {
+        let described = interpreter.invoke_static("Unwind", "describe", "(I)Ljava/lang/String;", vec![Value::Int(kind)]).unwrap();
+        string(&interpreter, described).unwrap()
+    }).collect();
+    assert_eq!(described, [
+        "java.lang.NullPointerException",
+        "java.lang.ArrayIndexOutOfBoundsException: Index 2 out of bounds for length 1",
+        "java.lang.ArithmeticException: / by zero",
+        "java.lang.ClassCastException: class java.lang.String cannot be cast to class java.lang.Integer",
+        // out of bounds is reported before the element is checked against the array type
+        "java.lang.ArrayIndexOutOfBoundsException: Index 5 out of bounds for length 1",
+        "java.lang.NegativeArraySizeException: -1",
+    ]);
+    assert_eq!(interpreter.invoke_static("Unwind", "guarded", "(I)I", vec![Value::Int(0)]).unwrap(), Some(Value::Int(5)));
+    assert_eq!(interpreter.invoke_static("Unwind", "guarded", "(I)I", vec![Value::Int(7)]).unwrap(), Some(Value::Int(7)));
+
+    // a catch-all handler rethrowing, past the frame called from Rust
+    assert!(matches!(interpreter.invoke_static("Unwind", "rethrow", "()V", vec![]), Err(RuntimeError::Exception { class, message })
+        if class == "Failure" && message.as_deref() == Some("failure")));
+    let log = interpreter.static_field("Unwind", "log");
+    assert_eq!(string(&interpreter, log).as_deref(), Some("finally"));
+    assert!(interpreter.frames().is_empty());
+
+    // stack traces name the frames where the throwable was made, causes sharing some of them
+    interpreter.invoke_static("Unwind", "report", "()V", vec![]).unwrap();
+    assert_eq!(String::from_utf8(std::mem::take(&mut *err.lock().unwrap())).unwrap(), [
+        "java.lang.IllegalStateException: wrapped",
+        "\tat Unwind.chain(Unwind.java:47)",
+        "\tat Unwind.report(Unwind.java:50)",
+        "Caused by: Failure: failure",
+        "\tat Unwind.raise(Unwind.java:31)",
+        "\tat Unwind.chain(Unwind.java:45)",
+        "\t... 1 more\n",
+    ].join("\n"));
+
+    // what main doesn't catch ends it with the report of the JVM
+    assert!(matches!(interpreter.run_main("Unwind", &[]), Err(RuntimeError::Exception { class, .. }) if class == "java/lang/ArithmeticException"));
+    assert_eq!(String::from_utf8(std::mem::take(&mut *err.lock().unwrap())).unwrap(), [
+        "Exception in thread \"main\" java.lang.ArithmeticException: / by zero",
+        "\tat Unwind.fail(Unwind.java:13)",
+        "\tat Unwind.relay(Unwind.java:20)",
+        "\tat Unwind.main(Unwind.java:55)\n",
+    ].join("\n"));
+    assert!(interpreter.frames().is_empty());
+}
+
+const THREAD_FIXTURES: [&str; 7] = [
+    r#"
+.class public Counter
+.super java/lang/Object
+.implements java/lang/Runnable
+.field public count I
+.field public static blocks I
+.field public static final LOCK Ljava/lang/Object;
+
+.method static <clinit>()V
+    new java/lang/Object
+    dup
+    invokespecial java/lang/Object/<init>()V
+    putstatic Counter/LOCK Ljava/lang/Object;
+    return
+.end method
+
+.method public <init>()V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+
+.method public synchronized increment()V
+    aload_0
+    dup
+    getfield Counter/count I
+    iconst_1
+    iadd
+    putfield Counter/count I
+    return
+.end method
+
+; half the increments in a synchronized method, half in a synchronized block
+.method public run()V
+    iconst_0
+    istore_1
+Loop:
+    iload_1
+    sipush 2000
+    if_icmpge Done
+    aload_0
+    invokevirtual Counter/increment()V
+    getstatic Counter/LOCK Ljava/lang/Object;
+    dup
+    astore_2
+    monitorenter
+    getstatic Counter/blocks I
+    iconst_1
+    iadd
+    putstatic Counter/blocks I
+    aload_2
+    monitorexit
+    iinc 1 1
+    goto Loop
+Done:
+    return
+.end method
+"#,
+    r#"
+.class public Channel
+.super java/lang/Object
+.field private value I
+.field private full Z
+
+.method public <init>()V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    return
+.end method
+
+.method public synchronized put(I)V
+Check:
+    aload_0
+    getfield Channel/full Z
+    ifeq Store
+    aload_0
+    invokevirtual java/lang/Object/wait()V
+    goto Check
+Store:
+    aload_0
+    iload_1
+    putfield Channel/value I
+    aload_0
+    iconst_1
+    putfield Channel/full Z
+    aload_0
+    invokevirtual java/lang/Object/notifyAll()V
+    return
+.end method
+
+.method public synchronized take()I
+Check:
+    aload_0
+    getfield Channel/full Z
+    ifne Load
+    aload_0
+    invokevirtual java/lang/Object/wait()V
+    goto Check
+Load:
+    aload_0
+    iconst_0
+    putfield Channel/full Z
+    aload_0
+    invokevirtual java/lang/Object/notifyAll()V
+    aload_0
+    getfield Channel/value I
+    ireturn
+.end method
+"#,
+    r#"
+.class public Producer
+.super java/lang/Object
+.implements java/lang/Runnable
+.field private channel LChannel;
+
+.method public <init>(LChannel;)V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    aload_0
+    aload_1
+    putfield Producer/channel LChannel;
+    return
+.end method
+
+.method public run()V
+    iconst_1
+    istore_1
+Loop:
+    iload_1
+    bipush 100
+    if_icmpgt Done
+    aload_0
+    getfield Producer/channel LChannel;
+    iload_1
+    invokevirtual Channel/put(I)V
+    iinc 1 1
+    goto Loop
+Done:
+    return
+.end method
+"#,
+    r#"
+.class public Sleeper
+.super java/lang/Thread
+.field public static result Ljava/lang/String;
+
+.method public <init>()V
+    aload_0
+    ldc "sleeper"
+    invokespecial java/lang/Thread/<init>(Ljava/lang/String;)V
+    return
+.end method
+
+.method public run()V
+Start:
+    ldc2_w 60000
+    invokestatic java/lang/Thread/sleep(J)V
+End:
+    ldc "slept"
+    putstatic Sleeper/result Ljava/lang/String;
+    return
+Interrupted:
+    pop
+    invokestatic java/lang/Thread/currentThread()Ljava/lang/Thread;
+    invokevirtual java/lang/Thread/getName()Ljava/lang/String;
+    ldc " interrupted"
+    invokevirtual java/lang/String/concat(Ljava/lang/String;)Ljava/lang/String;
+    putstatic Sleeper/result Ljava/lang/String;
+    return
+.catch java/lang/InterruptedException from Start to End using Interrupted
+.end method
+"#,
+    r#"
+.class public Locker
+.super java/lang/Object
+.implements java/lang/Runnable
+.field private first Ljava/lang/Object;
+.field private second Ljava/lang/Object;
+.field private static arrived I
+
+.method private static synchronized arrive()V
+    getstatic Locker/arrived I
+    iconst_1
+    iadd
+    putstatic Locker/arrived I
+    return
+.end method
+
+.method public <init>(Ljava/lang/Object;Ljava/lang/Object;)V
+    aload_0
+    invokespecial java/lang/Object/<init>()V
+    aload_0
+    aload_1
+    putfield Locker/first Ljava/lang/Object;
+    aload_0
+    aload_2
+    putfield Locker/second Ljava/lang/Object;
+    return
+.end method
+
+.method public run()V
+    aload_0
+    getfield Locker/first Ljava/lang/Object;
+    monitorenter
+    invokestatic Locker/arrive()V
+; both threads hold their first lock before they take the other
+Wait:
+    getstatic Locker/arrived I
+    iconst_2
+    if_icmpge Lock
+    lconst_1
+    invokestatic java/lang/Thread/sleep(J)V
+    goto Wait
+Lock:
+    aload_0
+    getfield Locker/second Ljava/lang/Object;
+    monitorenter
+    aload_0
+    getfield Locker/second Ljava/lang/Object;
+    monitorexit
+    aload_0
+    getfield Locker/first Ljava/lang/Object;
+    monitorexit
+    return
+.end method
+"#,
+    r#"
+.class public Starter
+.super java/lang/Object
+
+.method public static start(Ljava/lang/Runnable;)Ljava/lang/Thread;
+    new java/lang/Thread
+    dup
+    aload_0
+    invokespecial java/lang/Thread/<init>(Ljava/lang/Runnable;)V
+    dup
+    invokevirtual java/lang/Thread/start()V
+    areturn
+.end method
+"#,
+    r#"
+.class public Threads
+.super java/lang/Object
+
+; four threads sharing a counter
+.method public static count()I
+    new Counter
+    dup
+    invokespecial Counter/<init>()V
+    astore_0
+    iconst_4
+    anewarray java/lang/Thread
+    astore_1
+    iconst_0
+    istore_2
+Start:
+    iload_2
+    iconst_4
+    if_icmpge Started
+    aload_1
+    iload_2
+    aload_0
+    invokestatic Starter/start(Ljava/lang/Runnable;)Ljava/lang/Thread;
+    aastore
+    iinc 2 1
+    goto Start
+Started:
+    iconst_0
+    istore_2
+Join:
+    iload_2
+    iconst_4
+    if_icmpge Joined
+    aload_1
+    iload_2
+    aaload
+    invokevirtual java/lang/Thread/join()V
+    iinc 2 1
+    goto Join
+Joined:
+    aload_0
+    getfield Counter/count I
+    getstatic Counter/blocks I
+    iadd
+    ireturn
+.end method
+
+; sums what a producer hands over one value at a time
+.method public static transfer()I
+    new Channel
+    dup
+    invokespecial Channel/<init>()V
+    astore_0
+    new Producer
+    dup
+    aload_0
+    invokespecial Producer/<init>(LChannel;)V
+    invokestatic Starter/start(Ljava/lang/Runnable;)Ljava/lang/Thread;
+    astore_1
+    iconst_0
+    istore_2
+    iconst_0
+    istore_3
+Loop:
+    iload_3
+    bipush 100
+    if_icmpge Done
+    iload_2
+    aload_0
+    invokevirtual Channel/take()I
+    iadd
+    istore_2
+    iinc 3 1
+    goto Loop
+Done:
+    aload_1
+    invokevirtual java/lang/Thread/join()V
+    aload_1
+    invokevirtual java/lang/Thread/isAlive()Z
+    ifeq Ended
+    iconst_m1
+    ireturn
+Ended:
+    iload_2
+    ireturn
+.end method
+
+.method public static interrupt()Ljava/lang/String;
+    new Sleeper
+    dup
+    invokespecial Sleeper/<init>()V
+    astore_0
+    aload_0
+    invokevirtual java/lang/Thread/start()V
+    aload_0
+    invokevirtual java/lang/Thread/interrupt()V
+    aload_0
+    invokevirtual java/lang/Thread/join()V
+    invokestatic java/lang/Thread/currentThread()Ljava/lang/Thread;
+    invokevirtual java/lang/Thread/getName()Ljava/lang/String;
+    ldc ": "
+    invokevirtual java/lang/String/concat(Ljava/lang/String;)Ljava/lang/String;
+    getstatic Sleeper/result Ljava/lang/String;
+    invokevirtual java/lang/String/concat(Ljava/lang/String;)Ljava/lang/String;
+    areturn
+.end method
+
+.method public static unowned()V
+    new java/lang/Object
+    dup
+    invokespecial java/lang/Object/<init>()V
+    invokevirtual java/lang/Object/notify()V
+    return
+.end method
+
+; each thread takes one lock, then waits for the other's
+.method public static deadlock()V
+    new java/lang/Object
+    dup
+    invokespecial java/lang/Object/<init>()V
+    astore_0
+    new java/lang/Object
+    dup
+    invokespecial java/lang/Object/<init>()V
+    astore_1
+    new Locker
+    dup
+    aload_0
+    aload_1
+    invokespecial Locker/<init>(Ljava/lang/Object;Ljava/lang/Object;)V
+    invokestatic Starter/start(Ljava/lang/Runnable;)Ljava/lang/Thread;
+    new Locker
+    dup
+    aload_1
+    aload_0
+    invokespecial Locker/<init>(Ljava/lang/Object;Ljava/lang/Object;)V
+    invokestatic Starter/start(Ljava/lang/Runnable;)Ljava/lang/Thread;
+    invokevirtual java/lang/Thread/join()V
+    invokevirtual java/lang/Thread/join()V
+    return
+.end method
+"#,
+];
+
+#[test]
+pub fn threads_and_monitors() {
+    let mut classpath = runtime::classlib::bootstrap_classpath();
+    for source in THREAD_FIXTURES {
+        classpath.add(assemble(source).unwrap()).unwrap();
+    }
+    let mut interpreter = Interpreter::new(&classpath).unwrap();
+
+    // monitors keep the increments of threads switching in the middle of them apart
+    assert_eq!(interpreter.invoke_static("Threads", "count", "()I", vec![]).unwrap(), Some(Value::Int(16000)));
+    assert_eq!(interpreter.invoke_static("Threads", "transfer", "()I", vec![]).unwrap(), Some(Value::Int(5050)));
+    let interrupted = interpreter.invoke_static("Threads", "interrupt", "()Ljava/lang/String;", vec![]).unwrap();
+    let interrupted = match interrupted {
+        Some(Value::Reference(Some(string))) => interpreter.heap().string(string).map(JString::to_string),
+        _ => None,
+    };
+    assert_eq!(interrupted.as_deref(), Some("main: sleeper interrupted"));
+    assert!(matches!(interpreter.invoke_static("Threads", "unowned", "()V", vec![]), Err(RuntimeError::Exception { class, .. })
+        if class == "java/lang/IllegalMonitorStateException"));
+
+    // threads waiting for each other end the call with a dump of who waits for whom
+    let dump = match interpreter.invoke_static("Threads", "deadlock", "()V", vec![]) {
+        Err(RuntimeError::Deadlock(dump)) => dump,
+        other => panic!("expected a deadlock, got {:?}", other),
+    };
+    assert!(dump.contains("\"main\" #1 WAITING (on object monitor)\n\tat java.lang.Thread.join(Unknown Source)\n\tat Threads.deadlock(Unknown Source)\n"));
+    assert!(dump.contains("BLOCKED (on object monitor)\n\tat Locker.run(Unknown Source)\n\t- waiting to lock <#"));
+    assert!(dump.contains("Found one Java-level deadlock:"));
+    assert_eq!(dump.matches("waiting to lock monitor").count(), 2);
+    assert!(interpreter.frames().is_empty());
+    // which the threads ended with, releasing their monitors
+    assert_eq!(interpreter.invoke_static("Threads", "transfer", "()I", vec![]).unwrap(), Some(Value::Int(5050)));
+}
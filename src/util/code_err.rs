@@ -1,5 +1,7 @@
 use std::{error::Error, fmt::Display};
 
+use crate::runtime::heap::ObjectRef;
+
 #[derive(Debug)]
 pub enum CodeParseError {
     EarlyEOF(String),
@@ -54,3 +56,202 @@ impl Display for ClassParseError {
     }
 }
 impl Error for ClassParseError {}
+
+/// An error raised while assembling a textual class, `line` and `column` are 1-based
+/// positions in the source.
+#[derive(Debug)]
+pub enum AssemblyError {
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    UnknownMnemonic {
+        line: usize,
+        column: usize,
+        mnemonic: String,
+    },
+    UndefinedLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+    /// The code is well-formed but its types or stack heights don't line up
+    Frames {
+        line: usize,
+        message: String,
+    },
+    ClassParseError(ClassParseError),
+}
+impl AssemblyError {
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            AssemblyError::Syntax { line, .. }
+            | AssemblyError::UnknownMnemonic { line, .. }
+            | AssemblyError::UndefinedLabel { line, .. }
+            | AssemblyError::DuplicateLabel { line, .. }
+            | AssemblyError::Frames { line, .. } => Some(*line),
+            AssemblyError::ClassParseError(_) => None,
+        }
+    }
+}
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssemblyError::Syntax { line, column, message } => write!(f, "{}:{}: {}", line, column, message),
+            AssemblyError::UnknownMnemonic { line, column, mnemonic } => write!(f, "{}:{}: unknown instruction `{}`", line, column, mnemonic),
+            AssemblyError::UndefinedLabel { line, label } => write!(f, "{}: undefined label `{}`", line, label),
+            AssemblyError::DuplicateLabel { line, label } => write!(f, "{}: label `{}` is already defined", line, label),
+            AssemblyError::Frames { line, message } => write!(f, "{}: {}", line, message),
+            AssemblyError::ClassParseError(err) => write!(f, "{}", err),
+        }
+    }
+}
+impl Error for AssemblyError {}
+impl From<ClassParseError> for AssemblyError {
+    fn from(err: ClassParseError) -> Self {
+        AssemblyError::ClassParseError(err)
+    }
+}
+
+/// A symbolic reference that does not resolve, or an invocation that selects no method
+/// (JVMS §5.4.3, §5.4.6). Variants are named after the error the JVM would throw.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolutionError {
+    /// A class needed to resolve the reference is not on the class path
+    NoClassDefFound(String),
+    IncompatibleClassChange(String),
+    NoSuchField {
+        class: String,
+        name: String,
+        descriptor: String,
+    },
+    NoSuchMethod {
+        class: String,
+        name: String,
+        descriptor: String,
+    },
+    AbstractMethod {
+        class: String,
+        name: String,
+        descriptor: String,
+    },
+}
+impl ResolutionError {
+    /// Internal name of the `LinkageError` subclass the JVM throws for this error.
+    pub fn error_class(&self) -> &'static str {
+        match self {
+            ResolutionError::NoClassDefFound(_) => "java/lang/NoClassDefFoundError",
+            ResolutionError::IncompatibleClassChange(_) => "java/lang/IncompatibleClassChangeError",
+            ResolutionError::NoSuchField { .. } => "java/lang/NoSuchFieldError",
+            ResolutionError::NoSuchMethod { .. } => "java/lang/NoSuchMethodError",
+            ResolutionError::AbstractMethod { .. } => "java/lang/AbstractMethodError",
+        }
+    }
+}
+impl Display for ResolutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolutionError::NoClassDefFound(class) => write!(f, "class {} is not on the class path", class),
+            ResolutionError::IncompatibleClassChange(message) => write!(f, "{}", message),
+            ResolutionError::NoSuchField { class, name, descriptor } => write!(f, "no field {}.{}:{}", class, name, descriptor),
+            ResolutionError::NoSuchMethod { class, name, descriptor } => write!(f, "no method {}.{}{}", class, name, descriptor),
+            ResolutionError::AbstractMethod { class, name, descriptor } => write!(f, "no implementation of {}.{}{}", class, name, descriptor),
+        }
+    }
+}
+impl Error for ResolutionError {}
+
+/// Why the interpreter stopped running a program.
+#[derive(Debug)]
+pub enum RuntimeError {
+    /// A class could not be read or its code could not be decoded
+    ClassParseError(ClassParseError),
+    /// A symbolic reference did not resolve, see [ResolutionError::error_class] for what the JVM throws
+    Resolution(ResolutionError),
+    /// A Java exception that reached the code calling into the interpreter, by the internal
+    /// name of its class
+    Exception {
+        class: String,
+        message: Option<String>,
+    },
+    /// A throwable object on its way to a handler, which the interpreter turns into
+    /// [RuntimeError::Exception] if none catches it
+    Thrown(ObjectRef),
+    /// Code that a verifier would have rejected, such as an operand of the wrong type
+    InvalidCode {
+        method: String,
+        pc: u32,
+        message: String,
+    },
+    /// Something the interpreter does not implement, such as an unknown bootstrap method
+    Unsupported(String),
+    /// A native method was passed or returned a value not of its declared type
+    InvalidNative {
+        method: String,
+        message: String,
+    },
+    /// Every thread waited for another, with the thread dump taken then
+    Deadlock(String),
+}
+impl RuntimeError {
+    pub fn exception(class: &str, message: Option<String>) -> Self {
+        RuntimeError::Exception { class: class.to_string(), message }
+    }
+}
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeError::ClassParseError(err) => write!(f, "{}", err),
+            RuntimeError::Resolution(err) => write!(f, "{}: {}", err.error_class().replace('/', "."), err),
+            RuntimeError::Exception { class, message: None } => write!(f, "{}", class.replace('/', ".")),
+            RuntimeError::Exception { class, message: Some(message) } => write!(f, "{}: {}", class.replace('/', "."), message),
+            RuntimeError::Thrown(object) => write!(f, "throwable #{} was thrown", object.index()),
+            RuntimeError::InvalidCode { method, pc, message } => write!(f, "{} at pc {}: {}", method, pc, message),
+            RuntimeError::Unsupported(what) => write!(f, "unsupported: {}", what),
+            RuntimeError::InvalidNative { method, message } => write!(f, "native {}: {}", method, message),
+            RuntimeError::Deadlock(dump) => write!(f, "deadlock\n{}", dump),
+        }
+    }
+}
+impl Error for RuntimeError {}
+impl From<ClassParseError> for RuntimeError {
+    fn from(err: ClassParseError) -> Self {
+        RuntimeError::ClassParseError(err)
+    }
+}
+impl From<ResolutionError> for RuntimeError {
+    fn from(err: ResolutionError) -> Self {
+        RuntimeError::Resolution(err)
+    }
+}
+
+/// A Java exception thrown by Rust code standing in for Java, such as a native method.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JavaThrowable {
+    /// Internal name of the class of the exception
+    pub class: String,
+    pub message: Option<String>,
+}
+impl JavaThrowable {
+    pub fn new(class: &str, message: impl Into<Option<String>>) -> Self {
+        JavaThrowable { class: class.to_string(), message: message.into() }
+    }
+}
+impl Display for JavaThrowable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.class.replace('/', "."), message),
+            None => write!(f, "{}", self.class.replace('/', ".")),
+        }
+    }
+}
+impl Error for JavaThrowable {}
+impl From<JavaThrowable> for RuntimeError {
+    fn from(throwable: JavaThrowable) -> Self {
+        RuntimeError::Exception { class: throwable.class, message: throwable.message }
+    }
+}
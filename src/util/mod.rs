@@ -1 +1,2 @@
-pub mod code_err;
\ No newline at end of file
+pub mod code_err;
+pub mod json;
\ No newline at end of file